@@ -43,13 +43,34 @@ fn setupWhisper(b: *std.Build, exe: *std.Build.Step.Compile) void {
     exe.linkLibrary(lib);
 }
 
-fn setupRustGui(b: *std.Build, opt: std.builtin.OptimizeMode) !std.Build.LazyPath {
+fn setupRustGui(b: *std.Build, opt: std.builtin.OptimizeMode, profiling: bool, wgpu: bool, midi: bool, scripting: bool, remote: bool) !std.Build.LazyPath {
     const tool_run = b.addSystemCommand(&.{"cargo"});
     tool_run.setCwd(b.path("src/gui/rust"));
     tool_run.addArgs(&.{
         "build",
     });
 
+    var features = std.ArrayList([]const u8).init(b.allocator);
+    if (profiling) {
+        try features.append("profiling");
+    }
+    if (wgpu) {
+        try features.append("wgpu");
+    }
+    if (midi) {
+        try features.append("midi");
+    }
+    if (scripting) {
+        try features.append("scripting");
+    }
+    if (remote) {
+        try features.append("remote");
+    }
+
+    if (features.items.len > 0) {
+        tool_run.addArgs(&.{ "--features", try std.mem.join(b.allocator, ",", features.items) });
+    }
+
     var opt_path: []const u8 = undefined;
     switch (opt) {
         .ReleaseSafe,
@@ -82,6 +103,11 @@ pub fn build(b: *std.Build) !void {
     const opt = b.standardOptimizeOption(.{});
 
     const fake_ui = b.option(bool, "fake_ui", "whether we should build the fake UI") orelse false;
+    const profiling = b.option(bool, "profiling", "whether the GUI should include the puffin profiler") orelse false;
+    const wgpu = b.option(bool, "wgpu", "whether the GUI should render with wgpu instead of OpenGL") orelse false;
+    const midi = b.option(bool, "midi", "whether the GUI should take commands from MIDI controllers") orelse false;
+    const scripting = b.option(bool, "scripting", "whether the GUI should have a console for rhai scripts") orelse false;
+    const remote = b.option(bool, "remote", "whether the GUI should take commands over a WebSocket") orelse false;
 
     const exe = b.addExecutable(.{
         .name = "video-editor",
@@ -95,6 +121,7 @@ pub fn build(b: *std.Build) !void {
     exe.linkSystemLibrary("avformat");
     exe.linkSystemLibrary("avcodec");
     exe.linkSystemLibrary("avutil");
+    exe.linkSystemLibrary("swscale");
 
     if (fake_ui) {
         exe.addCSourceFile(.{
@@ -102,9 +129,13 @@ pub fn build(b: *std.Build) !void {
             .flags = &.{ "-Wall", "-Wextra", "-Wpedantic", "-Werror" },
         });
     } else {
-        const libgui_path = try setupRustGui(b, opt);
+        const libgui_path = try setupRustGui(b, opt, profiling, wgpu, midi, scripting, remote);
         exe.addLibraryPath(libgui_path.dirname());
         exe.linkSystemLibrary("gui");
+        if (midi) {
+            // midir talks to ALSA, the static lib does not carry its link flags
+            exe.linkSystemLibrary("asound");
+        }
     }
 
     setupWhisper(b, exe);
@@ -117,4 +148,18 @@ pub fn build(b: *std.Build) !void {
     exe.linkLibC();
     exe.linkLibCpp();
     b.installArtifact(exe);
+
+    // Backend logic that can be tested without a GUI or a video
+    const unit_tests = b.addTest(.{
+        .root_source_file = b.path("src/ClipManager.zig"),
+        .target = target,
+        .optimize = opt,
+    });
+    unit_tests.addIncludePath(b.path("src/gui"));
+    unit_tests.addIncludePath(b.path("vendor/whisper.cpp"));
+    unit_tests.addIncludePath(miniaudio_path.dirname());
+    unit_tests.linkLibC();
+
+    const test_step = b.step("test", "Run the backend unit tests");
+    test_step.dependOn(&b.addRunArtifact(unit_tests).step);
 }
@@ -13,14 +13,44 @@ def parse_args():
     return parser.parse_args()
 
 
+# Older ffmpeg only takes atempo between 0.5 and 2, so larger changes are
+# chained
+def atempo_filters(speed):
+    ret = ""
+    while speed > 2.0:
+        ret += ",atempo=2.0"
+        speed /= 2.0
+    while speed < 0.5:
+        ret += ",atempo=0.5"
+        speed /= 0.5
+    if speed != 1.0:
+        ret += f",atempo={speed}"
+    return ret
+
+
+# Silences the source audio in the given regions, applied before trimming so
+# that the times are the source's
+def mute_filter(mute_regions):
+    if not mute_regions:
+        return ""
+    enable = "+".join(f"between(t,{region['start']},{region['end']})" for region in mute_regions)
+    return f"volume=0:enable='{enable}',"
+
+
 class FfmpegFilterGenerator:
-    def __init__(self, input):
+    def __init__(self, input, mute_regions):
         self.segment_idx = 0
+        self.mute_filter = mute_filter(mute_regions)
         self.output = ["ffmpeg", "-i", input, "-filter_complex", ""]
 
-    def add_segment(self, start, end):
-        self.output[len(self.output) - 1] += f"[0:v]trim=start={start}:end={end},setpts=PTS-STARTPTS[{self.segment_idx}v];"
-        self.output[len(self.output) - 1] += f"[0:a]atrim=start={start}:end={end},asetpts=PTS-STARTPTS[{self.segment_idx}a];"
+    def add_segment(self, start, end, speed, freeze):
+        if freeze:
+            # First frame only, cloned for as long as the audio runs
+            video_filter = f"trim=start={start},trim=end_frame=1,setpts=PTS-STARTPTS,tpad=stop_mode=clone:stop_duration={(end - start) / speed}"
+        else:
+            video_filter = f"trim=start={start}:end={end},setpts=(PTS-STARTPTS)/{speed}"
+        self.output[len(self.output) - 1] += f"[0:v]{video_filter}[{self.segment_idx}v];"
+        self.output[len(self.output) - 1] += f"[0:a]{self.mute_filter}atrim=start={start}:end={end},asetpts=PTS-STARTPTS{atempo_filters(speed)}[{self.segment_idx}a];"
         self.segment_idx += 1
 
     def finish(self, output_file):
@@ -34,9 +64,11 @@ def main(video_path, save_file_path):
         save_file = json.load(f)
 
 
-    generator = FfmpegFilterGenerator(video_path)
+    generator = FfmpegFilterGenerator(video_path, save_file.get("mute_regions", []))
     for segment in save_file["clips"]:
-        generator.add_segment(segment["start"], segment["end"])
+        # Saves from before clips had a speed
+        speed = segment.get("speed", 1.0) or 1.0
+        generator.add_segment(segment["start"], segment["end"], speed, segment.get("freeze", False))
     generator.finish("out.mkv")
 
     subprocess.run(generator.output, check=True)
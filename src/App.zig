@@ -3,33 +3,91 @@ const Allocator = std.mem.Allocator;
 
 const c = @import("c.zig");
 const ClipManager = @import("ClipManager.zig");
+const MarkerManager = @import("MarkerManager.zig");
+const MuteManager = @import("MuteManager.zig");
 const FrameRenderer = @import("FrameRenderer.zig");
 const decoder = @import("decoder.zig");
 const audio = @import("audio.zig");
 const save_mod = @import("save.zig");
 const WordTimestampGenerator = @import("WordTimestampGenerator.zig");
+const ThumbnailGenerator = @import("ThumbnailGenerator.zig");
+const JobTracker = @import("JobTracker.zig");
+const captions_mod = @import("captions.zig");
+const AudioRenderer = @import("AudioRenderer.zig");
+const Journal = @import("Journal.zig");
+const EditHistory = @import("EditHistory.zig");
+const Exporter = @import("Exporter.zig");
 
 pub const AppRefs = struct {
     alloc: Allocator,
     frame_renderer: *FrameRenderer.SharedData,
     gui: ?*c.Gui,
     app_state: *AppState,
+    // The newest of decoders that playback runs on
     dec: *decoder.VideoDecoder,
+    decoders: *Decoders,
+    audio_renderer: *AudioRenderer,
     audio_player: ?*audio.Player,
     clip_manager: *ClipManager,
+    marker_manager: *MarkerManager,
+    mute_manager: *MuteManager,
     wtm: ?*WordTimestampGenerator,
-    save_path: []const u8,
+    thumbnail_generator: *ThumbnailGenerator,
+    exporter: *Exporter,
+    jobs: *JobTracker,
+    journal: *Journal,
+    history: *EditHistory,
+    // Only touched by the App thread
+    caption_style: *c.GuiCaptionStyle,
+    input_path: [:0]const u8,
+    save_path: [:0]const u8,
+
+    fn audioSink(self: AppRefs) ?AudioSink {
+        const player = self.audio_player orelse return null;
+        return .{ .player = player, .mute_manager = self.mute_manager };
+    }
+};
+
+// Where decoded audio goes during playback
+const AudioSink = struct {
+    player: *audio.Player,
+    mute_manager: *const MuteManager,
 };
 
+// Every decoder the source was opened with. Frames point back into the pool of
+// the decoder that made them and may still be queued for audio or halfway
+// through an upload on the GUI thread when the source is reloaded, so a
+// replaced decoder is only freed once all of its frames are back, see
+// freeUnusedDecoders. Freed ones leave a null behind. Segmented so that
+// decoders never move
+pub const Decoders = std.SegmentedList(?decoder.VideoDecoder, 2);
+
+// Fastest the shuttle can play in either direction
+const max_speed = 8.0;
+
 const App = @This();
 refs: AppRefs,
 
 player_state: PlayerState,
+decode_stats: DecodeStats,
 last_pts: f32,
 stream_id: usize,
+// Journaled edits the GUI asked to replay, applied after the actions that
+// came with the request
+replay: ?Journal.Recovered = null,
+dual_preview: bool = false,
+// Applied edits since the last save or load, reverts through the history
+// included. Set where they are journaled, so failed ones never count
+unsaved: bool = false,
+// Decodes the program monitor's frames, opened the first time it is needed.
+// Lives in decoders with the rest
+program_dec: ?*decoder.VideoDecoder = null,
+// Position the program monitor's own frame was decoded for, null if it is
+// showing the source or nothing
+program_pts: ?f32 = null,
 
 pub fn init(refs: AppRefs) !App {
-    const img = try getNextVideoFrame(refs.dec, refs.audio_player, null) orelse {
+    const img = try getNextVideoFrame(refs.dec, refs.audioSink(), null) orelse {
         std.log.err("Video should have at least one frame", .{});
         return error.InvalidData;
     };
@@ -45,6 +103,7 @@ pub fn init(refs: AppRefs) !App {
     return .{
         .refs = refs,
         .player_state = PlayerState.init(try std.time.Instant.now()),
+        .decode_stats = DecodeStats.init(try std.time.Instant.now()),
         .stream_id = img.stream_id,
         .last_pts = img.pts,
     };
@@ -60,51 +119,55 @@ pub fn run(self: *App) !void {
             break;
         }
         try self.updateVideoFrame(&now);
+        try self.updateProgramFrame();
+        self.freeUnusedDecoders();
         try self.updateAppState();
         try self.sleepUntilNextFrame();
     }
 }
 
 fn applyGuiActions(self: *App, now: *std.time.Instant) !bool {
+    // Replays are requested and finished within one call, anything left over
+    // is from a close or an error
+    defer if (self.replay) |*replay| {
+        replay.deinit();
+        self.replay = null;
+    };
+
     var seek_position: ?f32 = null;
+    var actions: [64]c.GuiAction = undefined;
     while (true) {
-        const action = c.gui_next_action(self.refs.gui);
-        switch (action.tag) {
-            c.gui_action_toggle_pause => {
-                self.player_state.togglePause(now.*);
-                c.gui_notify_update(self.refs.gui);
-            },
-            c.gui_action_none => {
-                break;
-            },
-            c.gui_action_close => {
-                return true;
-            },
-            c.gui_action_seek => {
-                seek_position = action.data.seek_position;
-                c.gui_notify_update(self.refs.gui);
-            },
-            c.gui_action_clip_edit => {
-                self.refs.clip_manager.update(action.data.clip);
-                c.gui_notify_update(self.refs.gui);
-            },
-            c.gui_action_clip_remove => {
-                const clip = self.refs.clip_manager.clipForPts(action.data.seek_position);
-                if (clip) |cl| {
-                    self.refs.clip_manager.remove(cl.id);
+        const num_actions = c.gui_next_actions(self.refs.gui, &actions, actions.len);
+        if (num_actions == 0) {
+            break;
+        }
+
+        for (actions[0..num_actions]) |action| {
+            switch (try self.applyGuiAction(now, action, &seek_position)) {
+                .applied => self.journalEdit(&action),
+                .ignored => {},
+                .close => return true,
+            }
+        }
+    }
+
+    if (self.replay) |replay| {
+        for (replay.entries) |*entry| switch (entry.*) {
+            .action => |*action| {
+                if (try self.applyGuiAction(now, action.*, &seek_position) == .applied) {
+                    self.journalEdit(action);
                 }
             },
-            c.gui_action_clip_add => {
-                try self.refs.clip_manager.add(action.data.clip);
-                c.gui_notify_update(self.refs.gui);
-            },
-            c.gui_action_save => {
-                try Save.save(self.refs);
-            },
-            else => {
-                std.debug.panic("invalid action: {d}", .{action.tag});
+            .restore => |state| {
+                self.refs.history.restore(state, self.historyRefs()) catch |e| {
+                    std.log.err("Failed to restore journaled revert: {s}", .{@errorName(e)});
+                    continue;
+                };
+                self.journalRestore();
             },
-        }
+        };
+        self.refs.journal.discardRecovered();
+        std.log.info("Replayed {d} edits", .{replay.entries.len});
     }
 
     if (seek_position) |s| {
@@ -114,10 +177,465 @@ fn applyGuiActions(self: *App, now: *std.time.Instant) !bool {
     return false;
 }
 
+// Losing the journal is not worth losing the edit over
+fn journalEdit(self: *App, action: *const c.GuiAction) void {
+    if (Journal.records(action)) self.unsaved = true;
+    self.refs.journal.append(action) catch |e| {
+        std.log.err("Failed to journal edit: {s}", .{@errorName(e)});
+    };
+}
+
+// A revert has no action to journal, so everything it left behind is
+// journaled instead. Only called once the revert went through
+fn journalRestore(self: *App) void {
+    self.unsaved = true;
+    self.refs.journal.appendRestore(.{
+        .clips = self.refs.clip_manager.clips.items,
+        .markers = self.refs.marker_manager.markers.items,
+        .mute_regions = self.refs.mute_manager.regions.items,
+        .caption_style = self.refs.caption_style.*,
+    }) catch |e| {
+        std.log.err("Failed to journal revert: {s}", .{@errorName(e)});
+    };
+}
+
+// Like the journal, the history is not worth failing an edit over
+fn recordEdit(self: *App, edit: EditHistory.Edit) void {
+    self.refs.history.record(edit, self.historyRefs()) catch |e| {
+        std.log.err("Failed to record edit history: {s}", .{@errorName(e)});
+    };
+}
+
+fn historyRefs(self: *App) EditHistory.Refs {
+    return .{
+        .clip_manager = self.refs.clip_manager,
+        .marker_manager = self.refs.marker_manager,
+        .mute_manager = self.refs.mute_manager,
+        .caption_style = self.refs.caption_style,
+    };
+}
+
+// Only applied edits are journaled and mark the project as unsaved, actions
+// that failed or referred to something that is gone left nothing to record
+const Applied = enum { applied, ignored, close };
+
+fn applyGuiAction(self: *App, now: *std.time.Instant, action: c.GuiAction, seek_position: *?f32) !Applied {
+    switch (action.tag) {
+        c.gui_action_toggle_pause => {
+            self.player_state.togglePause(now.*);
+        },
+        c.gui_action_none => {},
+        c.gui_action_close => {
+            return .close;
+        },
+        c.gui_action_seek => {
+            seek_position.* = action.data.seek_position;
+        },
+        c.gui_action_clip_edit => {
+            const clip = action.data.clip;
+            const old = self.refs.clip_manager.find(clip.id) orelse return .ignored;
+            self.refs.clip_manager.update(clip);
+            const updated = self.refs.clip_manager.find(clip.id).?;
+            const speed_changed = old.speed != updated.speed;
+            self.recordEdit(.{
+                .kind = if (speed_changed) c.gui_history_clip_speed else c.gui_history_clip_trim,
+                .position = .{ @min(clip.start, clip.end), @max(clip.start, clip.end) },
+                .target_id = clip.id,
+            });
+        },
+        c.gui_action_clip_remove => {
+            const clip = self.refs.clip_manager.clipForPts(action.data.seek_position) orelse return .ignored;
+            self.refs.clip_manager.remove(clip.id);
+            self.recordEdit(.{
+                .kind = c.gui_history_clip_remove,
+                .position = .{ clip.start, clip.end },
+                .target_id = clip.id,
+            });
+        },
+        c.gui_action_clip_move => {
+            const move = action.data.clip_move;
+            const clip = self.refs.clip_manager.find(move.id) orelse {
+                std.log.err("Cannot move clip {d}, it does not exist", .{move.id});
+                return .ignored;
+            };
+            self.refs.clip_manager.move(move.id, move.index);
+            self.recordEdit(.{
+                .kind = c.gui_history_clip_move,
+                .position = .{ clip.start, clip.end },
+                .target_id = clip.id,
+            });
+        },
+        c.gui_action_insert_freeze_frame => {
+            const freeze = action.data.freeze_frame;
+            if (!(freeze.duration > 0)) {
+                std.log.err("Freeze frames need a duration, got {d}", .{freeze.duration});
+                return .ignored;
+            }
+            const id = try self.refs.clip_manager.insertFreeze(freeze.position, freeze.duration);
+            self.recordEdit(.{
+                .kind = c.gui_history_freeze_frame,
+                .position = .{ freeze.position, freeze.position + freeze.duration },
+                .target_id = id,
+            });
+        },
+        c.gui_action_clip_add => {
+            const clip = action.data.clip;
+            try self.refs.clip_manager.add(clip);
+            self.recordEdit(.{
+                .kind = c.gui_history_clip_add,
+                .position = .{ @min(clip.start, clip.end), @max(clip.start, clip.end) },
+            });
+        },
+        c.gui_action_save => {
+            try Save.save(self.refs);
+            self.unsaved = false;
+            self.refs.journal.clear() catch |e| {
+                std.log.err("Failed to clear journal: {s}", .{@errorName(e)});
+            };
+        },
+        c.gui_action_flush_caches => {
+            self.refs.dec.frame_pool.trim();
+            // Seeking flushes the decoders' internal buffers and re-decodes
+            // the frame we're currently showing
+            if (seek_position.* == null) seek_position.* = self.last_pts;
+        },
+        c.gui_action_set_preview_quality => {
+            self.refs.dec.setPreviewQuality(previewQualityFromC(action.data.preview_quality));
+            if (self.program_dec) |dec| dec.setPreviewQuality(self.refs.dec.preview_quality);
+            self.program_pts = null;
+            // Re-decode the frame we're showing at the new resolution
+            if (seek_position.* == null) seek_position.* = self.last_pts;
+        },
+        c.gui_action_request_thumbnail => {
+            try self.refs.thumbnail_generator.request(action.data.thumbnail_request);
+        },
+        c.gui_action_cancel_job => {
+            self.refs.jobs.cancel(action.data.id);
+            self.refs.exporter.cancel(action.data.id);
+        },
+        c.gui_action_transcribe => {
+            const wtm = self.refs.wtm orelse {
+                std.log.err("Cannot transcribe, subtitle generation is disabled", .{});
+                return .ignored;
+            };
+
+            const request = action.data.transcribe;
+            const language = std.mem.sliceTo(&request.language, 0);
+            try wtm.retranscribe(.{
+                .model = transcriptionModelFromC(request.model),
+                .language = WordTimestampGenerator.Options.languageFromSlice(language),
+            });
+        },
+        c.gui_action_set_caption_style => {
+            self.refs.caption_style.* = action.data.caption_style;
+            self.recordEdit(.{ .kind = c.gui_history_caption_style });
+        },
+        c.gui_action_export_captions => {
+            // Nothing else depends on the export, no reason to bring the app down
+            exportCaptions(self.refs, captionFormatFromC(action.data.caption_format)) catch |e| {
+                std.log.err("Failed to export captions: {s}", .{@errorName(e)});
+                return .ignored;
+            };
+        },
+        c.gui_action_import_captions => {
+            const wtm = self.refs.wtm orelse {
+                std.log.err("Cannot import captions, subtitle generation is disabled", .{});
+                return .ignored;
+            };
+
+            // A bad file should not bring the app down
+            const path = std.mem.sliceTo(&action.data.import_request.path, 0);
+            wtm.importCaptions(path, self.refs.dec.duration) catch |e| {
+                std.log.err("Failed to import captions from {s}: {s}", .{ path, @errorName(e) });
+                return .ignored;
+            };
+            std.log.info("Imported captions from {s}", .{path});
+        },
+        c.gui_action_marker_add => {
+            const time = action.data.marker.time;
+            try self.refs.marker_manager.add(action.data.marker);
+            self.recordEdit(.{ .kind = c.gui_history_marker_add, .position = .{ time, time } });
+        },
+        c.gui_action_marker_remove => {
+            const marker = self.refs.marker_manager.find(action.data.id) orelse return .ignored;
+            self.refs.marker_manager.remove(marker.id);
+            self.recordEdit(.{
+                .kind = c.gui_history_marker_remove,
+                .position = .{ marker.time, marker.time },
+                .target_id = marker.id,
+            });
+        },
+        c.gui_action_mute_region_add => {
+            const region = action.data.mute_region;
+            const id = try self.refs.mute_manager.add(region);
+            self.recordEdit(.{
+                .kind = c.gui_history_mute_add,
+                .position = .{ @min(region.start, region.end), @max(region.start, region.end) },
+                .target_id = id,
+            });
+        },
+        c.gui_action_mute_region_edit => {
+            const region = action.data.mute_region;
+            if (self.refs.mute_manager.find(region.id) == null) return .ignored;
+            self.refs.mute_manager.update(region);
+            self.recordEdit(.{
+                .kind = c.gui_history_mute_trim,
+                .position = .{ @min(region.start, region.end), @max(region.start, region.end) },
+                .target_id = region.id,
+            });
+        },
+        c.gui_action_mute_region_remove => {
+            const region = self.refs.mute_manager.find(action.data.id) orelse return .ignored;
+            self.refs.mute_manager.remove(region.id);
+            self.recordEdit(.{
+                .kind = c.gui_history_mute_remove,
+                .position = .{ region.start, region.end },
+                .target_id = region.id,
+            });
+        },
+        c.gui_action_import_markers => {
+            const path = std.mem.sliceTo(&action.data.import_request.path, 0);
+            const num_imported = self.refs.marker_manager.importCsv(path) catch |e| {
+                std.log.err("Failed to import markers from {s}: {s}", .{ path, @errorName(e) });
+                return .ignored;
+            };
+            std.log.info("Imported {d} markers from {s}", .{ num_imported, path });
+            self.recordEdit(.{ .kind = c.gui_history_markers_imported });
+        },
+        c.gui_action_export_markers => {
+            exportMarkers(self.refs) catch |e| {
+                std.log.err("Failed to export markers: {s}", .{@errorName(e)});
+                return .ignored;
+            };
+        },
+        c.gui_action_export => {
+            const request = action.data.export_request;
+            self.refs.exporter.add(.{
+                .mode = exportModeFromC(request.mode),
+                .pattern = std.mem.sliceTo(&request.pattern, 0),
+                .input_path = self.refs.input_path,
+                .save_path = self.refs.save_path,
+                .clips = self.refs.clip_manager.clips.items,
+                .mute_regions = self.refs.mute_manager.regions.items,
+            }) catch |e| {
+                std.log.err("Failed to start export: {s}", .{@errorName(e)});
+                return .ignored;
+            };
+        },
+        c.gui_action_quick_export => {
+            const clip = self.refs.clip_manager.find(action.data.id) orelse return .ignored;
+            self.refs.exporter.add(.{
+                .mode = .clips,
+                .pattern = "{project}_{start}",
+                .input_path = self.refs.input_path,
+                .save_path = self.refs.save_path,
+                .clips = &.{clip},
+                .mute_regions = self.refs.mute_manager.regions.items,
+                .dir = std.posix.getenv("TMPDIR") orelse "/tmp",
+                .quick = true,
+            }) catch |e| {
+                std.log.err("Failed to start quick export: {s}", .{@errorName(e)});
+                return .ignored;
+            };
+        },
+        c.gui_action_cancel_exports => {
+            self.refs.exporter.cancelAll();
+        },
+        c.gui_action_pause_exports => {
+            self.refs.exporter.setPaused(action.data.enabled);
+        },
+        c.gui_action_set_speed => {
+            const speed = std.math.clamp(action.data.speed, -max_speed, max_speed);
+            if (speed == 0 or std.math.isNan(speed)) {
+                self.player_state.pause(now.*);
+                self.player_state.setSpeed(now.*, self.last_pts, 1.0);
+            } else {
+                self.player_state.setSpeed(now.*, self.last_pts, speed);
+                self.player_state.play(now.*);
+            }
+        },
+        c.gui_action_reload => {
+            // The file may well be halfway through being written, that should
+            // not bring the app down
+            switch (action.data.reload_target) {
+                c.gui_reload_source => self.reloadSource(now) catch |e| {
+                    std.log.err("Failed to reload {s}: {s}", .{ self.refs.input_path, @errorName(e) });
+                },
+                c.gui_reload_project => self.reloadProject() catch |e| {
+                    std.log.err("Failed to reload {s}: {s}", .{ self.refs.save_path, @errorName(e) });
+                },
+                else => std.log.err("Unknown reload target: {d}", .{action.data.reload_target}),
+            }
+        },
+        c.gui_action_replay_journal => {
+            self.replay = self.refs.journal.loadRecovered() catch |e| {
+                std.log.err("Failed to load journal: {s}", .{@errorName(e)});
+                return .ignored;
+            };
+        },
+        c.gui_action_discard_journal => {
+            self.refs.journal.discardRecovered();
+        },
+        c.gui_action_revert_history => {
+            self.refs.history.revert(action.data.id, self.historyRefs()) catch |e| {
+                std.log.err("Failed to revert to edit {d}: {s}", .{ action.data.id, @errorName(e) });
+                return .ignored;
+            };
+            self.journalRestore();
+        },
+        c.gui_action_set_dual_preview => {
+            self.dual_preview = action.data.enabled;
+            self.program_pts = null;
+            if (!self.dual_preview) {
+                self.refs.frame_renderer.setProgramContent(.source);
+            }
+        },
+        c.gui_action_step_frames => {
+            self.player_state.pause(now.*);
+            const frame_duration = self.refs.dec.frameDuration(self.stream_id);
+            const frames: f32 = @floatFromInt(action.data.frames);
+            // Seeks land on the first frame at or after the target, so aim
+            // half a frame short of where we want to end up. Steps that come
+            // in together pile onto the pending seek, which is already short
+            const target = if (seek_position.*) |pending|
+                pending + frames * frame_duration
+            else
+                self.last_pts + (frames - 0.5) * frame_duration;
+            seek_position.* = @max(target, 0.0);
+        },
+        else => {
+            std.debug.panic("invalid action: {d}", .{action.tag});
+        },
+    }
+
+    return .ignored;
+}
+
+fn reloadSource(self: *App, now: *std.time.Instant) !void {
+    const old_dec = self.refs.dec;
+    self.refs.dec = try self.openDecoder();
+    self.refs.dec.setPreviewQuality(old_dec.preview_quality);
+    old_dec.frame_pool.trim();
+
+    // Opened again from the new file the next time it is needed
+    if (self.program_dec) |dec| dec.frame_pool.trim();
+    self.program_dec = null;
+    self.program_pts = null;
+
+    // Back to where we were, or the end if the file got shorter
+    try self.seekToPts(now, @min(self.last_pts, self.refs.dec.duration));
+
+    // Stale waveforms and thumbnails are not worth failing the reload over
+    self.refs.audio_renderer.reload(self.refs.input_path) catch |e| {
+        std.log.err("Failed to reload waveform: {s}", .{@errorName(e)});
+    };
+    self.refs.thumbnail_generator.reload(self.refs.input_path) catch |e| {
+        std.log.err("Failed to reload thumbnails: {s}", .{@errorName(e)});
+    };
+
+    std.log.info("Reloaded {s}", .{self.refs.input_path});
+}
+
+// The transcript stays, it belongs to the source rather than the edit
+fn reloadProject(self: *App) !void {
+    const alloc = self.refs.alloc;
+
+    var save_data = Save.load(alloc, self.refs.save_path);
+    defer save_data.deinit();
+
+    // Load errors are logged and treated as an empty save, which would throw
+    // away everything we have
+    if (save_data.data == null) return error.InvalidSave;
+
+    var clip_manager = try ClipManager.init(alloc, save_data.clips());
+    errdefer clip_manager.deinit();
+
+    var marker_manager = try MarkerManager.init(alloc, save_data.markers());
+    errdefer marker_manager.deinit();
+
+    const mute_manager = try MuteManager.init(alloc, save_data.muteRegions());
+
+    self.refs.clip_manager.deinit();
+    self.refs.clip_manager.* = clip_manager;
+    self.refs.marker_manager.deinit();
+    self.refs.marker_manager.* = marker_manager;
+    self.refs.mute_manager.deinit();
+    self.refs.mute_manager.* = mute_manager;
+    self.refs.caption_style.* = save_data.captionStyle();
+
+    // Edits since the last save were just thrown away
+    try self.refs.journal.clear();
+    try self.refs.history.reset(self.historyRefs());
+    self.unsaved = false;
+
+    std.log.info("Reloaded {s}", .{self.refs.save_path});
+}
+
+fn exportCaptions(refs: AppRefs, format: captions_mod.Format) !void {
+    const wtm = refs.wtm orelse {
+        std.log.err("Cannot export captions, subtitle generation is disabled", .{});
+        return;
+    };
+
+    const path = try captions_mod.exportPath(refs.alloc, refs.save_path, format.extension());
+    defer refs.alloc.free(path);
+
+    try wtm.exportCaptions(path, refs.caption_style.*, format);
+    std.log.info("Exported captions to {s}", .{path});
+}
+
+fn exportMarkers(refs: AppRefs) !void {
+    const path = try captions_mod.exportPath(refs.alloc, refs.save_path, ".markers.csv");
+    defer refs.alloc.free(path);
+
+    try refs.marker_manager.exportCsv(path);
+    std.log.info("Exported markers to {s}", .{path});
+}
+
+fn captionFormatFromC(format: c.enum_GuiCaptionFormat) captions_mod.Format {
+    return switch (format) {
+        c.gui_caption_format_webvtt => .webvtt,
+        else => .srt,
+    };
+}
+
+fn exportModeFromC(mode: c.enum_GuiExportMode) Exporter.Mode {
+    return switch (mode) {
+        c.gui_export_mode_clips => .clips,
+        else => .sequence,
+    };
+}
+
+fn transcriptionModelFromC(model: c.enum_GuiTranscriptionModel) WordTimestampGenerator.Model {
+    return switch (model) {
+        c.gui_transcription_model_tiny => .tiny,
+        c.gui_transcription_model_base => .base,
+        c.gui_transcription_model_small => .small,
+        c.gui_transcription_model_medium => .medium,
+        c.gui_transcription_model_large => .large,
+        else => .builtin,
+    };
+}
+
+fn previewQualityFromC(quality: c.enum_GuiPreviewQuality) decoder.PreviewQuality {
+    return switch (quality) {
+        c.gui_preview_quality_half => .half,
+        c.gui_preview_quality_quarter => .quarter,
+        else => .full,
+    };
+}
+
+fn previewQualityToC(quality: decoder.PreviewQuality) c.enum_GuiPreviewQuality {
+    return switch (quality) {
+        .full => c.gui_preview_quality_full,
+        .half => c.gui_preview_quality_half,
+        .quarter => c.gui_preview_quality_quarter,
+    };
+}
+
 fn setEndOfVideo(self: *App, now: std.time.Instant) void {
     self.player_state.pause(now);
     self.last_pts = self.refs.dec.duration;
-    c.gui_notify_update(self.refs.gui);
 }
 
 fn seekToPts(self: *App, now: *std.time.Instant, pts: f32) !void {
@@ -149,17 +667,27 @@ fn seekToPts(self: *App, now: *std.time.Instant, pts: f32) !void {
     now.* = try std.time.Instant.now();
     self.player_state.seek(now.*, self.last_pts);
     self.refs.frame_renderer.swapFrame(img);
-    c.gui_notify_update(self.refs.gui);
+    c.gui_notify_update_kind(self.refs.gui, c.gui_update_frame);
 }
 
 fn updateVideoFrame(self: *App, now: *std.time.Instant) !void {
+    if (self.player_state.speed < 0) {
+        return self.updateVideoFrameReverse(now);
+    }
+
     const clip_for_pts = self.refs.clip_manager.clipForPts(self.last_pts);
+    const clip_speed = if (clip_for_pts) |cl| cl.speed else 1.0;
+    self.player_state.setClipSpeed(now.*, self.last_pts, clip_speed);
+
+    // Audio cannot keep up with anything but normal speed, so it is left out
+    const audio_sink = if (self.player_state.rate() == 1.0) self.refs.audioSink() else null;
 
     while (self.player_state.shouldUpdateFrame(now.*, self.last_pts)) {
-        var new_img = try getNextVideoFrame(self.refs.dec, self.refs.audio_player, self.stream_id) orelse {
+        var new_img = try getNextVideoFrame(self.refs.dec, audio_sink, self.stream_id) orelse {
             self.setEndOfVideo(now.*);
             break;
         };
+        self.decode_stats.frameDecoded(now.*);
 
         if (clip_for_pts) |cl| {
             if (new_img.pts > cl.end) {
@@ -171,15 +699,125 @@ fn updateVideoFrame(self: *App, now: *std.time.Instant) !void {
                     self.player_state.pause(now.*);
                 }
 
-                c.gui_notify_update(self.refs.gui);
                 break;
             }
+
+            // The picture stays on the frame the clip started with, only the
+            // audio moves on
+            if (cl.freeze) {
+                self.last_pts = new_img.pts;
+                new_img.deinit();
+                continue;
+            }
         }
 
         self.last_pts = new_img.pts;
         self.refs.frame_renderer.swapFrame(new_img);
-        c.gui_notify_update(self.refs.gui);
+        c.gui_notify_update_kind(self.refs.gui, c.gui_update_frame);
+    }
+}
+
+// Keeps the program monitor in sync with the playhead. Inside a clip the
+// program is the source, in a cut out section it is the start of the next
+// clip, which only needs decoding again when that clip changes
+fn updateProgramFrame(self: *App) !void {
+    if (!self.dual_preview) return;
+
+    if (self.refs.clip_manager.clipForPts(self.last_pts) != null) {
+        if (self.program_pts != null) {
+            self.refs.frame_renderer.setProgramContent(.source);
+            self.program_pts = null;
+        }
+        return;
+    }
+
+    const next_clip = self.refs.clip_manager.clipAfter(self.last_pts) orelse {
+        self.refs.frame_renderer.setProgramContent(.blank);
+        self.program_pts = null;
+        return;
+    };
+
+    if (self.program_pts == next_clip.start) return;
+
+    const dec = try self.programDecoder();
+    try dec.seek(next_clip.start, self.stream_id);
+
+    var img = try getNextVideoFrame(dec, null, self.stream_id) orelse {
+        self.refs.frame_renderer.setProgramContent(.blank);
+        self.program_pts = next_clip.start;
+        return;
+    };
+
+    while (img.pts < next_clip.start) {
+        const new_img = try getNextVideoFrame(dec, null, self.stream_id) orelse break;
+        img.deinit();
+        img = new_img;
     }
+
+    self.refs.frame_renderer.swapProgramFrame(img);
+    self.program_pts = next_clip.start;
+    c.gui_notify_update_kind(self.refs.gui, c.gui_update_frame);
+}
+
+fn programDecoder(self: *App) !*decoder.VideoDecoder {
+    if (self.program_dec) |dec| return dec;
+
+    const dec = try self.openDecoder();
+    dec.setPreviewQuality(self.refs.dec.preview_quality);
+    self.program_dec = dec;
+    return dec;
+}
+
+// Opens the source again, alongside the decoders already open
+fn openDecoder(self: *App) !*decoder.VideoDecoder {
+    const alloc = self.refs.alloc;
+    {
+        var new_dec = try decoder.VideoDecoder.init(alloc, self.refs.input_path);
+        errdefer new_dec.deinit();
+        try self.refs.decoders.append(alloc, new_dec);
+    }
+
+    return &self.refs.decoders.at(self.refs.decoders.count() - 1).*.?;
+}
+
+// Decoders a reload replaced are freed once the last of their frames is
+// released, which can take a while if the GUI or the audio player still hold
+// some
+fn freeUnusedDecoders(self: *App) void {
+    var it = self.refs.decoders.iterator(0);
+    while (it.next()) |slot| {
+        const dec = if (slot.*) |*d| d else continue;
+        const in_use = dec == self.refs.dec or
+            (self.program_dec != null and self.program_dec.? == dec) or
+            dec.frame_pool.inUse();
+        if (in_use) continue;
+
+        dec.deinit();
+        slot.* = null;
+    }
+}
+
+// The decoder only goes forwards, so playing backwards is a series of seeks.
+// Clips are ignored, this is for hunting down frames rather than watching the
+// edit
+fn updateVideoFrameReverse(self: *App, now: *std.time.Instant) !void {
+    self.player_state.setClipSpeed(now.*, self.last_pts, 1.0);
+
+    if (!self.player_state.shouldUpdateFrame(now.*, self.last_pts)) {
+        return;
+    }
+
+    const pts = self.player_state.currentPts(now.*) orelse return;
+    if (pts <= 0) {
+        try self.seekToPts(now, 0);
+        // Nothing left to play backwards, the next play should go forwards
+        self.player_state.pause(now.*);
+        self.player_state.setSpeed(now.*, self.last_pts, 1.0);
+        return;
+    }
+
+    try self.seekToPts(now, pts);
+    self.decode_stats.frameDecoded(now.*);
 }
 
 fn updateAppState(self: *App) !void {
@@ -194,14 +832,44 @@ fn updateAppState(self: *App) !void {
     var text_split_indices: []const u64 = &.{};
     if (self.refs.wtm) |wtm| text_split_indices = wtm.shared.split_indices.items;
 
-    try self.refs.app_state.setSnapshot(.{
+    // Workers take the job lock while holding their own, so this has to come
+    // after the wtm lock
+    self.refs.jobs.mutex.lock();
+    defer self.refs.jobs.mutex.unlock();
+
+    self.decode_stats.update(try std.time.Instant.now());
+    const memory_usage = self.refs.dec.frame_pool.memoryUsage();
+
+    const changes = try self.refs.app_state.setSnapshot(.{
         .paused = self.player_state.isPaused(),
+        .playback_speed = self.player_state.speed,
         .current_position = self.last_pts,
         .total_runtime = self.refs.dec.duration,
+        .frame_duration = self.refs.dec.frameDuration(self.stream_id),
+        .decode_fps = self.decode_stats.fps,
+        .frame_cache_bytes = memory_usage.video_bytes,
+        .audio_cache_bytes = memory_usage.audio_bytes,
+        .preview_quality = previewQualityToC(self.refs.dec.preview_quality),
+        .jobs = self.refs.jobs.jobs.items,
+        .caption_style = self.refs.caption_style.*,
         .clips = self.refs.clip_manager.clips.items,
+        .markers = self.refs.marker_manager.markers.items,
+        .mute_regions = self.refs.mute_manager.regions.items,
         .text = text,
         .text_split_indices = text_split_indices,
+        .input_path = self.refs.input_path,
+        .save_path = self.refs.save_path,
+        .num_recoverable_edits = self.refs.journal.num_recovered,
+        .history = self.refs.history.items(),
+        .dual_preview = self.dual_preview,
+        .unsaved_changes = self.unsaved,
     });
+
+    // Only wake the GUI up if there's something new to draw, otherwise we end
+    // up repainting at the rate of our update loop even when idle
+    if (changes != 0) {
+        c.gui_notify_update_kind(self.refs.gui, changes);
+    }
 }
 
 fn sleepUntilNextFrame(self: *App) !void {
@@ -224,59 +892,164 @@ pub const AppState = struct {
     snapshot: Snapshot,
 
     const Snapshot = struct {
+        generation: u64 = 0,
         paused: bool,
+        playback_speed: f32,
         current_position: f32,
         total_runtime: f32,
+        frame_duration: f32,
+        decode_fps: f32,
+        frame_cache_bytes: u64,
+        audio_cache_bytes: u64,
+        preview_quality: c.enum_GuiPreviewQuality,
+        jobs: []const c.GuiJob,
+        caption_style: c.GuiCaptionStyle,
         clips: []const c.Clip,
+        markers: []const c.GuiMarker,
+        mute_regions: []const c.GuiMuteRegion,
         text: []const u8,
         text_split_indices: []const u64,
+        // Point at the command line, which outlives the GUI, so they are
+        // shared rather than copied
+        input_path: [:0]const u8,
+        save_path: [:0]const u8,
+        num_recoverable_edits: u64,
+        history: []const c.GuiHistoryEntry,
+        dual_preview: bool,
+        unsaved_changes: bool,
 
         fn clone(self: *const @This(), alloc: Allocator) !Snapshot {
+            const new_jobs = try alloc.dupe(c.GuiJob, self.jobs);
+            errdefer alloc.free(new_jobs);
+
             const new_clips = try alloc.dupe(c.Clip, self.clips);
             errdefer alloc.free(new_clips);
 
+            const new_markers = try alloc.dupe(c.GuiMarker, self.markers);
+            errdefer alloc.free(new_markers);
+
+            const new_mute_regions = try alloc.dupe(c.GuiMuteRegion, self.mute_regions);
+            errdefer alloc.free(new_mute_regions);
+
             const new_text = try alloc.dupe(u8, self.text);
             errdefer alloc.free(new_text);
 
             const text_split_indices = try alloc.dupe(u64, self.text_split_indices);
             errdefer alloc.free(text_split_indices);
 
+            const new_history = try alloc.dupe(c.GuiHistoryEntry, self.history);
+            errdefer alloc.free(new_history);
+
             var ret = self.*;
+            ret.jobs = new_jobs;
             ret.clips = new_clips;
+            ret.markers = new_markers;
+            ret.mute_regions = new_mute_regions;
             ret.text = new_text;
             ret.text_split_indices = text_split_indices;
+            ret.history = new_history;
             return ret;
         }
 
+        fn stateEql(self: *const @This(), other: *const @This()) bool {
+            return self.paused == other.paused and
+                self.playback_speed == other.playback_speed and
+                self.current_position == other.current_position and
+                self.total_runtime == other.total_runtime and
+                self.frame_duration == other.frame_duration and
+                self.decode_fps == other.decode_fps and
+                self.frame_cache_bytes == other.frame_cache_bytes and
+                self.audio_cache_bytes == other.audio_cache_bytes and
+                self.preview_quality == other.preview_quality and
+                std.mem.eql(u8, std.mem.sliceAsBytes(self.jobs), std.mem.sliceAsBytes(other.jobs)) and
+                std.mem.eql(u8, std.mem.asBytes(&self.caption_style), std.mem.asBytes(&other.caption_style)) and
+                std.mem.eql(u8, std.mem.sliceAsBytes(self.clips), std.mem.sliceAsBytes(other.clips)) and
+                std.mem.eql(u8, std.mem.sliceAsBytes(self.markers), std.mem.sliceAsBytes(other.markers)) and
+                std.mem.eql(u8, std.mem.sliceAsBytes(self.mute_regions), std.mem.sliceAsBytes(other.mute_regions)) and
+                std.mem.eql(u8, self.input_path, other.input_path) and
+                std.mem.eql(u8, self.save_path, other.save_path) and
+                self.num_recoverable_edits == other.num_recoverable_edits and
+                std.mem.eql(u8, std.mem.sliceAsBytes(self.history), std.mem.sliceAsBytes(other.history)) and
+                self.dual_preview == other.dual_preview and
+                self.unsaved_changes == other.unsaved_changes;
+        }
+
+        fn transcriptEql(self: *const @This(), other: *const @This()) bool {
+            return std.mem.eql(u8, self.text, other.text) and
+                std.mem.eql(u64, self.text_split_indices, other.text_split_indices);
+        }
+
         fn toCRepr(self: *@This()) c.AppStateSnapshot {
             return .{
+                .generation = self.generation,
                 .paused = self.paused,
+                .playback_speed = self.playback_speed,
                 .current_position = self.current_position,
                 .total_runtime = self.total_runtime,
+                .frame_duration = self.frame_duration,
+                .decode_fps = self.decode_fps,
+                .frame_cache_bytes = self.frame_cache_bytes,
+                .audio_cache_bytes = self.audio_cache_bytes,
+                .preview_quality = self.preview_quality,
+                .jobs = self.jobs.ptr,
+                .num_jobs = self.jobs.len,
+                .caption_style = self.caption_style,
                 .clips = self.clips.ptr,
                 .num_clips = self.clips.len,
+                .markers = self.markers.ptr,
+                .num_markers = self.markers.len,
+                .mute_regions = self.mute_regions.ptr,
+                .num_mute_regions = self.mute_regions.len,
                 .text = self.text.ptr,
                 .text_len = self.text.len,
                 .text_split_indices = self.text_split_indices.ptr,
                 .text_split_indices_len = self.text_split_indices.len,
+                .input_path = self.input_path.ptr,
+                .save_path = self.save_path.ptr,
+                .num_recoverable_edits = self.num_recoverable_edits,
+                .history = self.history.ptr,
+                .num_history = self.history.len,
+                .dual_preview = self.dual_preview,
+                .unsaved_changes = self.unsaved_changes,
             };
         }
 
         fn fromCRepr(c_repr: c.AppStateSnapshot) Snapshot {
             return .{
+                .generation = c_repr.generation,
                 .paused = c_repr.paused,
+                .playback_speed = c_repr.playback_speed,
                 .current_position = c_repr.current_position,
                 .total_runtime = c_repr.total_runtime,
+                .frame_duration = c_repr.frame_duration,
+                .decode_fps = c_repr.decode_fps,
+                .frame_cache_bytes = c_repr.frame_cache_bytes,
+                .audio_cache_bytes = c_repr.audio_cache_bytes,
+                .preview_quality = c_repr.preview_quality,
+                .jobs = c_repr.jobs[0..c_repr.num_jobs],
+                .caption_style = c_repr.caption_style,
                 .clips = c_repr.clips[0..c_repr.num_clips],
+                .markers = c_repr.markers[0..c_repr.num_markers],
+                .mute_regions = c_repr.mute_regions[0..c_repr.num_mute_regions],
                 .text = c_repr.text[0..c_repr.text_len],
                 .text_split_indices = c_repr.text_split_indices[0..c_repr.text_split_indices_len],
+                .input_path = std.mem.span(c_repr.input_path),
+                .save_path = std.mem.span(c_repr.save_path),
+                .num_recoverable_edits = c_repr.num_recoverable_edits,
+                .history = c_repr.history[0..c_repr.num_history],
+                .dual_preview = c_repr.dual_preview,
+                .unsaved_changes = c_repr.unsaved_changes,
             };
         }
 
         fn deinit(self: *@This(), alloc: Allocator) void {
+            alloc.free(self.jobs);
             alloc.free(self.clips);
+            alloc.free(self.markers);
+            alloc.free(self.mute_regions);
             alloc.free(self.text);
             alloc.free(self.text_split_indices);
+            alloc.free(self.history);
         }
     };
 
@@ -286,22 +1059,49 @@ pub const AppState = struct {
             .alloc = alloc,
             .snapshot = .{
                 .paused = false,
+                .playback_speed = 1.0,
                 .current_position = 0.0,
                 .total_runtime = 0.0,
+                .frame_duration = 0.0,
+                .decode_fps = 0.0,
+                .frame_cache_bytes = 0,
+                .audio_cache_bytes = 0,
+                .preview_quality = c.gui_preview_quality_full,
+                .jobs = &.{},
+                .caption_style = captions_mod.default_style,
                 .clips = &.{},
+                .markers = &.{},
+                .mute_regions = &.{},
                 .text = &.{},
                 .text_split_indices = &.{},
+                .input_path = "",
+                .save_path = "",
+                .num_recoverable_edits = 0,
+                .history = &.{},
+                .dual_preview = false,
+                .unsaved_changes = false,
             },
         };
     }
 
-    pub fn setSnapshot(self: *AppState, snapshot: Snapshot) !void {
+    // Returns a GuiUpdateKind mask of what differs from the previous snapshot
+    pub fn setSnapshot(self: *AppState, snapshot: Snapshot) !c.enum_GuiUpdateKind {
         self.mutex.lock();
         defer self.mutex.unlock();
 
-        const new_snapshot = try snapshot.clone(self.alloc);
+        var changes: c.enum_GuiUpdateKind = 0;
+        if (!snapshot.stateEql(&self.snapshot)) changes |= c.gui_update_state;
+        if (!snapshot.transcriptEql(&self.snapshot)) changes |= c.gui_update_transcript;
+
+        if (changes == 0) {
+            return 0;
+        }
+
+        var new_snapshot = try snapshot.clone(self.alloc);
+        new_snapshot.generation = self.snapshot.generation + 1;
         self.snapshot.deinit(self.alloc);
         self.snapshot = new_snapshot;
+        return changes;
     }
 
     pub fn deinit(self: *AppState) void {
@@ -331,13 +1131,24 @@ pub export fn appstate_deinit(state: *AppState, c_repr: *const c.AppStateSnapsho
 const PlayerState = struct {
     start_time: std.time.Instant,
     pause_time: ?std.time.Instant,
+    // Wall clock time, the position is rate() * (time since start - this)
     time_adjustment_ns: i64,
+    // What the user asked for
+    speed: f32,
+    // Speed of the clip being played, on top of the user's
+    clip_speed: f32,
+
+    // How far the position has to move back before playing backwards seeks
+    // to a new frame. Every step is a seek, so they cannot come too often
+    const reverse_step_s = 0.1;
 
     fn init(now: std.time.Instant) PlayerState {
         return .{
             .start_time = now,
             .pause_time = null,
             .time_adjustment_ns = 0,
+            .speed = 1.0,
+            .clip_speed = 1.0,
         };
     }
 
@@ -361,13 +1172,42 @@ const PlayerState = struct {
 
     fn seek(self: *PlayerState, now: std.time.Instant, pts: f32) void {
         self.start_time = now;
-        const seek_pos_ns: i64 = @intFromFloat(pts * 1e9);
+        const seek_pos_ns: i64 = @intFromFloat(pts * 1e9 / self.rate());
         self.time_adjustment_ns = -seek_pos_ns;
         if (self.pause_time) |_| {
             self.pause_time = now;
         }
     }
 
+    // Restarts the clock from pts so that the position does not jump
+    fn setSpeed(self: *PlayerState, now: std.time.Instant, pts: f32, speed: f32) void {
+        self.speed = speed;
+        self.seek(now, pts);
+    }
+
+    fn setClipSpeed(self: *PlayerState, now: std.time.Instant, pts: f32, clip_speed: f32) void {
+        if (self.clip_speed == clip_speed) {
+            return;
+        }
+        self.clip_speed = clip_speed;
+        self.seek(now, pts);
+    }
+
+    fn rate(self: *const PlayerState) f32 {
+        return self.speed * self.clip_speed;
+    }
+
+    // Where playback should be right now, null if paused
+    fn currentPts(self: *const PlayerState, now: std.time.Instant) ?f32 {
+        if (self.pause_time != null) {
+            return null;
+        }
+
+        const time_since_start_ns: i64 = @intCast(now.since(self.start_time));
+        const time_since_start_adjusted: f32 = @floatFromInt(time_since_start_ns - self.time_adjustment_ns);
+        return time_since_start_adjusted * self.rate() / 1e9;
+    }
+
     fn isPaused(self: *const PlayerState) bool {
         return self.pause_time != null;
     }
@@ -392,14 +1232,51 @@ const PlayerState = struct {
             return null;
         }
 
+        // Backwards, the next frame is the one a step before the current one
+        const next_pts = if (self.speed < 0) frame_pts - reverse_step_s else frame_pts;
+
         const time_since_start_ns: i64 = @intCast(now.since(self.start_time));
         const time_since_start_adjusted: i64 = time_since_start_ns - self.time_adjustment_ns;
-        const frame_pts_ns: i64 = @intFromFloat(frame_pts * 1e9);
-        return frame_pts_ns - time_since_start_adjusted;
+        const next_pts_ns: i64 = @intFromFloat(next_pts * 1e9 / self.rate());
+        return next_pts_ns - time_since_start_adjusted;
     }
 };
 
-fn getNextVideoFrame(dec: *decoder.VideoDecoder, audio_player: ?*audio.Player, stream_id: ?usize) !?decoder.VideoFrame {
+// Counts frames decoded for playback over a sliding one second window
+const DecodeStats = struct {
+    window_start: std.time.Instant,
+    frames_in_window: u32,
+    fps: f32,
+
+    const window_ns = std.time.ns_per_s;
+
+    fn init(now: std.time.Instant) DecodeStats {
+        return .{
+            .window_start = now,
+            .frames_in_window = 0,
+            .fps = 0.0,
+        };
+    }
+
+    fn frameDecoded(self: *DecodeStats, now: std.time.Instant) void {
+        self.update(now);
+        self.frames_in_window += 1;
+    }
+
+    fn update(self: *DecodeStats, now: std.time.Instant) void {
+        const elapsed_ns = now.since(self.window_start);
+        if (elapsed_ns < window_ns) {
+            return;
+        }
+
+        const elapsed_s = @as(f32, @floatFromInt(elapsed_ns)) / std.time.ns_per_s;
+        self.fps = @as(f32, @floatFromInt(self.frames_in_window)) / elapsed_s;
+        self.frames_in_window = 0;
+        self.window_start = now;
+    }
+};
+
+fn getNextVideoFrame(dec: *decoder.VideoDecoder, audio_sink: ?AudioSink, stream_id: ?usize) !?decoder.VideoFrame {
     while (true) {
         var frame = try dec.next(null);
         if (frame == null) {
@@ -408,8 +1285,9 @@ fn getNextVideoFrame(dec: *decoder.VideoDecoder, audio_player: ?*audio.Player, s
 
         switch (frame.?) {
             .audio => |*af| {
-                if (audio_player) |p| {
-                    p.pushFrame(af.*) catch {
+                if (audio_sink) |sink| {
+                    sink.mute_manager.apply(af);
+                    sink.player.pushFrame(af.*) catch {
                         std.log.err("Audio thread falling behind, dropping frame", .{});
                         af.deinit();
                     };
@@ -437,7 +1315,10 @@ pub const Save = struct {
     data: ?save_mod.Data,
 
     const clip_key = "clips";
+    const marker_key = "markers";
+    const mute_key = "mute_regions";
     const wtm_key = "script_generator";
+    const caption_style_key = "caption_style";
 
     pub fn load(alloc: Allocator, path: []const u8) Save {
         if (save_mod.Data.load(alloc, path)) |sd| {
@@ -459,10 +1340,34 @@ pub const Save = struct {
         return self.getField(clip_key);
     }
 
+    pub fn markers(self: *Save) ?save_mod.Data.Field {
+        return self.getField(marker_key);
+    }
+
+    pub fn muteRegions(self: *Save) ?save_mod.Data.Field {
+        return self.getField(mute_key);
+    }
+
     pub fn wordTimestampMap(self: *Save) ?save_mod.Data.Field {
         return self.getField(wtm_key);
     }
 
+    // Falls back to the default style for saves from before captions could
+    // be styled
+    pub fn captionStyle(self: *Save) c.GuiCaptionStyle {
+        const field = self.getField(caption_style_key) orelse {
+            return captions_mod.default_style;
+        };
+
+        const loaded = field.as(c.GuiCaptionStyle) catch |e| {
+            std.log.err("Failed to load caption style, using the default: {any}", .{e});
+            return captions_mod.default_style;
+        };
+        defer loaded.deinit();
+
+        return loaded.value;
+    }
+
     fn getField(self: *Save, key: []const u8) ?save_mod.Data.Field {
         if (self.data == null) {
             return null;
@@ -475,6 +1380,8 @@ pub const Save = struct {
         var save_writer = try save_mod.Writer.init(refs.save_path);
 
         try refs.clip_manager.serialize(try save_writer.field(clip_key));
+        try refs.marker_manager.serialize(try save_writer.field(marker_key));
+        try refs.mute_manager.serialize(try save_writer.field(mute_key));
 
         const wtm_field = try save_writer.field(wtm_key);
         if (refs.wtm) |wtm| {
@@ -483,6 +1390,9 @@ pub const Save = struct {
             try wtm_field.write(null);
         }
 
+        const caption_style_field = try save_writer.field(caption_style_key);
+        try caption_style_field.write(refs.caption_style.*);
+
         try save_writer.finish();
     }
 };
@@ -4,6 +4,7 @@ const Allocator = std.mem.Allocator;
 const audio = @import("audio.zig");
 const c = @import("c.zig");
 const gl_helpers = @import("gl_helpers.zig");
+const JobTracker = @import("JobTracker.zig");
 
 pub export fn audiorenderer_render(self: *AudioRenderer, guigl: ?*anyopaque, zoom: f32, center_norm: f32) void {
     self.render(guigl, zoom, center_norm);
@@ -17,6 +18,35 @@ pub export fn audiorenderer_deinit_gl(self: *AudioRenderer, guigl: ?*anyopaque)
     self.deinitGl(guigl);
 }
 
+pub export fn audiorenderer_get_samples(self: *AudioRenderer, offset: u64, out: [*c]c.GuiWaveformSample, capacity: u64, total: *u64) u64 {
+    self.shared.mutex.lock();
+    defer self.shared.mutex.unlock();
+
+    total.* = self.shared.num_samples;
+
+    const samples = self.shared.samples.items;
+    const start: usize = @intCast(offset);
+    if (start >= samples.len) {
+        return 0;
+    }
+
+    const num_copied: usize = @intCast(@min(samples.len - start, capacity));
+    for (samples[start .. start + num_copied], 0..) |sample, i| {
+        out[i] = .{
+            .min = sample.min,
+            .max = sample.max,
+        };
+    }
+    return num_copied;
+}
+
+pub export fn audiorenderer_generation(self: *AudioRenderer) u64 {
+    self.shared.mutex.lock();
+    defer self.shared.mutex.unlock();
+
+    return self.shared.generation;
+}
+
 const AudioRenderer = @This();
 
 const Sample = struct {
@@ -30,8 +60,10 @@ const InitThread = struct {
     alloc: Allocator,
     path: [:0]const u8,
     shared: *InitData,
+    jobs: *JobTracker,
+    job_id: u64,
 
-    fn init(alloc: Allocator, path: [:0]const u8, shared: *InitData) !*InitThread {
+    fn init(alloc: Allocator, path: [:0]const u8, shared: *InitData, jobs: *JobTracker, job_id: u64) !*InitThread {
         const ret = try alloc.create(InitThread);
         errdefer alloc.destroy(ret);
 
@@ -43,6 +75,8 @@ const InitThread = struct {
             .alloc = alloc,
             .path = path_copy,
             .shared = shared,
+            .jobs = jobs,
+            .job_id = job_id,
         };
 
         return ret;
@@ -56,6 +90,21 @@ const InitThread = struct {
     fn run_and_consume(self: *InitThread) !void {
         defer self.deinit();
 
+        self.run() catch |e| {
+            self.jobs.finish(self.job_id, c.gui_job_state_failed);
+            return e;
+        };
+
+        // Cancelling the job and shutting down both go through the shutdown
+        // flag
+        if (self.shared.shutdown.load(std.builtin.AtomicOrder.unordered)) {
+            self.jobs.finish(self.job_id, c.gui_job_state_cancelled);
+        } else {
+            self.jobs.finish(self.job_id, c.gui_job_state_finished);
+        }
+    }
+
+    fn run(self: *InitThread) !void {
         const target_memory_usage_bytes = 3 * 1024 * 1024;
         var dec = try decoder.VideoDecoder.init(self.alloc, self.path);
         defer dec.deinit();
@@ -86,6 +135,8 @@ const InitThread = struct {
         };
 
         var num_samples_collected: usize = 0;
+        const expected_input_samples = @as(f32, @floatFromInt(stream_info.sample_rate)) * dec.duration;
+        self.jobs.setProgress(self.job_id, 0.0);
 
         while (!self.shared.shutdown.load(std.builtin.AtomicOrder.unordered)) {
             var frame = try dec.next(stream_info.stream_id) orelse {
@@ -120,6 +171,8 @@ const InitThread = struct {
                             };
                         }
                     }
+
+                    self.jobs.setProgress(self.job_id, @as(f32, @floatFromInt(num_samples_collected)) / expected_input_samples);
                 },
                 .video => {
                     continue;
@@ -165,6 +218,8 @@ const InitData = struct {
     mutex: std.Thread.Mutex,
     samples: std.ArrayList(Sample),
     num_samples: usize,
+    // Bumped every time samples starts over
+    generation: u64,
     // No mutex lock necessary
     shutdown: std.atomic.Value(bool),
 
@@ -174,11 +229,14 @@ const InitData = struct {
 };
 
 alloc: Allocator,
+jobs: *JobTracker,
 // Owned, but needs stable memory location for init thread
 shared: *InitData,
-init_thread_handle: std.Thread,
+// Null if restarting the init thread on reload failed
+init_thread_handle: ?std.Thread,
 program: c.GLuint = 0,
 last_buf_len: usize,
+last_generation: u64,
 vbo: c.GLuint,
 vao: c.GLuint,
 
@@ -199,11 +257,12 @@ pub fn initGl(self: *AudioRenderer, guigl: ?*anyopaque) void {
 fn updateVertexBuffer(self: *AudioRenderer, guigl: ?*c.GuiGl) void {
     self.shared.mutex.lock();
     defer self.shared.mutex.unlock();
-    if (self.last_buf_len == self.shared.samples.items.len) {
+    if (self.last_buf_len == self.shared.samples.items.len and self.last_generation == self.shared.generation) {
         return;
     }
 
     self.last_buf_len = self.shared.samples.items.len;
+    self.last_generation = self.shared.generation;
 
     var vertices = self.alloc.alloc(f32, self.shared.samples.items.len * 4) catch {
         std.log.err("Failed to allocate vertex buffer for audio data", .{});
@@ -248,7 +307,7 @@ pub fn render(self: *AudioRenderer, guigl: ?*anyopaque, zoom: f32, center_norm:
     c.guigl_draw_arrays(guigl, c.GL_LINES, 0, @intCast(self.last_buf_len * 2));
 }
 
-pub fn init(alloc: Allocator, path: [:0]const u8) !AudioRenderer {
+pub fn init(alloc: Allocator, path: [:0]const u8, jobs: *JobTracker) !AudioRenderer {
     const shared = try alloc.create(InitData);
     errdefer shared.deinit();
 
@@ -256,28 +315,60 @@ pub fn init(alloc: Allocator, path: [:0]const u8) !AudioRenderer {
         .mutex = .{},
         .samples = std.ArrayList(Sample).init(alloc),
         .num_samples = 1,
+        .generation = 0,
         .shutdown = std.atomic.Value(bool).init(false),
     };
 
-    const init_thread = try InitThread.init(alloc, path, shared);
-    const init_thread_handle = std.Thread.spawn(.{}, InitThread.run_and_consume, .{init_thread}) catch |e| {
-        init_thread.deinit();
-        return e;
-    };
-
-    return .{
+    var ret = AudioRenderer{
         .alloc = alloc,
-        .init_thread_handle = init_thread_handle,
+        .jobs = jobs,
+        .init_thread_handle = null,
         .shared = shared,
         .vbo = 0,
         .vao = 0,
         .last_buf_len = 0,
+        .last_generation = 0,
     };
+    try ret.startInitThread(path);
+    return ret;
 }
 
-pub fn deinit(self: *AudioRenderer) void {
+// Loads the waveform again, e.g. after the file changed on disk. Called from
+// the App thread while the GUI keeps rendering whatever is in shared
+pub fn reload(self: *AudioRenderer, path: [:0]const u8) !void {
+    self.stopInitThread();
+
+    {
+        self.shared.mutex.lock();
+        defer self.shared.mutex.unlock();
+        self.shared.samples.clearRetainingCapacity();
+        self.shared.num_samples = 1;
+        self.shared.generation += 1;
+    }
+    self.shared.shutdown.store(false, std.builtin.AtomicOrder.unordered);
+
+    try self.startInitThread(path);
+}
+
+fn startInitThread(self: *AudioRenderer, path: [:0]const u8) !void {
+    const job_id = try self.jobs.add(c.gui_job_kind_waveform, &self.shared.shutdown);
+    errdefer self.jobs.finish(job_id, c.gui_job_state_failed);
+
+    const init_thread = try InitThread.init(self.alloc, path, self.shared, self.jobs, job_id);
+    self.init_thread_handle = std.Thread.spawn(.{}, InitThread.run_and_consume, .{init_thread}) catch |e| {
+        init_thread.deinit();
+        return e;
+    };
+}
+
+fn stopInitThread(self: *AudioRenderer) void {
     self.shared.shutdown.store(true, std.builtin.AtomicOrder.unordered);
-    self.init_thread_handle.join();
+    if (self.init_thread_handle) |handle| handle.join();
+    self.init_thread_handle = null;
+}
+
+pub fn deinit(self: *AudioRenderer) void {
+    self.stopInitThread();
     self.shared.deinit();
     self.alloc.destroy(self.shared);
 }
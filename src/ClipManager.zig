@@ -8,11 +8,26 @@ const Allocator = std.mem.Allocator;
 
 const ClipList = std.ArrayList(c.Clip);
 
+// In output order. Saves from before clips could be reordered were sorted by
+// start, which is the order they played in back then
 clips: ClipList,
 clip_id: usize,
 
 const ClipManager = @This();
 
+pub const min_speed = 0.1;
+pub const max_speed = 16.0;
+
+// c.Clip as it is saved. Saves from before clips had a speed played them at
+// normal speed
+const SavedClip = struct {
+    id: u64,
+    start: f32,
+    end: f32,
+    speed: f32 = 1.0,
+    freeze: bool = false,
+};
+
 fn maxClipId(clips: []const c.Clip) usize {
     var ret: usize = 0;
 
@@ -26,10 +41,19 @@ fn maxClipId(clips: []const c.Clip) usize {
 pub fn init(alloc: Allocator, init_data: ?save.Data.Field) !ClipManager {
     var clips = ClipList.init(alloc);
     if (init_data) |id| {
-        const loaded = try id.as([]const c.Clip);
+        const loaded = try id.as([]const SavedClip);
         defer loaded.deinit();
 
-        try clips.appendSlice(loaded.value);
+        try clips.ensureTotalCapacity(loaded.value.len);
+        for (loaded.value) |saved| {
+            clips.appendAssumeCapacity(.{
+                .id = saved.id,
+                .start = saved.start,
+                .end = saved.end,
+                .speed = sanitizeSpeed(saved.speed),
+                .freeze = saved.freeze,
+            });
+        }
     }
 
     const clip_id = maxClipId(clips.items) + 1;
@@ -45,13 +69,6 @@ pub fn deinit(self: *ClipManager) void {
 }
 
 pub fn serialize(self: *ClipManager, writer: anytype) !void {
-    const lessThanWithContext = struct {
-        fn f(_: void, lhs: c.Clip, rhs: c.Clip) bool {
-            return lessThan(lhs, rhs);
-        }
-    }.f;
-
-    std.mem.sort(c.Clip, self.clips.items, {}, lessThanWithContext);
     try writer.write(self.clips.items);
 }
 
@@ -67,6 +84,12 @@ fn writeClip(clip: c.Clip, output: anytype) !void {
     try output.objectField("end");
     try output.write(clip.end);
 
+    try output.objectField("speed");
+    try output.write(clip.speed);
+
+    try output.objectField("freeze");
+    try output.write(clip.freeze);
+
     try output.endObject();
 }
 
@@ -74,6 +97,8 @@ pub fn update(self: *ClipManager, clip: c.Clip) void {
     if (self.findClipIdx(clip.id)) |i| {
         self.clips.items[i].start = @min(clip.start, clip.end);
         self.clips.items[i].end = @max(clip.start, clip.end);
+        self.clips.items[i].speed = sanitizeSpeed(clip.speed);
+        self.clips.items[i].freeze = clip.freeze;
     }
 }
 
@@ -81,54 +106,114 @@ pub fn add(self: *ClipManager, clip_in: c.Clip) !void {
     defer self.clip_id += 1;
     var clip = clip_in;
     clip.id = self.clip_id;
+    clip.speed = sanitizeSpeed(clip.speed);
     if (clip.start > clip.end) {
         std.mem.swap(f32, &clip.start, &clip.end);
     }
-    try self.clips.append(clip);
+
+    // After the last clip that comes before it in the source, so that a
+    // sequence nobody reordered stays in source order
+    var idx: usize = 0;
+    for (self.clips.items, 0..) |item, i| {
+        if (lessThan(item, clip)) idx = i + 1;
+    }
+    try self.clips.insert(idx, clip);
 }
 
 pub fn remove(self: *ClipManager, id: usize) void {
     if (self.findClipIdx(id)) |i| {
-        _ = self.clips.swapRemove(i);
+        _ = self.clips.orderedRemove(i);
     }
 }
 
+// See GuiClipMove
+pub fn move(self: *ClipManager, id: usize, index: usize) void {
+    const from = self.findClipIdx(id) orelse return;
+    const clip = self.clips.orderedRemove(from);
+    const to = @min(index, self.clips.items.len);
+    // Cannot fail, the clip was just taken out
+    self.clips.insertAssumeCapacity(to, clip);
+}
+
 pub fn clipForPts(self: *ClipManager, pts: f32) ?c.Clip {
+    const idx = self.clipIdxForPts(pts) orelse return null;
+    return self.clips.items[idx];
+}
+
+// Splits the clip under position so that the next duration seconds of it are
+// a freeze frame. The freeze frame stops at the end of that clip rather than
+// running into the source of the next one. Without a clip there, or with only
+// the end of one, the freeze frame is added on its own. Returns the id of the
+// freeze frame
+pub fn insertFreeze(self: *ClipManager, position: f32, duration: f32) !usize {
+    var freeze = c.Clip{
+        .id = self.clip_id,
+        .start = position,
+        .end = position + duration,
+        .speed = 1.0,
+        .freeze = true,
+    };
+
+    const idx = self.clipIdxForPts(position) orelse self.clips.items.len;
+    // A playhead on the end of a clip has nothing of it left to freeze
+    if (idx == self.clips.items.len or position >= self.clips.items[idx].end) {
+        try self.add(freeze);
+        return freeze.id;
+    }
+
+    try self.clips.ensureUnusedCapacity(2);
+    const clip = self.clips.items[idx];
+    if (freeze.end > clip.end) {
+        std.log.info("Freeze frame cut short to {d:.2}s to stay inside its clip", .{clip.end - position});
+        freeze.end = clip.end;
+    }
+
+    self.clip_id += 1;
+    self.clips.insertAssumeCapacity(idx + 1, freeze);
+
+    if (freeze.end < clip.end) {
+        var rest = clip;
+        rest.id = self.clip_id;
+        rest.start = freeze.end;
+        self.clip_id += 1;
+        self.clips.insertAssumeCapacity(idx + 2, rest);
+    }
+
+    if (position > clip.start) {
+        self.clips.items[idx].end = position;
+    } else {
+        _ = self.clips.orderedRemove(idx);
+    }
+
+    return freeze.id;
+}
+
+// The earliest clip that starts after pts
+pub fn clipAfter(self: *ClipManager, pts: f32) ?c.Clip {
+    var ret: ?c.Clip = null;
     for (self.clips.items) |clip| {
-        if (pts >= clip.start and pts <= clip.end) {
-            return clip;
-        }
+        if (clip.start <= pts) continue;
+        if (ret == null or clip.start < ret.?.start) ret = clip;
     }
 
-    return null;
+    return ret;
 }
 
+pub fn find(self: *ClipManager, id: usize) ?c.Clip {
+    const idx = self.findClipIdx(id) orelse return null;
+    return self.clips.items[idx];
+}
+
+// The clip that plays after clip_id in the output sequence
 pub fn nextClip(self: *ClipManager, clip_id: usize) ?c.Clip {
     const clip_idx = self.findClipIdx(clip_id) orelse {
         return null;
     };
 
-    const clip = self.clips.items[clip_idx];
-    var next_clip: ?c.Clip = null;
-    for (self.clips.items) |item| {
-        if (item.id == clip.id) {
-            continue;
-        }
-
-        if (lessThan(item, clip)) {
-            continue;
-        }
-
-        if (next_clip == null) {
-            next_clip = item;
-            continue;
-        }
-
-        if (lessThan(item, next_clip.?)) {
-            next_clip = item;
-        }
+    if (clip_idx + 1 >= self.clips.items.len) {
+        return null;
     }
-    return next_clip;
+    return self.clips.items[clip_idx + 1];
 }
 
 fn firstClip(self: *ClipManager) ?c.Clip {
@@ -146,6 +231,14 @@ fn firstClip(self: *ClipManager) ?c.Clip {
     return ret;
 }
 
+// A zeroed clip plays at normal speed
+fn sanitizeSpeed(speed: f32) f32 {
+    if (speed <= 0 or std.math.isNan(speed)) {
+        return 1.0;
+    }
+    return std.math.clamp(speed, min_speed, max_speed);
+}
+
 fn lessThan(a: c.Clip, b: c.Clip) bool {
     if (a.start != b.start) {
         return a.start < b.start;
@@ -158,6 +251,21 @@ fn lessThan(a: c.Clip, b: c.Clip) bool {
     return a.id < b.id;
 }
 
+// Touching clips share an edge, the one that starts there plays it
+fn clipIdxForPts(self: *ClipManager, pts: f32) ?usize {
+    var ret: ?usize = null;
+    for (self.clips.items, 0..) |clip, i| {
+        if (pts >= clip.start and pts < clip.end) {
+            return i;
+        }
+        if (ret == null and pts == clip.end) {
+            ret = i;
+        }
+    }
+
+    return ret;
+}
+
 fn findClipIdx(self: *ClipManager, id: usize) ?usize {
     var item_idx: ?usize = null;
     for (0..self.clips.items.len) |i| {
@@ -169,3 +277,44 @@ fn findClipIdx(self: *ClipManager, id: usize) ?usize {
 
     return item_idx;
 }
+
+fn testClip(start: f32, end: f32) c.Clip {
+    return .{ .id = 0, .start = start, .end = end, .speed = 1.0, .freeze = false };
+}
+
+fn expectSpans(manager: *const ClipManager, expected: []const [2]f32) !void {
+    try std.testing.expectEqual(expected.len, manager.clips.items.len);
+    for (manager.clips.items, expected) |clip, span| {
+        try std.testing.expectEqual(span[0], clip.start);
+        try std.testing.expectEqual(span[1], clip.end);
+    }
+}
+
+test "freeze frame stops at the end of its clip" {
+    var manager = try ClipManager.init(std.testing.allocator, null);
+    defer manager.deinit();
+
+    try manager.add(testClip(0.0, 2.0));
+    try manager.add(testClip(2.0, 4.0));
+
+    const id = try manager.insertFreeze(1.5, 1.0);
+    try expectSpans(&manager, &.{ .{ 0.0, 1.5 }, .{ 1.5, 2.0 }, .{ 2.0, 4.0 } });
+    try std.testing.expect(manager.find(id).?.freeze);
+}
+
+test "freeze frame on a clip boundary" {
+    var manager = try ClipManager.init(std.testing.allocator, null);
+    defer manager.deinit();
+
+    try manager.add(testClip(0.0, 2.0));
+    try manager.add(testClip(2.0, 4.0));
+
+    // Between two touching clips, the one that starts there is split
+    _ = try manager.insertFreeze(2.0, 1.0);
+    try expectSpans(&manager, &.{ .{ 0.0, 2.0 }, .{ 2.0, 3.0 }, .{ 3.0, 4.0 } });
+
+    // On the end of the last clip, nothing is left to split
+    const id = try manager.insertFreeze(4.0, 1.0);
+    try expectSpans(&manager, &.{ .{ 0.0, 2.0 }, .{ 2.0, 3.0 }, .{ 3.0, 4.0 }, .{ 4.0, 5.0 } });
+    try std.testing.expect(manager.find(id).?.freeze);
+}
@@ -0,0 +1,248 @@
+// Every edit made since the project was opened, each with a copy of the
+// clips, markers and caption style it left behind, so that any of them can be
+// gone back to
+
+const std = @import("std");
+const c = @import("c.zig");
+const ClipManager = @import("ClipManager.zig");
+const MarkerManager = @import("MarkerManager.zig");
+const MuteManager = @import("MuteManager.zig");
+const Journal = @import("Journal.zig");
+
+const Allocator = std.mem.Allocator;
+
+alloc: Allocator,
+entries: std.ArrayList(Entry),
+// Kept next to entries so that snapshots can point straight at them
+gui_entries: std.ArrayList(c.GuiHistoryEntry),
+next_id: u64,
+
+const EditHistory = @This();
+
+// A few copies of the clip list per entry, this is plenty for an editing
+// session and stays in the megabytes
+const max_entries = 1000;
+
+// Dragging a clip edge or a style slider sends an edit per frame, those are
+// folded into one entry as long as they keep coming
+const merge_window_ms = 1000;
+
+pub const Edit = struct {
+    kind: c.enum_GuiHistoryKind,
+    position: ?[2]f32 = null,
+    // Clip or marker the edit was made to, so that merged edits stay on the
+    // same one
+    target_id: u64 = 0,
+};
+
+pub const Refs = struct {
+    clip_manager: *ClipManager,
+    marker_manager: *MarkerManager,
+    mute_manager: *MuteManager,
+    caption_style: *c.GuiCaptionStyle,
+};
+
+const State = struct {
+    clips: []c.Clip,
+    markers: []c.GuiMarker,
+    mute_regions: []c.GuiMuteRegion,
+    caption_style: c.GuiCaptionStyle,
+
+    fn init(alloc: Allocator, refs: Refs) !State {
+        const clips = try alloc.dupe(c.Clip, refs.clip_manager.clips.items);
+        errdefer alloc.free(clips);
+
+        const markers = try alloc.dupe(c.GuiMarker, refs.marker_manager.markers.items);
+        errdefer alloc.free(markers);
+
+        const mute_regions = try alloc.dupe(c.GuiMuteRegion, refs.mute_manager.regions.items);
+        errdefer alloc.free(mute_regions);
+
+        return .{
+            .clips = clips,
+            .markers = markers,
+            .mute_regions = mute_regions,
+            .caption_style = refs.caption_style.*,
+        };
+    }
+
+    fn deinit(self: *State, alloc: Allocator) void {
+        alloc.free(self.clips);
+        alloc.free(self.markers);
+        alloc.free(self.mute_regions);
+    }
+};
+
+const Entry = struct {
+    target_id: u64,
+    state: State,
+};
+
+pub fn init(alloc: Allocator, refs: Refs) !EditHistory {
+    var ret = EditHistory{
+        .alloc = alloc,
+        .entries = std.ArrayList(Entry).init(alloc),
+        .gui_entries = std.ArrayList(c.GuiHistoryEntry).init(alloc),
+        .next_id = 1,
+    };
+    errdefer ret.deinit();
+
+    try ret.record(.{ .kind = c.gui_history_opened }, refs);
+    return ret;
+}
+
+pub fn deinit(self: *EditHistory) void {
+    for (self.entries.items) |*entry| {
+        entry.state.deinit(self.alloc);
+    }
+    self.entries.deinit();
+    self.gui_entries.deinit();
+}
+
+pub fn items(self: *const EditHistory) []const c.GuiHistoryEntry {
+    return self.gui_entries.items;
+}
+
+// Starts over from what refs hold now, e.g. after the project was reloaded
+pub fn reset(self: *EditHistory, refs: Refs) !void {
+    for (self.entries.items) |*entry| {
+        entry.state.deinit(self.alloc);
+    }
+    self.entries.clearRetainingCapacity();
+    self.gui_entries.clearRetainingCapacity();
+
+    try self.record(.{ .kind = c.gui_history_opened }, refs);
+}
+
+// Called after the edit has been applied to refs
+pub fn record(self: *EditHistory, edit: Edit, refs: Refs) !void {
+    const now = std.time.milliTimestamp();
+
+    var state = try State.init(self.alloc, refs);
+    errdefer state.deinit(self.alloc);
+
+    if (self.mergeTarget(edit, now)) |i| {
+        const entry = &self.entries.items[i];
+        entry.state.deinit(self.alloc);
+        entry.state = state;
+
+        const gui_entry = &self.gui_entries.items[i];
+        gui_entry.time_ms = now;
+        setPosition(gui_entry, edit.position);
+        return;
+    }
+
+    var gui_entry = c.GuiHistoryEntry{
+        .id = self.next_id,
+        .kind = edit.kind,
+        .time_ms = now,
+        .has_position = false,
+        .start = 0,
+        .end = 0,
+        .revert_target = 0,
+    };
+    setPosition(&gui_entry, edit.position);
+
+    try self.entries.ensureUnusedCapacity(1);
+    try self.gui_entries.ensureUnusedCapacity(1);
+    self.entries.appendAssumeCapacity(.{
+        .target_id = edit.target_id,
+        .state = state,
+    });
+    self.gui_entries.appendAssumeCapacity(gui_entry);
+    self.next_id += 1;
+
+    if (self.entries.items.len > max_entries) {
+        var oldest = self.entries.orderedRemove(0);
+        oldest.state.deinit(self.alloc);
+        _ = self.gui_entries.orderedRemove(0);
+    }
+}
+
+// Puts refs back to how they were after the entry with the given id, and
+// records that as an edit of its own
+pub fn revert(self: *EditHistory, id: u64, refs: Refs) !void {
+    const idx = for (self.gui_entries.items, 0..) |entry, i| {
+        if (entry.id == id) break i;
+    } else return error.UnknownEntry;
+
+    const target = self.entries.items[idx].state;
+    const target_gui = self.gui_entries.items[idx];
+
+    // Ids keep counting up from where the managers are, so that clips and
+    // markers made after the entry do not share ids with the restored ones
+    try putBack(.{
+        .clips = target.clips,
+        .markers = target.markers,
+        .mute_regions = target.mute_regions,
+        .caption_style = target.caption_style,
+    }, refs);
+
+    try self.record(.{
+        .kind = c.gui_history_revert,
+        .position = if (target_gui.has_position) .{ target_gui.start, target_gui.end } else null,
+    }, refs);
+    self.gui_entries.items[self.gui_entries.items.len - 1].revert_target = id;
+}
+
+// A revert replayed from the journal. The entry it went back to belonged to
+// the last run's history, so it is recorded as a revert to nothing in this one
+pub fn restore(self: *EditHistory, state: Journal.State, refs: Refs) !void {
+    try putBack(state, refs);
+
+    // The managers may not have counted as far as the restored ids yet
+    for (state.clips) |clip| {
+        refs.clip_manager.clip_id = @max(refs.clip_manager.clip_id, clip.id + 1);
+    }
+    for (state.markers) |marker| {
+        refs.marker_manager.marker_id = @max(refs.marker_manager.marker_id, marker.id + 1);
+    }
+    for (state.mute_regions) |region| {
+        refs.mute_manager.region_id = @max(refs.mute_manager.region_id, region.id + 1);
+    }
+
+    try self.record(.{ .kind = c.gui_history_revert }, refs);
+}
+
+fn putBack(state: Journal.State, refs: Refs) !void {
+    try refs.clip_manager.clips.ensureTotalCapacity(state.clips.len);
+    try refs.marker_manager.markers.ensureTotalCapacity(state.markers.len);
+    try refs.mute_manager.regions.ensureTotalCapacity(state.mute_regions.len);
+
+    refs.clip_manager.clips.clearRetainingCapacity();
+    refs.clip_manager.clips.appendSliceAssumeCapacity(state.clips);
+    refs.marker_manager.markers.clearRetainingCapacity();
+    refs.marker_manager.markers.appendSliceAssumeCapacity(state.markers);
+    refs.mute_manager.regions.clearRetainingCapacity();
+    refs.mute_manager.regions.appendSliceAssumeCapacity(state.mute_regions);
+    refs.caption_style.* = state.caption_style;
+}
+
+fn mergeTarget(self: *EditHistory, edit: Edit, now: i64) ?usize {
+    switch (edit.kind) {
+        c.gui_history_clip_trim, c.gui_history_clip_speed, c.gui_history_mute_trim, c.gui_history_caption_style => {},
+        else => return null,
+    }
+
+    if (self.entries.items.len == 0) return null;
+    const last = self.entries.items.len - 1;
+    const last_gui = self.gui_entries.items[last];
+
+    if (last_gui.kind != edit.kind) return null;
+    if (self.entries.items[last].target_id != edit.target_id) return null;
+    if (now - last_gui.time_ms > merge_window_ms) return null;
+
+    return last;
+}
+
+fn setPosition(entry: *c.GuiHistoryEntry, position: ?[2]f32) void {
+    if (position) |pos| {
+        entry.has_position = true;
+        entry.start = pos[0];
+        entry.end = pos[1];
+    } else {
+        entry.has_position = false;
+        entry.start = 0;
+        entry.end = 0;
+    }
+}
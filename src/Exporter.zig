@@ -0,0 +1,430 @@
+// Renders the edit with ffmpeg on a background thread, one job per output
+// file. The command line is built when the export is requested, so edits made
+// while it waits in the queue do not change what it renders. The filter graph
+// matches the one run_ffmpeg_on_save.py builds from a save.
+//
+// Progress comes from ffmpeg's -progress output. Pausing stops the ffmpeg
+// process, which also holds up the exports queued behind it
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const c = @import("c.zig");
+const JobTracker = @import("JobTracker.zig");
+
+const Exporter = @This();
+
+pub const Mode = enum {
+    sequence,
+    clips,
+};
+
+// Slices only have to stay valid for the duration of add
+pub const Request = struct {
+    mode: Mode,
+    // See GuiExportRequest.pattern
+    pattern: []const u8,
+    input_path: []const u8,
+    save_path: []const u8,
+    // In output order
+    clips: []const c.Clip,
+    mute_regions: []const c.GuiMuteRegion,
+    // Where the files go, next to the save file if null
+    dir: ?[]const u8 = null,
+    // Passed on to the result, see GuiExportResult
+    quick: bool = false,
+};
+
+const Export = struct {
+    job_id: u64,
+    // Owns everything below
+    arena: std.heap.ArenaAllocator,
+    argv: []const []const u8,
+    output_path: [:0]const u8,
+    // Of the output, in seconds
+    duration: f32,
+    // Handed to the job tracker, set when the job is cancelled
+    cancel: *std.atomic.Value(bool),
+    quick: bool,
+
+    fn deinit(self: *Export) void {
+        self.arena.deinit();
+    }
+};
+
+const Shared = struct {
+    mutex: std.Thread.Mutex = .{},
+    cond: std.Thread.Condition = .{},
+    // Oldest first
+    queue: std.ArrayList(Export),
+    running: ?Running = null,
+    // Only while something is running
+    paused: bool = false,
+    shutdown: bool = false,
+};
+
+// Taken off the queue by the worker
+const Running = struct {
+    job_id: u64,
+    // Null until ffmpeg is started
+    pid: ?std.process.Child.Id = null,
+};
+
+alloc: Allocator,
+// Owned, but needs a stable location for the worker thread
+shared: *Shared,
+jobs: *JobTracker,
+thread: std.Thread,
+
+pub fn init(alloc: Allocator, gui: ?*c.Gui, jobs: *JobTracker) !Exporter {
+    const shared = try alloc.create(Shared);
+    errdefer alloc.destroy(shared);
+
+    shared.* = .{
+        .queue = std.ArrayList(Export).init(alloc),
+    };
+    errdefer shared.queue.deinit();
+
+    const thread = try std.Thread.spawn(.{}, run, .{ alloc, shared, gui, jobs });
+
+    return .{
+        .alloc = alloc,
+        .shared = shared,
+        .jobs = jobs,
+        .thread = thread,
+    };
+}
+
+// Exports that have not finished yet are cancelled
+pub fn deinit(self: *Exporter) void {
+    {
+        self.shared.mutex.lock();
+        defer self.shared.mutex.unlock();
+        self.shared.shutdown = true;
+        if (self.shared.running != null) {
+            self.stopRunning();
+        }
+    }
+    self.shared.cond.signal();
+    self.thread.join();
+
+    for (self.shared.queue.items) |*item| {
+        self.jobs.finish(item.job_id, c.gui_job_state_cancelled);
+        item.deinit();
+    }
+    self.shared.queue.deinit();
+    self.alloc.destroy(self.shared);
+}
+
+pub fn add(self: *Exporter, request: Request) !void {
+    if (request.clips.len == 0) {
+        return error.NoClips;
+    }
+
+    switch (request.mode) {
+        .sequence => try self.addOne(request, request.clips, 1),
+        .clips => for (request.clips, 1..) |clip, n| {
+            try self.addOne(request, &.{clip}, n);
+        },
+    }
+}
+
+// Has to be called for every cancelled job, exports or not. The job tracker
+// only sets the cancel flag, which ffmpeg knows nothing about
+pub fn cancel(self: *Exporter, job_id: u64) void {
+    self.shared.mutex.lock();
+    defer self.shared.mutex.unlock();
+
+    for (self.shared.queue.items, 0..) |item, i| {
+        if (item.job_id != job_id) continue;
+
+        var removed = self.shared.queue.orderedRemove(i);
+        self.jobs.finish(removed.job_id, c.gui_job_state_cancelled);
+        removed.deinit();
+        return;
+    }
+
+    if (self.shared.running) |running| {
+        if (running.job_id == job_id) self.stopRunning();
+    }
+}
+
+pub fn cancelAll(self: *Exporter) void {
+    self.shared.mutex.lock();
+    defer self.shared.mutex.unlock();
+
+    for (self.shared.queue.items) |*item| {
+        self.jobs.finish(item.job_id, c.gui_job_state_cancelled);
+        item.deinit();
+    }
+    self.shared.queue.clearRetainingCapacity();
+
+    if (self.shared.running != null) {
+        self.stopRunning();
+    }
+}
+
+pub fn setPaused(self: *Exporter, paused: bool) void {
+    self.shared.mutex.lock();
+    defer self.shared.mutex.unlock();
+
+    const running = self.shared.running orelse return;
+    const pid = running.pid orelse return;
+    if (self.shared.paused == paused) return;
+
+    const sig: u8 = if (paused) std.posix.SIG.STOP else std.posix.SIG.CONT;
+    std.posix.kill(pid, sig) catch |e| {
+        std.log.err("Failed to pause export: {s}", .{@errorName(e)});
+        return;
+    };
+    self.shared.paused = paused;
+    self.jobs.setPaused(running.job_id, paused);
+}
+
+// Sets the cancel flag, which the worker checks before it starts ffmpeg and
+// once ffmpeg is gone. Has to be called with the mutex held
+fn stopRunning(self: *Exporter) void {
+    const running = self.shared.running.?;
+    self.jobs.cancel(running.job_id);
+    if (running.pid) |pid| stop(pid);
+}
+
+// n and the start of the first clip fill in the pattern
+fn addOne(self: *Exporter, request: Request, clips: []const c.Clip, n: usize) !void {
+    var arena = std.heap.ArenaAllocator.init(self.alloc);
+    errdefer arena.deinit();
+    const arena_alloc = arena.allocator();
+
+    const dir = request.dir orelse std.fs.path.dirname(request.save_path) orelse ".";
+    const output_path = try outputPath(arena_alloc, dir, request.save_path, request.pattern, n, clips[0].start);
+    const argv = try ffmpegArgs(arena_alloc, request.input_path, clips, request.mute_regions, output_path);
+
+    var duration: f32 = 0;
+    for (clips) |clip| {
+        duration += (clip.end - clip.start) / clip.speed;
+    }
+
+    const cancel_flag = try arena_alloc.create(std.atomic.Value(bool));
+    cancel_flag.* = std.atomic.Value(bool).init(false);
+
+    const job_id = try self.jobs.add(c.gui_job_kind_export, cancel_flag);
+    errdefer self.jobs.finish(job_id, c.gui_job_state_failed);
+
+    {
+        self.shared.mutex.lock();
+        defer self.shared.mutex.unlock();
+        try self.shared.queue.append(.{
+            .job_id = job_id,
+            .arena = arena,
+            .argv = argv,
+            .output_path = output_path,
+            .duration = duration,
+            .cancel = cancel_flag,
+            .quick = request.quick,
+        });
+    }
+    self.shared.cond.signal();
+}
+
+fn run(alloc: Allocator, shared: *Shared, gui: ?*c.Gui, jobs: *JobTracker) void {
+    while (true) {
+        var item = blk: {
+            shared.mutex.lock();
+            defer shared.mutex.unlock();
+
+            while (shared.queue.items.len == 0 and !shared.shutdown) {
+                shared.cond.wait(&shared.mutex);
+            }
+
+            if (shared.shutdown) {
+                return;
+            }
+
+            const item = shared.queue.orderedRemove(0);
+            shared.running = .{ .job_id = item.job_id };
+            break :blk item;
+        };
+        defer item.deinit();
+
+        jobs.setProgress(item.job_id, 0.0);
+        const state = render(alloc, shared, jobs, &item) catch |e| blk: {
+            std.log.err("Failed to export {s}: {s}", .{ item.output_path, @errorName(e) });
+            break :blk c.gui_job_state_failed;
+        };
+
+        {
+            shared.mutex.lock();
+            defer shared.mutex.unlock();
+            shared.running = null;
+            shared.paused = false;
+        }
+        jobs.finish(item.job_id, state);
+
+        const event_tag: c.enum_GuiEventTag = switch (state) {
+            c.gui_job_state_finished => c.gui_event_export_finished,
+            c.gui_job_state_failed => c.gui_event_export_failed,
+            else => continue,
+        };
+        if (state == c.gui_job_state_finished) {
+            std.log.info("Exported {s}", .{item.output_path});
+        }
+
+        const event = c.GuiEvent{
+            .tag = event_tag,
+            .data = .{ .export_result = .{
+                .job_id = item.job_id,
+                .path = item.output_path.ptr,
+                .quick = item.quick,
+            } },
+        };
+        c.gui_push_event(gui, &event);
+    }
+}
+
+fn render(alloc: Allocator, shared: *Shared, jobs: *JobTracker, item: *const Export) !c.enum_GuiJobState {
+    var child = std.process.Child.init(item.argv, alloc);
+    child.stdin_behavior = .Ignore;
+    child.stdout_behavior = .Pipe;
+    try child.spawn();
+
+    {
+        shared.mutex.lock();
+        defer shared.mutex.unlock();
+        shared.running.?.pid = child.id;
+        // Cancelled or shut down between the queue and the spawn
+        if (shared.shutdown or item.cancel.load(std.builtin.AtomicOrder.unordered)) {
+            stop(child.id);
+        }
+    }
+
+    // key=value lines, a block of them about twice a second
+    var line_buf: [256]u8 = undefined;
+    const reader = child.stdout.?.reader();
+    while (reader.readUntilDelimiterOrEof(&line_buf, '\n') catch null) |line| {
+        const prefix = "out_time_us=";
+        if (!std.mem.startsWith(u8, line, prefix) or item.duration <= 0) continue;
+
+        // N/A until the first frame is out
+        const out_time_us = std.fmt.parseInt(i64, line[prefix.len..], 10) catch continue;
+        jobs.setProgress(item.job_id, @as(f32, @floatFromInt(out_time_us)) / 1e6 / item.duration);
+    }
+
+    const term = try child.wait();
+
+    if (item.cancel.load(std.builtin.AtomicOrder.unordered)) {
+        return c.gui_job_state_cancelled;
+    }
+
+    switch (term) {
+        .Exited => |code| if (code == 0) return c.gui_job_state_finished,
+        else => {},
+    }
+    std.log.err("ffmpeg exited with {any}", .{term});
+    return c.gui_job_state_failed;
+}
+
+// See GuiExportRequest.pattern
+fn outputPath(alloc: Allocator, dir: []const u8, save_path: []const u8, pattern: []const u8, n: usize, start: f32) ![:0]const u8 {
+    var name = std.ArrayList(u8).init(alloc);
+    const writer = name.writer();
+
+    var rest = pattern;
+    while (rest.len > 0) {
+        if (std.mem.startsWith(u8, rest, "{project}")) {
+            try writer.writeAll(std.fs.path.stem(save_path));
+            rest = rest["{project}".len..];
+        } else if (std.mem.startsWith(u8, rest, "{n}")) {
+            try writer.print("{d}", .{n});
+            rest = rest["{n}".len..];
+        } else if (std.mem.startsWith(u8, rest, "{start}")) {
+            try writer.print("{d:.2}", .{start});
+            rest = rest["{start}".len..];
+        } else {
+            try writer.writeByte(rest[0]);
+            rest = rest[1..];
+        }
+    }
+    try writer.writeAll(".mkv");
+
+    return std.fs.path.joinZ(alloc, &.{ dir, name.items });
+}
+
+// A paused ffmpeg only sees the TERM once it runs again
+fn stop(pid: std.process.Child.Id) void {
+    std.posix.kill(pid, std.posix.SIG.TERM) catch {};
+    std.posix.kill(pid, std.posix.SIG.CONT) catch {};
+}
+
+// Plays clips back to back into output_path
+fn ffmpegArgs(alloc: Allocator, input_path: []const u8, clips: []const c.Clip, mute_regions: []const c.GuiMuteRegion, output_path: []const u8) ![]const []const u8 {
+    var filter = std.ArrayList(u8).init(alloc);
+    const writer = filter.writer();
+
+    for (clips, 0..) |clip, i| {
+        if (clip.freeze) {
+            // First frame only, cloned for as long as the audio runs
+            try writer.print("[0:v]trim=start={d},trim=end_frame=1,setpts=PTS-STARTPTS,tpad=stop_mode=clone:stop_duration={d}[{d}v];", .{ clip.start, (clip.end - clip.start) / clip.speed, i });
+        } else {
+            try writer.print("[0:v]trim=start={d}:end={d},setpts=(PTS-STARTPTS)/{d}[{d}v];", .{ clip.start, clip.end, clip.speed, i });
+        }
+
+        try writer.writeAll("[0:a]");
+        // Before the trim, so that the times are the source's
+        try writeMuteFilter(writer, mute_regions);
+        try writer.print("atrim=start={d}:end={d},asetpts=PTS-STARTPTS", .{ clip.start, clip.end });
+        try writeAtempoFilters(writer, clip.speed);
+        try writer.print("[{d}a];", .{i});
+    }
+
+    for (0..clips.len) |i| {
+        try writer.print("[{d}v][{d}a]", .{ i, i });
+    }
+    try writer.print("concat=n={d}:v=1:a=1[outv][outa]", .{clips.len});
+
+    return alloc.dupe([]const u8, &.{
+        "ffmpeg",
+        "-nostdin",
+        "-y",
+        "-loglevel",
+        "error",
+        "-nostats",
+        "-progress",
+        "pipe:1",
+        "-i",
+        input_path,
+        "-filter_complex",
+        filter.items,
+        "-map",
+        "[outv]",
+        "-map",
+        "[outa]",
+        output_path,
+    });
+}
+
+fn writeMuteFilter(writer: anytype, mute_regions: []const c.GuiMuteRegion) !void {
+    if (mute_regions.len == 0) return;
+
+    try writer.writeAll("volume=0:enable='");
+    for (mute_regions, 0..) |region, i| {
+        if (i != 0) try writer.writeByte('+');
+        try writer.print("between(t,{d},{d})", .{ region.start, region.end });
+    }
+    try writer.writeAll("',");
+}
+
+// Older ffmpeg only takes atempo between 0.5 and 2, so larger changes are
+// chained
+fn writeAtempoFilters(writer: anytype, speed_in: f32) !void {
+    var speed = speed_in;
+    while (speed > 2.0) {
+        try writer.writeAll(",atempo=2.0");
+        speed /= 2.0;
+    }
+    while (speed < 0.5) {
+        try writer.writeAll(",atempo=0.5");
+        speed /= 0.5;
+    }
+    if (speed != 1.0) {
+        try writer.print(",atempo={d}", .{speed});
+    }
+}
@@ -8,6 +8,8 @@ const VideoFrame = decoder.VideoFrame;
 pub const SharedData = struct {
     mutex: std.Thread.Mutex = .{},
     img: ?decoder.VideoFrame = null,
+    program_img: ?decoder.VideoFrame = null,
+    program_content: Content = .source,
 
     pub fn swapFrame(self: *SharedData, frame: decoder.VideoFrame) void {
         self.mutex.lock();
@@ -19,28 +21,105 @@ pub const SharedData = struct {
         self.img = frame;
     }
 
-    pub fn consumeFrame(self: *SharedData) ?decoder.VideoFrame {
+    pub fn swapProgramFrame(self: *SharedData, frame: decoder.VideoFrame) void {
         self.mutex.lock();
         defer self.mutex.unlock();
 
-        defer self.img = null;
-        return self.img;
+        if (self.program_img) |*img| {
+            img.deinit();
+        }
+        self.program_img = frame;
+        self.program_content = .own;
+    }
+
+    // For anything but .own, use swapProgramFrame for that
+    pub fn setProgramContent(self: *SharedData, content: Content) void {
+        std.debug.assert(content != .own);
+
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        if (self.program_img) |*img| {
+            img.deinit();
+        }
+        self.program_img = null;
+        self.program_content = content;
+    }
+
+    pub fn content(self: *SharedData, view: c.enum_GuiFrameView) Content {
+        if (view != c.gui_frame_view_program) return .own;
+
+        self.mutex.lock();
+        defer self.mutex.unlock();
+        return self.program_content;
+    }
+
+    pub fn consumeFrame(self: *SharedData, view: c.enum_GuiFrameView) ?decoder.VideoFrame {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        const img = if (view == c.gui_frame_view_program) &self.program_img else &self.img;
+        defer img.* = null;
+        return img.*;
     }
 
     pub fn deinit(self: *SharedData) void {
         if (self.img) |*img| {
             img.deinit();
         }
+        if (self.program_img) |*img| {
+            img.deinit();
+        }
+    }
+};
+
+// What a view should draw, see GuiFrameContent
+pub const Content = enum {
+    own,
+    source,
+    blank,
+
+    fn toC(self: Content) c.enum_GuiFrameContent {
+        return switch (self) {
+            .own => c.gui_frame_content_own,
+            .source => c.gui_frame_content_source,
+            .blank => c.gui_frame_content_blank,
+        };
+    }
+};
+
+// Textures of the last frame uploaded for a view
+const View = struct {
+    y_texture: c.GLuint = 0,
+    u_texture: c.GLuint = 0,
+    v_texture: c.GLuint = 0,
+    width_ratio: f32 = 1.0,
+    image_aspect_ratio: f32 = 1.0,
+    colorspace: c.AVColorSpace = c.AVCOL_SPC_BT470BG,
+    full_range: bool = false,
+};
+
+// Luma weights of a YUV to RGB matrix
+const LumaCoefficients = struct {
+    kr: f32,
+    kb: f32,
+
+    const bt601 = LumaCoefficients{ .kr = 0.299, .kb = 0.114 };
+    const bt709 = LumaCoefficients{ .kr = 0.2126, .kb = 0.0722 };
+
+    fn resolve(matrix: c.enum_GuiColorMatrix, colorspace: c.AVColorSpace) LumaCoefficients {
+        return switch (matrix) {
+            c.gui_color_matrix_bt601 => bt601,
+            c.gui_color_matrix_bt709 => bt709,
+            else => if (colorspace == c.AVCOL_SPC_BT709) bt709 else bt601,
+        };
     }
 };
 
 shared: *SharedData,
 
-y_texture: c.GLuint = 0,
-u_texture: c.GLuint = 0,
-v_texture: c.GLuint = 0,
-width_ratio: f32 = 1.0,
-image_aspect_ratio: f32 = 1.0,
+// Indexed by GuiFrameView
+views: [2]View = .{ .{}, .{} },
 program: c.GLuint = 0,
 
 const Self = @This();
@@ -52,8 +131,8 @@ const Error = error{
     Initialization,
 };
 
-pub export fn framerenderer_render(self: *Self, width: f32, height: f32, guigl: ?*anyopaque) void {
-    self.render(width, height, guigl);
+pub export fn framerenderer_render(self: *Self, view: c.enum_GuiFrameView, width: f32, height: f32, color: *const c.GuiColorManagement, guigl: ?*anyopaque) void {
+    self.render(view, width, height, color.*, guigl);
 }
 
 pub export fn framerenderer_init_gl(self: *Self, guigl: ?*anyopaque) void {
@@ -64,6 +143,27 @@ pub export fn framerenderer_deinit_gl(self: *Self, guigl: ?*anyopaque) void {
     self.deinitGl(guigl);
 }
 
+pub export fn framerenderer_content(self: *Self, view: c.enum_GuiFrameView) c.enum_GuiFrameContent {
+    return self.shared.content(view).toC();
+}
+
+pub export fn framerenderer_consume_frame(self: *Self, view: c.enum_GuiFrameView, callback: c.GuiVideoFrameCallback, userdata: ?*anyopaque) void {
+    var frame: decoder.VideoFrame = self.shared.consumeFrame(view) orelse {
+        return;
+    };
+    defer frame.deinit();
+
+    const c_frame = c.GuiVideoFrame{
+        .y = frame.y.ptr,
+        .u = frame.u.ptr,
+        .v = frame.v.ptr,
+        .width = @intCast(frame.width),
+        .height = @intCast(frame.height),
+        .stride = @intCast(frame.stride),
+    };
+    callback.?(userdata, &c_frame);
+}
+
 pub fn init(shared: *SharedData) Self {
     return .{
         .shared = shared,
@@ -72,77 +172,104 @@ pub fn init(shared: *SharedData) Self {
 
 pub fn initGl(self: *Self, guigl: ?*anyopaque) void {
     const program = gl_helpers.compileProgram(guigl, vertex_shader_source, fragment_shader_source);
-    errdefer c.guigl_delete_program(guigl, program);
-
-    const y_texture = makeTexture(guigl);
-    errdefer c.guigl_delete_texture(guigl, y_texture);
-
-    const u_texture = makeTexture(guigl);
-    errdefer c.guigl_delete_texture(guigl, u_texture);
-
-    const v_texture = makeTexture(guigl);
-    errdefer c.guigl_delete_texture(guigl, v_texture);
 
-    self.y_texture = y_texture;
-    self.u_texture = u_texture;
-    self.v_texture = v_texture;
-    self.width_ratio = 1.0;
-    self.image_aspect_ratio = 1.0;
+    for (&self.views) |*view| {
+        view.* = .{
+            .y_texture = makeTexture(guigl),
+            .u_texture = makeTexture(guigl),
+            .v_texture = makeTexture(guigl),
+        };
+    }
     self.program = program;
 }
 
 pub fn deinitGl(self: *Self, guigl: ?*anyopaque) void {
-    c.guigl_delete_texture(guigl, self.y_texture);
-    c.guigl_delete_texture(guigl, self.u_texture);
-    c.guigl_delete_texture(guigl, self.v_texture);
+    for (&self.views) |*view| {
+        c.guigl_delete_texture(guigl, view.y_texture);
+        c.guigl_delete_texture(guigl, view.u_texture);
+        c.guigl_delete_texture(guigl, view.v_texture);
+    }
     c.guigl_delete_program(guigl, self.program);
 }
 
-pub fn render(self: *Self, width: f32, height: f32, guigl: ?*anyopaque) void {
-    self.updateTextures(guigl);
+pub fn render(self: *Self, view_id: c.enum_GuiFrameView, width: f32, height: f32, color: c.GuiColorManagement, guigl: ?*anyopaque) void {
+    if (view_id != c.gui_frame_view_source and view_id != c.gui_frame_view_program) {
+        std.log.err("Unknown frame view: {d}", .{view_id});
+        return;
+    }
+
+    // Textures of a view that shows the source are left alone, the source
+    // view is what uploads frames to those
+    const view = switch (self.shared.content(view_id)) {
+        .blank => return,
+        .source => &self.views[@intCast(c.gui_frame_view_source)],
+        .own => blk: {
+            const own_view = &self.views[view_id];
+            updateTextures(own_view, self.shared, view_id, guigl);
+            break :blk own_view;
+        },
+    };
 
-    const aspect_ratio_ratio = width / height / self.image_aspect_ratio;
+    const aspect_ratio_ratio = width / height / view.image_aspect_ratio;
+
+    // Players decode with the file's own matrix and hand the result to the
+    // display as sRGB
+    const matrix: c.enum_GuiColorMatrix = if (color.match_export) c.gui_color_matrix_auto else color.matrix;
+    const input_transfer: c.enum_GuiTransferFunction = if (color.match_export) c.gui_transfer_srgb else color.input_transfer;
+    const coefficients = LumaCoefficients.resolve(matrix, view.colorspace);
+
+    // egui draws with it off, so it is only on for our draw
+    if (color.srgb_framebuffer) c.guigl_enable(guigl, c.GL_FRAMEBUFFER_SRGB);
+    defer if (color.srgb_framebuffer) c.guigl_disable(guigl, c.GL_FRAMEBUFFER_SRGB);
 
     c.guigl_use_program(guigl, self.program);
 
     c.guigl_active_texture(guigl, c.GL_TEXTURE0);
-    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, self.y_texture);
+    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, view.y_texture);
 
     c.guigl_active_texture(guigl, c.GL_TEXTURE1);
-    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, self.u_texture);
+    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, view.u_texture);
 
     c.guigl_active_texture(guigl, c.GL_TEXTURE2);
-    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, self.v_texture);
+    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, view.v_texture);
 
     c.guigl_uniform_1i(guigl, c.guigl_get_uniform_location(guigl, self.program, "y_tex"), 0);
     c.guigl_uniform_1i(guigl, c.guigl_get_uniform_location(guigl, self.program, "u_tex"), 1);
     c.guigl_uniform_1i(guigl, c.guigl_get_uniform_location(guigl, self.program, "v_tex"), 2);
-    c.guigl_uniform_1f(guigl, c.guigl_get_uniform_location(guigl, self.program, "width_ratio"), self.width_ratio);
+    c.guigl_uniform_1f(guigl, c.guigl_get_uniform_location(guigl, self.program, "width_ratio"), view.width_ratio);
     c.guigl_uniform_1f(guigl, c.guigl_get_uniform_location(guigl, self.program, "aspect_ratio_ratio"), aspect_ratio_ratio);
+    c.guigl_uniform_1f(guigl, c.guigl_get_uniform_location(guigl, self.program, "kr"), coefficients.kr);
+    c.guigl_uniform_1f(guigl, c.guigl_get_uniform_location(guigl, self.program, "kb"), coefficients.kb);
+    c.guigl_uniform_1i(guigl, c.guigl_get_uniform_location(guigl, self.program, "full_range"), @intFromBool(view.full_range));
+    c.guigl_uniform_1i(guigl, c.guigl_get_uniform_location(guigl, self.program, "input_transfer"), @intCast(input_transfer));
+    c.guigl_uniform_1i(guigl, c.guigl_get_uniform_location(guigl, self.program, "linear_output"), @intFromBool(color.srgb_framebuffer));
 
     c.guigl_draw_arrays(guigl, c.GL_TRIANGLE_STRIP, 0, 4);
 }
 
-fn updateTextures(self: *Self, guigl: ?*anyopaque) void {
-    var frame: decoder.VideoFrame = self.shared.consumeFrame() orelse {
+fn updateTextures(view: *View, shared: *SharedData, view_id: c.enum_GuiFrameView, guigl: ?*anyopaque) void {
+    var frame: decoder.VideoFrame = shared.consumeFrame(view_id) orelse {
         return;
     };
     defer frame.deinit();
 
-    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, self.y_texture);
+    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, view.y_texture);
     c.guigl_tex_image_2d(guigl, c.GL_TEXTURE_2D, 0, c.GL_RED, @intCast(frame.stride), @intCast(frame.height), 0, c.GL_RED, c.GL_UNSIGNED_BYTE, frame.y.ptr);
 
-    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, self.u_texture);
+    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, view.u_texture);
     c.guigl_tex_image_2d(guigl, c.GL_TEXTURE_2D, 0, c.GL_RED, @intCast(@divTrunc(frame.stride, 2)), @intCast(@divTrunc(frame.height, 2)), 0, c.GL_RED, c.GL_UNSIGNED_BYTE, frame.u.ptr);
 
-    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, self.v_texture);
+    c.guigl_bind_texture(guigl, c.GL_TEXTURE_2D, view.v_texture);
     c.guigl_tex_image_2d(guigl, c.GL_TEXTURE_2D, 0, c.GL_RED, @intCast(@divTrunc(frame.stride, 2)), @intCast(@divTrunc(frame.height, 2)), 0, c.GL_RED, c.GL_UNSIGNED_BYTE, frame.v.ptr);
 
-    self.width_ratio = @floatFromInt(frame.width);
-    self.width_ratio /= @floatFromInt(frame.stride);
+    view.width_ratio = @floatFromInt(frame.width);
+    view.width_ratio /= @floatFromInt(frame.stride);
+
+    view.image_aspect_ratio = @floatFromInt(frame.width);
+    view.image_aspect_ratio /= @floatFromInt(frame.height);
 
-    self.image_aspect_ratio = @floatFromInt(frame.width);
-    self.image_aspect_ratio /= @floatFromInt(frame.height);
+    view.colorspace = frame.colorspace;
+    view.full_range = frame.full_range;
 }
 
 fn makeTexture(guigl: ?*anyopaque) c.GLuint {
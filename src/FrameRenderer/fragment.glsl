@@ -8,6 +8,38 @@ uniform float aspect_ratio_ratio = 1.0;
 uniform sampler2D y_tex;
 uniform sampler2D u_tex;
 uniform sampler2D v_tex;
+// Luma weights of the YUV to RGB matrix, BT.601 by default
+uniform float kr = 0.299;
+uniform float kb = 0.114;
+uniform int full_range = 0;
+// GuiTransferFunction
+uniform int input_transfer = 0;
+// Write linear light and leave the sRGB encoding to GL_FRAMEBUFFER_SRGB
+uniform int linear_output = 0;
+
+const int transfer_srgb = 0;
+const int transfer_bt1886 = 1;
+const int transfer_gamma22 = 2;
+
+vec3 srgbToLinear(vec3 v)
+{
+    return mix(v / 12.92, pow((v + 0.055) / 1.055, vec3(2.4)), step(0.04045, v));
+}
+
+vec3 linearToSrgb(vec3 v)
+{
+    return mix(v * 12.92, 1.055 * pow(v, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, v));
+}
+
+vec3 toLinear(vec3 v)
+{
+    if (input_transfer == transfer_bt1886) {
+        return pow(v, vec3(2.4));
+    } else if (input_transfer == transfer_gamma22) {
+        return pow(v, vec3(2.2));
+    }
+    return srgbToLinear(v);
+}
 
 void main()
 {
@@ -94,16 +126,30 @@ void main()
     float u = texture(u_tex, frag_coord).r;
     float v = texture(v_tex, frag_coord).r;
 
-    // https://en.wikipedia.org/wiki/YCbCr#ITU-R_BT.601_conversion
-    y -= 16.0 / 255.0;
-    v -= 0.5;
-    u -= 0.5;
-    y *= 255.0 / 219.0;
-    u *= 255.0 / 224.0 * 1.772;
-    v *= 255.0 / 224.0 * 1.402;
-    float r = y + v;
-    float g = y - u * 0.114 / 0.587 - v * 0.299 / 0.587;
-    float b = y + u;
-    fragment = vec4(r, g, b, 1.0);
-}
+    // https://en.wikipedia.org/wiki/YCbCr#ITU-R_BT.601_conversion, with the
+    // luma weights of whichever matrix the video uses
+    if (full_range == 0) {
+        y -= 16.0 / 255.0;
+        y *= 255.0 / 219.0;
+        u = (u - 128.0 / 255.0) * 255.0 / 224.0;
+        v = (v - 128.0 / 255.0) * 255.0 / 224.0;
+    } else {
+        u -= 128.0 / 255.0;
+        v -= 128.0 / 255.0;
+    }
+    float kg = 1.0 - kr - kb;
+    float r = y + v * 2.0 * (1.0 - kr);
+    float b = y + u * 2.0 * (1.0 - kb);
+    float g = (y - kr * r - kb * b) / kg;
+    vec3 rgb = clamp(vec3(r, g, b), 0.0, 1.0);
 
+    // Untagged video is shown as sRGB by players, so sRGB in and sRGB out is
+    // a no-op and skipped
+    if (input_transfer != transfer_srgb || linear_output != 0) {
+        rgb = toLinear(rgb);
+        if (linear_output == 0) {
+            rgb = linearToSrgb(rgb);
+        }
+    }
+    fragment = vec4(rgb, 1.0);
+}
@@ -0,0 +1,105 @@
+// Bookkeeping for long running background work, so that the GUI can show what
+// the backend is busy with. Workers register a job and report progress, the
+// GUI cancels jobs by id through gui_action_cancel_job
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const c = @import("c.zig");
+
+const JobTracker = @This();
+
+// The mutex has to be held while reading jobs, e.g. when building a snapshot
+mutex: std.Thread.Mutex = .{},
+jobs: std.ArrayList(c.GuiJob),
+// Parallel to jobs. Set for cancellable jobs that are still active, the worker
+// is expected to poll it
+cancel_flags: std.ArrayList(?*std.atomic.Value(bool)),
+
+pub fn init(alloc: Allocator) JobTracker {
+    return .{
+        .jobs = std.ArrayList(c.GuiJob).init(alloc),
+        .cancel_flags = std.ArrayList(?*std.atomic.Value(bool)).init(alloc),
+    };
+}
+
+pub fn deinit(self: *JobTracker) void {
+    self.jobs.deinit();
+    self.cancel_flags.deinit();
+}
+
+// cancel_flag is stored to when the GUI asks for the job to stop. It has to
+// stay valid until the job reaches a final state. Pass null for jobs that
+// cannot be cancelled
+pub fn add(self: *JobTracker, kind: c.enum_GuiJobKind, cancel_flag: ?*std.atomic.Value(bool)) !u64 {
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    const id: u64 = self.jobs.items.len;
+    try self.jobs.append(.{
+        .id = id,
+        .kind = kind,
+        .state = c.gui_job_state_queued,
+        .progress = 0.0,
+        .cancellable = cancel_flag != null,
+        .cancel_requested = false,
+    });
+    errdefer _ = self.jobs.pop();
+
+    try self.cancel_flags.append(cancel_flag);
+    return id;
+}
+
+// Marks the job as running, unless it is paused, and updates its progress.
+// Negative progress means unknown
+pub fn setProgress(self: *JobTracker, id: u64, progress: f32) void {
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    const job = &self.jobs.items[@intCast(id)];
+    if (job.state != c.gui_job_state_paused) {
+        job.state = c.gui_job_state_running;
+    }
+    job.progress = if (progress < 0.0) progress else @min(progress, 1.0);
+}
+
+pub fn setPaused(self: *JobTracker, id: u64, paused: bool) void {
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    const job = &self.jobs.items[@intCast(id)];
+    job.state = if (paused) c.gui_job_state_paused else c.gui_job_state_running;
+}
+
+pub fn finish(self: *JobTracker, id: u64, state: c.enum_GuiJobState) void {
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    const idx: usize = @intCast(id);
+    const job = &self.jobs.items[idx];
+    job.state = state;
+    if (state == c.gui_job_state_finished) {
+        job.progress = 1.0;
+    }
+
+    // The worker is done with the flag, it may not even exist anymore
+    job.cancellable = false;
+    self.cancel_flags.items[idx] = null;
+}
+
+pub fn cancel(self: *JobTracker, id: u64) void {
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    if (id >= self.jobs.items.len) {
+        std.log.warn("Ignoring cancel for unknown job {d}", .{id});
+        return;
+    }
+
+    const idx: usize = @intCast(id);
+    const flag = self.cancel_flags.items[idx] orelse {
+        return;
+    };
+
+    flag.store(true, std.builtin.AtomicOrder.unordered);
+    self.jobs.items[idx].cancel_requested = true;
+}
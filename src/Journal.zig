@@ -0,0 +1,385 @@
+// Edits since the last save, appended to a file next to the save as they are
+// applied. If the editor goes down before the next save, the next run can
+// replay them on top of the save. One JSON object per line, e.g.
+// {"time_ms":1718000000000,"action":{"clip_add":{"id":0,"start":1.5,"end":3}}}
+
+const std = @import("std");
+const c = @import("c.zig");
+
+const Allocator = std.mem.Allocator;
+const ArenaAllocator = std.heap.ArenaAllocator;
+
+alloc: Allocator,
+file: std.fs.File,
+path: []const u8,
+recovered_path: []const u8,
+// Edits left behind by a run that did not exit cleanly, waiting to be
+// replayed or discarded
+num_recovered: usize,
+
+const Journal = @This();
+
+// A journal is a few hundred bytes per edit, anything past this is not one
+const max_journal_size = 64 * 1024 * 1024;
+
+// Only what ends up in the save is journaled. Playback is not worth
+// recovering, and transcripts are too large to journal and can be redone
+const Action = union(enum) {
+    clip_edit: c.Clip,
+    clip_add: c.Clip,
+    // Position of the clip to remove
+    clip_remove: f32,
+    clip_move: c.GuiClipMove,
+    freeze_frame: c.GuiFreezeFrame,
+    mute_add: c.GuiMuteRegion,
+    mute_edit: c.GuiMuteRegion,
+    mute_remove: u64,
+    marker_add: Marker,
+    marker_remove: u64,
+    // Path of the CSV, which is read again on replay
+    import_markers: []const u8,
+    set_caption_style: c.GuiCaptionStyle,
+    // Everything in the save as it was after a revert. History entries only
+    // exist for the run that made them, so the revert itself can't be replayed
+    restore: Restore,
+};
+
+// The C side keeps names in a fixed size buffer, the journal keeps them as
+// strings
+const Marker = struct {
+    time: f32,
+    color: u32,
+    name: []const u8,
+};
+
+const Restore = struct {
+    clips: []const c.Clip,
+    markers: []const RestoredMarker,
+    mute_regions: []const c.GuiMuteRegion,
+    caption_style: c.GuiCaptionStyle,
+};
+
+// Unlike an added marker, a restored one keeps the id it had
+const RestoredMarker = struct {
+    id: u64,
+    time: f32,
+    color: u32,
+    name: []const u8,
+};
+
+const Entry = struct {
+    // Milliseconds since the epoch, for anyone reading the journal by hand
+    time_ms: i64,
+    action: Action,
+};
+
+// What a restore entry puts back, laid out the way the managers keep it
+pub const State = struct {
+    clips: []const c.Clip,
+    markers: []const c.GuiMarker,
+    mute_regions: []const c.GuiMuteRegion,
+    caption_style: c.GuiCaptionStyle,
+};
+
+pub const Replayed = union(enum) {
+    action: c.GuiAction,
+    restore: State,
+};
+
+pub const Recovered = struct {
+    arena: ArenaAllocator,
+    entries: []const Replayed,
+
+    pub fn deinit(self: *Recovered) void {
+        self.arena.deinit();
+    }
+};
+
+pub fn init(alloc: Allocator, save_path: []const u8) !Journal {
+    const path = try std.fmt.allocPrint(alloc, "{s}.journal", .{save_path});
+    errdefer alloc.free(path);
+
+    const recovered_path = try std.fmt.allocPrint(alloc, "{s}.journal.recovered", .{save_path});
+    errdefer alloc.free(recovered_path);
+
+    const num_recovered = try collectRecovered(alloc, path, recovered_path);
+    if (num_recovered > 0) {
+        std.log.warn("Found {d} edits from a run that did not exit cleanly", .{num_recovered});
+    }
+
+    const file = try std.fs.cwd().createFile(path, .{});
+
+    return .{
+        .alloc = alloc,
+        .file = file,
+        .path = path,
+        .recovered_path = recovered_path,
+        .num_recovered = num_recovered,
+    };
+}
+
+// The journal is only removed on a clean exit, anything else leaves it for
+// the next run to recover
+pub fn deinit(self: *Journal, clean_exit: bool) void {
+    self.file.close();
+    if (clean_exit) {
+        std.fs.cwd().deleteFile(self.path) catch |e| {
+            std.log.err("Failed to remove journal {s}: {s}", .{ self.path, @errorName(e) });
+        };
+    }
+
+    self.alloc.free(self.path);
+    self.alloc.free(self.recovered_path);
+}
+
+// Actions that do not change the save are ignored
+pub fn append(self: *Journal, action: *const c.GuiAction) !void {
+    const journal_action = fromGuiAction(action) orelse return;
+    try self.write(journal_action);
+}
+
+// Whether append() would write action down, i.e. whether it changes the save
+pub fn records(action: *const c.GuiAction) bool {
+    return fromGuiAction(action) != null;
+}
+
+// For edits that are not a GuiAction, e.g. a revert, replaying this puts
+// state back as is
+pub fn appendRestore(self: *Journal, state: State) !void {
+    const markers = try self.alloc.alloc(RestoredMarker, state.markers.len);
+    defer self.alloc.free(markers);
+
+    for (state.markers, markers) |*marker, *out| {
+        out.* = .{
+            .id = marker.id,
+            .time = marker.time,
+            .color = marker.color,
+            .name = std.mem.sliceTo(&marker.name, 0),
+        };
+    }
+
+    try self.write(.{ .restore = .{
+        .clips = state.clips,
+        .markers = markers,
+        .mute_regions = state.mute_regions,
+        .caption_style = state.caption_style,
+    } });
+}
+
+fn write(self: *Journal, journal_action: Action) !void {
+    var line = std.ArrayList(u8).init(self.alloc);
+    defer line.deinit();
+
+    try std.json.stringify(Entry{
+        .time_ms = std.time.milliTimestamp(),
+        .action = journal_action,
+    }, .{}, line.writer());
+    try line.append('\n');
+
+    // One write per entry, so a crash leaves at most one partial line. No
+    // fsync, this guards against the editor going down, not the machine
+    try self.file.writeAll(line.items);
+}
+
+// Everything journaled so far is in the save now
+pub fn clear(self: *Journal) !void {
+    try self.file.setEndPos(0);
+    try self.file.seekTo(0);
+}
+
+// Edits to apply on top of the save to get back to where the last run left
+// off. Unreadable entries, e.g. the one being written during a crash, are
+// skipped
+pub fn loadRecovered(self: *Journal) !Recovered {
+    var arena = ArenaAllocator.init(self.alloc);
+    errdefer arena.deinit();
+    const arena_alloc = arena.allocator();
+
+    const data = try readIfExists(arena_alloc, self.recovered_path);
+
+    var entries = std.ArrayList(Replayed).init(arena_alloc);
+    var line_it = std.mem.splitScalar(u8, data, '\n');
+    var line_num: usize = 0;
+    while (line_it.next()) |line| {
+        line_num += 1;
+        if (std.mem.trim(u8, line, " \t\r").len == 0) continue;
+
+        const entry = std.json.parseFromSliceLeaky(Entry, arena_alloc, line, .{}) catch |e| {
+            std.log.warn("Skipping journal entry on line {d}: {s}", .{ line_num, @errorName(e) });
+            continue;
+        };
+        try entries.append(switch (entry.action) {
+            .restore => |restore| .{ .restore = try toState(arena_alloc, restore) },
+            else => .{ .action = toGuiAction(entry.action) },
+        });
+    }
+
+    return .{
+        .arena = arena,
+        .entries = entries.items,
+    };
+}
+
+pub fn discardRecovered(self: *Journal) void {
+    self.num_recovered = 0;
+    std.fs.cwd().deleteFile(self.recovered_path) catch |e| switch (e) {
+        error.FileNotFound => {},
+        else => std.log.err("Failed to remove {s}: {s}", .{ self.recovered_path, @errorName(e) }),
+    };
+}
+
+// Moves what the last run left in the journal over to the recovered file,
+// after whatever earlier runs left there if nobody dealt with it. Returns how
+// many edits there are to recover
+fn collectRecovered(alloc: Allocator, path: []const u8, recovered_path: []const u8) !usize {
+    const old_recovered = try readIfExists(alloc, recovered_path);
+    defer alloc.free(old_recovered);
+
+    const journal = try readIfExists(alloc, path);
+    defer alloc.free(journal);
+
+    if (countEntries(journal) > 0) {
+        // Written to the side and renamed over, so that crashing here does
+        // not lose the old recovered edits
+        const tmp_path = try std.fmt.allocPrint(alloc, "{s}.tmp", .{recovered_path});
+        defer alloc.free(tmp_path);
+
+        {
+            const f = try std.fs.cwd().createFile(tmp_path, .{});
+            defer f.close();
+
+            try f.writeAll(old_recovered);
+            // A partial last line must not swallow the next entry
+            if (old_recovered.len > 0 and old_recovered[old_recovered.len - 1] != '\n') {
+                try f.writeAll("\n");
+            }
+            try f.writeAll(journal);
+        }
+        try std.fs.cwd().rename(tmp_path, recovered_path);
+    }
+
+    return countEntries(old_recovered) + countEntries(journal);
+}
+
+fn readIfExists(alloc: Allocator, path: []const u8) ![]u8 {
+    return std.fs.cwd().readFileAlloc(alloc, path, max_journal_size) catch |e| switch (e) {
+        error.FileNotFound => try alloc.alloc(u8, 0),
+        else => return e,
+    };
+}
+
+fn countEntries(data: []const u8) usize {
+    var ret: usize = 0;
+    var line_it = std.mem.splitScalar(u8, data, '\n');
+    while (line_it.next()) |line| {
+        if (std.mem.trim(u8, line, " \t\r").len != 0) ret += 1;
+    }
+    return ret;
+}
+
+// Names and paths point into action
+fn fromGuiAction(action: *const c.GuiAction) ?Action {
+    return switch (action.tag) {
+        c.gui_action_clip_edit => .{ .clip_edit = action.data.clip },
+        c.gui_action_clip_add => .{ .clip_add = action.data.clip },
+        c.gui_action_clip_remove => .{ .clip_remove = action.data.seek_position },
+        c.gui_action_clip_move => .{ .clip_move = action.data.clip_move },
+        c.gui_action_insert_freeze_frame => .{ .freeze_frame = action.data.freeze_frame },
+        c.gui_action_mute_region_add => .{ .mute_add = action.data.mute_region },
+        c.gui_action_mute_region_edit => .{ .mute_edit = action.data.mute_region },
+        c.gui_action_mute_region_remove => .{ .mute_remove = action.data.id },
+        c.gui_action_marker_add => .{ .marker_add = .{
+            .time = action.data.marker.time,
+            .color = action.data.marker.color,
+            .name = std.mem.sliceTo(&action.data.marker.name, 0),
+        } },
+        c.gui_action_marker_remove => .{ .marker_remove = action.data.id },
+        c.gui_action_import_markers => .{ .import_markers = std.mem.sliceTo(&action.data.import_request.path, 0) },
+        c.gui_action_set_caption_style => .{ .set_caption_style = action.data.caption_style },
+        else => null,
+    };
+}
+
+fn toGuiAction(action: Action) c.GuiAction {
+    var ret = std.mem.zeroes(c.GuiAction);
+    switch (action) {
+        .clip_edit => |clip| {
+            ret.tag = c.gui_action_clip_edit;
+            ret.data.clip = clip;
+        },
+        .clip_add => |clip| {
+            ret.tag = c.gui_action_clip_add;
+            ret.data.clip = clip;
+        },
+        .clip_remove => |pos| {
+            ret.tag = c.gui_action_clip_remove;
+            ret.data.seek_position = pos;
+        },
+        .clip_move => |move| {
+            ret.tag = c.gui_action_clip_move;
+            ret.data.clip_move = move;
+        },
+        .freeze_frame => |freeze| {
+            ret.tag = c.gui_action_insert_freeze_frame;
+            ret.data.freeze_frame = freeze;
+        },
+        .mute_add => |region| {
+            ret.tag = c.gui_action_mute_region_add;
+            ret.data.mute_region = region;
+        },
+        .mute_edit => |region| {
+            ret.tag = c.gui_action_mute_region_edit;
+            ret.data.mute_region = region;
+        },
+        .mute_remove => |id| {
+            ret.tag = c.gui_action_mute_region_remove;
+            ret.data.id = id;
+        },
+        .marker_add => |marker| {
+            ret.tag = c.gui_action_marker_add;
+            ret.data.marker.time = marker.time;
+            ret.data.marker.color = marker.color;
+            copyTerminated(&ret.data.marker.name, marker.name);
+        },
+        .marker_remove => |id| {
+            ret.tag = c.gui_action_marker_remove;
+            ret.data.id = id;
+        },
+        .import_markers => |path| {
+            ret.tag = c.gui_action_import_markers;
+            copyTerminated(&ret.data.import_request.path, path);
+        },
+        .set_caption_style => |style| {
+            ret.tag = c.gui_action_set_caption_style;
+            ret.data.caption_style = style;
+        },
+        // Not an action, see toState
+        .restore => unreachable,
+    }
+    return ret;
+}
+
+fn toState(alloc: Allocator, restore: Restore) !State {
+    const markers = try alloc.alloc(c.GuiMarker, restore.markers.len);
+    for (restore.markers, markers) |marker, *out| {
+        out.* = std.mem.zeroes(c.GuiMarker);
+        out.id = marker.id;
+        out.time = marker.time;
+        out.color = marker.color;
+        copyTerminated(&out.name, marker.name);
+    }
+
+    return .{
+        .clips = restore.clips,
+        .markers = markers,
+        .mute_regions = restore.mute_regions,
+        .caption_style = restore.caption_style,
+    };
+}
+
+// Truncates to leave room for the terminator, the rest of dst is expected to
+// be zeroed already
+fn copyTerminated(dst: []u8, src: []const u8) void {
+    const len = @min(src.len, dst.len - 1);
+    @memcpy(dst[0..len], src[0..len]);
+}
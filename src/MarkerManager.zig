@@ -0,0 +1,252 @@
+// Named points on the timeline, e.g. chapter starts or moments flagged while
+// recording. Markers can be swapped with other tools as CSV
+
+const std = @import("std");
+const c = @import("c.zig");
+const save = @import("save.zig");
+const captions_mod = @import("captions.zig");
+
+const Allocator = std.mem.Allocator;
+
+const MarkerList = std.ArrayList(c.GuiMarker);
+
+markers: MarkerList,
+marker_id: u64,
+
+const MarkerManager = @This();
+
+pub const default_color = 0xffd700ff;
+
+// Marker CSV files are small, anything past this is not one
+const max_csv_size = 16 * 1024 * 1024;
+
+// The C side keeps names in a fixed size buffer, saves keep them as strings
+const SaveMarker = struct {
+    time: f32,
+    name: []const u8,
+    color: u32 = default_color,
+};
+
+pub fn init(alloc: Allocator, init_data: ?save.Data.Field) !MarkerManager {
+    var ret = MarkerManager{
+        .markers = MarkerList.init(alloc),
+        .marker_id = 1,
+    };
+    errdefer ret.deinit();
+
+    if (init_data) |id| {
+        const loaded = try id.as([]const SaveMarker);
+        defer loaded.deinit();
+
+        for (loaded.value) |marker| {
+            try ret.add(makeMarker(marker.time, marker.name, marker.color));
+        }
+    }
+
+    return ret;
+}
+
+pub fn deinit(self: *MarkerManager) void {
+    self.markers.deinit();
+}
+
+pub fn serialize(self: *MarkerManager, writer: save.Writer.FieldWriter) !void {
+    var save_markers = try std.ArrayList(SaveMarker).initCapacity(self.markers.allocator, self.markers.items.len);
+    defer save_markers.deinit();
+
+    for (self.markers.items) |*marker| {
+        save_markers.appendAssumeCapacity(.{
+            .time = marker.time,
+            .name = std.mem.sliceTo(&marker.name, 0),
+            .color = marker.color,
+        });
+    }
+
+    try writer.write(save_markers.items);
+}
+
+pub fn add(self: *MarkerManager, marker_in: c.GuiMarker) !void {
+    defer self.marker_id += 1;
+    var marker = marker_in;
+    marker.id = self.marker_id;
+    // Names from the GUI may fill the whole buffer
+    marker.name[marker.name.len - 1] = 0;
+
+    const idx = std.sort.upperBound(c.GuiMarker, marker, self.markers.items, {}, timeLessThan);
+    try self.markers.insert(idx, marker);
+}
+
+pub fn find(self: *MarkerManager, id: u64) ?c.GuiMarker {
+    for (self.markers.items) |marker| {
+        if (marker.id == id) return marker;
+    }
+    return null;
+}
+
+pub fn remove(self: *MarkerManager, id: u64) void {
+    for (self.markers.items, 0..) |marker, i| {
+        if (marker.id == id) {
+            _ = self.markers.orderedRemove(i);
+            return;
+        }
+    }
+}
+
+// Writes time,name,color rows with a header. Times are HH:MM:SS.mmm, colors
+// #RRGGBB
+pub fn exportCsv(self: *MarkerManager, path: []const u8) !void {
+    const f = try std.fs.cwd().createFile(path, .{});
+    defer f.close();
+
+    var buffered = std.io.bufferedWriter(f.writer());
+    try writeCsv(buffered.writer(), self.markers.items);
+    try buffered.flush();
+}
+
+// Adds the markers of a CSV file to the ones we already have and returns how
+// many there were. Columns are time, name and color. Times can be seconds or
+// [HH:]MM:SS[.mmm], name and color (#RRGGBB or #RRGGBBAA) may be left out. A
+// first row without a valid time is taken as the header
+pub fn importCsv(self: *MarkerManager, path: []const u8) !usize {
+    const alloc = self.markers.allocator;
+
+    const data = try std.fs.cwd().readFileAlloc(alloc, path, max_csv_size);
+    defer alloc.free(data);
+
+    var arena = std.heap.ArenaAllocator.init(alloc);
+    defer arena.deinit();
+
+    const markers = try parseCsv(arena.allocator(), data);
+    for (markers) |marker| {
+        try self.add(marker);
+    }
+    return markers.len;
+}
+
+fn makeMarker(time: f32, name: []const u8, color: u32) c.GuiMarker {
+    var ret = c.GuiMarker{
+        .id = 0,
+        .time = time,
+        .color = color,
+        .name = std.mem.zeroes([64]u8),
+    };
+
+    // Leave room for the terminator
+    const len = @min(name.len, ret.name.len - 1);
+    @memcpy(ret.name[0..len], name[0..len]);
+    return ret;
+}
+
+fn timeLessThan(_: void, lhs: c.GuiMarker, rhs: c.GuiMarker) bool {
+    return lhs.time < rhs.time;
+}
+
+fn writeCsv(writer: anytype, markers: []const c.GuiMarker) !void {
+    try writer.writeAll("time,name,color\n");
+    for (markers) |*marker| {
+        try captions_mod.writeTimestamp(writer, marker.time, '.');
+        try writer.writeByte(',');
+        try writeCsvField(writer, std.mem.sliceTo(&marker.name, 0));
+        try writer.print(",#{x:0>6}\n", .{marker.color >> 8});
+    }
+}
+
+// Names are single line, so only separators and quotes need quoting
+fn writeCsvField(writer: anytype, s: []const u8) !void {
+    if (std.mem.indexOfAny(u8, s, ",\"") == null) {
+        try writer.writeAll(s);
+        return;
+    }
+
+    try writer.writeByte('"');
+    for (s) |char| {
+        if (char == '"') try writer.writeByte('"');
+        try writer.writeByte(char);
+    }
+    try writer.writeByte('"');
+}
+
+// Everything is allocated with alloc, which is expected to be an arena
+fn parseCsv(alloc: Allocator, data: []const u8) ![]c.GuiMarker {
+    var ret = std.ArrayList(c.GuiMarker).init(alloc);
+
+    var line_it = std.mem.splitScalar(u8, data, '\n');
+    var line_num: usize = 0;
+    var is_first_row = true;
+    while (line_it.next()) |raw_line| {
+        line_num += 1;
+
+        const line = std.mem.trimRight(u8, raw_line, "\r");
+        if (std.mem.trim(u8, line, " \t").len == 0) continue;
+
+        defer is_first_row = false;
+
+        const fields = try parseCsvRow(alloc, line);
+        const time = parseTime(fields[0]) catch {
+            if (!is_first_row) {
+                std.log.warn("Skipping marker on line {d}, \"{s}\" is not a time", .{ line_num, fields[0] });
+            }
+            continue;
+        };
+
+        const name = if (fields.len > 1) std.mem.trim(u8, fields[1], " \t") else "";
+        const color = if (fields.len > 2) parseColor(fields[2]) else null;
+        try ret.append(makeMarker(time, name, color orelse default_color));
+    }
+
+    return ret.items;
+}
+
+fn parseCsvRow(alloc: Allocator, line: []const u8) ![]const []const u8 {
+    var fields = std.ArrayList([]const u8).init(alloc);
+    var field = std.ArrayList(u8).init(alloc);
+
+    var in_quotes = false;
+    var i: usize = 0;
+    while (i < line.len) : (i += 1) {
+        const char = line[i];
+        if (in_quotes) {
+            if (char != '"') {
+                try field.append(char);
+            } else if (i + 1 < line.len and line[i + 1] == '"') {
+                // Doubled quotes are a literal quote
+                try field.append('"');
+                i += 1;
+            } else {
+                in_quotes = false;
+            }
+            continue;
+        }
+
+        switch (char) {
+            '"' => in_quotes = true,
+            ',' => try fields.append(try field.toOwnedSlice()),
+            else => try field.append(char),
+        }
+    }
+
+    try fields.append(try field.toOwnedSlice());
+    return fields.items;
+}
+
+fn parseTime(s: []const u8) !f32 {
+    const trimmed = std.mem.trim(u8, s, " \t");
+    const ret = if (std.mem.indexOfScalar(u8, trimmed, ':') != null)
+        try captions_mod.parseTimestamp(trimmed)
+    else
+        try std.fmt.parseFloat(f32, trimmed);
+
+    if (ret < 0 or std.math.isNan(ret)) return error.InvalidTime;
+    return ret;
+}
+
+// #RRGGBB or #RRGGBBAA, null for anything else
+fn parseColor(s: []const u8) ?u32 {
+    const hex = std.mem.trimLeft(u8, std.mem.trim(u8, s, " \t"), "#");
+    const val = std.fmt.parseInt(u32, hex, 16) catch return null;
+    return switch (hex.len) {
+        6 => val << 8 | 0xff,
+        8 => val,
+        else => null,
+    };
+}
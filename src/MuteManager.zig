@@ -0,0 +1,112 @@
+// Sections of the source where the audio is silenced while the picture plays
+// on, e.g. to cover a name being read out or a notification sound
+
+const std = @import("std");
+const c = @import("c.zig");
+const decoder = @import("decoder.zig");
+const save = @import("save.zig");
+
+const Allocator = std.mem.Allocator;
+
+const RegionList = std.ArrayList(c.GuiMuteRegion);
+
+// Sorted by start
+regions: RegionList,
+region_id: u64,
+
+const MuteManager = @This();
+
+pub fn init(alloc: Allocator, init_data: ?save.Data.Field) !MuteManager {
+    var ret = MuteManager{
+        .regions = RegionList.init(alloc),
+        .region_id = 1,
+    };
+    errdefer ret.deinit();
+
+    if (init_data) |id| {
+        const loaded = try id.as([]const c.GuiMuteRegion);
+        defer loaded.deinit();
+
+        for (loaded.value) |region| {
+            _ = try ret.add(region);
+        }
+    }
+
+    return ret;
+}
+
+pub fn deinit(self: *MuteManager) void {
+    self.regions.deinit();
+}
+
+pub fn serialize(self: *MuteManager, writer: save.Writer.FieldWriter) !void {
+    try writer.write(self.regions.items);
+}
+
+// Returns the id the region was given
+pub fn add(self: *MuteManager, region_in: c.GuiMuteRegion) !u64 {
+    defer self.region_id += 1;
+    var region = region_in;
+    region.id = self.region_id;
+    if (region.start > region.end) {
+        std.mem.swap(f32, &region.start, &region.end);
+    }
+
+    const idx = std.sort.upperBound(c.GuiMuteRegion, region, self.regions.items, {}, startLessThan);
+    try self.regions.insert(idx, region);
+    return region.id;
+}
+
+pub fn update(self: *MuteManager, region: c.GuiMuteRegion) void {
+    const i = self.findIdx(region.id) orelse return;
+    self.regions.items[i].start = @min(region.start, region.end);
+    self.regions.items[i].end = @max(region.start, region.end);
+    std.sort.insertion(c.GuiMuteRegion, self.regions.items, {}, startLessThan);
+}
+
+pub fn remove(self: *MuteManager, id: u64) void {
+    const i = self.findIdx(id) orelse return;
+    _ = self.regions.orderedRemove(i);
+}
+
+pub fn find(self: *MuteManager, id: u64) ?c.GuiMuteRegion {
+    const i = self.findIdx(id) orelse return null;
+    return self.regions.items[i];
+}
+
+// Silences the samples of frame that fall into a region. Only for frames that
+// have not been handed to anyone else yet, they are written in place
+pub fn apply(self: *const MuteManager, frame: *decoder.AudioFrame) void {
+    const sample_rate: f32 = @floatFromInt(frame.info.sample_rate);
+    const frame_end = frame.pts + @as(f32, @floatFromInt(frame.num_samples)) / sample_rate;
+    const sample_size = frame.info.format.sampleSize();
+
+    for (self.regions.items) |region| {
+        if (region.end <= frame.pts or region.start >= frame_end) continue;
+
+        const first = sampleIdx(region.start - frame.pts, sample_rate, frame.num_samples);
+        const last = sampleIdx(region.end - frame.pts, sample_rate, frame.num_samples);
+        for (frame.channel_data.items) |channel| {
+            // All zero bytes is silence in every Format
+            const samples: []u8 = @constCast(channel);
+            @memset(samples[first * sample_size .. last * sample_size], 0);
+        }
+    }
+}
+
+fn sampleIdx(offset_s: f32, sample_rate: f32, num_samples: usize) usize {
+    if (offset_s <= 0) return 0;
+    const idx: usize = @intFromFloat(offset_s * sample_rate);
+    return @min(idx, num_samples);
+}
+
+fn findIdx(self: *MuteManager, id: u64) ?usize {
+    for (self.regions.items, 0..) |region, i| {
+        if (region.id == id) return i;
+    }
+    return null;
+}
+
+fn startLessThan(_: void, a: c.GuiMuteRegion, b: c.GuiMuteRegion) bool {
+    return a.start < b.start;
+}
@@ -0,0 +1,254 @@
+// Everything that belongs to one open video and its save file. Each project
+// gets its own backend thread and shows up as a tab in the GUI
+
+const std = @import("std");
+const c = @import("c.zig");
+const WordTimestampGenerator = @import("WordTimestampGenerator.zig");
+const FrameRenderer = @import("FrameRenderer.zig");
+const decoder = @import("decoder.zig");
+const audio = @import("audio.zig");
+const App = @import("App.zig");
+const AudioRenderer = @import("AudioRenderer.zig");
+const ClipManager = @import("ClipManager.zig");
+const MarkerManager = @import("MarkerManager.zig");
+const MuteManager = @import("MuteManager.zig");
+const ThumbnailGenerator = @import("ThumbnailGenerator.zig");
+const Exporter = @import("Exporter.zig");
+const JobTracker = @import("JobTracker.zig");
+const Journal = @import("Journal.zig");
+const EditHistory = @import("EditHistory.zig");
+
+const Allocator = std.mem.Allocator;
+
+alloc: Allocator,
+input_path: [:0]const u8,
+save_path: [:0]const u8,
+decoders: App.Decoders,
+jobs: JobTracker,
+audio_renderer: AudioRenderer,
+frame_renderer_shared: FrameRenderer.SharedData,
+frame_renderer: FrameRenderer,
+app_state: App.AppState,
+save_data: App.Save,
+clip_manager: ClipManager,
+marker_manager: MarkerManager,
+mute_manager: MuteManager,
+caption_style: c.GuiCaptionStyle,
+history: EditHistory,
+journal: Journal,
+audio_player: ?*audio.Player,
+gui: *c.Gui,
+wtm: ?WordTimestampGenerator,
+thumbnail_generator: ThumbnailGenerator,
+exporter: Exporter,
+thread: ?std.Thread,
+// Only set if the app shut down on its own terms, anything else leaves the
+// journal for the next run
+app_exited: bool,
+clean_exit: bool,
+
+const Project = @This();
+
+pub const Options = struct {
+    debug_output: ?[:0]const u8,
+    model_dir: [:0]const u8,
+    generate_subtitles: bool,
+};
+
+// Heap allocated since the backend thread and the GUI hold pointers into it
+pub fn create(alloc: Allocator, input_path: [:0]const u8, save_path: [:0]const u8, options: Options) !*Project {
+    const self = try alloc.create(Project);
+    errdefer alloc.destroy(self);
+
+    self.alloc = alloc;
+    self.input_path = input_path;
+    self.save_path = save_path;
+    self.thread = null;
+    self.app_exited = false;
+    self.clean_exit = false;
+
+    self.decoders = .{};
+    errdefer self.deinitDecoders();
+
+    {
+        var first_dec = try decoder.VideoDecoder.init(alloc, input_path);
+        errdefer first_dec.deinit();
+        try self.decoders.append(alloc, first_dec);
+    }
+    const dec = &self.decoders.at(0).*.?;
+
+    self.jobs = JobTracker.init(alloc);
+    errdefer self.jobs.deinit();
+
+    self.audio_renderer = try AudioRenderer.init(alloc, input_path, &self.jobs);
+    errdefer self.audio_renderer.deinit();
+
+    self.frame_renderer_shared = FrameRenderer.SharedData{};
+    errdefer self.frame_renderer_shared.deinit();
+
+    self.frame_renderer = FrameRenderer.init(&self.frame_renderer_shared);
+
+    self.app_state = App.AppState.init(alloc);
+    errdefer self.app_state.deinit();
+
+    self.save_data = App.Save.load(alloc, save_path);
+    errdefer self.save_data.deinit();
+
+    self.clip_manager = try ClipManager.init(alloc, self.save_data.clips());
+    errdefer self.clip_manager.deinit();
+
+    self.marker_manager = try MarkerManager.init(alloc, self.save_data.markers());
+    errdefer self.marker_manager.deinit();
+
+    self.mute_manager = try MuteManager.init(alloc, self.save_data.muteRegions());
+    errdefer self.mute_manager.deinit();
+
+    self.caption_style = self.save_data.captionStyle();
+
+    self.history = try EditHistory.init(alloc, .{
+        .clip_manager = &self.clip_manager,
+        .marker_manager = &self.marker_manager,
+        .mute_manager = &self.mute_manager,
+        .caption_style = &self.caption_style,
+    });
+    errdefer self.history.deinit();
+
+    self.journal = try Journal.init(alloc, save_path);
+    errdefer self.journal.deinit(false);
+
+    self.audio_player = try makeAudioPlayer(alloc, dec);
+    errdefer if (self.audio_player) |p| p.deinit();
+
+    self.gui = c.gui_init(&self.app_state) orelse return error.Gui;
+    errdefer c.gui_free(self.gui);
+
+    // Created after the GUI so that it is shut down before the GUI it sends
+    // events to goes away
+    self.wtm = null;
+    if (options.generate_subtitles) {
+        self.wtm = try WordTimestampGenerator.init(alloc, input_path, self.save_data.wordTimestampMap(), options.debug_output, options.model_dir, self.gui, &self.jobs);
+    }
+    errdefer if (self.wtm) |*w| w.deinit();
+
+    self.thumbnail_generator = try ThumbnailGenerator.init(alloc, input_path, self.gui, &self.jobs);
+    errdefer self.thumbnail_generator.deinit();
+
+    self.exporter = try Exporter.init(alloc, self.gui, &self.jobs);
+    errdefer self.exporter.deinit();
+
+    return self;
+}
+
+// join has to have been called if start was
+pub fn destroy(self: *Project) void {
+    self.exporter.deinit();
+    self.thumbnail_generator.deinit();
+    if (self.wtm) |*w| w.deinit();
+    c.gui_free(self.gui);
+    if (self.audio_player) |p| p.deinit();
+    self.journal.deinit(self.clean_exit);
+    self.history.deinit();
+    self.mute_manager.deinit();
+    self.marker_manager.deinit();
+    self.clip_manager.deinit();
+    self.save_data.deinit();
+    self.app_state.deinit();
+    self.frame_renderer_shared.deinit();
+    self.audio_renderer.deinit();
+    self.jobs.deinit();
+    self.deinitDecoders();
+
+    self.alloc.destroy(self);
+}
+
+pub fn guiProject(self: *Project) c.GuiProject {
+    return .{
+        .gui = self.gui,
+        .frame_renderer = &self.frame_renderer,
+        .audio_renderer = &self.audio_renderer,
+        .wtm = if (self.wtm) |*w| w else null,
+        .save_path = self.save_path.ptr,
+    };
+}
+
+pub fn start(self: *Project) !void {
+    self.thread = try std.Thread.spawn(.{}, mainLoop, .{self});
+}
+
+pub fn join(self: *Project, gui_status: c.enum_GuiStatus) void {
+    if (self.thread) |t| t.join();
+    self.thread = null;
+    self.clean_exit = self.app_exited and gui_status == c.gui_status_ok;
+}
+
+fn deinitDecoders(self: *Project) void {
+    var it = self.decoders.iterator(0);
+    while (it.next()) |slot| {
+        if (slot.*) |*d| d.deinit();
+    }
+    self.decoders.deinit(self.alloc);
+}
+
+fn appRefs(self: *Project) App.AppRefs {
+    return .{
+        .alloc = self.alloc,
+        .frame_renderer = &self.frame_renderer_shared,
+        .gui = self.gui,
+        .app_state = &self.app_state,
+        .dec = &self.decoders.at(0).*.?,
+        .decoders = &self.decoders,
+        .audio_renderer = &self.audio_renderer,
+        .audio_player = self.audio_player,
+        .clip_manager = &self.clip_manager,
+        .marker_manager = &self.marker_manager,
+        .mute_manager = &self.mute_manager,
+        .wtm = if (self.wtm) |*w| w else null,
+        .thumbnail_generator = &self.thumbnail_generator,
+        .exporter = &self.exporter,
+        .jobs = &self.jobs,
+        .journal = &self.journal,
+        .history = &self.history,
+        .caption_style = &self.caption_style,
+        .input_path = self.input_path,
+        .save_path = self.save_path,
+    };
+}
+
+fn mainLoop(self: *Project) !void {
+    const refs = self.appRefs();
+
+    // If main thread init fails, we need to close the GUI, but if the GUI
+    // hadn't launched yet it will miss the shutdown notification and stay
+    // open forever
+    if (c.gui_wait_start(refs.gui) != c.gui_status_ok) {
+        std.log.err("GUI failed to start, exiting", .{});
+        return;
+    }
+    defer c.gui_close(refs.gui);
+
+    var app = try App.init(refs);
+    try app.run();
+    self.app_exited = true;
+}
+
+fn makeAudioPlayer(alloc: Allocator, dec: *decoder.VideoDecoder) !?*audio.Player {
+    var audio_player: ?*audio.Player = null;
+
+    var streams = dec.streams();
+    while (try streams.next()) |stream| {
+        switch (stream) {
+            .audio => |params| {
+                audio_player = try audio.Player.init(alloc, .{
+                    .channels = params.num_channels,
+                    .format = params.format,
+                    .sample_rate = params.sample_rate,
+                });
+                // No UI for dealing with multiple streams, video ignored
+                break;
+            },
+            else => {},
+        }
+    }
+
+    return audio_player;
+}
@@ -0,0 +1,230 @@
+// Decodes still frames for the GUI on a background thread with its own
+// decoder, so that thumbnail requests never stall playback. Results are handed
+// back through gui_push_event
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const c = @import("c.zig");
+const decoder = @import("decoder.zig");
+const JobTracker = @import("JobTracker.zig");
+
+const ThumbnailGenerator = @This();
+
+const Shared = struct {
+    mutex: std.Thread.Mutex = .{},
+    cond: std.Thread.Condition = .{},
+    requests: std.ArrayList(c.GuiThumbnailRequest),
+    shutdown: bool = false,
+};
+
+const Worker = struct {
+    alloc: Allocator,
+    shared: *Shared,
+    dec: decoder.VideoDecoder,
+    stream_id: usize,
+    gui: ?*c.Gui,
+    sws_ctx: ?*c.SwsContext = null,
+    jobs: *JobTracker,
+    job_id: u64,
+    // Thumbnails generated since the queue last ran dry
+    num_completed: usize = 0,
+
+    fn run(self: *Worker) void {
+        while (true) {
+            const request = blk: {
+                self.shared.mutex.lock();
+                defer self.shared.mutex.unlock();
+
+                while (self.shared.requests.items.len == 0 and !self.shared.shutdown) {
+                    if (self.num_completed != 0) {
+                        self.jobs.finish(self.job_id, c.gui_job_state_finished);
+                        self.num_completed = 0;
+                    }
+                    self.shared.cond.wait(&self.shared.mutex);
+                }
+
+                if (self.shared.shutdown) {
+                    return;
+                }
+
+                const num_queued = self.shared.requests.items.len;
+                self.jobs.setProgress(self.job_id, @as(f32, @floatFromInt(self.num_completed)) / @as(f32, @floatFromInt(self.num_completed + num_queued)));
+
+                // Newest first, whatever the GUI asked for last is what it is
+                // most likely still waiting on
+                break :blk self.shared.requests.pop();
+            };
+
+            self.generate(request) catch |e| {
+                std.log.err("Failed to generate thumbnail at {d}: {s}", .{ request.time, @errorName(e) });
+                const event = c.GuiEvent{
+                    .tag = c.gui_event_thumbnail_failed,
+                    .data = .{ .request_id = request.request_id },
+                };
+                c.gui_push_event(self.gui, &event);
+            };
+            self.num_completed += 1;
+        }
+    }
+
+    fn generate(self: *Worker, request: c.GuiThumbnailRequest) !void {
+        try self.dec.seek(request.time, self.stream_id);
+
+        // Seeking lands on the keyframe before the requested time, decode
+        // forwards until we reach it
+        while (true) {
+            var frame = try self.dec.next(self.stream_id) orelse {
+                return error.NoFrame;
+            };
+            defer frame.deinit();
+
+            switch (frame) {
+                .video => |vf| {
+                    if (vf.pts < request.time) {
+                        continue;
+                    }
+
+                    try self.pushThumbnail(request, self.dec.frame_pool.get(vf.frame_id));
+                    return;
+                },
+                .audio => continue,
+            }
+        }
+    }
+
+    fn pushThumbnail(self: *Worker, request: c.GuiThumbnailRequest, frame: *c.AVFrame) !void {
+        // Fit into the requested size while keeping the aspect ratio
+        const scale = @min(
+            @as(f32, @floatFromInt(request.width)) / @as(f32, @floatFromInt(frame.width)),
+            @as(f32, @floatFromInt(request.height)) / @as(f32, @floatFromInt(frame.height)),
+        );
+        const width: c_int = @max(1, @as(c_int, @intFromFloat(@as(f32, @floatFromInt(frame.width)) * scale)));
+        const height: c_int = @max(1, @as(c_int, @intFromFloat(@as(f32, @floatFromInt(frame.height)) * scale)));
+
+        self.sws_ctx = c.sws_getCachedContext(self.sws_ctx, frame.width, frame.height, frame.format, width, height, c.AV_PIX_FMT_RGBA, c.SWS_BILINEAR, null, null, null) orelse {
+            return error.ScalingContext;
+        };
+
+        const stride: usize = @as(usize, @intCast(width)) * 4;
+        const rgba = try self.alloc.alloc(u8, stride * @as(usize, @intCast(height)));
+        defer self.alloc.free(rgba);
+
+        var dst_data = [4][*c]u8{ rgba.ptr, null, null, null };
+        var dst_linesize = [4]c_int{ @intCast(stride), 0, 0, 0 };
+        if (c.sws_scale(self.sws_ctx, @ptrCast(&frame.data), &frame.linesize, 0, frame.height, &dst_data, &dst_linesize) < 0) {
+            return error.Scale;
+        }
+
+        const event = c.GuiEvent{
+            .tag = c.gui_event_thumbnail_ready,
+            .data = .{ .thumbnail = .{
+                .request_id = request.request_id,
+                .width = @intCast(width),
+                .height = @intCast(height),
+                .rgba = rgba.ptr,
+            } },
+        };
+        c.gui_push_event(self.gui, &event);
+    }
+
+    fn deinit(self: *Worker) void {
+        c.sws_freeContext(self.sws_ctx);
+        self.dec.deinit();
+    }
+};
+
+alloc: Allocator,
+// Owned, but needs a stable location for the worker thread
+shared: *Shared,
+worker: *Worker,
+thread: std.Thread,
+
+pub fn init(alloc: Allocator, path: [:0]const u8, gui: ?*c.Gui, jobs: *JobTracker) !ThumbnailGenerator {
+    const shared = try alloc.create(Shared);
+    errdefer alloc.destroy(shared);
+
+    shared.* = .{
+        .requests = std.ArrayList(c.GuiThumbnailRequest).init(alloc),
+    };
+    errdefer shared.requests.deinit();
+
+    var dec = try decoder.VideoDecoder.init(alloc, path);
+    errdefer dec.deinit();
+
+    const stream_id = try findFirstVideoStream(&dec) orelse {
+        std.log.err("No video stream found", .{});
+        return error.NoVideoStream;
+    };
+
+    // One job for the lifetime of the generator, running while there are
+    // requests queued. Individual thumbnails are too cheap to be worth
+    // cancelling
+    const job_id = try jobs.add(c.gui_job_kind_thumbnails, null);
+    jobs.finish(job_id, c.gui_job_state_finished);
+
+    const worker = try alloc.create(Worker);
+    errdefer alloc.destroy(worker);
+
+    worker.* = .{
+        .alloc = alloc,
+        .shared = shared,
+        .dec = dec,
+        .stream_id = stream_id,
+        .gui = gui,
+        .jobs = jobs,
+        .job_id = job_id,
+    };
+
+    const thread = try std.Thread.spawn(.{}, Worker.run, .{worker});
+
+    return .{
+        .alloc = alloc,
+        .shared = shared,
+        .worker = worker,
+        .thread = thread,
+    };
+}
+
+pub fn deinit(self: *ThumbnailGenerator) void {
+    {
+        self.shared.mutex.lock();
+        defer self.shared.mutex.unlock();
+        self.shared.shutdown = true;
+    }
+    self.shared.cond.signal();
+    self.thread.join();
+
+    self.worker.deinit();
+    self.alloc.destroy(self.worker);
+    self.shared.requests.deinit();
+    self.alloc.destroy(self.shared);
+}
+
+// Starts over with a decoder for path, e.g. after the file changed on disk.
+// Queued requests are dropped, the GUI throws away its thumbnails on reload
+pub fn reload(self: *ThumbnailGenerator, path: [:0]const u8) !void {
+    const new_generator = try init(self.alloc, path, self.worker.gui, self.worker.jobs);
+    self.deinit();
+    self.* = new_generator;
+}
+
+pub fn request(self: *ThumbnailGenerator, req: c.GuiThumbnailRequest) !void {
+    {
+        self.shared.mutex.lock();
+        defer self.shared.mutex.unlock();
+        try self.shared.requests.append(req);
+    }
+    self.shared.cond.signal();
+}
+
+fn findFirstVideoStream(dec: *decoder.VideoDecoder) !?usize {
+    var stream_it = dec.streams();
+    while (try stream_it.next()) |stream| {
+        switch (stream) {
+            .video => |vs| return vs.stream_id,
+            else => continue,
+        }
+    }
+
+    return null;
+}
@@ -4,6 +4,8 @@ const std = @import("std");
 const audio = @import("audio.zig");
 const save = @import("save.zig");
 const WavWriter = @import("WavWriter.zig");
+const JobTracker = @import("JobTracker.zig");
+const captions_mod = @import("captions.zig");
 const Allocator = std.mem.Allocator;
 const Thread = std.Thread;
 const Mutex = Thread.Mutex;
@@ -13,11 +15,55 @@ const SegmentBounds = struct {
     char_end: usize,
     start: f32,
     end: f32,
+    // Mean probability whisper assigned to the tokens of the word, 0-1
+    confidence: f32 = 1.0,
 };
 
 alloc: Allocator,
-init_thread: Thread,
+run_params: RunParams,
+gui: ?*c.Gui,
+// Null once stopped
+thread: ?Thread,
+// The transcript wtm_* and snapshots read from
 shared: *Shared,
+// Output of a run started with retranscribe. Swapped into shared once the run
+// completes, so that the old transcript stays usable until then
+rerun_shared: ?*Shared = null,
+
+pub const Model = enum {
+    builtin,
+    tiny,
+    base,
+    small,
+    medium,
+    large,
+};
+
+pub const Options = struct {
+    model: Model = .builtin,
+    // Whisper language code, or "auto" to detect it
+    language: [max_language_len:0]u8 = languageFromSlice("en"),
+
+    pub const max_language_len = 8;
+
+    // Codes longer than max_language_len get truncated
+    pub fn languageFromSlice(code: []const u8) [max_language_len:0]u8 {
+        var ret = [_:0]u8{0} ** max_language_len;
+        const len = @min(code.len, max_language_len);
+        @memcpy(ret[0..len], code[0..len]);
+        return ret;
+    }
+};
+
+// Everything a transcription run needs besides where its output goes
+const RunParams = struct {
+    alloc: Allocator,
+    path: [:0]const u8,
+    debug_output: ?[]const u8,
+    // Holds ggml-<model>.bin for the models that are not built in
+    model_dir: []const u8,
+    jobs: *JobTracker,
+};
 
 const Shared = struct {
     mutex: Mutex,
@@ -137,6 +183,7 @@ const WhisperRunner = struct {
             // Relative to this run of whisper
             buf_start_s: f32,
             buf_end_s: f32,
+            confidence: f32,
         };
 
         fn next(self: *@This()) ?Output {
@@ -160,8 +207,32 @@ const WhisperRunner = struct {
                 .file_start_s = buf_segment_start_s + @as(f32, @floatFromInt(self.start_time_cs)) / 100,
                 .file_end_s = buf_segment_end_s + @as(f32, @floatFromInt(self.start_time_cs)) / 100,
                 .text = s[0..s_len],
+                .confidence = self.segmentConfidence(),
             };
         }
+
+        fn segmentConfidence(self: *const @This()) f32 {
+            // Timestamps and other special tokens come after eot and say
+            // nothing about how well the words were recognized
+            const eot = c.whisper_token_eot(self.ctx);
+
+            var p_sum: f32 = 0.0;
+            var num_tokens: usize = 0;
+            const n_tokens = c.whisper_full_n_tokens(self.ctx, self.i);
+            var token_idx: c_int = 0;
+            while (token_idx < n_tokens) : (token_idx += 1) {
+                if (c.whisper_full_get_token_id(self.ctx, self.i, token_idx) >= eot) {
+                    continue;
+                }
+                p_sum += c.whisper_full_get_token_p(self.ctx, self.i, token_idx);
+                num_tokens += 1;
+            }
+
+            if (num_tokens == 0) {
+                return 1.0;
+            }
+            return p_sum / @as(f32, @floatFromInt(num_tokens));
+        }
     };
 
     fn abortCallback(userdata: ?*anyopaque) callconv(.C) bool {
@@ -169,17 +240,14 @@ const WhisperRunner = struct {
         return val.load(std.builtin.AtomicOrder.unordered);
     }
 
-    fn init(shutdown: *std.atomic.Value(bool), required_context_s: f32) !WhisperRunner {
+    // options has to outlive the runner
+    fn init(alloc: Allocator, shutdown: *std.atomic.Value(bool), required_context_s: f32, model_dir: []const u8, options: *const Options) !WhisperRunner {
         c.whisper_log_set(logCallback, null);
 
-        const cparams = c.whisper_context_default_params();
-        const model: []const u8 = @embedFile("WordTimestampGenerator/ggml-tiny.en-q5_1.bin");
-        const ctx = c.whisper_init_from_buffer_with_params(@constCast(model.ptr), model.len, cparams) orelse {
-            std.log.err("Failed to create whisper context", .{});
-            return error.Whisper;
-        };
+        const ctx = try loadModel(alloc, model_dir, options.model);
 
         var params = c.whisper_full_default_params(c.WHISPER_SAMPLING_BEAM_SEARCH);
+        params.language = &options.language;
         params.max_len = 1;
         params.token_timestamps = true;
         params.abort_callback = abortCallback;
@@ -193,6 +261,26 @@ const WhisperRunner = struct {
         };
     }
 
+    fn loadModel(alloc: Allocator, model_dir: []const u8, model: Model) !*c.whisper_context {
+        const cparams = c.whisper_context_default_params();
+
+        if (model == .builtin) {
+            const buf: []const u8 = @embedFile("WordTimestampGenerator/ggml-tiny.en-q5_1.bin");
+            return c.whisper_init_from_buffer_with_params(@constCast(buf.ptr), buf.len, cparams) orelse {
+                std.log.err("Failed to create whisper context", .{});
+                return error.Whisper;
+            };
+        }
+
+        const model_path = try std.fmt.allocPrintZ(alloc, "{s}/ggml-{s}.bin", .{ model_dir, @tagName(model) });
+        defer alloc.free(model_path);
+
+        return c.whisper_init_from_file_with_params(model_path, cparams) orelse {
+            std.log.err("Failed to load whisper model from {s}", .{model_path});
+            return error.Whisper;
+        };
+    }
+
     fn deinit(self: *WhisperRunner) void {
         c.whisper_free(self.ctx);
     }
@@ -253,6 +341,7 @@ fn pushWhisperSegments(segment_it: *WhisperRunner.SegmentIt, shared: *Shared, bu
             .end = whisper_segment.file_end_s,
             .char_start = shared.text.items.len,
             .char_end = shared.text.items.len + whisper_segment.text.len,
+            .confidence = whisper_segment.confidence,
         };
 
         try shared.segments.append(segment);
@@ -269,6 +358,7 @@ fn pushWhisperSegments(segment_it: *WhisperRunner.SegmentIt, shared: *Shared, bu
             .end = whisper_segment.file_end_s,
             .char_start = shared.text.items.len,
             .char_end = shared.text.items.len + whisper_segment.text.len,
+            .confidence = whisper_segment.confidence,
         };
 
         // Any comparison with the last word should only happen at the
@@ -373,10 +463,65 @@ const DebugOutput = struct {
     }
 };
 
-fn initThread(alloc: Allocator, path: [:0]const u8, shared: *Shared, debug_output_path: ?[]const u8) !void {
-    var debug_output = try DebugOutput.init(debug_output_path);
+fn initThread(run_params: RunParams, shared: *Shared, options: Options, job_id: u64) !void {
+    const jobs = run_params.jobs;
+    transcribe(run_params, shared, &options, job_id) catch |e| {
+        jobs.finish(job_id, c.gui_job_state_failed);
+        return e;
+    };
+
+    // Cancelling the job and shutting down both go through the shutdown flag
+    if (shared.shutdown.load(std.builtin.AtomicOrder.unordered)) {
+        jobs.finish(job_id, c.gui_job_state_cancelled);
+    } else {
+        jobs.finish(job_id, c.gui_job_state_finished);
+    }
+}
+
+fn rerunThread(self: *Whisper, pending: *Shared, options: Options, job_id: u64) void {
+    const jobs = self.run_params.jobs;
+    transcribe(self.run_params, pending, &options, job_id) catch |e| {
+        std.log.err("Transcription failed: {s}", .{@errorName(e)});
+        jobs.finish(job_id, c.gui_job_state_failed);
+        pushEvent(self.gui, c.gui_event_transcription_failed);
+        return;
+    };
+
+    // Cancelled runs leave the current transcript alone
+    if (pending.shutdown.load(std.builtin.AtomicOrder.unordered)) {
+        jobs.finish(job_id, c.gui_job_state_cancelled);
+        return;
+    }
 
-    var dec = try decoder.VideoDecoder.init(alloc, path);
+    {
+        self.shared.mutex.lock();
+        defer self.shared.mutex.unlock();
+
+        // pending ends up with the old transcript, which gets freed with it
+        std.mem.swap(std.ArrayList(SegmentBounds), &self.shared.segments, &pending.segments);
+        std.mem.swap(std.ArrayList(u8), &self.shared.text, &pending.text);
+        std.mem.swap(std.ArrayList(u64), &self.shared.split_indices, &pending.split_indices);
+        self.shared.num_samples_processed = pending.num_samples_processed;
+    }
+
+    jobs.finish(job_id, c.gui_job_state_finished);
+    pushEvent(self.gui, c.gui_event_transcription_finished);
+}
+
+fn pushEvent(gui: ?*c.Gui, tag: c.enum_GuiEventTag) void {
+    const event = c.GuiEvent{
+        .tag = tag,
+        .data = .{ .request_id = 0 },
+    };
+    c.gui_push_event(gui, &event);
+}
+
+fn transcribe(run_params: RunParams, shared: *Shared, options: *const Options, job_id: u64) !void {
+    const alloc = run_params.alloc;
+    const jobs = run_params.jobs;
+    var debug_output = try DebugOutput.init(run_params.debug_output);
+
+    var dec = try decoder.VideoDecoder.init(alloc, run_params.path);
     defer dec.deinit();
 
     var sampler = try WhisperInputResampler.init(&dec);
@@ -386,6 +531,9 @@ fn initThread(alloc: Allocator, path: [:0]const u8, shared: *Shared, debug_outpu
         _ = try sampler.next();
     }
 
+    const expected_output_samples = dec.duration * whisper_sample_rate;
+    jobs.setProgress(job_id, @as(f32, @floatFromInt(sampler.output_samples)) / expected_output_samples);
+
     c.whisper_log_set(logCallback, null);
 
     const sample_size = sampler.stream.format.sampleSize();
@@ -398,7 +546,7 @@ fn initThread(alloc: Allocator, path: [:0]const u8, shared: *Shared, debug_outpu
 
     var buf_pos: usize = 0;
 
-    var whisper = try WhisperRunner.init(&shared.shutdown, required_context_s);
+    var whisper = try WhisperRunner.init(alloc, &shared.shutdown, required_context_s, run_params.model_dir, options);
     defer whisper.deinit();
     whisper.start_time_ms = @divTrunc(@as(i64, @intCast(shared.num_samples_processed)) * 1000, whisper_sample_rate);
 
@@ -433,6 +581,8 @@ fn initThread(alloc: Allocator, path: [:0]const u8, shared: *Shared, debug_outpu
 
             @memcpy(audio_buf[0 .. whisper_sample_rate * overlap_size_s * sample_size], audio_buf[audio_buf.len - whisper_sample_rate * overlap_size_s * sample_size .. audio_buf.len]);
             buf_pos = whisper_sample_rate * overlap_size_s * sample_size;
+
+            jobs.setProgress(job_id, @as(f32, @floatFromInt(sampler.output_samples)) / expected_output_samples);
         }
     }
 
@@ -440,7 +590,7 @@ fn initThread(alloc: Allocator, path: [:0]const u8, shared: *Shared, debug_outpu
     try pushWhisperSegments(&segment_it, shared, buf_size_s, required_context_s);
 }
 
-pub fn init(alloc: Allocator, path: [:0]const u8, init_data: ?save.Data.Field, debug_output: ?[]const u8) !Whisper {
+pub fn init(alloc: Allocator, path: [:0]const u8, init_data: ?save.Data.Field, debug_output: ?[]const u8, model_dir: []const u8, gui: ?*c.Gui, jobs: *JobTracker) !Whisper {
     var segments = std.ArrayList(SegmentBounds).init(alloc);
     errdefer segments.deinit();
 
@@ -451,11 +601,24 @@ pub fn init(alloc: Allocator, path: [:0]const u8, init_data: ?save.Data.Field, d
     errdefer alloc.destroy(shared);
     shared.* = try sharedFromInitData(alloc, init_data);
 
-    const init_thread = try Thread.spawn(.{}, initThread, .{ alloc, path, shared, debug_output });
+    const job_id = try jobs.add(c.gui_job_kind_transcription, &shared.shutdown);
+    errdefer jobs.finish(job_id, c.gui_job_state_failed);
+
+    const run_params = RunParams{
+        .alloc = alloc,
+        .path = path,
+        .debug_output = debug_output,
+        .model_dir = model_dir,
+        .jobs = jobs,
+    };
+
+    const init_thread = try Thread.spawn(.{}, initThread, .{ run_params, shared, Options{}, job_id });
 
     var ret = Whisper{
         .alloc = alloc,
-        .init_thread = init_thread,
+        .run_params = run_params,
+        .gui = gui,
+        .thread = init_thread,
         .shared = shared,
     };
 
@@ -464,12 +627,53 @@ pub fn init(alloc: Allocator, path: [:0]const u8, init_data: ?save.Data.Field, d
 }
 
 pub fn deinit(self: *Whisper) void {
-    self.shared.shutdown.store(true, std.builtin.AtomicOrder.unordered);
-    self.init_thread.join();
+    self.stop();
+    self.freeRerunShared();
     self.shared.deinit();
     self.alloc.destroy(self.shared);
 }
 
+// Starts transcribing from scratch with different options. Whatever is
+// running right now is stopped, the current transcript stays until the new
+// one is complete. Clips are time based and are not affected
+pub fn retranscribe(self: *Whisper, options: Options) !void {
+    self.stop();
+    self.freeRerunShared();
+
+    const pending = try self.alloc.create(Shared);
+    errdefer self.alloc.destroy(pending);
+    pending.* = try sharedFromInitData(self.alloc, null);
+    errdefer pending.deinit();
+
+    const jobs = self.run_params.jobs;
+    const job_id = try jobs.add(c.gui_job_kind_transcription, &pending.shutdown);
+    errdefer jobs.finish(job_id, c.gui_job_state_failed);
+
+    // self has to stay put while the thread is running, which holds as long as
+    // nobody moves the generator between init and deinit
+    self.thread = try Thread.spawn(.{}, rerunThread, .{ self, pending, options, job_id });
+    self.rerun_shared = pending;
+}
+
+fn stop(self: *Whisper) void {
+    const thread = self.thread orelse {
+        return;
+    };
+
+    const running_shared = self.rerun_shared orelse self.shared;
+    running_shared.shutdown.store(true, std.builtin.AtomicOrder.unordered);
+    thread.join();
+    self.thread = null;
+}
+
+fn freeRerunShared(self: *Whisper) void {
+    if (self.rerun_shared) |s| {
+        s.deinit();
+        self.alloc.destroy(s);
+        self.rerun_shared = null;
+    }
+}
+
 pub const SaveData = struct {
     text: []const u8,
     segment_timestamps: []const SegmentBounds,
@@ -508,6 +712,180 @@ fn calculateSplits(self: *Whisper) !void {
     self.shared.split_indices = split_indices;
 }
 
+pub const Caption = struct {
+    // Range in the transcript text, without surrounding whitespace
+    char_start: usize,
+    char_end: usize,
+    start: f32,
+    end: f32,
+};
+
+pub const CaptionParams = struct {
+    max_line_len: usize = 42,
+    // A silence at least this long ends the caption
+    max_pause_s: f32 = 1.0,
+    max_duration_s: f32 = 6.0,
+};
+
+// Groups words into caption lines. Lines end at the end of a sentence, or
+// before a word that would break one of the limits in params. Punctuation is
+// always kept with the word before it
+fn groupCaptions(alloc: Allocator, text: []const u8, segments: []const SegmentBounds, params: CaptionParams) ![]Caption {
+    var ret = std.ArrayList(Caption).init(alloc);
+    errdefer ret.deinit();
+
+    var current: ?Caption = null;
+    for (segments) |segment| {
+        if (segment.char_start >= segment.char_end or segment.char_end > text.len) {
+            continue;
+        }
+
+        const word_text = text[segment.char_start..segment.char_end];
+        const trimmed = std.mem.trimLeft(u8, word_text, " ");
+        if (trimmed.len == 0) {
+            continue;
+        }
+
+        if (current) |*caption| {
+            const fits = segment.char_end - caption.char_start <= params.max_line_len and
+                segment.start - caption.end < params.max_pause_s and
+                segment.end - caption.start <= params.max_duration_s;
+
+            if (fits or isPunctuation(word_text)) {
+                caption.char_end = segment.char_end;
+                caption.end = @max(caption.end, segment.end);
+            } else {
+                try ret.append(caption.*);
+                current = null;
+            }
+        }
+
+        if (current == null) {
+            current = .{
+                .char_start = segment.char_end - trimmed.len,
+                .char_end = segment.char_end,
+                .start = segment.start,
+                .end = segment.end,
+            };
+        }
+
+        if (shouldSplit(word_text)) {
+            try ret.append(current.?);
+            current = null;
+        }
+    }
+
+    if (current) |caption| {
+        try ret.append(caption);
+    }
+
+    return try ret.toOwnedSlice();
+}
+
+// Caption lines for the current transcript, owned by the caller
+pub fn captions(self: *Whisper, alloc: Allocator, params: CaptionParams) ![]Caption {
+    self.shared.mutex.lock();
+    defer self.shared.mutex.unlock();
+
+    return groupCaptions(alloc, self.shared.text.items, self.shared.segments.items, params);
+}
+
+// Writes the transcript as a caption file to path
+pub fn exportCaptions(self: *Whisper, path: []const u8, style: c.GuiCaptionStyle, format: captions_mod.Format) !void {
+    self.shared.mutex.lock();
+    defer self.shared.mutex.unlock();
+
+    const text = self.shared.text.items;
+    const lines = try groupCaptions(self.alloc, text, self.shared.segments.items, .{
+        .max_line_len = style.max_line_len,
+    });
+    defer self.alloc.free(lines);
+
+    const f = try std.fs.cwd().createFile(path, .{});
+    defer f.close();
+
+    var buffered = std.io.bufferedWriter(f.writer());
+    switch (format) {
+        .srt => try captions_mod.writeSrt(buffered.writer(), text, lines),
+        .webvtt => try captions_mod.writeVtt(buffered.writer(), text, lines, style),
+    }
+    try buffered.flush();
+}
+
+// Caption files are small, anything past this is not one
+const max_caption_file_size = 16 * 1024 * 1024;
+
+// Replaces the transcript with the cues of an SRT or WebVTT file, e.g.
+// subtitles that were already corrected by hand. Any running transcription is
+// stopped, duration_s marks the whole video as processed so that it does not
+// start again on the next load
+pub fn importCaptions(self: *Whisper, path: []const u8, duration_s: f32) !void {
+    const data = try std.fs.cwd().readFileAlloc(self.alloc, path, max_caption_file_size);
+    defer self.alloc.free(data);
+
+    const cues = try captions_mod.parseCues(self.alloc, data);
+    defer {
+        captions_mod.freeCues(self.alloc, cues);
+        self.alloc.free(cues);
+    }
+
+    var text = std.ArrayList(u8).init(self.alloc);
+    defer text.deinit();
+
+    var segments = std.ArrayList(SegmentBounds).init(self.alloc);
+    defer segments.deinit();
+
+    try segmentsFromCues(cues, &text, &segments);
+
+    self.stop();
+    self.freeRerunShared();
+
+    {
+        self.shared.mutex.lock();
+        defer self.shared.mutex.unlock();
+
+        // The old transcript ends up in the locals and is freed with them
+        std.mem.swap(std.ArrayList(SegmentBounds), &self.shared.segments, &segments);
+        std.mem.swap(std.ArrayList(u8), &self.shared.text, &text);
+        self.shared.num_samples_processed = @intFromFloat(duration_s * whisper_sample_rate);
+    }
+
+    try self.calculateSplits();
+}
+
+// Caption files only time whole cues, so each word gets a share of its cue
+// proportional to its length. Words are stored with a leading space, the same
+// way whisper hands them to us
+fn segmentsFromCues(cues: []const captions_mod.Cue, text: *std.ArrayList(u8), segments: *std.ArrayList(SegmentBounds)) !void {
+    for (cues) |cue| {
+        var num_chars: usize = 0;
+        var word_it = std.mem.tokenizeScalar(u8, cue.text, ' ');
+        while (word_it.next()) |word| {
+            num_chars += word.len;
+        }
+
+        if (num_chars == 0) continue;
+
+        const s_per_char = @max(cue.end - cue.start, 0.0) / @as(f32, @floatFromInt(num_chars));
+        var word_start = cue.start;
+
+        word_it.reset();
+        while (word_it.next()) |word| {
+            const word_end = word_start + s_per_char * @as(f32, @floatFromInt(word.len));
+            const char_start = text.items.len;
+            try text.append(' ');
+            try text.appendSlice(word);
+            try segments.append(.{
+                .char_start = char_start,
+                .char_end = text.items.len,
+                .start = word_start,
+                .end = word_end,
+            });
+            word_start = word_end;
+        }
+    }
+}
+
 fn sharedFromInitData(alloc: Allocator, init_data: ?save.Data.Field) !Shared {
     var segments = std.ArrayList(SegmentBounds).init(alloc);
     errdefer segments.deinit();
@@ -560,6 +938,58 @@ pub export fn wtm_get_time(m: *Whisper, char_pos: u64) f32 {
     return m.shared.segments.items[elem -| 1].start;
 }
 
+pub export fn wtm_get_words(m: *Whisper, offset: u64, out: [*c]c.GuiWord, capacity: u64, total: *u64) u64 {
+    m.shared.mutex.lock();
+    defer m.shared.mutex.unlock();
+
+    const segments = m.shared.segments.items;
+    total.* = segments.len;
+
+    const start: usize = @intCast(offset);
+    if (start >= segments.len) {
+        return 0;
+    }
+
+    const num_copied: usize = @intCast(@min(segments.len - start, capacity));
+    for (segments[start .. start + num_copied], 0..) |segment, i| {
+        out[i] = .{
+            .char_start = segment.char_start,
+            .char_end = segment.char_end,
+            .start = segment.start,
+            .end = segment.end,
+            .confidence = segment.confidence,
+        };
+    }
+    return num_copied;
+}
+
+pub export fn wtm_get_captions(m: *Whisper, max_line_len: u64, offset: u64, out: [*c]c.GuiCaption, capacity: u64, total: *u64) u64 {
+    total.* = 0;
+
+    const lines = m.captions(m.alloc, .{ .max_line_len = @intCast(max_line_len) }) catch |e| {
+        std.log.err("Failed to group captions: {s}", .{@errorName(e)});
+        return 0;
+    };
+    defer m.alloc.free(lines);
+    total.* = lines.len;
+
+    const start: usize = @intCast(offset);
+    if (start >= lines.len) {
+        return 0;
+    }
+
+    const num_copied: usize = @intCast(@min(lines.len - start, capacity));
+    for (lines[start .. start + num_copied], 0..) |line, i| {
+        out[i] = .{
+            .char_start = line.char_start,
+            .char_end = line.char_end,
+            .start = line.start,
+            .end = line.end,
+        };
+    }
+    return num_copied;
+}
+
 pub export fn wtm_get_char_pos(m: *Whisper, pts: f32) u64 {
     const lessThan = struct {
         fn f(_: void, lhs: f32, rhs: SegmentBounds) bool {
@@ -3,6 +3,7 @@ pub usingnamespace @cImport({
     @cInclude("libavcodec/avcodec.h");
     @cInclude("libavutil/samplefmt.h");
     @cInclude("libavutil/imgutils.h");
+    @cInclude("libswscale/swscale.h");
     @cInclude("miniaudio.h");
     @cInclude("gui.h");
     @cInclude("whisper.h");
@@ -0,0 +1,263 @@
+// Caption style defaults, caption file writers and the parser for importing
+// them. Grouping the transcript into caption lines happens in
+// WordTimestampGenerator, which owns the words
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const c = @import("c.zig");
+const WordTimestampGenerator = @import("WordTimestampGenerator.zig");
+
+pub const default_style = c.GuiCaptionStyle{
+    .font = c.gui_caption_font_proportional,
+    .size = 0.05,
+    .color = 0xffffffff,
+    .outline_color = 0x000000ff,
+    .outline_width = 0.08,
+    .position = 0.05,
+    .max_line_len = 42,
+};
+
+// Swaps the extension of save_path for extension, e.g. project.json ->
+// project.srt
+pub fn exportPath(alloc: Allocator, save_path: []const u8, extension: []const u8) ![]u8 {
+    const stem_len = save_path.len - std.fs.path.extension(save_path).len;
+    return std.mem.concat(alloc, u8, &.{ save_path[0..stem_len], extension });
+}
+
+pub fn writeSrt(writer: anytype, text: []const u8, lines: []const WordTimestampGenerator.Caption) !void {
+    for (lines, 1..) |line, i| {
+        try writer.print("{d}\n", .{i});
+        try writeTimestamp(writer, line.start, ',');
+        try writer.writeAll(" --> ");
+        try writeTimestamp(writer, line.end, ',');
+        try writer.print("\n{s}\n\n", .{text[line.char_start..line.char_end]});
+    }
+}
+
+pub const Format = enum {
+    srt,
+    webvtt,
+
+    pub fn extension(self: Format) []const u8 {
+        return switch (self) {
+            .srt => ".srt",
+            .webvtt => ".vtt",
+        };
+    }
+};
+
+// WebVTT has no way to size text relative to the video, so only the position,
+// colors, font family and outline are carried over from the style
+pub fn writeVtt(writer: anytype, text: []const u8, lines: []const WordTimestampGenerator.Caption, style: c.GuiCaptionStyle) !void {
+    try writer.writeAll("WEBVTT\n\n");
+
+    const font_family = if (style.font == c.gui_caption_font_monospace) "monospace" else "sans-serif";
+    try writer.print("STYLE\n::cue {{\n  font-family: {s};\n  color: ", .{font_family});
+    try writeCssColor(writer, style.color);
+    try writer.writeAll(";\n  background-color: transparent;\n");
+    if (style.outline_width > 0) {
+        // Four offset shadows are the closest CSS gets to a text outline
+        try writer.writeAll("  text-shadow:");
+        const offsets = [_][2]f32{ .{ -1, -1 }, .{ 1, -1 }, .{ -1, 1 }, .{ 1, 1 } };
+        for (offsets, 0..) |offset, i| {
+            if (i != 0) try writer.writeByte(',');
+            try writer.print(" {d:.3}em {d:.3}em 0 ", .{
+                offset[0] * style.outline_width,
+                offset[1] * style.outline_width,
+            });
+            try writeCssColor(writer, style.outline_color);
+        }
+        try writer.writeAll(";\n");
+    }
+    try writer.writeAll("}\n\n");
+
+    // line-align end puts the bottom of the cue on the line, which matches how
+    // the position is measured in the preview
+    const line_pct = std.math.clamp(100.0 - style.position * 100.0, 0.0, 100.0);
+
+    for (lines) |line| {
+        try writeTimestamp(writer, line.start, '.');
+        try writer.writeAll(" --> ");
+        try writeTimestamp(writer, line.end, '.');
+        try writer.print(" line:{d:.1}%,end align:center\n", .{line_pct});
+        try writeVttText(writer, text[line.char_start..line.char_end]);
+        try writer.writeAll("\n\n");
+    }
+}
+
+// Cue text is parsed for tags and entities, so markup characters have to be
+// escaped
+fn writeVttText(writer: anytype, s: []const u8) !void {
+    for (s) |char| {
+        switch (char) {
+            '&' => try writer.writeAll("&amp;"),
+            '<' => try writer.writeAll("&lt;"),
+            '>' => try writer.writeAll("&gt;"),
+            else => try writer.writeByte(char),
+        }
+    }
+}
+
+fn writeCssColor(writer: anytype, rgba: u32) !void {
+    const bytes = std.mem.toBytes(std.mem.nativeToBig(u32, rgba));
+    try writer.print("rgba({d}, {d}, {d}, {d:.3})", .{
+        bytes[0],
+        bytes[1],
+        bytes[2],
+        @as(f32, @floatFromInt(bytes[3])) / 255.0,
+    });
+}
+
+pub const Cue = struct {
+    start: f32,
+    end: f32,
+    // Tags are stripped and the lines of the cue joined with spaces
+    text: []const u8,
+};
+
+// Reads the cues of an SRT or WebVTT file. Both are blocks separated by blank
+// lines with a "start --> end" timing line, blocks without one, e.g. the
+// WEBVTT header, STYLE or NOTE blocks, are skipped. Cue numbers come before
+// the timing line and are skipped along with them. Cue texts are allocated
+// with alloc, free them with freeCues
+pub fn parseCues(alloc: Allocator, data: []const u8) ![]Cue {
+    var cues = std.ArrayList(Cue).init(alloc);
+    defer cues.deinit();
+    errdefer freeCues(alloc, cues.items);
+
+    var text = std.ArrayList(u8).init(alloc);
+    defer text.deinit();
+
+    var timing: ?[2]f32 = null;
+    var line_it = std.mem.splitScalar(u8, data, '\n');
+    while (line_it.next()) |raw_line| {
+        const line = std.mem.trim(u8, raw_line, " \t\r");
+
+        if (line.len == 0) {
+            if (timing) |t| try finishCue(alloc, &cues, t, &text);
+            timing = null;
+            continue;
+        }
+
+        if (timing == null) {
+            if (std.mem.indexOf(u8, line, "-->") != null) {
+                timing = try parseTiming(line);
+            }
+            continue;
+        }
+
+        if (text.items.len > 0) try text.append(' ');
+        try appendCueText(&text, line);
+    }
+
+    if (timing) |t| try finishCue(alloc, &cues, t, &text);
+
+    return try cues.toOwnedSlice();
+}
+
+pub fn freeCues(alloc: Allocator, cues: []const Cue) void {
+    for (cues) |cue| {
+        alloc.free(cue.text);
+    }
+}
+
+fn finishCue(alloc: Allocator, cues: *std.ArrayList(Cue), timing: [2]f32, text: *std.ArrayList(u8)) !void {
+    defer text.clearRetainingCapacity();
+
+    // Nothing to put in the transcript
+    if (text.items.len == 0) return;
+
+    const cue_text = try alloc.dupe(u8, text.items);
+    errdefer alloc.free(cue_text);
+
+    try cues.append(.{
+        .start = timing[0],
+        .end = timing[1],
+        .text = cue_text,
+    });
+}
+
+// "start --> end", optionally followed by WebVTT cue settings
+fn parseTiming(line: []const u8) ![2]f32 {
+    const arrow = std.mem.indexOf(u8, line, "-->") orelse return error.InvalidTiming;
+    const start = std.mem.trim(u8, line[0..arrow], " \t");
+
+    var end_it = std.mem.tokenizeAny(u8, line[arrow + 3 ..], " \t");
+    const end = end_it.next() orelse return error.InvalidTiming;
+
+    return .{ try parseTimestamp(start), try parseTimestamp(end) };
+}
+
+// [HH:]MM:SS with a comma (SRT) or a dot (WebVTT) before the fraction
+pub fn parseTimestamp(s: []const u8) !f32 {
+    var ret: f32 = 0;
+    var num_parts: usize = 0;
+    var part_it = std.mem.splitScalar(u8, s, ':');
+    while (part_it.next()) |part| : (num_parts += 1) {
+        const separator = std.mem.indexOfAny(u8, part, ",.");
+        const whole = try std.fmt.parseInt(u32, part[0 .. separator orelse part.len], 10);
+        ret = ret * 60 + @as(f32, @floatFromInt(whole));
+
+        if (separator) |i| {
+            // Only the seconds have a fraction
+            if (part_it.peek() != null) return error.InvalidTimestamp;
+
+            const fraction = part[i + 1 ..];
+            const fraction_val = try std.fmt.parseInt(u32, fraction, 10);
+            const scale = std.math.pow(f32, 10, @floatFromInt(fraction.len));
+            ret += @as(f32, @floatFromInt(fraction_val)) / scale;
+        }
+    }
+
+    if (num_parts < 2 or num_parts > 3) return error.InvalidTimestamp;
+    return ret;
+}
+
+// Drops formatting tags, e.g. <i> or <c.yellow>, and undoes the escaping
+// writeVttText does
+fn appendCueText(out: *std.ArrayList(u8), line: []const u8) !void {
+    const entities = [_][2][]const u8{
+        .{ "&amp;", "&" },
+        .{ "&lt;", "<" },
+        .{ "&gt;", ">" },
+        .{ "&nbsp;", " " },
+    };
+
+    var i: usize = 0;
+    outer: while (i < line.len) {
+        switch (line[i]) {
+            '<' => {
+                const tag_end = std.mem.indexOfScalarPos(u8, line, i, '>') orelse line.len - 1;
+                i = tag_end + 1;
+            },
+            '&' => {
+                for (entities) |entity| {
+                    if (std.mem.startsWith(u8, line[i..], entity[0])) {
+                        try out.appendSlice(entity[1]);
+                        i += entity[0].len;
+                        continue :outer;
+                    }
+                }
+                try out.append('&');
+                i += 1;
+            },
+            else => {
+                try out.append(line[i]);
+                i += 1;
+            },
+        }
+    }
+}
+
+// HH:MM:SS followed by milliseconds, SRT and WebVTT only disagree on the
+// separator
+pub fn writeTimestamp(writer: anytype, pts: f32, ms_separator: u8) !void {
+    const total_ms: u64 = @intFromFloat(@max(pts, 0.0) * 1000.0);
+    try writer.print("{d:0>2}:{d:0>2}:{d:0>2}{c}{d:0>3}", .{
+        total_ms / std.time.ms_per_hour,
+        total_ms / std.time.ms_per_min % 60,
+        total_ms / std.time.ms_per_s % 60,
+        ms_separator,
+        total_ms % std.time.ms_per_s,
+    });
+}
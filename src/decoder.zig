@@ -6,15 +6,16 @@ const Allocator = std.mem.Allocator;
 pub const FramePool = struct {
     // Frames may be released from other threads
     mutex: std.Thread.Mutex,
-    // All allocated AVFrames. Some may be in use
-    pool: std.ArrayList(*c.AVFrame),
+    // All allocated AVFrames. Some may be in use. Null entries were freed by
+    // trim() and are reallocated when their id is next acquired
+    pool: std.ArrayList(?*c.AVFrame),
     // Frames ready to be reused
     free_ids: std.ArrayList(usize),
 
     pub fn init(alloc: Allocator) FramePool {
         return .{
             .mutex = std.Thread.Mutex{},
-            .pool = std.ArrayList(*c.AVFrame).init(alloc),
+            .pool = std.ArrayList(?*c.AVFrame).init(alloc),
             .free_ids = std.ArrayList(usize).init(alloc),
         };
     }
@@ -47,18 +48,76 @@ pub const FramePool = struct {
         if (self.free_ids.items.len == 0) {
             try self.allocateFrame();
         }
-        return self.free_ids.pop();
+
+        const id = self.free_ids.pop();
+        if (self.pool.items[id] == null) {
+            self.pool.items[id] = makeFrame() catch |e| {
+                self.free_ids.appendAssumeCapacity(id);
+                return e;
+            };
+        }
+        return id;
+    }
+
+    pub fn get(self: *FramePool, id: usize) *c.AVFrame {
+        return self.pool.items[id].?;
     }
 
     pub fn release(self: *FramePool, id: usize) void {
         self.mutex.lock();
         defer self.mutex.unlock();
 
-        c.av_frame_unref(self.pool.items[id]);
+        c.av_frame_unref(self.pool.items[id].?);
         self.free_ids.append(id) catch {
             _ = self.pool.swapRemove(id);
         };
     }
+
+    // Frees all frames that are not currently in use
+    pub fn trim(self: *FramePool) void {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        for (self.free_ids.items) |id| {
+            c.av_frame_free(@ptrCast(&self.pool.items[id]));
+        }
+    }
+
+    // Whether any frame has been handed out and not released yet
+    pub fn inUse(self: *FramePool) bool {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        return self.free_ids.items.len < self.pool.items.len;
+    }
+
+    pub const MemoryUsage = struct {
+        video_bytes: u64 = 0,
+        audio_bytes: u64 = 0,
+    };
+
+    // Bytes held by frames that have been handed out and not released yet
+    pub fn memoryUsage(self: *FramePool) MemoryUsage {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        var ret = MemoryUsage{};
+        for (self.pool.items) |maybe_frame| {
+            const frame = maybe_frame orelse continue;
+
+            var frame_bytes: u64 = 0;
+            for (frame.buf) |buf| {
+                if (buf != null) frame_bytes += @intCast(buf.*.size);
+            }
+
+            if (frame.nb_samples > 0) {
+                ret.audio_bytes += frame_bytes;
+            } else {
+                ret.video_bytes += frame_bytes;
+            }
+        }
+        return ret;
+    }
 };
 
 pub const VideoFrame = struct {
@@ -70,6 +129,10 @@ pub const VideoFrame = struct {
     y: []const u8,
     u: []const u8,
     v: []const u8,
+    // Resolved, never AVCOL_SPC_UNSPECIFIED
+    colorspace: c.AVColorSpace,
+    // JPEG range, as opposed to the usual 16-235 of video
+    full_range: bool,
     frame_pool: *FramePool,
     frame_id: usize,
 
@@ -176,6 +239,22 @@ pub const StreamIt = struct {
     }
 };
 
+// Resolution video frames are handed out at. Anything below full trades
+// quality for decoding and upload speed while scrubbing
+pub const PreviewQuality = enum {
+    full,
+    half,
+    quarter,
+
+    fn divisor(self: PreviewQuality) c_int {
+        return switch (self) {
+            .full => 1,
+            .half => 2,
+            .quarter => 4,
+        };
+    }
+};
+
 const VideoDecoderError = error{
     Again,
     Unimplemented,
@@ -193,6 +272,9 @@ pub const VideoDecoder = struct {
     frame_pool: FramePool,
     packet: *c.AVPacket,
     duration: f32,
+    preview_quality: PreviewQuality,
+    // Only created once frames need to be scaled down
+    sws_ctx: ?*c.SwsContext,
 
     fn freeDecoderContexts(ctxs: *std.ArrayList(*c.AVCodecContext)) void {
         for (ctxs.items) |*ctx| {
@@ -298,6 +380,8 @@ pub const VideoDecoder = struct {
             .frame_pool = frame_pool,
             .packet = pkt,
             .duration = duration,
+            .preview_quality = .full,
+            .sws_ctx = null,
         };
     }
 
@@ -309,6 +393,7 @@ pub const VideoDecoder = struct {
     }
 
     pub fn deinit(self: *VideoDecoder) void {
+        c.sws_freeContext(self.sws_ctx);
         c.av_packet_free(@ptrCast(&self.packet));
         self.frame_pool.deinit();
         freeDecoderContexts(&self.decoder_ctxs);
@@ -334,32 +419,99 @@ pub const VideoDecoder = struct {
         }
     }
 
-    pub fn handleVideoFrame(self: *VideoDecoder, frame_id: usize) VideoDecoderError!Frame {
-        const frame = self.frame_pool.pool.items[frame_id];
-        if (frame.format != c.AV_PIX_FMT_YUV420P) {
+    // Time between frames of stream_id, falls back to 30 fps for streams that
+    // do not say
+    pub fn frameDuration(self: *VideoDecoder, stream_id: usize) f32 {
+        const frame_rate = self.fmt_ctx.streams[stream_id].*.avg_frame_rate;
+        if (frame_rate.num <= 0 or frame_rate.den <= 0) {
+            return 1.0 / 30.0;
+        }
+
+        return @as(f32, @floatFromInt(frame_rate.den)) / @as(f32, @floatFromInt(frame_rate.num));
+    }
+
+    pub fn setPreviewQuality(self: *VideoDecoder, quality: PreviewQuality) void {
+        self.preview_quality = quality;
+
+        // Skipping the deblocking filter makes a big difference for codecs
+        // that have one, and the artifacts are hard to spot once the frame is
+        // scaled down anyways
+        const skip_loop_filter = if (quality == .full) c.AVDISCARD_DEFAULT else c.AVDISCARD_ALL;
+        for (self.decoder_ctxs.items) |decoder_ctx| {
+            decoder_ctx.skip_loop_filter = skip_loop_filter;
+        }
+    }
+
+    // Scales the frame down according to preview_quality into a new frame
+    // from the pool, and releases the original
+    fn downscaleFrame(self: *VideoDecoder, frame_id: usize) VideoDecoderError!usize {
+        const src = self.frame_pool.get(frame_id);
+        const divisor = self.preview_quality.divisor();
+        // Keep dimensions even so that the chroma planes stay exactly half the
+        // size of the luma plane
+        const width = @max(2, @divTrunc(src.width, divisor) & ~@as(c_int, 1));
+        const height = @max(2, @divTrunc(src.height, divisor) & ~@as(c_int, 1));
+
+        self.sws_ctx = c.sws_getCachedContext(self.sws_ctx, src.width, src.height, c.AV_PIX_FMT_YUV420P, width, height, c.AV_PIX_FMT_YUV420P, c.SWS_FAST_BILINEAR, null, null, null) orelse {
+            std.log.err("Failed to create scaling context", .{});
+            return VideoDecoderError.InternalError;
+        };
+
+        const dst_id = try self.frame_pool.acquire();
+        errdefer self.frame_pool.release(dst_id);
+
+        const dst = self.frame_pool.get(dst_id);
+        try allocYuv420Buffer(dst, width, height);
+        dst.pts = src.pts;
+        dst.colorspace = src.colorspace;
+        dst.color_range = src.color_range;
+
+        if (c.sws_scale(self.sws_ctx, @ptrCast(&src.data), &src.linesize, 0, src.height, @ptrCast(&dst.data), &dst.linesize) < 0) {
+            std.log.err("Failed to scale frame", .{});
+            return VideoDecoderError.InternalError;
+        }
+
+        self.frame_pool.release(frame_id);
+        return dst_id;
+    }
+
+    pub fn handleVideoFrame(self: *VideoDecoder, decoded_frame_id: usize) VideoDecoderError!Frame {
+        const decoded_frame = self.frame_pool.get(decoded_frame_id);
+        if (decoded_frame.format != c.AV_PIX_FMT_YUV420P) {
             // Major assumption made in OpenGL conversion about data format
-            std.log.err("Unsupported frame format: {s}", .{c.av_get_pix_fmt_name(frame.format)});
+            std.log.err("Unsupported frame format: {s}", .{c.av_get_pix_fmt_name(decoded_frame.format)});
             return VideoDecoderError.Unimplemented;
         }
 
-        const width = try cIntToUsize(frame.width, "frame width");
-        const height = try cIntToUsize(frame.height, "frame height");
-        const stride = try cIntToUsize(frame.linesize[0], "frame stride");
+        // Before downscaling, a quarter resolution preview of a 1080p video is
+        // still HD as far as the guess goes
+        const decoded_height = try cIntToUsize(decoded_frame.height, "frame height");
+        const colorspace = resolveColorspaceYuv(decoded_frame.colorspace, decoded_height);
+        const full_range = decoded_frame.color_range == c.AVCOL_RANGE_JPEG;
 
-        const colorspace = resolveColorspaceYuv(frame.colorspace, height);
-
-        if (colorspace != c.AVCOL_SPC_BT470BG) {
+        if (colorspace != c.AVCOL_SPC_BT470BG and colorspace != c.AVCOL_SPC_SMPTE170M and colorspace != c.AVCOL_SPC_BT709) {
             const already = struct {
                 var warned: bool = false;
             };
 
             if (!already.warned) {
-                // Major assumption made in OpenGL conversion about data format
-                std.log.warn("Unsupported colorspace: {d}", .{frame.colorspace});
+                // The OpenGL conversion only knows BT.601 and BT.709
+                std.log.warn("Unsupported colorspace: {d}", .{decoded_frame.colorspace});
                 already.warned = true;
             }
         }
 
+        var frame_id = decoded_frame_id;
+        if (self.preview_quality != .full) {
+            frame_id = try self.downscaleFrame(frame_id);
+        }
+
+        const frame = self.frame_pool.get(frame_id);
+
+        const width = try cIntToUsize(frame.width, "frame width");
+        const height = try cIntToUsize(frame.height, "frame height");
+        const stride = try cIntToUsize(frame.linesize[0], "frame stride");
+
         if (frame.linesize[1] != @divTrunc(frame.linesize[0], 2) or frame.linesize[2] != @divTrunc(frame.linesize[0], 2)) {
             std.log.err("Assumption that UV channel stride is half Y stride is not true", .{});
             return VideoDecoderError.Unimplemented;
@@ -380,6 +532,8 @@ pub const VideoDecoder = struct {
             .y = y,
             .u = u,
             .v = v,
+            .colorspace = colorspace,
+            .full_range = full_range,
             .pts = pts,
             .frame_pool = &self.frame_pool,
             .frame_id = frame_id,
@@ -387,7 +541,7 @@ pub const VideoDecoder = struct {
     }
 
     pub fn handleAudioFrame(self: *VideoDecoder, frame_id: usize) VideoDecoderError!?Frame {
-        const frame = self.frame_pool.pool.items[frame_id];
+        const frame = self.frame_pool.get(frame_id);
 
         const format = try ffmpegFormatToAudioFormat(frame.format);
 
@@ -461,7 +615,7 @@ pub const VideoDecoder = struct {
 
             const frame_id = try self.frame_pool.acquire();
             errdefer self.frame_pool.release(frame_id);
-            const frame = self.frame_pool.pool.items[frame_id];
+            const frame = self.frame_pool.get(frame_id);
 
             const ret = c.avcodec_receive_frame(decoder_ctx, frame);
             if (ret == c.AVERROR(c.EAGAIN)) {
@@ -518,6 +672,30 @@ fn makeFrame() VideoDecoderError!*c.AVFrame {
     };
 }
 
+// Like av_frame_get_buffer, but guarantees that the chroma strides are exactly
+// half the luma stride, which the renderers rely on
+fn allocYuv420Buffer(frame: *c.AVFrame, width: c_int, height: c_int) VideoDecoderError!void {
+    const stride = std.mem.alignForward(usize, @intCast(width), 64);
+    const luma_size = stride * @as(usize, @intCast(height));
+    const chroma_size = luma_size / 4;
+
+    const buf = c.av_buffer_alloc(@intCast(luma_size + 2 * chroma_size)) orelse {
+        std.log.err("Failed to alloc frame buffer", .{});
+        return VideoDecoderError.OutOfMemory;
+    };
+
+    frame.buf[0] = buf;
+    frame.data[0] = buf.*.data;
+    frame.data[1] = buf.*.data + luma_size;
+    frame.data[2] = buf.*.data + luma_size + chroma_size;
+    frame.linesize[0] = @intCast(stride);
+    frame.linesize[1] = @intCast(stride / 2);
+    frame.linesize[2] = @intCast(stride / 2);
+    frame.format = c.AV_PIX_FMT_YUV420P;
+    frame.width = width;
+    frame.height = height;
+}
+
 fn cIntToUsize(in: c_int, purpose: []const u8) !usize {
     if (in < 0) {
         std.log.err("{s} was negative", .{purpose});
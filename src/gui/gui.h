@@ -15,6 +15,26 @@ struct Clip {
     uint64_t id;
     float start;
     float end;
+    // Playback rate while inside the clip, 1 is normal speed. Clips can be
+    // sped up past boring sections without changing what they cover
+    float speed;
+    // Holds the picture on the first frame for the whole clip while the audio
+    // plays on, e.g. to stay on a diagram while the narration continues
+    bool freeze;
+};
+
+// Moves a clip to another spot in the output sequence
+struct GuiClipMove {
+    uint64_t id;
+    // Where the clip ends up, counted after it was taken out. Past the end
+    // moves it to the end
+    uint64_t index;
+};
+
+struct GuiFreezeFrame {
+    float position;
+    // Seconds to hold the picture for
+    float duration;
 };
 
 enum GuiActionTag {
@@ -26,6 +46,229 @@ enum GuiActionTag {
     gui_action_clip_add,
     gui_action_clip_remove,
     gui_action_save,
+    // Release decoded frames the backend is holding on to that it can recreate
+    gui_action_flush_caches,
+    gui_action_set_preview_quality,
+    // Answered asynchronously with a gui_event_thumbnail_* event carrying the
+    // same request_id
+    gui_action_request_thumbnail,
+    // Asks the backend to stop the job in data.id. Only meaningful for jobs
+    // that are cancellable, the job reports gui_job_state_cancelled once it
+    // has actually stopped
+    gui_action_cancel_job,
+    // Transcribes the audio again from scratch. The current transcript is
+    // replaced once the new one is done, signaled with
+    // gui_event_transcription_finished
+    gui_action_transcribe,
+    // Replaces the caption style, which is saved with the project
+    gui_action_set_caption_style,
+    // Writes the captions next to the save file in data.caption_format, using
+    // the current caption style
+    gui_action_export_captions,
+    // Replaces the transcript with the cues of the SRT or WebVTT file in
+    // data.import_request instead of transcribing the audio
+    gui_action_import_captions,
+    // Adds data.marker, the id is assigned by the backend
+    gui_action_marker_add,
+    // Removes the marker with id data.id
+    gui_action_marker_remove,
+    // Adds the markers of the CSV file in data.import_request, see
+    // MarkerManager.importCsv for the columns
+    gui_action_import_markers,
+    // Writes the markers as CSV next to the save file
+    gui_action_export_markers,
+    // Plays at data.speed times normal speed, negative plays backwards. 0
+    // pauses and resets the speed to 1 for the next play
+    gui_action_set_speed,
+    // Pauses and moves data.frames frames forward, or back if negative
+    gui_action_step_frames,
+    // Opens data.reload_target again after it was changed outside of the
+    // editor
+    gui_action_reload,
+    // Applies the edits journaled by a run that did not exit cleanly on top
+    // of the save
+    gui_action_replay_journal,
+    // Throws away the edits journaled by a run that did not exit cleanly
+    gui_action_discard_journal,
+    // Puts the clips, markers and caption style back the way they were after
+    // the history entry with id data.id. The revert is an entry of its own,
+    // so it can be reverted too
+    gui_action_revert_history,
+    // Turns the program monitor, see GuiFrameView, on or off with
+    // data.enabled. The backend only decodes program frames while it is on
+    gui_action_set_dual_preview,
+    // Reorders the output sequence with data.clip_move
+    gui_action_clip_move,
+    // Freezes the picture from data.freeze_frame.position on. A clip under
+    // the position is split around the freeze frame, otherwise it becomes a
+    // clip of its own
+    gui_action_insert_freeze_frame,
+    // Adds data.mute_region, the id is assigned by the backend
+    gui_action_mute_region_add,
+    // Moves the edges of the mute region with id data.mute_region.id
+    gui_action_mute_region_edit,
+    // Removes the mute region with id data.id
+    gui_action_mute_region_remove,
+    // Renders the edit with ffmpeg as data.export_request asks. Every output
+    // file is a gui_job_kind_export job of its own, queued behind any exports
+    // that are still running
+    gui_action_export,
+    // Stops every export that is queued or running, they end up
+    // gui_job_state_cancelled
+    gui_action_cancel_exports,
+    // Suspends the running export and holds the ones queued behind it with
+    // data.enabled set, carries on with it unset
+    gui_action_pause_exports,
+    // Renders the clip with id data.id on its own into the temp directory, to
+    // be handed to other applications. Finishes like gui_action_export, with
+    // quick set in the result
+    gui_action_quick_export,
+};
+
+// Resolution the backend decodes at. Lower qualities keep scrubbing responsive
+// on large videos
+enum GuiPreviewQuality {
+    gui_preview_quality_full,
+    gui_preview_quality_half,
+    gui_preview_quality_quarter,
+};
+
+struct GuiThumbnailRequest {
+    // Chosen by the GUI, only used to match up the response
+    uint64_t request_id;
+    float time;
+    // The thumbnail is scaled to fit in this box, keeping its aspect ratio
+    uint32_t width;
+    uint32_t height;
+};
+
+enum GuiTranscriptionModel {
+    // Small English only model that ships with the editor
+    gui_transcription_model_builtin,
+    // Loaded from ggml-<name>.bin in the backend's model directory
+    gui_transcription_model_tiny,
+    gui_transcription_model_base,
+    gui_transcription_model_small,
+    gui_transcription_model_medium,
+    gui_transcription_model_large,
+};
+
+struct GuiTranscribeRequest {
+    enum GuiTranscriptionModel model;
+    // Whisper language code, e.g. "en", or "auto" to detect it. Not null
+    // terminated if all 8 characters are used
+    char language[8];
+};
+
+enum GuiCaptionFont {
+    gui_caption_font_proportional,
+    gui_caption_font_monospace,
+};
+
+// How captions look in the preview and in exports. Sizes and positions are
+// relative to the video so that they look the same at every resolution
+struct GuiCaptionStyle {
+    enum GuiCaptionFont font;
+    // Fraction of the video height
+    float size;
+    // 0xRRGGBBAA
+    uint32_t color;
+    uint32_t outline_color;
+    // Fraction of the font size, 0 for no outline
+    float outline_width;
+    // Gap between the bottom of the caption and the bottom of the video, as
+    // a fraction of the video height
+    float position;
+    // Longest caption line in bytes
+    uint32_t max_line_len;
+};
+
+enum GuiHistoryKind {
+    // The state the project was opened or reloaded with
+    gui_history_opened,
+    gui_history_clip_add,
+    gui_history_clip_trim,
+    gui_history_clip_remove,
+    gui_history_marker_add,
+    gui_history_marker_remove,
+    gui_history_markers_imported,
+    gui_history_caption_style,
+    // revert_target holds the id of the entry that was reverted to
+    gui_history_revert,
+    gui_history_clip_move,
+    gui_history_clip_speed,
+    gui_history_freeze_frame,
+    gui_history_mute_add,
+    gui_history_mute_trim,
+    gui_history_mute_remove,
+};
+
+// One edit in the history panel
+struct GuiHistoryEntry {
+    uint64_t id;
+    enum GuiHistoryKind kind;
+    // Wall clock time of the edit, milliseconds since the epoch
+    int64_t time_ms;
+    // Where on the timeline the edit happened. end equals start for edits
+    // at a single point
+    bool has_position;
+    float start;
+    float end;
+    uint64_t revert_target;
+};
+
+struct GuiImportRequest {
+    // Null terminated
+    char path[1024];
+};
+
+// Named point on the timeline, e.g. a chapter start
+struct GuiMarker {
+    uint64_t id;
+    float time;
+    // 0xRRGGBBAA
+    uint32_t color;
+    // Null terminated
+    char name[64];
+};
+
+// Section of the source where the audio is silenced while the picture plays
+// on, e.g. to cover a name being read out
+struct GuiMuteRegion {
+    uint64_t id;
+    float start;
+    float end;
+};
+
+enum GuiCaptionFormat {
+    gui_caption_format_srt,
+    // Cues are positioned and styled according to the caption style
+    gui_caption_format_webvtt,
+};
+
+enum GuiExportMode {
+    // The whole edit into one file
+    gui_export_mode_sequence,
+    // Every clip into a file of its own, e.g. to upload each take separately
+    gui_export_mode_clips,
+};
+
+struct GuiExportRequest {
+    enum GuiExportMode mode;
+    // Output file name without the extension, the file goes next to the save
+    // file. {project} is replaced with the name of the save file, {n} with the
+    // clip's place in the sequence counting from 1 and {start} with where it
+    // starts in the source in seconds. Null terminated
+    char pattern[256];
+};
+
+enum GuiReloadTarget {
+    // The video, along with its waveform and thumbnails. The transcript is
+    // kept
+    gui_reload_source,
+    // Clips, markers and caption style from the save file, replacing any
+    // unsaved edits
+    gui_reload_project,
 };
 
 struct GuiAction {
@@ -34,29 +277,211 @@ struct GuiAction {
         float seek_position;
         struct Clip clip;
         uint64_t id;
+        enum GuiPreviewQuality preview_quality;
+        struct GuiThumbnailRequest thumbnail_request;
+        struct GuiTranscribeRequest transcribe;
+        struct GuiCaptionStyle caption_style;
+        enum GuiCaptionFormat caption_format;
+        struct GuiImportRequest import_request;
+        struct GuiMarker marker;
+        float speed;
+        int32_t frames;
+        enum GuiReloadTarget reload_target;
+        bool enabled;
+        struct GuiClipMove clip_move;
+        struct GuiFreezeFrame freeze_frame;
+        struct GuiMuteRegion mute_region;
+        struct GuiExportRequest export_request;
     } data;
 };
 
+enum GuiEventTag {
+    gui_event_thumbnail_ready,
+    gui_event_thumbnail_failed,
+    // No data, the new transcript shows up in the next snapshot
+    gui_event_transcription_finished,
+    gui_event_transcription_failed,
+    // data.export_result, sent when an export job finishes or fails but not
+    // when it is cancelled
+    gui_event_export_finished,
+    gui_event_export_failed,
+};
+
+struct GuiExportResult {
+    uint64_t job_id;
+    // The file that was written, or would have been. Null terminated
+    const char* path;
+    // Whether it came from gui_action_quick_export
+    bool quick;
+};
+
+struct GuiThumbnail {
+    uint64_t request_id;
+    uint32_t width;
+    uint32_t height;
+    // width * height RGBA pixels, rows are tightly packed
+    const uint8_t* rgba;
+};
+
+// Backend -> GUI counterpart of GuiAction, for results that show up
+// asynchronously
+struct GuiEvent {
+    enum GuiEventTag tag;
+    union {
+        struct GuiThumbnail thumbnail;
+        uint64_t request_id;
+        struct GuiExportResult export_result;
+    } data;
+};
+
+enum GuiJobKind {
+    gui_job_kind_transcription,
+    gui_job_kind_waveform,
+    gui_job_kind_thumbnails,
+    gui_job_kind_export,
+};
+
+enum GuiJobState {
+    gui_job_state_queued,
+    gui_job_state_running,
+    // Suspended by gui_action_pause_exports
+    gui_job_state_paused,
+    gui_job_state_finished,
+    gui_job_state_failed,
+    gui_job_state_cancelled,
+};
+
+// Long running backend work, e.g. transcribing the audio
+struct GuiJob {
+    uint64_t id;
+    enum GuiJobKind kind;
+    enum GuiJobState state;
+    // 0-1, negative if the job cannot tell how far along it is
+    float progress;
+    bool cancellable;
+    bool cancel_requested;
+};
+
+// Bitmask describing what changed in a gui_notify_update_kind call
+enum GuiUpdateKind {
+    gui_update_frame = 1 << 0,
+    gui_update_state = 1 << 1,
+    gui_update_transcript = 1 << 2,
+    gui_update_all = gui_update_frame | gui_update_state | gui_update_transcript,
+};
+
 struct AppStateSnapshot {
+    // Incremented every time the backend state changes
+    uint64_t generation;
     bool paused;
+    // Speed playback runs at when not paused, negative is backwards
+    float playback_speed;
     float current_position;
     float total_runtime;
+    // Seconds per frame of the video stream
+    float frame_duration;
+    // Frames decoded per second over the last second of playback
+    float decode_fps;
+    // Bytes held by decoded video/audio frames that have not been released yet
+    uint64_t frame_cache_bytes;
+    uint64_t audio_cache_bytes;
+    enum GuiPreviewQuality preview_quality;
+    // Every job since startup, including the ones that are done
+    const struct GuiJob* jobs;
+    uint64_t num_jobs;
+    struct GuiCaptionStyle caption_style;
+    // In the order they play in the edited video, which does not have to be
+    // the order they appear in the source
     const struct Clip* clips;
     uint64_t num_clips;
+    // Sorted by time
+    const struct GuiMarker* markers;
+    uint64_t num_markers;
+    // Sorted by start
+    const struct GuiMuteRegion* mute_regions;
+    uint64_t num_mute_regions;
     const char* text;
     uint64_t text_len;
     const uint64_t* text_split_indices;
     uint64_t text_split_indices_len;
+    // Files the project was opened with, so that the GUI can notice when
+    // they change on disk
+    const char* input_path;
+    const char* save_path;
+    // Edits journaled by a run that did not exit cleanly, waiting for
+    // gui_action_replay_journal or gui_action_discard_journal
+    uint64_t num_recoverable_edits;
+    // Oldest first
+    const struct GuiHistoryEntry* history;
+    uint64_t num_history;
+    // Whether gui_frame_view_program is being kept up to date
+    bool dual_preview;
+    // Edits made since the project was last saved or loaded
+    bool unsaved_changes;
+};
+
+enum GuiStatus {
+    gui_status_ok,
+    gui_status_error,
+    gui_status_no_display,
+    gui_status_gl_init_failed,
+    gui_status_invalid_argument,
+};
+
+typedef void (*GuiActionCallback)(void* userdata, const struct GuiAction* action);
+
+// One open video and save file. Every project has a Gui of its own to talk to
+// its backend through, and shows up as a tab in the window
+struct GuiProject {
+    Gui* gui;
+    FrameRenderer* frame_renderer;
+    AudioRenderer* audio_renderer;
+    // Null if subtitle generation is disabled
+    WordTimestampMap* wtm;
+    // The tab is named after the save file
+    const char* save_path;
 };
 
 // GUI interface
 Gui* gui_init(AppState* state);
 void gui_free(Gui* gui);
-void gui_run(Gui* gui, FrameRenderer* frame_renderer, AudioRenderer* audio_renderer, WordTimestampMap* wtm);
+// Runs the window until it is closed. The status is reported to every
+// project's Gui, so any of them can be asked for the error message
+enum GuiStatus gui_run(const struct GuiProject* projects, uint64_t num_projects);
 struct GuiAction gui_next_action(Gui* gui);
-void gui_wait_start(Gui* gui);
+// Drains up to capacity pending actions into out, returns the number written.
+// A return of 0 means there is nothing left to process
+uint64_t gui_next_actions(Gui* gui, struct GuiAction* out, uint64_t capacity);
+// Alternative to polling with gui_next_action(s). Once registered, actions are
+// delivered to callback from a dedicated GUI owned thread instead of the queue.
+// Can only be registered once
+enum GuiStatus gui_set_action_callback(Gui* gui, GuiActionCallback callback, void* userdata);
+// Blocks until the GUI is up, or returns an error if it failed to start
+enum GuiStatus gui_wait_start(Gui* gui);
+// For when gui_run is never going to be called, e.g. starting another project
+// failed. gui_wait_start returns an error instead of waiting forever
+void gui_cancel_start(Gui* gui);
 void gui_notify_update(Gui* gui);
+void gui_notify_update_kind(Gui* gui, enum GuiUpdateKind kind);
+// Closes the project's tab, the window only closes with the last one
 void gui_close(Gui* gui);
+// Anything the event points to is copied before this returns. Safe to call
+// from any thread
+void gui_push_event(Gui* gui, const struct GuiEvent* event);
+// Copies the details for the last non-ok GuiStatus into buf, null terminated
+// and cut short if they do not fit. Returns the length of the whole message
+uint64_t gui_last_error_message(Gui* gui, char* buf, uint64_t buf_len);
+
+enum GuiLogLevel {
+    gui_log_level_error,
+    gui_log_level_warn,
+    gui_log_level_info,
+    gui_log_level_debug,
+};
+
+// Routes backend logging through the GUI so that everything ends up in one
+// place. Safe to call before gui_init
+void gui_log(enum GuiLogLevel level, const char* msg);
 
 // Gui OpenGL wrappers
 // GUI owns window creation, and has to draw widgets to the screen. This means
@@ -84,6 +509,8 @@ void   guigl_draw_arrays(GuiGl* guigl, GLenum mode, GLint first, GLsizei count);
 void   guigl_clear_color(GuiGl* guigl, GLfloat r, GLfloat g, GLfloat b, GLfloat a);
 void   guigl_clear(GuiGl* guigl, GLbitfield mask);
 void   guigl_line_width(GuiGl* guigl, GLfloat width);
+void   guigl_enable(GuiGl* guigl, GLenum cap);
+void   guigl_disable(GuiGl* guigl, GLenum cap);
 
 GLuint guigl_create_buffer(GuiGl* guigl);
 void   guigl_delete_buffer(GuiGl* guigl, GLuint buffer_id);
@@ -95,18 +522,133 @@ void   guigl_bind_vertex_array(GuiGl* guigl, GLuint array_id);
 void   guigl_vertex_attrib_pointer(GuiGl* guigl, GLuint index, GLint size, GLenum type, GLboolean normalized, GLsizei stride, const void * pointer);
 void   guigl_enable_vertex_attrib_array(GuiGl* guigl, GLuint index);
 
+GLuint guigl_create_query(GuiGl* guigl);
+void   guigl_delete_query(GuiGl* guigl, GLuint query);
+void   guigl_begin_query(GuiGl* guigl, GLenum target, GLuint query);
+void   guigl_end_query(GuiGl* guigl, GLenum target);
+// Writes the query result (e.g. nanoseconds for GL_TIME_ELAPSED) to result and
+// returns true if it is available. Returns false without blocking otherwise
+bool   guigl_get_query_result(GuiGl* guigl, GLuint query, GLuint* result);
+
+// Frame renderers draw one of two monitors
+enum GuiFrameView {
+    // The raw video at the playhead, cut out or not
+    gui_frame_view_source,
+    // What the edited video shows at the playhead. In a cut out section that
+    // is the start of the next clip
+    gui_frame_view_program,
+};
+
+// What a view draws at the moment
+enum GuiFrameContent {
+    // A frame of its own
+    gui_frame_content_own,
+    // Whatever the source view shows, e.g. the playhead is inside a clip
+    gui_frame_content_source,
+    // Nothing, e.g. there is no clip past the playhead
+    gui_frame_content_blank,
+};
+
+// YUV to RGB matrix of the decoded video
+enum GuiColorMatrix {
+    // From the file's tags, or guessed from the resolution like players do
+    gui_color_matrix_auto,
+    gui_color_matrix_bt601,
+    gui_color_matrix_bt709,
+};
+
+// How the video's RGB values turn into light on the display
+enum GuiTransferFunction {
+    // What players and browsers do with untagged video on a desktop display
+    gui_transfer_srgb,
+    // Gamma 2.4 of reference monitors, darker shadows than sRGB
+    gui_transfer_bt1886,
+    gui_transfer_gamma22,
+};
+
+// How the preview shows the decoded video
+struct GuiColorManagement {
+    // Show the video the way players show the export, ignores matrix and
+    // input_transfer
+    bool match_export;
+    enum GuiColorMatrix matrix;
+    enum GuiTransferFunction input_transfer;
+    // Write linear light and let GL_FRAMEBUFFER_SRGB encode it, instead of
+    // encoding in the shader. Blends and filters in linear light, but only
+    // makes a difference if the window's framebuffer is sRGB capable
+    bool srgb_framebuffer;
+};
+
 // Zig interface
 void framerenderer_init_gl(FrameRenderer* renderer, GuiGl* guigl);
-void framerenderer_render(FrameRenderer* renderer, float width, float height, GuiGl* guigl);
+void framerenderer_render(FrameRenderer* renderer, enum GuiFrameView view, float width, float height, const struct GuiColorManagement* color, GuiGl* guigl);
 void framerenderer_deinit_gl(FrameRenderer* renderer, GuiGl* guigl);
 
+struct GuiVideoFrame {
+    // Planar YUV 4:2:0, u and v are half the stride and height of y
+    const uint8_t* y;
+    const uint8_t* u;
+    const uint8_t* v;
+    uint32_t width;
+    uint32_t height;
+    uint32_t stride;
+};
+
+typedef void (*GuiVideoFrameCallback)(void* userdata, const struct GuiVideoFrame* frame);
+
+// For GUIs that draw without GuiGl. Calls callback with the newest frame if it
+// has not been consumed yet. The frame is only valid for the duration of the
+// call
+void framerenderer_consume_frame(FrameRenderer* renderer, enum GuiFrameView view, GuiVideoFrameCallback callback, void* userdata);
+enum GuiFrameContent framerenderer_content(FrameRenderer* renderer, enum GuiFrameView view);
+
 void audiorenderer_init_gl(AudioRenderer* renderer, GuiGl* guigl);
 void audiorenderer_render(AudioRenderer* renderer, GuiGl* guigl, float zoom, float center_norm);
 void audiorenderer_deinit_gl(AudioRenderer* renderer, GuiGl* guigl);
 
+struct GuiWaveformSample {
+    float min;
+    float max;
+};
+
+// For GUIs that draw without GuiGl. Copies up to capacity waveform samples
+// starting at offset into out and returns the number copied. Samples keep
+// getting appended while the audio loads, total is set to how many there will
+// be once it is done
+uint64_t audiorenderer_get_samples(AudioRenderer* renderer, uint64_t offset, struct GuiWaveformSample* out, uint64_t capacity, uint64_t* total);
+// Incremented whenever the waveform starts loading over, e.g. after the source
+// was reloaded. Samples read before then are stale
+uint64_t audiorenderer_generation(AudioRenderer* renderer);
+
 float wtm_get_time(WordTimestampMap* m, uint64_t char_pos);
 uint64_t wtm_get_char_pos(WordTimestampMap* m, float pts);
 
+struct GuiWord {
+    // Byte range in the snapshot text
+    uint64_t char_start;
+    uint64_t char_end;
+    float start;
+    float end;
+    // How sure the transcription is about the word, 0-1
+    float confidence;
+};
+
+// Copies up to capacity words starting at offset into out and returns the
+// number copied. total is set to the number of words in the transcript
+uint64_t wtm_get_words(WordTimestampMap* m, uint64_t offset, struct GuiWord* out, uint64_t capacity, uint64_t* total);
+
+struct GuiCaption {
+    // Byte range in the snapshot text
+    uint64_t char_start;
+    uint64_t char_end;
+    float start;
+    float end;
+};
+
+// Groups the transcript into caption lines of at most max_line_len bytes and
+// copies them like wtm_get_words
+uint64_t wtm_get_captions(WordTimestampMap* m, uint64_t max_line_len, uint64_t offset, struct GuiCaption* out, uint64_t capacity, uint64_t* total);
+
 struct AppStateSnapshot appstate_snapshot(AppState* app);
 void appstate_deinit(AppState* app, const struct AppStateSnapshot* snapshot);
 
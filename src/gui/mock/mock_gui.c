@@ -12,6 +12,8 @@ struct GuiImpl {
   int trigger_action;
   int next_action_id;
   float seek_pos;
+  GuiActionCallback action_callback;
+  void* action_callback_userdata;
   unsigned int allocation_id;
   void* allocations[MAX_ALLOCATIONS];
 };
@@ -141,6 +143,16 @@ void guigl_line_width(GuiGl* guigl, GLfloat width) {
   (void)width;
 }
 
+void guigl_enable(GuiGl* guigl, GLenum cap) {
+  (void)guigl;
+  (void)cap;
+}
+
+void guigl_disable(GuiGl* guigl, GLenum cap) {
+  (void)guigl;
+  (void)cap;
+}
+
 GLuint guigl_create_buffer(GuiGl* guigl) { return impl_alloc(guigl); }
 
 void guigl_delete_buffer(GuiGl* guigl, GLuint buffer_id) {
@@ -189,6 +201,30 @@ void guigl_enable_vertex_attrib_array(GuiGl* guigl, GLuint index) {
   (void)index;
 }
 
+GLuint guigl_create_query(GuiGl* guigl) { return impl_alloc(guigl); }
+
+void guigl_delete_query(GuiGl* guigl, GLuint query) {
+  impl_free(guigl, query);
+}
+
+void guigl_begin_query(GuiGl* guigl, GLenum target, GLuint query) {
+  (void)guigl;
+  (void)target;
+  (void)query;
+}
+
+void guigl_end_query(GuiGl* guigl, GLenum target) {
+  (void)guigl;
+  (void)target;
+}
+
+bool guigl_get_query_result(GuiGl* guigl, GLuint query, GLuint* result) {
+  (void)guigl;
+  (void)query;
+  *result = 0;
+  return true;
+}
+
 // GUI interface
 Gui* gui_init(AppState* state) {
   (void)state;
@@ -197,6 +233,8 @@ Gui* gui_init(AppState* state) {
   impl->allocation_id = 0;
   impl->trigger_action = -1;
   impl->next_action_id = 0;
+  impl->action_callback = NULL;
+  impl->action_callback_userdata = NULL;
   memset(impl->allocations, 0, MAX_ALLOCATIONS);
 
   return impl;
@@ -204,12 +242,14 @@ Gui* gui_init(AppState* state) {
 
 void gui_free(Gui* gui) { free(gui); }
 
+const struct GuiColorManagement kColorManagement = {.match_export = true};
+
 struct TimedGuiAction {
   int i;
   struct GuiAction action;
 };
 
-#define NUM_GUI_ACTIONS 9
+#define NUM_GUI_ACTIONS 15
 #define NUM_ITERS (60 * 3)
 const struct TimedGuiAction kGuiActions[NUM_GUI_ACTIONS] = {
     {.i = 0, .action = {.tag = gui_action_clip_add, .data = {.clip = {0}}}},
@@ -225,11 +265,37 @@ const struct TimedGuiAction kGuiActions[NUM_GUI_ACTIONS] = {
          {
              .tag = gui_action_toggle_pause,
          }},
+    {.i = 40,
+     .action = {.tag = gui_action_set_preview_quality,
+                .data = {.preview_quality = gui_preview_quality_quarter}}},
+    {.i = 50,
+     .action = {.tag = gui_action_request_thumbnail,
+                .data = {.thumbnail_request = {.request_id = 0,
+                                               .time = 2.0F,
+                                               .width = 160,
+                                               .height = 90}}}},
+    {.i = 60,
+     .action = {.tag = gui_action_set_preview_quality,
+                .data = {.preview_quality = gui_preview_quality_full}}},
     {.i = 70,
      .action = {.tag = gui_action_seek, .data = {.seek_position = 0.0F}}},
+    {.i = 80, .action = {.tag = gui_action_cancel_job, .data = {.id = 0}}},
+    {.i = 85,
+     .action = {.tag = gui_action_transcribe,
+                .data = {.transcribe = {.model = gui_transcription_model_builtin,
+                                        .language = "en"}}}},
     {.i = 95,
      .action = {.tag = gui_action_clip_edit,
                 .data = {.clip = {.id = 0, .start = 0, .end = 5}}}},
+    {.i = 98,
+     .action = {.tag = gui_action_set_caption_style,
+                .data = {.caption_style = {.font = gui_caption_font_monospace,
+                                           .size = 0.08F,
+                                           .color = 0xffff00ff,
+                                           .outline_color = 0x000000ff,
+                                           .outline_width = 0.1F,
+                                           .position = 0.1F,
+                                           .max_line_len = 32}}}},
     {.i = 100, .action = {.tag = gui_action_save}},
     {.i = 105,
      .action = {.tag = gui_action_clip_remove, .data = {.seek_position = 2}}},
@@ -240,29 +306,45 @@ const struct TimedGuiAction kGuiActions[NUM_GUI_ACTIONS] = {
          }},
 };
 
-void gui_run(Gui* gui, FrameRenderer* frame_renderer,
-             AudioRenderer* audio_renderer, WordTimestampMap* wtm) {
-  (void)wtm;
-  struct GuiImpl* impl = gui;
-  framerenderer_init_gl(frame_renderer, gui);
-  audiorenderer_init_gl(audio_renderer, gui);
+// Every project gets the same scripted actions
+enum GuiStatus gui_run(const struct GuiProject* projects,
+                       uint64_t num_projects) {
+  for (uint64_t p = 0; p < num_projects; ++p) {
+    framerenderer_init_gl(projects[p].frame_renderer, projects[p].gui);
+    audiorenderer_init_gl(projects[p].audio_renderer, projects[p].gui);
+  }
+
   for (int i = 0; i < NUM_ITERS; ++i) {
-    framerenderer_render(frame_renderer, 800.0, 600.0, gui);
-    audiorenderer_render(audio_renderer, gui, 1.0, 0.5);
-
-    if (impl->next_action_id < NUM_GUI_ACTIONS &&
-        i == kGuiActions[impl->next_action_id].i) {
-      pthread_mutex_lock(&impl->trigger_action_mutex);
-      impl->trigger_action = impl->next_action_id;
-      pthread_mutex_unlock(&impl->trigger_action_mutex);
-      impl->next_action_id += 1;
+    for (uint64_t p = 0; p < num_projects; ++p) {
+      Gui* gui = projects[p].gui;
+      struct GuiImpl* impl = gui;
+      framerenderer_render(projects[p].frame_renderer, gui_frame_view_source,
+                           800.0, 600.0, &kColorManagement, gui);
+      audiorenderer_render(projects[p].audio_renderer, gui, 1.0, 0.5);
+
+      if (impl->next_action_id < NUM_GUI_ACTIONS &&
+          i == kGuiActions[impl->next_action_id].i) {
+        if (impl->action_callback != NULL) {
+          impl->action_callback(impl->action_callback_userdata,
+                                &kGuiActions[impl->next_action_id].action);
+        } else {
+          pthread_mutex_lock(&impl->trigger_action_mutex);
+          impl->trigger_action = impl->next_action_id;
+          pthread_mutex_unlock(&impl->trigger_action_mutex);
+        }
+        impl->next_action_id += 1;
+      }
     }
 
     // 60fps
     usleep(16666);
   }
-  audiorenderer_deinit_gl(audio_renderer, gui);
-  framerenderer_deinit_gl(frame_renderer, gui);
+
+  for (uint64_t p = 0; p < num_projects; ++p) {
+    audiorenderer_deinit_gl(projects[p].audio_renderer, projects[p].gui);
+    framerenderer_deinit_gl(projects[p].frame_renderer, projects[p].gui);
+  }
+  return gui_status_ok;
 }
 
 struct GuiAction gui_next_action(Gui* gui) {
@@ -279,8 +361,61 @@ struct GuiAction gui_next_action(Gui* gui) {
   return ret;
 }
 
-void gui_wait_start(Gui* gui) { (void)gui; }
+uint64_t gui_next_actions(Gui* gui, struct GuiAction* out, uint64_t capacity) {
+  uint64_t num_actions = 0;
+  while (num_actions < capacity) {
+    struct GuiAction action = gui_next_action(gui);
+    if (action.tag == gui_action_none) {
+      break;
+    }
+    out[num_actions] = action;
+    num_actions += 1;
+  }
+  return num_actions;
+}
+
+enum GuiStatus gui_set_action_callback(Gui* gui, GuiActionCallback callback,
+                                       void* userdata) {
+  struct GuiImpl* impl = gui;
+  impl->action_callback = callback;
+  impl->action_callback_userdata = userdata;
+  return gui_status_ok;
+}
+
+enum GuiStatus gui_wait_start(Gui* gui) {
+  (void)gui;
+  return gui_status_ok;
+}
+
+void gui_cancel_start(Gui* gui) { (void)gui; }
 
 void gui_notify_update(Gui* gui) { (void)gui; }
 
+void gui_notify_update_kind(Gui* gui, enum GuiUpdateKind kind) {
+  (void)gui;
+  (void)kind;
+}
+
 void gui_close(Gui* gui) { (void)gui; }
+
+void gui_push_event(Gui* gui, const struct GuiEvent* event) {
+  (void)gui;
+  (void)event;
+}
+
+uint64_t gui_last_error_message(Gui* gui, char* buf, uint64_t buf_len) {
+  (void)gui;
+  if (buf_len > 0) {
+    buf[0] = '\0';
+  }
+  return 0;
+}
+
+void gui_log(enum GuiLogLevel level, const char* msg) {
+  static const char* level_names[] = {"ERROR", "WARN", "INFO", "DEBUG"};
+  const char* level_name = "DEBUG";
+  if (level <= gui_log_level_debug) {
+    level_name = level_names[level];
+  }
+  fprintf(stderr, "[%s backend] %s\n", level_name, msg);
+}
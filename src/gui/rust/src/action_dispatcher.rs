@@ -0,0 +1,81 @@
+use crate::{actions::Action, c_bindings};
+
+use std::{
+    ffi::c_void,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+struct ActionCallback {
+    f: unsafe extern "C" fn(*mut c_void, *const c_bindings::GuiAction),
+    userdata: *mut c_void,
+}
+
+// The backend is responsible for making sure userdata can be used from the dispatcher thread
+unsafe impl Send for ActionCallback {}
+
+/// Pushes actions to a backend provided callback as they arrive, instead of waiting for the
+/// backend to poll for them
+pub struct ActionDispatcher {
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ActionDispatcher {
+    pub fn new(
+        action_rx: Receiver<Action>,
+        queued_actions: Arc<AtomicUsize>,
+        f: unsafe extern "C" fn(*mut c_void, *const c_bindings::GuiAction),
+        userdata: *mut c_void,
+    ) -> ActionDispatcher {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let callback = ActionCallback { f, userdata };
+
+        let thread_shutdown = Arc::clone(&shutdown);
+        let handle = std::thread::spawn(move || {
+            dispatch(action_rx, queued_actions, callback, thread_shutdown)
+        });
+
+        ActionDispatcher {
+            shutdown,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for ActionDispatcher {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn dispatch(
+    action_rx: Receiver<Action>,
+    queued_actions: Arc<AtomicUsize>,
+    callback: ActionCallback,
+    shutdown: Arc<AtomicBool>,
+) {
+    // The GUI always holds a sender, so we cannot rely on the channel disconnecting to know when
+    // to stop. Wake up every now and then to check if we've been asked to stop
+    const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    while !shutdown.load(Ordering::Relaxed) {
+        match action_rx.recv_timeout(SHUTDOWN_POLL_INTERVAL) {
+            Ok(action) => unsafe {
+                queued_actions.fetch_sub(1, Ordering::Relaxed);
+                let action: c_bindings::GuiAction = action.into();
+                (callback.f)(callback.userdata, &action)
+            },
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
@@ -0,0 +1,526 @@
+use crate::c_bindings::*;
+
+/// Rust side representation of a GuiAction. The rest of the GUI should only ever deal with this,
+/// conversion to the C union happens when handing actions to the backend
+#[derive(Clone, Copy, Debug)]
+pub enum Action {
+    None,
+    TogglePause,
+    Close,
+    Seek(f32),
+    ClipEdit(Clip),
+    ClipAdd(Clip),
+    /// Removes the clip under the given position
+    ClipRemove(f32),
+    /// Moves a clip to another spot in the output sequence
+    ClipMove(GuiClipMove),
+    Save,
+    FlushCaches,
+    SetPreviewQuality(PreviewQuality),
+    /// Asks the backend for a still frame, answered with a thumbnail event
+    RequestThumbnail(GuiThumbnailRequest),
+    /// Cancels the backend job with the given id
+    CancelJob(u64),
+    Transcribe(GuiTranscribeRequest),
+    SetCaptionStyle(GuiCaptionStyle),
+    /// Writes the captions next to the save file in the given format
+    ExportCaptions(GuiCaptionFormat),
+    /// Replaces the transcript with the cues of an SRT or WebVTT file
+    ImportCaptions(GuiImportRequest),
+    /// The id is ignored, the backend picks one
+    MarkerAdd(GuiMarker),
+    MarkerRemove(u64),
+    /// Adds the markers of a CSV file
+    ImportMarkers(GuiImportRequest),
+    /// Writes the markers as CSV next to the save file
+    ExportMarkers,
+    /// Plays at the given multiple of normal speed, negative is backwards and 0 pauses
+    SetSpeed(f32),
+    /// Pauses and moves the given number of frames, negative is backwards
+    StepFrames(i32),
+    /// Opens the source or project file again after it changed on disk
+    Reload(GuiReloadTarget),
+    /// Applies the edits a crashed run left in the journal on top of the save
+    ReplayJournal,
+    DiscardJournal,
+    /// Goes back to the state after the history entry with the given id
+    RevertHistory(u64),
+    /// Shows the edited program next to the source
+    SetDualPreview(bool),
+    /// Holds the picture still from the given position on
+    InsertFreezeFrame(GuiFreezeFrame),
+    /// The id is ignored, the backend picks one
+    MuteRegionAdd(GuiMuteRegion),
+    MuteRegionEdit(GuiMuteRegion),
+    MuteRegionRemove(u64),
+    /// Renders the edit with ffmpeg in the background
+    Export(GuiExportRequest),
+    /// Stops every queued or running export
+    CancelExports,
+    /// Suspends or resumes exports
+    PauseExports(bool),
+    /// Renders the clip with the given id into the temp directory, see drag_out
+    QuickExport(u64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreviewQuality {
+    Full,
+    Half,
+    Quarter,
+}
+
+impl PreviewQuality {
+    pub const ALL: [PreviewQuality; 3] = [
+        PreviewQuality::Full,
+        PreviewQuality::Half,
+        PreviewQuality::Quarter,
+    ];
+
+    /// Unknown values are treated as full quality, since that is what the backend falls back to
+    pub fn from_c(quality: GuiPreviewQuality) -> PreviewQuality {
+        match quality {
+            GuiPreviewQuality_gui_preview_quality_half => PreviewQuality::Half,
+            GuiPreviewQuality_gui_preview_quality_quarter => PreviewQuality::Quarter,
+            _ => PreviewQuality::Full,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PreviewQuality::Full => "Full",
+            PreviewQuality::Half => "Half",
+            PreviewQuality::Quarter => "Quarter",
+        }
+    }
+}
+
+impl From<PreviewQuality> for GuiPreviewQuality {
+    fn from(quality: PreviewQuality) -> GuiPreviewQuality {
+        match quality {
+            PreviewQuality::Full => GuiPreviewQuality_gui_preview_quality_full,
+            PreviewQuality::Half => GuiPreviewQuality_gui_preview_quality_half,
+            PreviewQuality::Quarter => GuiPreviewQuality_gui_preview_quality_quarter,
+        }
+    }
+}
+
+/// None if the path is empty or does not fit in the request with its terminator
+pub fn import_request(path: &str) -> Option<GuiImportRequest> {
+    let mut ret = GuiImportRequest { path: [0; 1024] };
+    if path.is_empty() || path.len() >= ret.path.len() || path.contains('\0') {
+        return None;
+    }
+
+    for (dst, src) in ret.path.iter_mut().zip(path.bytes()) {
+        *dst = src as std::ffi::c_char;
+    }
+    Some(ret)
+}
+
+#[derive(Debug)]
+pub struct InvalidActionTag(pub GuiActionTag);
+
+fn make_action(tag: GuiActionTag) -> GuiAction {
+    unsafe {
+        let mut ret = std::mem::MaybeUninit::<GuiAction>::zeroed();
+        (*ret.as_mut_ptr()).tag = tag;
+        ret.assume_init()
+    }
+}
+
+impl From<Action> for GuiAction {
+    fn from(action: Action) -> GuiAction {
+        match action {
+            Action::None => make_action(GuiActionTag_gui_action_none),
+            Action::TogglePause => make_action(GuiActionTag_gui_action_toggle_pause),
+            Action::Close => make_action(GuiActionTag_gui_action_close),
+            Action::Seek(pos) => {
+                let mut ret = make_action(GuiActionTag_gui_action_seek);
+                ret.data.seek_position = pos;
+                ret
+            }
+            Action::ClipEdit(clip) => {
+                let mut ret = make_action(GuiActionTag_gui_action_clip_edit);
+                ret.data.clip = clip;
+                ret
+            }
+            Action::ClipAdd(clip) => {
+                let mut ret = make_action(GuiActionTag_gui_action_clip_add);
+                ret.data.clip = clip;
+                ret
+            }
+            Action::ClipRemove(pos) => {
+                let mut ret = make_action(GuiActionTag_gui_action_clip_remove);
+                ret.data.seek_position = pos;
+                ret
+            }
+            Action::Save => make_action(GuiActionTag_gui_action_save),
+            Action::FlushCaches => make_action(GuiActionTag_gui_action_flush_caches),
+            Action::SetPreviewQuality(quality) => {
+                let mut ret = make_action(GuiActionTag_gui_action_set_preview_quality);
+                ret.data.preview_quality = quality.into();
+                ret
+            }
+            Action::RequestThumbnail(request) => {
+                let mut ret = make_action(GuiActionTag_gui_action_request_thumbnail);
+                ret.data.thumbnail_request = request;
+                ret
+            }
+            Action::CancelJob(id) => {
+                let mut ret = make_action(GuiActionTag_gui_action_cancel_job);
+                ret.data.id = id;
+                ret
+            }
+            Action::Transcribe(request) => {
+                let mut ret = make_action(GuiActionTag_gui_action_transcribe);
+                ret.data.transcribe = request;
+                ret
+            }
+            Action::SetCaptionStyle(style) => {
+                let mut ret = make_action(GuiActionTag_gui_action_set_caption_style);
+                ret.data.caption_style = style;
+                ret
+            }
+            Action::ExportCaptions(format) => {
+                let mut ret = make_action(GuiActionTag_gui_action_export_captions);
+                ret.data.caption_format = format;
+                ret
+            }
+            Action::ImportCaptions(request) => {
+                let mut ret = make_action(GuiActionTag_gui_action_import_captions);
+                ret.data.import_request = request;
+                ret
+            }
+            Action::MarkerAdd(marker) => {
+                let mut ret = make_action(GuiActionTag_gui_action_marker_add);
+                ret.data.marker = marker;
+                ret
+            }
+            Action::MarkerRemove(id) => {
+                let mut ret = make_action(GuiActionTag_gui_action_marker_remove);
+                ret.data.id = id;
+                ret
+            }
+            Action::ImportMarkers(request) => {
+                let mut ret = make_action(GuiActionTag_gui_action_import_markers);
+                ret.data.import_request = request;
+                ret
+            }
+            Action::ExportMarkers => make_action(GuiActionTag_gui_action_export_markers),
+            Action::SetSpeed(speed) => {
+                let mut ret = make_action(GuiActionTag_gui_action_set_speed);
+                ret.data.speed = speed;
+                ret
+            }
+            Action::StepFrames(frames) => {
+                let mut ret = make_action(GuiActionTag_gui_action_step_frames);
+                ret.data.frames = frames;
+                ret
+            }
+            Action::Reload(target) => {
+                let mut ret = make_action(GuiActionTag_gui_action_reload);
+                ret.data.reload_target = target;
+                ret
+            }
+            Action::ReplayJournal => make_action(GuiActionTag_gui_action_replay_journal),
+            Action::DiscardJournal => make_action(GuiActionTag_gui_action_discard_journal),
+            Action::RevertHistory(id) => {
+                let mut ret = make_action(GuiActionTag_gui_action_revert_history);
+                ret.data.id = id;
+                ret
+            }
+            Action::SetDualPreview(enabled) => {
+                let mut ret = make_action(GuiActionTag_gui_action_set_dual_preview);
+                ret.data.enabled = enabled;
+                ret
+            }
+            Action::ClipMove(clip_move) => {
+                let mut ret = make_action(GuiActionTag_gui_action_clip_move);
+                ret.data.clip_move = clip_move;
+                ret
+            }
+            Action::InsertFreezeFrame(freeze_frame) => {
+                let mut ret = make_action(GuiActionTag_gui_action_insert_freeze_frame);
+                ret.data.freeze_frame = freeze_frame;
+                ret
+            }
+            Action::MuteRegionAdd(region) => {
+                let mut ret = make_action(GuiActionTag_gui_action_mute_region_add);
+                ret.data.mute_region = region;
+                ret
+            }
+            Action::MuteRegionEdit(region) => {
+                let mut ret = make_action(GuiActionTag_gui_action_mute_region_edit);
+                ret.data.mute_region = region;
+                ret
+            }
+            Action::MuteRegionRemove(id) => {
+                let mut ret = make_action(GuiActionTag_gui_action_mute_region_remove);
+                ret.data.id = id;
+                ret
+            }
+            Action::Export(request) => {
+                let mut ret = make_action(GuiActionTag_gui_action_export);
+                ret.data.export_request = request;
+                ret
+            }
+            Action::CancelExports => make_action(GuiActionTag_gui_action_cancel_exports),
+            Action::PauseExports(paused) => {
+                let mut ret = make_action(GuiActionTag_gui_action_pause_exports);
+                ret.data.enabled = paused;
+                ret
+            }
+            Action::QuickExport(clip_id) => {
+                let mut ret = make_action(GuiActionTag_gui_action_quick_export);
+                ret.data.id = clip_id;
+                ret
+            }
+        }
+    }
+}
+
+impl TryFrom<GuiAction> for Action {
+    type Error = InvalidActionTag;
+
+    fn try_from(action: GuiAction) -> Result<Action, InvalidActionTag> {
+        // The tag tells us which union member is valid
+        unsafe {
+            let ret = match action.tag {
+                GuiActionTag_gui_action_none => Action::None,
+                GuiActionTag_gui_action_toggle_pause => Action::TogglePause,
+                GuiActionTag_gui_action_close => Action::Close,
+                GuiActionTag_gui_action_seek => Action::Seek(action.data.seek_position),
+                GuiActionTag_gui_action_clip_edit => Action::ClipEdit(action.data.clip),
+                GuiActionTag_gui_action_clip_add => Action::ClipAdd(action.data.clip),
+                GuiActionTag_gui_action_clip_remove => {
+                    Action::ClipRemove(action.data.seek_position)
+                }
+                GuiActionTag_gui_action_save => Action::Save,
+                GuiActionTag_gui_action_flush_caches => Action::FlushCaches,
+                GuiActionTag_gui_action_set_preview_quality => {
+                    Action::SetPreviewQuality(PreviewQuality::from_c(action.data.preview_quality))
+                }
+                GuiActionTag_gui_action_request_thumbnail => {
+                    Action::RequestThumbnail(action.data.thumbnail_request)
+                }
+                GuiActionTag_gui_action_cancel_job => Action::CancelJob(action.data.id),
+                GuiActionTag_gui_action_transcribe => Action::Transcribe(action.data.transcribe),
+                GuiActionTag_gui_action_set_caption_style => {
+                    Action::SetCaptionStyle(action.data.caption_style)
+                }
+                GuiActionTag_gui_action_export_captions => {
+                    Action::ExportCaptions(action.data.caption_format)
+                }
+                GuiActionTag_gui_action_import_captions => {
+                    Action::ImportCaptions(action.data.import_request)
+                }
+                GuiActionTag_gui_action_marker_add => Action::MarkerAdd(action.data.marker),
+                GuiActionTag_gui_action_marker_remove => Action::MarkerRemove(action.data.id),
+                GuiActionTag_gui_action_import_markers => {
+                    Action::ImportMarkers(action.data.import_request)
+                }
+                GuiActionTag_gui_action_export_markers => Action::ExportMarkers,
+                GuiActionTag_gui_action_set_speed => Action::SetSpeed(action.data.speed),
+                GuiActionTag_gui_action_step_frames => Action::StepFrames(action.data.frames),
+                GuiActionTag_gui_action_reload => Action::Reload(action.data.reload_target),
+                GuiActionTag_gui_action_replay_journal => Action::ReplayJournal,
+                GuiActionTag_gui_action_discard_journal => Action::DiscardJournal,
+                GuiActionTag_gui_action_revert_history => Action::RevertHistory(action.data.id),
+                GuiActionTag_gui_action_set_dual_preview => {
+                    Action::SetDualPreview(action.data.enabled)
+                }
+                GuiActionTag_gui_action_clip_move => Action::ClipMove(action.data.clip_move),
+                GuiActionTag_gui_action_insert_freeze_frame => {
+                    Action::InsertFreezeFrame(action.data.freeze_frame)
+                }
+                GuiActionTag_gui_action_mute_region_add => {
+                    Action::MuteRegionAdd(action.data.mute_region)
+                }
+                GuiActionTag_gui_action_mute_region_edit => {
+                    Action::MuteRegionEdit(action.data.mute_region)
+                }
+                GuiActionTag_gui_action_mute_region_remove => {
+                    Action::MuteRegionRemove(action.data.id)
+                }
+                GuiActionTag_gui_action_export => Action::Export(action.data.export_request),
+                GuiActionTag_gui_action_cancel_exports => Action::CancelExports,
+                GuiActionTag_gui_action_pause_exports => Action::PauseExports(action.data.enabled),
+                GuiActionTag_gui_action_quick_export => Action::QuickExport(action.data.id),
+                tag => return Err(InvalidActionTag(tag)),
+            };
+            Ok(ret)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn c_chars<const N: usize>(s: &str) -> [std::ffi::c_char; N] {
+        let mut ret = [0; N];
+        for (dst, src) in ret.iter_mut().zip(s.bytes()) {
+            *dst = src as std::ffi::c_char;
+        }
+        ret
+    }
+
+    /// One of every variant, with every field set to something other than zero
+    fn every_action() -> Vec<Action> {
+        let clip = Clip {
+            id: 3,
+            start: 1.5,
+            end: 4.25,
+            speed: 2.0,
+            freeze: true,
+        };
+        let region = GuiMuteRegion {
+            id: 7,
+            start: 2.0,
+            end: 3.5,
+        };
+
+        vec![
+            Action::None,
+            Action::TogglePause,
+            Action::Close,
+            Action::Seek(12.5),
+            Action::ClipEdit(clip),
+            Action::ClipAdd(clip),
+            Action::ClipRemove(2.75),
+            Action::ClipMove(GuiClipMove { id: 3, index: 1 }),
+            Action::Save,
+            Action::FlushCaches,
+            Action::SetPreviewQuality(PreviewQuality::Quarter),
+            Action::RequestThumbnail(GuiThumbnailRequest {
+                request_id: 9,
+                time: 6.0,
+                width: 160,
+                height: 90,
+            }),
+            Action::CancelJob(4),
+            Action::Transcribe(GuiTranscribeRequest {
+                model: GuiTranscriptionModel_gui_transcription_model_base,
+                language: c_chars("de"),
+            }),
+            Action::SetCaptionStyle(GuiCaptionStyle {
+                font: GuiCaptionFont_gui_caption_font_monospace,
+                size: 0.05,
+                color: 0xffff00ff,
+                outline_color: 0x000000ff,
+                outline_width: 0.1,
+                position: 0.08,
+                max_line_len: 42,
+            }),
+            Action::ExportCaptions(GuiCaptionFormat_gui_caption_format_webvtt),
+            Action::ImportCaptions(import_request("a.srt").unwrap()),
+            Action::MarkerAdd(GuiMarker {
+                id: 2,
+                time: 8.0,
+                color: 0xff0000ff,
+                name: c_chars("intro"),
+            }),
+            Action::MarkerRemove(2),
+            Action::ImportMarkers(import_request("markers.csv").unwrap()),
+            Action::ExportMarkers,
+            Action::SetSpeed(-2.0),
+            Action::StepFrames(-3),
+            Action::Reload(GuiReloadTarget_gui_reload_project),
+            Action::ReplayJournal,
+            Action::DiscardJournal,
+            Action::RevertHistory(5),
+            Action::SetDualPreview(true),
+            Action::InsertFreezeFrame(GuiFreezeFrame {
+                position: 3.0,
+                duration: 1.5,
+            }),
+            Action::MuteRegionAdd(region),
+            Action::MuteRegionEdit(region),
+            Action::MuteRegionRemove(7),
+            Action::Export(GuiExportRequest {
+                mode: GuiExportMode_gui_export_mode_clips,
+                pattern: c_chars("{project}-{n}"),
+            }),
+            Action::CancelExports,
+            Action::PauseExports(true),
+            Action::QuickExport(3),
+        ]
+    }
+
+    #[test]
+    fn every_action_survives_the_c_union() {
+        let actions = every_action();
+
+        // Catches a variant being added without a sample above
+        let variants: std::collections::HashSet<_> =
+            actions.iter().map(std::mem::discriminant).collect();
+        assert_eq!(variants.len(), actions.len());
+        for action in &actions {
+            match action {
+                Action::None
+                | Action::TogglePause
+                | Action::Close
+                | Action::Seek(_)
+                | Action::ClipEdit(_)
+                | Action::ClipAdd(_)
+                | Action::ClipRemove(_)
+                | Action::ClipMove(_)
+                | Action::Save
+                | Action::FlushCaches
+                | Action::SetPreviewQuality(_)
+                | Action::RequestThumbnail(_)
+                | Action::CancelJob(_)
+                | Action::Transcribe(_)
+                | Action::SetCaptionStyle(_)
+                | Action::ExportCaptions(_)
+                | Action::ImportCaptions(_)
+                | Action::MarkerAdd(_)
+                | Action::MarkerRemove(_)
+                | Action::ImportMarkers(_)
+                | Action::ExportMarkers
+                | Action::SetSpeed(_)
+                | Action::StepFrames(_)
+                | Action::Reload(_)
+                | Action::ReplayJournal
+                | Action::DiscardJournal
+                | Action::RevertHistory(_)
+                | Action::SetDualPreview(_)
+                | Action::InsertFreezeFrame(_)
+                | Action::MuteRegionAdd(_)
+                | Action::MuteRegionEdit(_)
+                | Action::MuteRegionRemove(_)
+                | Action::Export(_)
+                | Action::CancelExports
+                | Action::PauseExports(_)
+                | Action::QuickExport(_) => (),
+            }
+        }
+
+        for action in actions {
+            let c_action = GuiAction::from(action);
+            let back = Action::try_from(c_action).unwrap();
+            // The C structs have no PartialEq, their Debug output covers every field
+            assert_eq!(format!("{back:?}"), format!("{action:?}"));
+        }
+    }
+
+    #[test]
+    fn unknown_tags_are_rejected() {
+        let action = make_action(GuiActionTag::MAX);
+        match Action::try_from(action) {
+            Err(InvalidActionTag(tag)) => assert_eq!(tag, GuiActionTag::MAX),
+            Ok(action) => panic!("unexpected action: {action:?}"),
+        }
+    }
+
+    #[test]
+    fn import_paths_are_null_terminated() {
+        let request = import_request("a.srt").unwrap();
+        let path: Vec<u8> = request.path[..6].iter().map(|c| *c as u8).collect();
+        assert_eq!(path, b"a.srt\0");
+
+        assert!(import_request("").is_none());
+        assert!(import_request(&"a".repeat(1024)).is_none());
+    }
+}
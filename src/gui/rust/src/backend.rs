@@ -0,0 +1,323 @@
+use std::{ops::Deref, sync::Arc};
+
+use crate::{
+    c_bindings,
+    c_renderer::{CFrameRenderer, CWaveformRenderer},
+    profiling::profile_scope,
+    render::{FrameRenderer, WaveformRenderer},
+};
+
+#[cfg(feature = "wgpu")]
+use crate::wgpu_renderer::{WgpuFrameRenderer, WgpuWaveformRenderer};
+
+/// Everything the GUI needs from the backend. ProjectView and the widgets only talk to the backend
+/// through this, so that they can run against a fake one in tests
+pub trait Backend: Clone + Send + Sync + 'static {
+    type Snapshot: Deref<Target = c_bindings::AppStateSnapshot>;
+
+    fn snapshot(&self) -> Self::Snapshot;
+    fn frame_renderer(&self) -> Arc<FrameRenderer>;
+    fn waveform_renderer(&self) -> Arc<WaveformRenderer>;
+    /// None if there is no transcript to look the position up in
+    fn wtm_get_time(&self, char_pos: u64) -> Option<f32>;
+    /// None if there is no transcript to look the position up in
+    fn wtm_get_char_pos(&self, pts: f32) -> Option<u64>;
+    /// Every word of the transcript in text order, empty if there is no transcript
+    fn wtm_get_words(&self) -> Vec<c_bindings::GuiWord>;
+    /// Caption lines of at most max_line_len bytes in time order, empty if there is no transcript
+    fn wtm_get_captions(&self, max_line_len: u64) -> Vec<c_bindings::GuiCaption>;
+}
+
+/// The real backend, implemented by the Zig side
+#[derive(Clone)]
+pub struct CBackend {
+    app_state: *mut c_bindings::AppState,
+    frame_renderer: Arc<FrameRenderer>,
+    waveform_renderer: Arc<WaveformRenderer>,
+    wtm: *mut c_bindings::WordTimestampMap,
+}
+
+// The backend is responsible for making these safe to use from the GUI thread
+unsafe impl Send for CBackend {}
+unsafe impl Sync for CBackend {}
+
+impl CBackend {
+    /// All pointers have to outlive the GUI. wtm may be null if there is no transcript
+    pub unsafe fn new(
+        app_state: *mut c_bindings::AppState,
+        frame_renderer: *mut c_bindings::FrameRenderer,
+        audio_renderer: *mut c_bindings::AudioRenderer,
+        wtm: *mut c_bindings::WordTimestampMap,
+    ) -> CBackend {
+        let frame_renderer = CFrameRenderer::new(frame_renderer);
+        let waveform_renderer = CWaveformRenderer::new(audio_renderer);
+
+        #[cfg(feature = "wgpu")]
+        let (frame_renderer, waveform_renderer) = (
+            WgpuFrameRenderer::new(frame_renderer),
+            WgpuWaveformRenderer::new(waveform_renderer),
+        );
+
+        CBackend {
+            app_state,
+            frame_renderer: Arc::new(frame_renderer),
+            waveform_renderer: Arc::new(waveform_renderer),
+            wtm,
+        }
+    }
+}
+
+impl Backend for CBackend {
+    type Snapshot = SnapshotHolder;
+
+    fn snapshot(&self) -> SnapshotHolder {
+        SnapshotHolder::new(self.app_state)
+    }
+
+    fn frame_renderer(&self) -> Arc<FrameRenderer> {
+        Arc::clone(&self.frame_renderer)
+    }
+
+    fn waveform_renderer(&self) -> Arc<WaveformRenderer> {
+        Arc::clone(&self.waveform_renderer)
+    }
+
+    fn wtm_get_time(&self, char_pos: u64) -> Option<f32> {
+        if self.wtm.is_null() {
+            return None;
+        }
+
+        unsafe { Some(c_bindings::wtm_get_time(self.wtm, char_pos)) }
+    }
+
+    fn wtm_get_char_pos(&self, pts: f32) -> Option<u64> {
+        if self.wtm.is_null() {
+            return None;
+        }
+
+        unsafe { Some(c_bindings::wtm_get_char_pos(self.wtm, pts)) }
+    }
+
+    fn wtm_get_words(&self) -> Vec<c_bindings::GuiWord> {
+        const CHUNK_SIZE: usize = 4096;
+
+        let mut words = Vec::new();
+        if self.wtm.is_null() {
+            return words;
+        }
+
+        loop {
+            words.reserve(CHUNK_SIZE);
+            let num_read = unsafe {
+                let mut total = 0;
+                let num_read = c_bindings::wtm_get_words(
+                    self.wtm,
+                    words.len() as u64,
+                    words.spare_capacity_mut().as_mut_ptr().cast(),
+                    CHUNK_SIZE as u64,
+                    &mut total,
+                ) as usize;
+                words.set_len(words.len() + num_read);
+                num_read
+            };
+
+            if num_read < CHUNK_SIZE {
+                return words;
+            }
+        }
+    }
+
+    fn wtm_get_captions(&self, max_line_len: u64) -> Vec<c_bindings::GuiCaption> {
+        let mut captions = Vec::new();
+        if self.wtm.is_null() {
+            return captions;
+        }
+
+        // The backend regroups on every call, so ask for everything at once
+        let mut total = 0;
+        unsafe {
+            c_bindings::wtm_get_captions(
+                self.wtm,
+                max_line_len,
+                0,
+                std::ptr::null_mut(),
+                0,
+                &mut total,
+            );
+        }
+
+        captions.reserve(total as usize);
+        unsafe {
+            let num_read = c_bindings::wtm_get_captions(
+                self.wtm,
+                max_line_len,
+                0,
+                captions.as_mut_ptr(),
+                captions.capacity() as u64,
+                &mut total,
+            );
+            captions.set_len(num_read as usize);
+        }
+        captions
+    }
+}
+
+pub struct SnapshotHolder {
+    app_state: *mut c_bindings::AppState,
+    snapshot: c_bindings::AppStateSnapshot,
+}
+
+impl SnapshotHolder {
+    fn new(app_state: *mut c_bindings::AppState) -> SnapshotHolder {
+        profile_scope!("SnapshotHolder::new");
+        let snapshot = unsafe { c_bindings::appstate_snapshot(app_state) };
+        SnapshotHolder {
+            app_state,
+            snapshot,
+        }
+    }
+}
+
+impl Deref for SnapshotHolder {
+    type Target = c_bindings::AppStateSnapshot;
+    fn deref(&self) -> &Self::Target {
+        &self.snapshot
+    }
+}
+
+impl Drop for SnapshotHolder {
+    fn drop(&mut self) {
+        unsafe { c_bindings::appstate_deinit(self.app_state, &self.snapshot) }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    use std::sync::{Mutex, MutexGuard};
+
+    use crate::render::{CommandRecorder, FrameParams, Renderer, WaveformParams};
+
+    #[derive(Default)]
+    pub struct MockState {
+        pub paused: bool,
+        pub current_position: f32,
+        pub total_runtime: f32,
+        pub clips: Vec<c_bindings::Clip>,
+        pub words: Vec<c_bindings::GuiWord>,
+        pub captions: Vec<c_bindings::GuiCaption>,
+        pub frames_rendered: usize,
+        pub audio_rendered: usize,
+    }
+
+    /// A clip at normal speed that is not a freeze frame, which is what most tests need
+    pub fn clip(id: u64, start: f32, end: f32) -> c_bindings::Clip {
+        c_bindings::Clip {
+            id,
+            start,
+            end,
+            speed: 1.0,
+            freeze: false,
+        }
+    }
+
+    /// Backend that serves snapshots from MockState and counts render calls instead of drawing
+    #[derive(Clone, Default)]
+    pub struct MockBackend {
+        state: Arc<Mutex<MockState>>,
+    }
+
+    impl MockBackend {
+        pub fn state(&self) -> MutexGuard<'_, MockState> {
+            self.state.lock().unwrap()
+        }
+    }
+
+    pub struct MockSnapshot {
+        snapshot: c_bindings::AppStateSnapshot,
+        // Owns the memory the snapshot points into
+        _clips: Vec<c_bindings::Clip>,
+    }
+
+    impl Deref for MockSnapshot {
+        type Target = c_bindings::AppStateSnapshot;
+        fn deref(&self) -> &Self::Target {
+            &self.snapshot
+        }
+    }
+
+    impl Backend for MockBackend {
+        type Snapshot = MockSnapshot;
+
+        fn snapshot(&self) -> MockSnapshot {
+            let state = self.state();
+            let clips = state.clips.clone();
+
+            let mut snapshot: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+            snapshot.paused = state.paused;
+            snapshot.playback_speed = 1.0;
+            snapshot.current_position = state.current_position;
+            snapshot.total_runtime = state.total_runtime;
+            snapshot.clips = clips.as_ptr();
+            snapshot.num_clips = clips.len() as u64;
+
+            MockSnapshot {
+                snapshot,
+                _clips: clips,
+            }
+        }
+
+        fn frame_renderer(&self) -> Arc<FrameRenderer> {
+            Arc::new(MockFrameRenderer(self.clone()))
+        }
+
+        fn waveform_renderer(&self) -> Arc<WaveformRenderer> {
+            Arc::new(MockWaveformRenderer(self.clone()))
+        }
+
+        fn wtm_get_time(&self, _char_pos: u64) -> Option<f32> {
+            None
+        }
+
+        fn wtm_get_char_pos(&self, _pts: f32) -> Option<u64> {
+            None
+        }
+
+        fn wtm_get_words(&self) -> Vec<c_bindings::GuiWord> {
+            self.state().words.clone()
+        }
+
+        fn wtm_get_captions(&self, _max_line_len: u64) -> Vec<c_bindings::GuiCaption> {
+            self.state().captions.clone()
+        }
+    }
+
+    struct MockFrameRenderer(MockBackend);
+
+    impl Renderer for MockFrameRenderer {
+        type Params = FrameParams;
+
+        fn init(&self, _recorder: &mut CommandRecorder) {}
+
+        fn render(&self, _recorder: &mut CommandRecorder, _params: FrameParams) {
+            self.0.state().frames_rendered += 1;
+        }
+
+        fn deinit(&self, _recorder: &mut CommandRecorder) {}
+    }
+
+    struct MockWaveformRenderer(MockBackend);
+
+    impl Renderer for MockWaveformRenderer {
+        type Params = WaveformParams;
+
+        fn init(&self, _recorder: &mut CommandRecorder) {}
+
+        fn render(&self, _recorder: &mut CommandRecorder, _params: WaveformParams) {
+            self.0.state().audio_rendered += 1;
+        }
+
+        fn deinit(&self, _recorder: &mut CommandRecorder) {}
+    }
+}
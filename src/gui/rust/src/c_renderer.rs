@@ -0,0 +1,190 @@
+use eframe::glow;
+
+use std::ffi::c_void;
+
+use crate::{
+    c_bindings,
+    profiling::profile_scope,
+    render::{CommandRecorder, FrameParams, Renderer, WaveformParams},
+};
+
+#[cfg(feature = "wgpu")]
+use crate::panic_guard::catch_panic;
+
+/// The C renderers get handed the glow context as an opaque userdata pointer and call back into
+/// the guigl_* exports with it. This is the only place that should know about that
+///
+/// None for recorders that are not backed by GL, the C renderers cannot draw with those
+fn gl_userdata(recorder: &mut CommandRecorder) -> Option<*mut c_void> {
+    match recorder {
+        CommandRecorder::Glow(gl) => Some(*gl as *const glow::Context as *mut c_void),
+        #[cfg(feature = "wgpu")]
+        CommandRecorder::Wgpu(_) => None,
+    }
+}
+
+/// A decoded frame in planar YUV 4:2:0, see GuiVideoFrame
+#[cfg(feature = "wgpu")]
+pub struct VideoFrame<'a> {
+    pub y: &'a [u8],
+    pub u: &'a [u8],
+    pub v: &'a [u8],
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+}
+
+pub struct CFrameRenderer(*mut c_bindings::FrameRenderer);
+
+pub struct CWaveformRenderer(*mut c_bindings::AudioRenderer);
+
+// The backend is responsible for making these safe to use from the GUI thread
+unsafe impl Send for CFrameRenderer {}
+unsafe impl Sync for CFrameRenderer {}
+unsafe impl Send for CWaveformRenderer {}
+unsafe impl Sync for CWaveformRenderer {}
+
+impl CFrameRenderer {
+    /// renderer has to outlive the GUI
+    pub unsafe fn new(renderer: *mut c_bindings::FrameRenderer) -> CFrameRenderer {
+        CFrameRenderer(renderer)
+    }
+
+    /// What view should draw at the moment, see GuiFrameContent
+    #[cfg(feature = "wgpu")]
+    pub fn content(&self, view: c_bindings::GuiFrameView) -> c_bindings::GuiFrameContent {
+        unsafe { c_bindings::framerenderer_content(self.0, view) }
+    }
+
+    /// Calls f with the newest frame of view if the backend has one that has not been consumed
+    /// yet. This is how renderers that do not speak GL get at the frames
+    #[cfg(feature = "wgpu")]
+    pub fn consume_frame<F: FnOnce(VideoFrame)>(&self, view: c_bindings::GuiFrameView, f: F) {
+        unsafe extern "C" fn callback<F: FnOnce(VideoFrame)>(
+            userdata: *mut c_void,
+            frame: *const c_bindings::GuiVideoFrame,
+        ) {
+            catch_panic((), || {
+                let f = (*(userdata as *mut Option<F>)).take().unwrap();
+                let frame = &*frame;
+                let chroma_len = (frame.stride / 2 * frame.height / 2) as usize;
+                f(VideoFrame {
+                    y: std::slice::from_raw_parts(frame.y, (frame.stride * frame.height) as usize),
+                    u: std::slice::from_raw_parts(frame.u, chroma_len),
+                    v: std::slice::from_raw_parts(frame.v, chroma_len),
+                    width: frame.width,
+                    height: frame.height,
+                    stride: frame.stride,
+                })
+            })
+        }
+
+        let mut f = Some(f);
+        unsafe {
+            c_bindings::framerenderer_consume_frame(
+                self.0,
+                view,
+                Some(callback::<F>),
+                &mut f as *mut Option<F> as *mut c_void,
+            );
+        }
+    }
+}
+
+impl CWaveformRenderer {
+    /// renderer has to outlive the GUI
+    pub unsafe fn new(renderer: *mut c_bindings::AudioRenderer) -> CWaveformRenderer {
+        CWaveformRenderer(renderer)
+    }
+
+    /// Appends whatever the backend has loaded past the end of samples. Returns how many samples
+    /// there will be once it is done loading
+    #[cfg(feature = "wgpu")]
+    pub fn read_samples(&self, samples: &mut Vec<c_bindings::GuiWaveformSample>) -> u64 {
+        const CHUNK_SIZE: usize = 4096;
+
+        let mut total = 0;
+        loop {
+            samples.reserve(CHUNK_SIZE);
+            let num_read = unsafe {
+                let num_read = c_bindings::audiorenderer_get_samples(
+                    self.0,
+                    samples.len() as u64,
+                    samples.spare_capacity_mut().as_mut_ptr().cast(),
+                    CHUNK_SIZE as u64,
+                    &mut total,
+                ) as usize;
+                samples.set_len(samples.len() + num_read);
+                num_read
+            };
+
+            if num_read < CHUNK_SIZE {
+                return total;
+            }
+        }
+    }
+
+    /// Changes when the backend starts loading the waveform over, samples read before then
+    /// have to be thrown away
+    #[cfg(feature = "wgpu")]
+    pub fn generation(&self) -> u64 {
+        unsafe { c_bindings::audiorenderer_generation(self.0) }
+    }
+}
+
+impl Renderer for CFrameRenderer {
+    type Params = FrameParams;
+
+    fn init(&self, recorder: &mut CommandRecorder) {
+        if let Some(gl) = gl_userdata(recorder) {
+            unsafe { c_bindings::framerenderer_init_gl(self.0, gl) }
+        }
+    }
+
+    fn render(&self, recorder: &mut CommandRecorder, params: FrameParams) {
+        profile_scope!("framerenderer_render");
+        if let Some(gl) = gl_userdata(recorder) {
+            unsafe {
+                c_bindings::framerenderer_render(
+                    self.0,
+                    params.view,
+                    params.width,
+                    params.height,
+                    &params.color,
+                    gl,
+                );
+            }
+        }
+    }
+
+    fn deinit(&self, recorder: &mut CommandRecorder) {
+        if let Some(gl) = gl_userdata(recorder) {
+            unsafe { c_bindings::framerenderer_deinit_gl(self.0, gl) }
+        }
+    }
+}
+
+impl Renderer for CWaveformRenderer {
+    type Params = WaveformParams;
+
+    fn init(&self, recorder: &mut CommandRecorder) {
+        if let Some(gl) = gl_userdata(recorder) {
+            unsafe { c_bindings::audiorenderer_init_gl(self.0, gl) }
+        }
+    }
+
+    fn render(&self, recorder: &mut CommandRecorder, params: WaveformParams) {
+        profile_scope!("audiorenderer_render");
+        if let Some(gl) = gl_userdata(recorder) {
+            unsafe {
+                c_bindings::audiorenderer_render(self.0, gl, params.zoom, params.center_norm);
+            }
+        }
+    }
+
+    fn deinit(&self, recorder: &mut CommandRecorder) {
+        if let Some(gl) = gl_userdata(recorder) {
+            unsafe { c_bindings::audiorenderer_deinit_gl(self.0, gl) }
+        }
+    }
+}
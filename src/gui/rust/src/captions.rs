@@ -0,0 +1,279 @@
+use eframe::egui;
+
+use crate::{actions::Action, backend::Backend, c_bindings, ActionRequestor};
+
+/// Draws the caption line for the current position over the video preview, so that caption
+/// timing and line breaks can be checked before exporting
+pub struct CaptionPreview {
+    pub enabled: bool,
+    /// The max line length the captions were grouped with, and the captions. None when they have
+    /// to be fetched again, e.g. because the transcript changed
+    captions: Option<(u32, Vec<c_bindings::GuiCaption>)>,
+}
+
+impl CaptionPreview {
+    pub fn new() -> CaptionPreview {
+        CaptionPreview {
+            enabled: false,
+            captions: None,
+        }
+    }
+
+    pub fn transcript_changed(&mut self) {
+        self.captions = None;
+    }
+
+    /// rect is the area the video is drawn in
+    pub fn show<B: Backend>(
+        &mut self,
+        ui: &egui::Ui,
+        rect: egui::Rect,
+        state: &c_bindings::AppStateSnapshot,
+        backend: &B,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        let style = &state.caption_style;
+        if self
+            .captions
+            .as_ref()
+            .is_some_and(|(max_line_len, _)| *max_line_len != style.max_line_len)
+        {
+            self.captions = None;
+        }
+
+        let (_, captions) = self.captions.get_or_insert_with(|| {
+            let max_line_len = style.max_line_len;
+            (max_line_len, backend.wtm_get_captions(max_line_len.into()))
+        });
+
+        let Some(caption) = current_caption(captions, state.current_position) else {
+            return;
+        };
+
+        let Some(text) = (unsafe { caption_text(state, caption) }) else {
+            return;
+        };
+
+        let font_size = style.size * rect.height();
+        let family = if style.font == c_bindings::GuiCaptionFont_gui_caption_font_monospace {
+            egui::FontFamily::Monospace
+        } else {
+            egui::FontFamily::Proportional
+        };
+
+        // Laid out with the placeholder color so that the same galley can be painted as both the
+        // outline and the text
+        let painter = ui.painter_at(rect);
+        let galley = painter.layout_no_wrap(
+            text.to_string(),
+            egui::FontId::new(font_size, family),
+            egui::Color32::PLACEHOLDER,
+        );
+
+        let bottom_center = rect.center_bottom() - egui::vec2(0.0, rect.height() * style.position);
+        let text_pos = egui::Align2::CENTER_BOTTOM
+            .anchor_size(bottom_center, galley.size())
+            .min;
+
+        let outline_width = style.outline_width * font_size;
+        if outline_width > 0.0 {
+            let outline_color = color_from_c(style.outline_color);
+            for i in 0..8 {
+                let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+                let offset = egui::Vec2::angled(angle) * outline_width;
+                painter.galley(text_pos + offset, galley.clone(), outline_color);
+            }
+        }
+        painter.galley(text_pos, galley, color_from_c(style.color));
+    }
+}
+
+/// Edits the caption style the backend keeps with the project, and starts caption exports
+pub struct CaptionStyleEditor {
+    pub open: bool,
+    /// The GUI is the only one that changes the style, so once we have a copy it is always the
+    /// latest. Editing the snapshot's would lose changes that the backend has not applied yet
+    style: Option<c_bindings::GuiCaptionStyle>,
+}
+
+impl CaptionStyleEditor {
+    pub fn new() -> CaptionStyleEditor {
+        CaptionStyleEditor {
+            open: false,
+            style: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let style = self.style.get_or_insert(state.caption_style);
+
+        let mut open = self.open;
+        egui::Window::new("Caption style")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let mut changed = false;
+
+                egui::ComboBox::from_label("font")
+                    .selected_text(font_label(style.font))
+                    .show_ui(ui, |ui| {
+                        for font in [
+                            c_bindings::GuiCaptionFont_gui_caption_font_proportional,
+                            c_bindings::GuiCaptionFont_gui_caption_font_monospace,
+                        ] {
+                            changed |= ui
+                                .selectable_value(&mut style.font, font, font_label(font))
+                                .changed();
+                        }
+                    });
+
+                changed |= ui
+                    .add(egui::Slider::new(&mut style.size, 0.02..=0.15).text("size"))
+                    .changed();
+
+                ui.horizontal(|ui| {
+                    changed |= color_edit(ui, &mut style.color);
+                    ui.label("color");
+                });
+
+                ui.horizontal(|ui| {
+                    changed |= color_edit(ui, &mut style.outline_color);
+                    ui.label("outline color");
+                });
+
+                changed |= ui
+                    .add(egui::Slider::new(&mut style.outline_width, 0.0..=0.3).text("outline"))
+                    .changed();
+
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut style.position, 0.0..=0.5)
+                            .text("distance from bottom"),
+                    )
+                    .changed();
+
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut style.max_line_len).clamp_range(10..=100))
+                        .changed();
+                    ui.label("max line length");
+                });
+
+                if changed {
+                    action_tx.send(Action::SetCaptionStyle(*style));
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Export");
+                    if ui.button("SRT").clicked() {
+                        action_tx.send(Action::ExportCaptions(
+                            c_bindings::GuiCaptionFormat_gui_caption_format_srt,
+                        ));
+                    }
+                    if ui
+                        .button("WebVTT")
+                        .on_hover_text("Keeps the caption position and colors")
+                        .clicked()
+                    {
+                        action_tx.send(Action::ExportCaptions(
+                            c_bindings::GuiCaptionFormat_gui_caption_format_webvtt,
+                        ));
+                    }
+                });
+            });
+        self.open = open;
+    }
+}
+
+fn font_label(font: c_bindings::GuiCaptionFont) -> &'static str {
+    match font {
+        c_bindings::GuiCaptionFont_gui_caption_font_monospace => "monospace",
+        _ => "proportional",
+    }
+}
+
+pub fn color_edit(ui: &mut egui::Ui, rgba: &mut u32) -> bool {
+    let mut color = color_from_c(*rgba);
+    // Only written back on change, alpha makes the round trip lossy
+    let changed = ui.color_edit_button_srgba(&mut color).changed();
+    if changed {
+        *rgba = color_to_c(color);
+    }
+    changed
+}
+
+pub fn color_from_c(rgba: u32) -> egui::Color32 {
+    let [r, g, b, a] = rgba.to_be_bytes();
+    egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+fn color_to_c(color: egui::Color32) -> u32 {
+    u32::from_be_bytes(color.to_srgba_unmultiplied())
+}
+
+/// The caption shown at pts, captions have to be in time order
+fn current_caption(
+    captions: &[c_bindings::GuiCaption],
+    pts: f32,
+) -> Option<&c_bindings::GuiCaption> {
+    let idx = captions.partition_point(|caption| caption.start <= pts);
+    let caption = captions[..idx].last()?;
+    (pts < caption.end).then_some(caption)
+}
+
+/// None if the caption does not line up with the snapshot text, e.g. because the transcript
+/// changed since the captions were fetched
+unsafe fn caption_text<'a>(
+    state: &'a c_bindings::AppStateSnapshot,
+    caption: &c_bindings::GuiCaption,
+) -> Option<&'a str> {
+    if state.text_len == 0 {
+        // text may be null
+        return None;
+    }
+
+    let s = std::slice::from_raw_parts(state.text as *const u8, state.text_len as usize);
+    let s = std::str::from_utf8_unchecked(s);
+    s.get(caption.char_start as usize..caption.char_end as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn caption(start: f32, end: f32) -> c_bindings::GuiCaption {
+        c_bindings::GuiCaption {
+            char_start: 0,
+            char_end: 0,
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn current_caption_covers_its_time_only() {
+        let captions = [caption(1.0, 2.0), caption(2.0, 3.0), caption(5.0, 6.0)];
+
+        assert!(current_caption(&captions, 0.5).is_none());
+        assert_eq!(current_caption(&captions, 1.5).unwrap().start, 1.0);
+        assert_eq!(current_caption(&captions, 2.0).unwrap().start, 2.0);
+        assert!(current_caption(&captions, 4.0).is_none());
+        assert!(current_caption(&captions, 6.0).is_none());
+    }
+
+    #[test]
+    fn colors_are_rgba() {
+        let color = color_from_c(0x112233ff);
+        assert_eq!(color, egui::Color32::from_rgb(0x11, 0x22, 0x33));
+        assert_eq!(color_to_c(color), 0x112233ff);
+    }
+}
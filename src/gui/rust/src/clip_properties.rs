@@ -0,0 +1,135 @@
+use eframe::egui;
+
+use crate::{actions::Action, c_bindings, transcript, ActionRequestor};
+
+/// Same limits as the backend clamps to
+const MIN_SPEED: f32 = 0.1;
+const MAX_SPEED: f32 = 16.0;
+
+const SPEED_PRESETS: [f32; 5] = [0.5, 1.0, 2.0, 4.0, 8.0];
+
+/// Settings of the clip under the playhead
+pub struct ClipPropertiesPanel {
+    pub open: bool,
+}
+
+impl ClipPropertiesPanel {
+    pub fn new() -> ClipPropertiesPanel {
+        ClipPropertiesPanel { open: false }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let clip = clip_at(transcript::clips(state), state.current_position);
+
+        let mut open = self.open;
+        egui::Window::new("Clip properties")
+            .open(&mut open)
+            .default_width(240.0)
+            .show(ctx, |ui| {
+                let Some(clip) = clip else {
+                    ui.weak("Move the playhead into a clip to edit it");
+                    return;
+                };
+
+                let length = clip.end - clip.start;
+                ui.label(format!("{:.2}–{:.2}", clip.start, clip.end));
+                ui.label(format!(
+                    "{length:.1}s of source, plays for {:.1}s",
+                    length / clip.speed
+                ));
+                ui.separator();
+
+                let mut percent = clip.speed * 100.0;
+                let mut changed = ui
+                    .horizontal(|ui| {
+                        ui.label("Speed");
+                        ui.add(
+                            egui::DragValue::new(&mut percent)
+                                .clamp_range(MIN_SPEED * 100.0..=MAX_SPEED * 100.0)
+                                .speed(5.0)
+                                .suffix("%"),
+                        )
+                        .changed()
+                    })
+                    .inner;
+                let mut speed = percent / 100.0;
+
+                ui.horizontal(|ui| {
+                    for preset in SPEED_PRESETS {
+                        if ui
+                            .selectable_label(clip.speed == preset, speed_label(preset))
+                            .clicked()
+                        {
+                            speed = preset;
+                            changed = true;
+                        }
+                    }
+                });
+
+                if changed && speed != clip.speed {
+                    action_tx.send(Action::ClipEdit(c_bindings::Clip { speed, ..clip }));
+                }
+
+                let mut freeze = clip.freeze;
+                if ui
+                    .checkbox(&mut freeze, "Freeze frame")
+                    .on_hover_text("Hold the first frame while the audio plays on")
+                    .changed()
+                {
+                    action_tx.send(Action::ClipEdit(c_bindings::Clip { freeze, ..clip }));
+                }
+            });
+        self.open = open;
+    }
+}
+
+/// e.g. "400%"
+pub fn speed_label(speed: f32) -> String {
+    format!("{:.0}%", speed * 100.0)
+}
+
+/// The clip the backend plays at position. Touching clips share an edge, the one that starts
+/// there plays it
+fn clip_at(clips: &[c_bindings::Clip], position: f32) -> Option<c_bindings::Clip> {
+    clips
+        .iter()
+        .find(|clip| position >= clip.start && position < clip.end)
+        .or_else(|| clips.iter().find(|clip| position == clip.end))
+        .copied()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn speeds_are_labelled_as_percentages() {
+        assert_eq!(speed_label(4.0), "400%");
+        assert_eq!(speed_label(0.5), "50%");
+        assert_eq!(speed_label(1.0 / 3.0), "33%");
+    }
+
+    #[test]
+    fn first_clip_under_the_playhead_is_edited() {
+        let clip = |id, start, end| c_bindings::Clip {
+            id,
+            start,
+            end,
+            speed: 1.0,
+            freeze: false,
+        };
+        let clips = [clip(1, 5.0, 8.0), clip(2, 0.0, 6.0)];
+        assert_eq!(clip_at(&clips, 5.5).map(|clip| clip.id), Some(1));
+        assert_eq!(clip_at(&clips, 2.0).map(|clip| clip.id), Some(2));
+        assert!(clip_at(&clips, 9.0).is_none());
+
+        let touching = [clip(1, 0.0, 2.0), clip(2, 2.0, 4.0)];
+        assert_eq!(clip_at(&touching, 2.0).map(|clip| clip.id), Some(2));
+        assert_eq!(clip_at(&touching, 4.0).map(|clip| clip.id), Some(2));
+    }
+}
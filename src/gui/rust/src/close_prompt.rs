@@ -0,0 +1,174 @@
+use eframe::egui;
+
+use std::time::{Duration, Instant};
+
+/// A save that has not gone through after this long has most likely failed, see the log
+const SAVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub enum Answer {
+    /// Save the projects with unsaved changes, the window closes once they are
+    Save,
+    Discard,
+}
+
+/// Holds the window open when it is closed with unsaved changes, until the user says what should
+/// happen to them
+pub struct ClosePrompt {
+    open: bool,
+    /// When the saves we are waiting for were asked for
+    saving: Option<Instant>,
+    /// The next close request goes through
+    confirmed: bool,
+}
+
+impl ClosePrompt {
+    pub fn new() -> ClosePrompt {
+        ClosePrompt {
+            open: false,
+            saving: None,
+            confirmed: false,
+        }
+    }
+
+    /// Whether a close request has to be held back. Closes the backend or a panic asked for are
+    /// never held back, nobody would be around to answer
+    fn hold_close(&mut self, forced: bool, unsaved: bool) -> bool {
+        if forced || self.confirmed || !unsaved {
+            return false;
+        }
+        self.open = true;
+        true
+    }
+
+    /// Whether the saves asked for went through and the window can go
+    fn saves_done(&mut self, unsaved: bool) -> bool {
+        if self.saving.is_none() || unsaved {
+            return false;
+        }
+        self.saving = None;
+        self.open = false;
+        self.confirmed = true;
+        true
+    }
+
+    /// unsaved names the projects with unsaved changes. It is only asked while a close is being
+    /// requested or answered, the rest of the time nobody needs to know
+    pub fn show<'a>(
+        &mut self,
+        ctx: &egui::Context,
+        forced: bool,
+        unsaved: impl FnOnce() -> Vec<&'a str>,
+    ) -> Option<Answer> {
+        let close_requested = ctx.input(|i| i.viewport().close_requested());
+        if !close_requested && !self.open {
+            return None;
+        }
+
+        let unsaved = unsaved();
+        if close_requested && self.hold_close(forced, !unsaved.is_empty()) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+        }
+
+        if self.saves_done(!unsaved.is_empty()) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            return None;
+        }
+
+        if !self.open {
+            return None;
+        }
+
+        // Keeps the rest of the window from being clicked while the question is up
+        egui::Area::new(egui::Id::new("close_prompt_backdrop"))
+            .order(egui::Order::PanelResizeLine)
+            .fixed_pos(egui::Pos2::ZERO)
+            .show(ctx, |ui| {
+                let screen_rect = ctx.screen_rect();
+                ui.painter()
+                    .rect_filled(screen_rect, 0.0, egui::Color32::from_black_alpha(128));
+                ui.allocate_rect(screen_rect, egui::Sense::click_and_drag());
+            });
+
+        let mut ret = None;
+        egui::Window::new("Unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                match unsaved.as_slice() {
+                    [name] => ui.label(format!("{name} has unsaved changes.")),
+                    names => ui.label(format!("{} have unsaved changes.", names.join(", "))),
+                };
+
+                if self
+                    .saving
+                    .is_some_and(|since| since.elapsed() > SAVE_TIMEOUT)
+                {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "Saving is taking long, it may have failed. See the log.",
+                    );
+                }
+
+                ui.horizontal(|ui| {
+                    if self.saving.is_some() {
+                        ui.spinner();
+                        ui.label("Saving...");
+                        // Picks up the timeout even if nothing else repaints
+                        ctx.request_repaint_after(Duration::from_secs(1));
+                    } else if ui.button("Save").clicked() {
+                        self.saving = Some(Instant::now());
+                        ret = Some(Answer::Save);
+                    }
+                    // Also while saving, a save that failed would otherwise hold the window
+                    // forever
+                    if ui.button("Discard").clicked() {
+                        self.open = false;
+                        self.saving = None;
+                        self.confirmed = true;
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                        ret = Some(Answer::Discard);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.open = false;
+                        self.saving = None;
+                    }
+                });
+            });
+
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_unsaved_changes_hold_the_window() {
+        let mut prompt = ClosePrompt::new();
+        assert!(!prompt.hold_close(false, false));
+        assert!(!prompt.hold_close(true, true));
+        assert!(!prompt.open);
+
+        assert!(prompt.hold_close(false, true));
+        assert!(prompt.open);
+
+        prompt.confirmed = true;
+        assert!(!prompt.hold_close(false, true));
+    }
+
+    #[test]
+    fn window_closes_once_saved() {
+        let mut prompt = ClosePrompt::new();
+        assert!(prompt.hold_close(false, true));
+        // Nothing was asked to be saved
+        assert!(!prompt.saves_done(false));
+
+        prompt.saving = Some(Instant::now());
+        assert!(!prompt.saves_done(true));
+        assert!(prompt.saves_done(false));
+        assert!(!prompt.open);
+        assert!(!prompt.hold_close(false, true));
+    }
+}
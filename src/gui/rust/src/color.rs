@@ -0,0 +1,268 @@
+use eframe::egui;
+
+use crate::{c_bindings, config};
+
+/// YUV to RGB matrix, see GuiColorMatrix
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Matrix {
+    Auto,
+    Bt601,
+    Bt709,
+}
+
+impl Matrix {
+    const ALL: [Matrix; 3] = [Matrix::Auto, Matrix::Bt601, Matrix::Bt709];
+
+    /// Name in the preferences file
+    fn name(&self) -> &'static str {
+        match self {
+            Matrix::Auto => "auto",
+            Matrix::Bt601 => "bt601",
+            Matrix::Bt709 => "bt709",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Matrix::Auto => "From the video",
+            Matrix::Bt601 => "BT.601",
+            Matrix::Bt709 => "BT.709",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Matrix> {
+        Matrix::ALL.into_iter().find(|matrix| matrix.name() == name)
+    }
+
+    fn to_c(self) -> c_bindings::GuiColorMatrix {
+        match self {
+            Matrix::Auto => c_bindings::GuiColorMatrix_gui_color_matrix_auto,
+            Matrix::Bt601 => c_bindings::GuiColorMatrix_gui_color_matrix_bt601,
+            Matrix::Bt709 => c_bindings::GuiColorMatrix_gui_color_matrix_bt709,
+        }
+    }
+}
+
+/// Input transfer function, see GuiTransferFunction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transfer {
+    Srgb,
+    Bt1886,
+    Gamma22,
+}
+
+impl Transfer {
+    const ALL: [Transfer; 3] = [Transfer::Srgb, Transfer::Bt1886, Transfer::Gamma22];
+
+    /// Name in the preferences file
+    fn name(&self) -> &'static str {
+        match self {
+            Transfer::Srgb => "srgb",
+            Transfer::Bt1886 => "bt1886",
+            Transfer::Gamma22 => "gamma22",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Transfer::Srgb => "sRGB",
+            Transfer::Bt1886 => "BT.1886 (gamma 2.4)",
+            Transfer::Gamma22 => "Gamma 2.2",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Transfer> {
+        Transfer::ALL
+            .into_iter()
+            .find(|transfer| transfer.name() == name)
+    }
+
+    fn to_c(self) -> c_bindings::GuiTransferFunction {
+        match self {
+            Transfer::Srgb => c_bindings::GuiTransferFunction_gui_transfer_srgb,
+            Transfer::Bt1886 => c_bindings::GuiTransferFunction_gui_transfer_bt1886,
+            Transfer::Gamma22 => c_bindings::GuiTransferFunction_gui_transfer_gamma22,
+        }
+    }
+}
+
+/// How the preview shows the video, kept in a preferences file next to the MIDI one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorPreferences {
+    /// Show the video the way players show the export, matrix and input_transfer only apply
+    /// when this is off
+    pub match_export: bool,
+    pub matrix: Matrix,
+    pub input_transfer: Transfer,
+    pub srgb_framebuffer: bool,
+}
+
+impl Default for ColorPreferences {
+    fn default() -> ColorPreferences {
+        ColorPreferences {
+            match_export: true,
+            matrix: Matrix::Auto,
+            input_transfer: Transfer::Srgb,
+            srgb_framebuffer: false,
+        }
+    }
+}
+
+impl ColorPreferences {
+    /// One entry per line, e.g. "manual", "matrix bt709", "transfer bt1886" or
+    /// "srgb_framebuffer". Anything not mentioned keeps its default
+    pub fn parse(data: &str) -> ColorPreferences {
+        let mut ret = ColorPreferences::default();
+
+        for (line_num, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            let known = match key {
+                "manual" => {
+                    ret.match_export = false;
+                    true
+                }
+                "srgb_framebuffer" => {
+                    ret.srgb_framebuffer = true;
+                    true
+                }
+                "matrix" => Matrix::from_name(value)
+                    .map(|matrix| ret.matrix = matrix)
+                    .is_some(),
+                "transfer" => Transfer::from_name(value)
+                    .map(|transfer| ret.input_transfer = transfer)
+                    .is_some(),
+                _ => false,
+            };
+            if !known {
+                log::warn!("Ignoring color setting on line {}: {line}", line_num + 1);
+            }
+        }
+
+        ret
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut ret = String::new();
+        if !self.match_export {
+            ret.push_str("manual\n");
+        }
+        ret.push_str(&format!("matrix {}\n", self.matrix.name()));
+        ret.push_str(&format!("transfer {}\n", self.input_transfer.name()));
+        if self.srgb_framebuffer {
+            ret.push_str("srgb_framebuffer\n");
+        }
+        ret
+    }
+
+    pub fn to_c(self) -> c_bindings::GuiColorManagement {
+        c_bindings::GuiColorManagement {
+            match_export: self.match_export,
+            matrix: self.matrix.to_c(),
+            input_transfer: self.input_transfer.to_c(),
+            srgb_framebuffer: self.srgb_framebuffer,
+        }
+    }
+
+    /// Short description for the status bar
+    fn summary(&self) -> String {
+        if self.match_export {
+            "match export".to_string()
+        } else {
+            format!("{} / {}", self.matrix.label(), self.input_transfer.label())
+        }
+    }
+}
+
+/// Color management menu for the status bar. Grading is done against this, so it is saved as
+/// soon as it changes
+pub struct ColorMenu {
+    pub preferences: ColorPreferences,
+}
+
+impl ColorMenu {
+    pub fn new() -> ColorMenu {
+        let preferences = config::load_preferences("color.conf")
+            .map(|data| ColorPreferences::parse(&data))
+            .unwrap_or_default();
+
+        ColorMenu { preferences }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let before = self.preferences;
+        let preferences = &mut self.preferences;
+
+        ui.label("color:");
+        ui.menu_button(preferences.summary(), |ui| {
+            ui.checkbox(&mut preferences.match_export, "Match export")
+                .on_hover_text(
+                    "Show the video the way players show the exported file: with the matrix it \
+                     is tagged with, straight to the display as sRGB",
+                );
+
+            ui.add_enabled_ui(!preferences.match_export, |ui| {
+                ui.separator();
+                ui.label("Matrix");
+                for matrix in Matrix::ALL {
+                    ui.radio_value(&mut preferences.matrix, matrix, matrix.label());
+                }
+
+                ui.separator();
+                ui.label("Input transfer");
+                for transfer in Transfer::ALL {
+                    ui.radio_value(&mut preferences.input_transfer, transfer, transfer.label());
+                }
+            });
+
+            ui.separator();
+            ui.checkbox(&mut preferences.srgb_framebuffer, "sRGB framebuffer")
+                .on_hover_text(
+                    "Let GL encode to sRGB instead of the shader. Only makes a difference if the \
+                     window's framebuffer is sRGB capable",
+                );
+        });
+
+        if self.preferences != before {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        config::save_preferences("color.conf", &self.preferences.serialize());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn preferences_round_trip() {
+        let preferences = ColorPreferences {
+            match_export: false,
+            matrix: Matrix::Bt709,
+            input_transfer: Transfer::Bt1886,
+            srgb_framebuffer: true,
+        };
+        assert_eq!(
+            ColorPreferences::parse(&preferences.serialize()),
+            preferences
+        );
+        assert_eq!(
+            ColorPreferences::parse(&ColorPreferences::default().serialize()),
+            ColorPreferences::default()
+        );
+    }
+
+    #[test]
+    fn bad_lines_keep_defaults() {
+        let preferences = ColorPreferences::parse("# grading\nmatrix rec2020\ntransfer\nbogus\n");
+        assert_eq!(preferences, ColorPreferences::default());
+    }
+}
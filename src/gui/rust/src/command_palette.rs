@@ -0,0 +1,166 @@
+use eframe::egui;
+
+use std::path::PathBuf;
+
+use crate::scripting;
+
+/// Something the palette can do, carried out by whoever shows it
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Runs a script from ~/.config/video-editor/scripts, see ScriptConsole::run_saved
+    RunScript(PathBuf),
+}
+
+impl Command {
+    fn label(&self) -> String {
+        match self {
+            Command::RunScript(path) => format!(
+                "Run script: {}",
+                path.file_stem().unwrap_or_default().to_string_lossy()
+            ),
+        }
+    }
+}
+
+/// Case insensitive, every word of the query has to show up somewhere in the label
+fn matches(label: &str, query: &str) -> bool {
+    let label = label.to_lowercase();
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .all(|word| label.contains(word))
+}
+
+/// Keyboard driven list of commands, opened with ctrl+shift+p. Type to filter, arrows to pick,
+/// enter to run
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    /// Gathered when the palette opens
+    commands: Vec<Command>,
+    /// Index into the commands that match the query
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn new() -> CommandPalette {
+        CommandPalette {
+            open: false,
+            query: String::new(),
+            commands: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    fn filtered(&self) -> Vec<Command> {
+        self.commands
+            .iter()
+            .filter(|command| matches(&command.label(), &self.query))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the command picked this frame, if any
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Command> {
+        let open_shortcut = egui::Modifiers::COMMAND | egui::Modifiers::SHIFT;
+        if ctx.input_mut(|i| i.consume_key(open_shortcut, egui::Key::P)) {
+            self.open = !self.open;
+            if self.open {
+                self.query.clear();
+                self.selected = 0;
+                self.commands = scripting::saved_script_paths()
+                    .into_iter()
+                    .map(Command::RunScript)
+                    .collect();
+            }
+        }
+
+        if !self.open {
+            return None;
+        }
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        if escape {
+            self.open = false;
+            return None;
+        }
+
+        let mut ret = None;
+        egui::Window::new("Command palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 40.0))
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                let query = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Type a command")
+                        .desired_width(f32::INFINITY),
+                );
+                query.request_focus();
+                let query_changed = query.changed();
+
+                let filtered = self.filtered();
+                if query_changed {
+                    self.selected = 0;
+                }
+                if down && self.selected + 1 < filtered.len() {
+                    self.selected += 1;
+                }
+                if up {
+                    self.selected = self.selected.saturating_sub(1);
+                }
+
+                if filtered.is_empty() {
+                    if self.commands.is_empty() {
+                        ui.weak(
+                            "Nothing to run, save .rhai files in ~/.config/video-editor/scripts",
+                        );
+                    } else {
+                        ui.weak("No matching commands");
+                    }
+                }
+
+                for (i, command) in filtered.iter().enumerate() {
+                    let clicked = ui
+                        .selectable_label(i == self.selected, command.label())
+                        .clicked();
+                    if clicked || (enter && i == self.selected) {
+                        ret = Some(command.clone());
+                    }
+                }
+            });
+
+        if ret.is_some() {
+            self.open = false;
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn every_query_word_has_to_match() {
+        assert!(matches("Run script: markers", ""));
+        assert!(matches("Run script: markers", "MARK"));
+        assert!(matches("Run script: markers", "run mark"));
+        assert!(!matches("Run script: markers", "run clips"));
+    }
+
+    #[test]
+    fn scripts_are_named_after_their_file() {
+        let command = Command::RunScript(PathBuf::from("/scripts/every_10_min.rhai"));
+        assert_eq!(command.label(), "Run script: every_10_min");
+    }
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+/// Where `name` lives in ~/.config/video-editor, honoring XDG_CONFIG_HOME. None if neither it nor
+/// HOME is set
+pub fn config_path(name: &str) -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("video-editor").join(name))
+}
+
+/// Contents of a file saved with save_preferences, None if there is none yet
+pub fn load_preferences(name: &str) -> Option<String> {
+    std::fs::read_to_string(config_path(name)?).ok()
+}
+
+/// Preferences are a nicety, failing to save them is logged and otherwise ignored
+pub fn save_preferences(name: &str, data: &str) {
+    let Some(path) = config_path(name) else {
+        log::warn!("Nowhere to save {name}, neither XDG_CONFIG_HOME nor HOME is set");
+        return;
+    };
+
+    let res = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, data));
+    if let Err(e) = res {
+        log::error!("Failed to save preferences to {}: {e}", path.display());
+    }
+}
@@ -0,0 +1,127 @@
+use eframe::egui;
+
+use std::time::Duration;
+
+use crate::{actions::Action, ActionRequestor};
+
+/// Numbers gathered while drawing a frame. Most of them describe the previous frame, since the
+/// overlay is drawn before the current one is done
+#[derive(Default)]
+pub struct FrameStats {
+    /// Time spent in ProjectView::update_ui
+    pub update_time: Duration,
+    /// Time spent waiting on the backend for a state snapshot
+    pub snapshot_time: Duration,
+    /// CPU time eframe reports for the whole frame, including tessellation
+    pub cpu_usage: Option<f32>,
+    /// Actions sent to the backend that it has not picked up yet
+    pub queued_actions: usize,
+    pub decode_fps: f32,
+    /// GPU time of framerenderer_render, None until the first timer query completes
+    pub frame_gpu_time: Option<Duration>,
+    /// GPU time of audiorenderer_render
+    pub audio_gpu_time: Option<Duration>,
+    pub frame_cache_bytes: u64,
+    pub audio_cache_bytes: u64,
+}
+
+pub struct DebugOverlay {
+    pub open: bool,
+}
+
+impl DebugOverlay {
+    pub fn new() -> DebugOverlay {
+        DebugOverlay { open: false }
+    }
+
+    pub fn handle_input(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.key_pressed(egui::Key::F3)) {
+            self.open = !self.open;
+        }
+    }
+
+    pub fn show(&self, ctx: &egui::Context, stats: &FrameStats, action_tx: &mut ActionRequestor) {
+        if !self.open {
+            return;
+        }
+
+        egui::Window::new("Stats")
+            .anchor(egui::Align2::LEFT_TOP, [8.0, 8.0])
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let dt = ctx.input(|i| i.unstable_dt);
+                ui.label(format!(
+                    "frame interval: {:.2} ms ({:.0} fps)",
+                    dt * 1000.0,
+                    1.0 / dt
+                ));
+                ui.label(format!(
+                    "update: {:.2} ms",
+                    stats.update_time.as_secs_f32() * 1000.0
+                ));
+                if let Some(cpu_usage) = stats.cpu_usage {
+                    ui.label(format!(
+                        "cpu (incl. tessellation): {:.2} ms",
+                        cpu_usage * 1000.0
+                    ));
+                }
+                ui.label(format!(
+                    "snapshot: {:.3} ms",
+                    stats.snapshot_time.as_secs_f32() * 1000.0
+                ));
+                ui.label(format!("queued actions: {}", stats.queued_actions));
+                ui.label(format!("decode: {:.1} fps", stats.decode_fps));
+                ui.label(format!(
+                    "gpu frame: {}",
+                    format_gpu_time(stats.frame_gpu_time)
+                ));
+                ui.label(format!(
+                    "gpu waveform: {}",
+                    format_gpu_time(stats.audio_gpu_time)
+                ));
+
+                ui.separator();
+
+                match process_rss_bytes() {
+                    Some(rss) => ui.label(format!("rss: {}", format_bytes(rss))),
+                    None => ui.label("rss: n/a"),
+                };
+                ui.label(format!(
+                    "frame cache: {}",
+                    format_bytes(stats.frame_cache_bytes)
+                ));
+                ui.label(format!(
+                    "audio cache: {}",
+                    format_bytes(stats.audio_cache_bytes)
+                ));
+                if ui.button("Flush caches").clicked() {
+                    action_tx.send(Action::FlushCaches);
+                }
+
+                // Paint stats (shape, vertex and index counts) live in egui's inspection UI
+                ui.collapsing("egui", |ui| ctx.inspection_ui(ui));
+            });
+    }
+}
+
+fn format_gpu_time(time: Option<Duration>) -> String {
+    match time {
+        Some(time) => format!("{:.3} ms", time.as_secs_f32() * 1000.0),
+        None => "n/a".to_string(),
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MIB: f64 = 1024.0 * 1024.0;
+    format!("{:.1} MiB", bytes as f64 / MIB)
+}
+
+/// Resident set size of the whole process, backend included
+fn process_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    // e.g. "VmRSS:     123456 kB"
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
@@ -0,0 +1,182 @@
+use eframe::egui;
+
+use std::process::{Command, Stdio};
+
+use crate::{actions::Action, events::Event};
+
+/// Tools that offer a file for dropping into other applications. winit has no way of starting a
+/// drag with a file, so the drop itself is left to them
+const DRAG_SOURCES: [&str; 2] = ["dragon-drop", "dragon"];
+
+/// Shift+dragging a clip off the top or bottom of the window renders it on its own into the temp
+/// directory, to drop it straight into a chat or another application. A plain drag is a seek, so
+/// it never takes the clip along. Leaving sideways is left alone, that is a drag overshooting the
+/// end of the timeline
+pub struct DragOut {
+    /// Clip the current drag is taking out of the window, if it started as one
+    dragged_clip: Option<u64>,
+    /// Whether the current drag already asked for an export
+    sent: bool,
+    /// Finished quick exports waiting to be handed on
+    ready: Vec<String>,
+}
+
+impl DragOut {
+    pub fn new() -> DragOut {
+        DragOut {
+            dragged_clip: None,
+            sent: false,
+            ready: Vec::new(),
+        }
+    }
+
+    pub fn start(&mut self, clip_id: Option<u64>) {
+        self.dragged_clip = clip_id;
+        self.sent = false;
+    }
+
+    /// Whether the last drag started belongs to the drag out, up to the next one starting. Seeks
+    /// keep out of it so that taking a clip along does not move the playhead
+    pub fn active(&self) -> bool {
+        self.dragged_clip.is_some()
+    }
+
+    /// Asks for the export once per drag, as soon as the pointer leaves the window
+    pub fn drag_to(&mut self, pos: egui::Pos2, screen_rect: egui::Rect) -> Option<Action> {
+        let clip_id = self.dragged_clip?;
+        let off_top_or_bottom = (screen_rect.left()..=screen_rect.right()).contains(&pos.x)
+            && !(screen_rect.top()..=screen_rect.bottom()).contains(&pos.y);
+        if self.sent || !off_top_or_bottom {
+            return None;
+        }
+
+        self.sent = true;
+        Some(Action::QuickExport(clip_id))
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Event::ExportFinished { path, quick: true } = event {
+            self.ready.push(path.clone());
+        }
+    }
+
+    /// Offers finished exports through a drag source if one is installed, or puts their path on
+    /// the clipboard. Returns what happened to each, for the user
+    pub fn hand_off(&mut self, ctx: &egui::Context) -> Vec<String> {
+        let mut ret = Vec::new();
+        for path in self.ready.drain(..) {
+            let source = spawn_drag_source(&path);
+            if !matches!(source, DragSource::Started) {
+                ctx.output_mut(|o| o.copied_text = path.clone());
+            }
+            ret.push(hand_off_message(&path, &source));
+        }
+        ret
+    }
+}
+
+enum DragSource {
+    Started,
+    /// None of DRAG_SOURCES is installed
+    Missing,
+    /// One is installed but would not start, with the reason
+    Failed(String),
+}
+
+/// Without a drag source there is nothing to drag, so the user has to hear why and what to do
+/// about it rather than find it in the logs
+fn hand_off_message(path: &str, source: &DragSource) -> String {
+    match source {
+        DragSource::Started => format!("Drag {path} from the window that opened"),
+        DragSource::Missing => format!(
+            "Exported {path} and copied its path. Install dragon-drop to drag clips out instead"
+        ),
+        DragSource::Failed(reason) => {
+            format!("Exported {path} and copied its path. Could not start {reason}")
+        }
+    }
+}
+
+fn spawn_drag_source(path: &str) -> DragSource {
+    for program in DRAG_SOURCES {
+        let child = Command::new(program)
+            .arg("--and-exit")
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn();
+
+        match child {
+            Ok(mut child) => {
+                // Reaped in the background, it stays up until the file is dropped somewhere
+                std::thread::spawn(move || child.wait());
+                return DragSource::Started;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                log::warn!("Failed to start {program}: {e}");
+                return DragSource::Failed(format!("{program}: {e}"));
+            }
+        }
+    }
+    log::warn!(
+        "No drag source installed, tried {}",
+        DRAG_SOURCES.join(", ")
+    );
+    DragSource::Missing
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn screen_rect() -> egui::Rect {
+        egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0))
+    }
+
+    #[test]
+    fn leaving_through_top_or_bottom_exports_once() {
+        let mut drag_out = DragOut::new();
+        drag_out.start(Some(3));
+
+        assert!(drag_out
+            .drag_to(egui::pos2(400.0, 300.0), screen_rect())
+            .is_none());
+        assert!(matches!(
+            drag_out.drag_to(egui::pos2(400.0, 620.0), screen_rect()),
+            Some(Action::QuickExport(3))
+        ));
+        assert!(drag_out
+            .drag_to(egui::pos2(400.0, -20.0), screen_rect())
+            .is_none());
+
+        drag_out.start(Some(3));
+        assert!(drag_out
+            .drag_to(egui::pos2(400.0, -20.0), screen_rect())
+            .is_some());
+    }
+
+    #[test]
+    fn sideways_and_clipless_drags_are_seeks() {
+        let mut drag_out = DragOut::new();
+        drag_out.start(Some(3));
+        assert!(drag_out
+            .drag_to(egui::pos2(900.0, 620.0), screen_rect())
+            .is_none());
+
+        drag_out.start(None);
+        assert!(drag_out
+            .drag_to(egui::pos2(400.0, 620.0), screen_rect())
+            .is_none());
+    }
+
+    #[test]
+    fn missing_drag_source_says_what_to_install() {
+        let message = hand_off_message("/tmp/clip.mp4", &DragSource::Missing);
+        assert!(message.contains("/tmp/clip.mp4"));
+        assert!(message.contains("Install dragon-drop"));
+
+        let failed = DragSource::Failed("dragon: permission denied".to_string());
+        assert!(hand_off_message("/tmp/clip.mp4", &failed).contains("permission denied"));
+    }
+}
@@ -0,0 +1,73 @@
+use eframe::egui;
+
+use crate::c_bindings::*;
+
+/// Rust side representation of a GuiEvent. Unlike the C version events own their data, anything
+/// the backend points to is copied while converting
+pub enum Event {
+    ThumbnailReady {
+        request_id: u64,
+        image: egui::ColorImage,
+    },
+    ThumbnailFailed {
+        request_id: u64,
+    },
+    /// The transcript in the snapshot has been replaced
+    TranscriptionFinished,
+    TranscriptionFailed,
+    /// An export job wrote path
+    ExportFinished {
+        path: String,
+        /// Rendered for a drag out of the timeline, see drag_out
+        quick: bool,
+    },
+    /// ffmpeg failed while writing path, the backend logs why
+    ExportFailed {
+        path: String,
+    },
+}
+
+#[derive(Debug)]
+pub struct InvalidEventTag(pub GuiEventTag);
+
+impl Event {
+    /// event has to be valid for its tag, e.g. thumbnail pixels have to be readable
+    pub unsafe fn from_c(event: &GuiEvent) -> Result<Event, InvalidEventTag> {
+        let ret = match event.tag {
+            GuiEventTag_gui_event_thumbnail_ready => {
+                let thumbnail = &event.data.thumbnail;
+                let size = [thumbnail.width as usize, thumbnail.height as usize];
+                let rgba = std::slice::from_raw_parts(thumbnail.rgba, size[0] * size[1] * 4);
+                Event::ThumbnailReady {
+                    request_id: thumbnail.request_id,
+                    image: egui::ColorImage::from_rgba_unmultiplied(size, rgba),
+                }
+            }
+            GuiEventTag_gui_event_thumbnail_failed => Event::ThumbnailFailed {
+                request_id: event.data.request_id,
+            },
+            GuiEventTag_gui_event_transcription_finished => Event::TranscriptionFinished,
+            GuiEventTag_gui_event_transcription_failed => Event::TranscriptionFailed,
+            GuiEventTag_gui_event_export_finished => Event::ExportFinished {
+                path: export_path(&event.data.export_result),
+                quick: event.data.export_result.quick,
+            },
+            GuiEventTag_gui_event_export_failed => Event::ExportFailed {
+                path: export_path(&event.data.export_result),
+            },
+            tag => return Err(InvalidEventTag(tag)),
+        };
+        Ok(ret)
+    }
+}
+
+/// Empty if the backend did not say
+unsafe fn export_path(result: &GuiExportResult) -> String {
+    if result.path.is_null() {
+        return String::new();
+    }
+
+    std::ffi::CStr::from_ptr(result.path)
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,280 @@
+use eframe::egui;
+
+use std::{
+    ffi::CStr,
+    time::{Duration, Instant},
+};
+
+use crate::{actions::Action, c_bindings, events::Event, jobs, transcript, ActionRequestor};
+
+const DEFAULT_SEQUENCE_PATTERN: &str = "{project}_edit";
+const DEFAULT_CLIPS_PATTERN: &str = "{project}_{n}_{start}";
+
+/// How long the result of an export stays up
+const TOAST_DURATION: Duration = Duration::from_secs(8);
+
+/// Renders the edit with ffmpeg in the backend, as a whole or with every clip in a file of its
+/// own. Exports run in the background, with their progress in the status bar
+pub struct ExportDialog {
+    pub open: bool,
+    mode: c_bindings::GuiExportMode,
+    /// Kept per mode, the two rarely want the same names
+    sequence_pattern: String,
+    clips_pattern: String,
+    /// Oldest first
+    toasts: Vec<Toast>,
+}
+
+struct Toast {
+    text: String,
+    shown_at: Instant,
+}
+
+impl ExportDialog {
+    pub fn new() -> ExportDialog {
+        ExportDialog {
+            open: false,
+            mode: c_bindings::GuiExportMode_gui_export_mode_sequence,
+            sequence_pattern: DEFAULT_SEQUENCE_PATTERN.to_string(),
+            clips_pattern: DEFAULT_CLIPS_PATTERN.to_string(),
+            toasts: Vec::new(),
+        }
+    }
+
+    /// Finished quick exports are announced by whoever hands them on, see drag_out
+    pub fn handle_event(&mut self, event: &Event) {
+        let text = match event {
+            Event::ExportFinished { path, quick: false } => format!("Exported {path}"),
+            Event::ExportFailed { path } => format!("Failed to export {path}, see the logs"),
+            _ => return,
+        };
+        self.notify(text);
+    }
+
+    pub fn notify(&mut self, text: String) {
+        self.toasts.push(Toast {
+            text,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Status bar widget while exports are queued, running or paused. Pausing and cancelling
+    /// apply to all of them
+    pub fn show_progress(
+        &self,
+        ui: &mut egui::Ui,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let active: Vec<_> =
+            jobs::active_of_kind(state, c_bindings::GuiJobKind_gui_job_kind_export).collect();
+        let queued =
+            |job: &c_bindings::GuiJob| job.state == c_bindings::GuiJobState_gui_job_state_queued;
+        let Some(current) = active.iter().find(|job| !queued(job)).or(active.first()) else {
+            return;
+        };
+
+        ui.separator();
+        ui.label(progress_label(active.len()));
+        ui.add(
+            egui::ProgressBar::new(current.progress.max(0.0))
+                .show_percentage()
+                .desired_width(120.0),
+        );
+
+        let paused = current.state == c_bindings::GuiJobState_gui_job_state_paused;
+        let pause_label = if paused { "Resume" } else { "Pause" };
+        if ui
+            .add_enabled(!queued(current), egui::Button::new(pause_label))
+            .clicked()
+        {
+            action_tx.send(Action::PauseExports(!paused));
+        }
+
+        if ui
+            .button("Cancel")
+            .on_hover_text("Stop this export and every one queued behind it")
+            .clicked()
+        {
+            action_tx.send(Action::CancelExports);
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        self.show_toasts(ctx);
+
+        let clips = transcript::clips(state);
+        let project = project_name(state);
+
+        let mut open = self.open;
+        egui::Window::new("Export")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(
+                        &mut self.mode,
+                        c_bindings::GuiExportMode_gui_export_mode_sequence,
+                        "Whole edit",
+                    );
+                    ui.selectable_value(
+                        &mut self.mode,
+                        c_bindings::GuiExportMode_gui_export_mode_clips,
+                        "Batch export clips",
+                    );
+                });
+
+                let is_clips = self.mode == c_bindings::GuiExportMode_gui_export_mode_clips;
+                let pattern = if is_clips {
+                    &mut self.clips_pattern
+                } else {
+                    &mut self.sequence_pattern
+                };
+
+                ui.horizontal(|ui| {
+                    ui.label("File name");
+                    ui.text_edit_singleline(pattern);
+                    ui.label(".mkv");
+                });
+                ui.weak("{project}, {n} and {start} are filled in, files go next to the save");
+
+                if let Some(first) = clips.first() {
+                    ui.weak(format!(
+                        "e.g. {}.mkv",
+                        file_name(pattern, &project, 1, first.start)
+                    ));
+                }
+
+                let request = export_request(self.mode, pattern);
+                let label = if is_clips {
+                    format!("Export {} clips", clips.len())
+                } else {
+                    "Export".to_string()
+                };
+                if ui
+                    .add_enabled(
+                        !clips.is_empty() && request.is_some(),
+                        egui::Button::new(label),
+                    )
+                    .on_disabled_hover_text("Needs at least one clip and a file name")
+                    .clicked()
+                {
+                    if let Some(request) = request {
+                        action_tx.send(Action::Export(request));
+                    }
+                }
+            });
+        self.open = open;
+    }
+
+    fn show_toasts(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        self.toasts
+            .retain(|toast| now - toast.shown_at < TOAST_DURATION);
+        let Some(oldest) = self.toasts.first() else {
+            return;
+        };
+        ctx.request_repaint_after(TOAST_DURATION - (now - oldest.shown_at));
+
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("export_toast"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for (i, toast) in self.toasts.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(&toast.text);
+                            if ui.small_button("✖").clicked() {
+                                dismissed = Some(i);
+                            }
+                        });
+                    }
+                });
+            });
+
+        if let Some(i) = dismissed {
+            self.toasts.remove(i);
+        }
+    }
+}
+
+/// e.g. "exporting, 3 more queued"
+fn progress_label(num_active: usize) -> String {
+    match num_active {
+        0 | 1 => "exporting".to_string(),
+        n => format!("exporting, {} more queued", n - 1),
+    }
+}
+
+/// None if the pattern is empty or does not fit in the request with its terminator
+pub fn export_request(
+    mode: c_bindings::GuiExportMode,
+    pattern: &str,
+) -> Option<c_bindings::GuiExportRequest> {
+    let mut ret = c_bindings::GuiExportRequest {
+        mode,
+        pattern: [0; 256],
+    };
+    if pattern.is_empty() || pattern.len() >= ret.pattern.len() || pattern.contains('\0') {
+        return None;
+    }
+
+    for (dst, src) in ret.pattern.iter_mut().zip(pattern.bytes()) {
+        *dst = src as std::ffi::c_char;
+    }
+    Some(ret)
+}
+
+/// Same expansion as the backend does, see GuiExportRequest
+fn file_name(pattern: &str, project: &str, n: usize, start: f32) -> String {
+    pattern
+        .replace("{project}", project)
+        .replace("{n}", &n.to_string())
+        .replace("{start}", &format!("{start:.2}"))
+}
+
+/// Name of the save file without its extension
+fn project_name(state: &c_bindings::AppStateSnapshot) -> String {
+    if state.save_path.is_null() {
+        return "project".to_string();
+    }
+
+    let save_path = unsafe { CStr::from_ptr(state.save_path) }.to_string_lossy();
+    std::path::Path::new(save_path.as_ref())
+        .file_stem()
+        .map_or_else(
+            || "project".to_string(),
+            |stem| stem.to_string_lossy().into_owned(),
+        )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn patterns_are_filled_in() {
+        assert_eq!(
+            file_name(DEFAULT_CLIPS_PATTERN, "vod", 3, 12.5),
+            "vod_3_12.50"
+        );
+        assert_eq!(file_name("take", "vod", 1, 0.0), "take");
+
+        let mode = c_bindings::GuiExportMode_gui_export_mode_clips;
+        assert!(export_request(mode, "").is_none());
+        assert!(export_request(mode, &"a".repeat(256)).is_none());
+        let request = export_request(mode, "a_{n}").unwrap();
+        assert_eq!(request.pattern[5], 0);
+    }
+
+    #[test]
+    fn queued_exports_are_counted() {
+        assert_eq!(progress_label(1), "exporting");
+        assert_eq!(progress_label(4), "exporting, 3 more queued");
+    }
+}
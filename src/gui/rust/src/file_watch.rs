@@ -0,0 +1,316 @@
+use eframe::egui;
+use notify::{event::ModifyKind, EventKind, Watcher};
+
+use std::{
+    ffi::{c_char, CStr},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+use crate::{actions::Action, c_bindings, thumbnails::ThumbnailCache, ActionRequestor};
+
+/// Changes to the save file this long after we asked for a save are our own
+const OWN_SAVE_WINDOW: Duration = Duration::from_secs(3);
+
+/// The files the project was opened with, made absolute so that they match the paths notify
+/// reports
+#[derive(Debug)]
+struct WatchedFiles {
+    source: PathBuf,
+    project: PathBuf,
+}
+
+impl WatchedFiles {
+    fn target(&self, path: &Path) -> Option<c_bindings::GuiReloadTarget> {
+        if path == self.source {
+            Some(c_bindings::GuiReloadTarget_gui_reload_source)
+        } else if path == self.project {
+            Some(c_bindings::GuiReloadTarget_gui_reload_project)
+        } else {
+            None
+        }
+    }
+
+    /// Files are watched through their directories, since tools that write a new file and rename
+    /// it over the old one leave a watch on the file itself pointing at the old one
+    fn dirs(&self) -> Vec<&Path> {
+        let mut ret: Vec<&Path> = [&self.source, &self.project]
+            .into_iter()
+            .filter_map(|path| path.parent())
+            .collect();
+        ret.dedup();
+        ret
+    }
+}
+
+/// Metadata changes happen on every read on some filesystems, the rest means the content may be
+/// different
+fn is_content_change(kind: &EventKind) -> bool {
+    match kind {
+        EventKind::Create(_) => true,
+        EventKind::Modify(ModifyKind::Metadata(_)) => false,
+        EventKind::Modify(_) => true,
+        _ => false,
+    }
+}
+
+/// Null or empty for backends without files
+fn path_from_c(path: *const c_char) -> Option<PathBuf> {
+    if path.is_null() {
+        return None;
+    }
+
+    let path = unsafe { CStr::from_ptr(path) }.to_str().ok()?;
+    if path.is_empty() {
+        return None;
+    }
+
+    // Not existing yet is fine for the save file, it shows up on the first save
+    let path = Path::new(path);
+    std::fs::canonicalize(path)
+        .ok()
+        .or_else(|| Some(std::env::current_dir().ok()?.join(path)))
+}
+
+/// Notices when the video or save file changes on disk, e.g. when OBS remuxes a recording, and
+/// offers to reload it
+pub struct FileWatcher {
+    files: Option<WatchedFiles>,
+    /// Only kept alive, events arrive on changed_paths
+    _watcher: Option<notify::RecommendedWatcher>,
+    changed_paths: Option<Receiver<PathBuf>>,
+    source_changed: bool,
+    project_changed: bool,
+    last_save: Option<Instant>,
+}
+
+impl FileWatcher {
+    pub fn new() -> FileWatcher {
+        FileWatcher {
+            files: None,
+            _watcher: None,
+            changed_paths: None,
+            source_changed: false,
+            project_changed: false,
+            last_save: None,
+        }
+    }
+
+    /// Has to be called whenever we save, so that our own writes are not mistaken for someone
+    /// else's
+    pub fn saving(&mut self) {
+        self.last_save = Some(Instant::now());
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+        thumbnails: &mut ThumbnailCache,
+    ) {
+        if self.files.is_none() {
+            self.start(ctx, state);
+        }
+
+        self.handle_changes();
+        if !self.source_changed && !self.project_changed {
+            return;
+        }
+
+        let Some(files) = &self.files else {
+            return;
+        };
+
+        egui::Area::new(egui::Id::new("file_changed_toast"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    if self.source_changed {
+                        let reload = show_change(ui, &files.source, "Reload video");
+                        if let Some(reload) = reload {
+                            if reload {
+                                action_tx.send(Action::Reload(
+                                    c_bindings::GuiReloadTarget_gui_reload_source,
+                                ));
+                                thumbnails.clear();
+                            }
+                            self.source_changed = false;
+                        }
+                    }
+
+                    if self.project_changed {
+                        let reload = show_change(ui, &files.project, "Reload project");
+                        if let Some(reload) = reload {
+                            if reload {
+                                action_tx.send(Action::Reload(
+                                    c_bindings::GuiReloadTarget_gui_reload_project,
+                                ));
+                            }
+                            self.project_changed = false;
+                        }
+                    }
+                });
+            });
+    }
+
+    fn start(&mut self, ctx: &egui::Context, state: &c_bindings::AppStateSnapshot) {
+        let (Some(source), Some(project)) =
+            (path_from_c(state.input_path), path_from_c(state.save_path))
+        else {
+            return;
+        };
+
+        let files = WatchedFiles { source, project };
+        let (tx, rx) = mpsc::channel();
+        let ctx = ctx.clone();
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let event = match res {
+                Ok(event) => event,
+                Err(e) => {
+                    log::warn!("File watcher error: {e}");
+                    return;
+                }
+            };
+
+            if !is_content_change(&event.kind) {
+                return;
+            }
+
+            for path in event.paths {
+                // Receiver gone means the GUI is shutting down
+                let _ = tx.send(path);
+            }
+            ctx.request_repaint();
+        });
+
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                log::warn!("Not watching for file changes: {e}");
+                self.files = Some(files);
+                return;
+            }
+        };
+
+        for dir in files.dirs() {
+            if let Err(e) = watcher.watch(dir, notify::RecursiveMode::NonRecursive) {
+                log::warn!("Failed to watch {}: {e}", dir.display());
+            }
+        }
+
+        self.files = Some(files);
+        self._watcher = Some(watcher);
+        self.changed_paths = Some(rx);
+    }
+
+    fn handle_changes(&mut self) {
+        let (Some(files), Some(changed_paths)) = (&self.files, &self.changed_paths) else {
+            return;
+        };
+
+        for path in changed_paths.try_iter() {
+            match files.target(&path) {
+                Some(c_bindings::GuiReloadTarget_gui_reload_source) => self.source_changed = true,
+                Some(c_bindings::GuiReloadTarget_gui_reload_project) => {
+                    let own_save = self
+                        .last_save
+                        .is_some_and(|last_save| last_save.elapsed() < OWN_SAVE_WINDOW);
+                    if !own_save {
+                        self.project_changed = true;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Some(true) to reload, Some(false) to dismiss
+fn show_change(ui: &mut egui::Ui, path: &Path, reload_label: &str) -> Option<bool> {
+    let name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+
+    let mut ret = None;
+    ui.horizontal(|ui| {
+        ui.label(format!("{name} changed on disk"));
+        if ui.button(reload_label).clicked() {
+            ret = Some(true);
+        }
+        if ui.button("Dismiss").clicked() {
+            ret = Some(false);
+        }
+    });
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use notify::event::{AccessKind, CreateKind, DataChange, MetadataKind, RenameMode};
+
+    fn files() -> WatchedFiles {
+        WatchedFiles {
+            source: PathBuf::from("/videos/stream.mkv"),
+            project: PathBuf::from("/videos/stream.json"),
+        }
+    }
+
+    #[test]
+    fn paths_map_to_their_reload_target() {
+        let files = files();
+        assert_eq!(
+            files.target(Path::new("/videos/stream.mkv")),
+            Some(c_bindings::GuiReloadTarget_gui_reload_source)
+        );
+        assert_eq!(
+            files.target(Path::new("/videos/stream.json")),
+            Some(c_bindings::GuiReloadTarget_gui_reload_project)
+        );
+        assert_eq!(files.target(Path::new("/videos/other.mkv")), None);
+    }
+
+    #[test]
+    fn shared_directories_are_watched_once() {
+        assert_eq!(files().dirs(), vec![Path::new("/videos")]);
+    }
+
+    #[test]
+    fn only_content_changes_count() {
+        assert!(is_content_change(&EventKind::Create(CreateKind::File)));
+        assert!(is_content_change(&EventKind::Modify(ModifyKind::Data(
+            DataChange::Any
+        ))));
+        assert!(is_content_change(&EventKind::Modify(ModifyKind::Name(
+            RenameMode::To
+        ))));
+        assert!(!is_content_change(&EventKind::Modify(
+            ModifyKind::Metadata(MetadataKind::AccessTime)
+        )));
+        assert!(!is_content_change(&EventKind::Access(AccessKind::Any)));
+    }
+
+    #[test]
+    fn own_saves_are_ignored() {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = FileWatcher::new();
+        watcher.files = Some(files());
+        watcher.changed_paths = Some(rx);
+
+        watcher.saving();
+        tx.send(PathBuf::from("/videos/stream.json")).unwrap();
+        watcher.handle_changes();
+        assert!(!watcher.project_changed);
+
+        watcher.last_save = None;
+        tx.send(PathBuf::from("/videos/stream.json")).unwrap();
+        tx.send(PathBuf::from("/videos/stream.mkv")).unwrap();
+        watcher.handle_changes();
+        assert!(watcher.project_changed);
+        assert!(watcher.source_changed);
+    }
+}
@@ -0,0 +1,220 @@
+use eframe::egui;
+
+use std::ops::Range;
+
+use crate::{actions::Action, c_bindings, script_edit, transcript, ActionRequestor};
+
+/// Bytes of transcript shown on each side of a match in the review list
+const CONTEXT_LEN: usize = 24;
+
+struct Match {
+    time: Range<f32>,
+    context: String,
+    selected: bool,
+}
+
+/// Finds every occurrence of a word or phrase in the transcript, e.g. a name that has to go or
+/// "um", and cuts the selected ones out of the edit in one go
+pub struct FindCutDialog {
+    pub open: bool,
+    query: String,
+    /// None when the search has to be redone, e.g. because the transcript changed
+    matches: Option<Vec<Match>>,
+}
+
+impl FindCutDialog {
+    pub fn new() -> FindCutDialog {
+        FindCutDialog {
+            open: false,
+            query: String::new(),
+            matches: None,
+        }
+    }
+
+    pub fn transcript_changed(&mut self) {
+        self.matches = None;
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        words: &[c_bindings::GuiWord],
+        action_tx: &mut ActionRequestor,
+    ) {
+        let mut open = self.open;
+        egui::Window::new("Find & cut")
+            .open(&mut open)
+            .default_width(350.0)
+            .show(ctx, |ui| {
+                let response =
+                    ui.add(egui::TextEdit::singleline(&mut self.query).hint_text("word or phrase"));
+                if response.changed() {
+                    self.matches = None;
+                }
+
+                let matches = self.matches.get_or_insert_with(|| {
+                    let s = unsafe { transcript_text(state) };
+                    find_matches(s, words, &self.query)
+                });
+
+                if self.query.trim().is_empty() {
+                    return;
+                }
+
+                let num_selected = matches.iter().filter(|m| m.selected).count();
+                ui.label(format!(
+                    "{} matches, {num_selected} selected",
+                    matches.len()
+                ));
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for m in matches.iter_mut() {
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut m.selected, "");
+                                if ui.link(transcript::format_timecode(m.time.start)).clicked() {
+                                    action_tx.send(Action::Seek(m.time.start));
+                                }
+                                ui.label(&m.context);
+                            });
+                        }
+                    });
+
+                let cut = ui.add_enabled(
+                    num_selected > 0,
+                    egui::Button::new(format!("Cut {num_selected} selected")),
+                );
+                if cut.clicked() {
+                    let cuts: Vec<_> = matches
+                        .iter()
+                        .filter(|m| m.selected)
+                        .map(|m| m.time.clone())
+                        .collect();
+                    let clips = transcript::clips(state);
+                    for action in script_edit::cut_actions(clips, state.total_runtime, &cuts) {
+                        action_tx.send(action);
+                    }
+                    log::info!("Cut {} matches of \"{}\"", cuts.len(), self.query.trim());
+                    // Cut matches are struck through now, search again for anything left
+                    self.matches = None;
+                }
+            });
+        self.open = open;
+    }
+}
+
+unsafe fn transcript_text(state: &c_bindings::AppStateSnapshot) -> &str {
+    if state.text_len == 0 {
+        // text may be null
+        return "";
+    }
+
+    let s = std::slice::from_raw_parts(state.text as *const u8, state.text_len as usize);
+    std::str::from_utf8_unchecked(s)
+}
+
+/// Case insensitive, whole word matches of query in s that line up with at least one word
+fn find_matches(s: &str, words: &[c_bindings::GuiWord], query: &str) -> Vec<Match> {
+    let query = query.trim().to_ascii_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    // ASCII lowercasing keeps byte offsets the same as in s
+    let haystack = s.to_ascii_lowercase();
+    let is_word_char = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+
+    let mut ret = Vec::new();
+    for (start, _) in haystack.match_indices(&query) {
+        let end = start + query.len();
+        if is_word_char(s[..start].chars().next_back()) || is_word_char(s[end..].chars().next()) {
+            continue;
+        }
+
+        let Some(time) = script_edit::selected_time_range(words, start..end) else {
+            continue;
+        };
+
+        ret.push(Match {
+            time,
+            context: context(s, start..end),
+            selected: true,
+        });
+    }
+    ret
+}
+
+fn context(s: &str, range: Range<usize>) -> String {
+    let mut start = range.start.saturating_sub(CONTEXT_LEN);
+    while !s.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    let mut end = (range.end + CONTEXT_LEN).min(s.len());
+    while !s.is_char_boundary(end) {
+        end += 1;
+    }
+
+    format!(
+        "…{}[{}]{}…",
+        s[start..range.start].trim_start(),
+        &s[range.clone()],
+        s[range.end..end].trim_end()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn word(char_start: u64, char_end: u64, start: f32) -> c_bindings::GuiWord {
+        c_bindings::GuiWord {
+            char_start,
+            char_end,
+            start,
+            end: start + 0.5,
+            confidence: 1.0,
+        }
+    }
+
+    const TEXT: &str = "Um so um, drum um";
+
+    fn words() -> Vec<c_bindings::GuiWord> {
+        vec![
+            word(0, 2, 0.0),
+            word(2, 5, 1.0),
+            word(5, 8, 2.0),
+            word(8, 9, 2.5),
+            word(9, 14, 3.0),
+            word(14, 17, 4.0),
+        ]
+    }
+
+    #[test]
+    fn matches_are_whole_words_ignoring_case() {
+        let matches = find_matches(TEXT, &words(), "um");
+        let times: Vec<_> = matches.iter().map(|m| m.time.start).collect();
+        assert_eq!(times, [0.0, 2.0, 4.0]);
+    }
+
+    #[test]
+    fn phrases_span_words() {
+        let matches = find_matches(TEXT, &words(), " So um ");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].context, "…Um [so um], drum um…");
+        assert_eq!(matches[0].time, 1.0..2.5);
+    }
+
+    #[test]
+    fn matches_without_words_are_skipped() {
+        assert!(find_matches(TEXT, &[], "um").is_empty());
+        assert!(find_matches(TEXT, &words(), "  ").is_empty());
+    }
+
+    #[test]
+    fn context_marks_the_match() {
+        assert_eq!(context("a b c", 2..3), "…a [b] c…");
+    }
+}
@@ -0,0 +1,44 @@
+use eframe::egui;
+
+use crate::{actions::Action, c_bindings, ActionRequestor};
+
+/// Button for the controls row that holds the picture still at the playhead, the duration is in
+/// its context menu
+pub struct FreezeFrame {
+    pub duration: f32,
+}
+
+impl FreezeFrame {
+    pub fn new() -> FreezeFrame {
+        FreezeFrame { duration: 2.0 }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let hover_text = format!(
+            "Hold the picture for {:.1}s while the audio plays on. Right click to change the \
+             duration",
+            self.duration
+        );
+        let response = ui.button("Insert freeze frame").on_hover_text(hover_text);
+
+        if response.clicked() {
+            action_tx.send(Action::InsertFreezeFrame(c_bindings::GuiFreezeFrame {
+                position: state.current_position,
+                duration: self.duration,
+            }));
+        }
+
+        response.context_menu(|ui| {
+            ui.add(
+                egui::Slider::new(&mut self.duration, 0.5..=30.0)
+                    .text("Duration (s)")
+                    .logarithmic(true),
+            );
+        });
+    }
+}
@@ -0,0 +1,83 @@
+use std::ops::Range;
+
+use crate::{actions::Action, c_bindings};
+
+/// Source time between clips that no clip covers, in source order. Overlapping and touching clips
+/// count as one
+pub fn gaps(clips: &[c_bindings::Clip]) -> Vec<Range<f32>> {
+    let mut sorted = clips.to_vec();
+    sorted.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let mut ret = Vec::new();
+    let mut covered_until: Option<f32> = None;
+    for clip in sorted {
+        match covered_until {
+            Some(end) if clip.start > end => {
+                ret.push(end..clip.start);
+                covered_until = Some(clip.end);
+            }
+            Some(end) => covered_until = Some(end.max(clip.end)),
+            None => covered_until = Some(clip.end),
+        }
+    }
+
+    ret
+}
+
+/// Extends the clip in front of the gap closest to position up to the clip after it, so that
+/// nothing between them is cut anymore. None if there are no gaps
+pub fn close_gap_action(clips: &[c_bindings::Clip], position: f32) -> Option<Action> {
+    let distance = |gap: &Range<f32>| {
+        if gap.contains(&position) {
+            0.0
+        } else {
+            (gap.start - position).abs().min((gap.end - position).abs())
+        }
+    };
+
+    let gap = gaps(clips)
+        .into_iter()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))?;
+
+    // Whichever clip the covered span before the gap ended with
+    let clip = clips.iter().find(|clip| clip.end == gap.start)?;
+    Some(Action::ClipEdit(c_bindings::Clip {
+        end: gap.end,
+        ..*clip
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::mock::clip;
+
+    #[test]
+    fn overlapping_clips_have_no_gap_between_them() {
+        let clips = [
+            clip(1, 5.0, 8.0),
+            clip(2, 0.0, 2.0),
+            clip(3, 1.5, 3.0),
+            clip(4, 8.0, 9.0),
+        ];
+        assert_eq!(gaps(&clips), vec![3.0..5.0]);
+        assert!(gaps(&[]).is_empty());
+    }
+
+    #[test]
+    fn nearest_gap_is_closed() {
+        let clips = [clip(1, 0.0, 2.0), clip(2, 3.0, 4.0), clip(3, 10.0, 12.0)];
+
+        let Some(Action::ClipEdit(edit)) = close_gap_action(&clips, 3.5) else {
+            panic!("expected a clip edit");
+        };
+        assert_eq!((edit.id, edit.start, edit.end), (1, 0.0, 3.0));
+
+        let Some(Action::ClipEdit(edit)) = close_gap_action(&clips, 9.0) else {
+            panic!("expected a clip edit");
+        };
+        assert_eq!((edit.id, edit.start, edit.end), (2, 3.0, 10.0));
+
+        assert!(close_gap_action(&clips[..1], 1.0).is_none());
+    }
+}
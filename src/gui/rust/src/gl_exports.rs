@@ -1,21 +1,23 @@
-use crate::c_bindings::*;
+use crate::{c_bindings::*, panic_guard::catch_panic};
 use eframe::glow::{self, HasContext};
 use std::ffi::c_void;
 
 #[no_mangle]
 unsafe extern "C" fn guigl_create_shader(context: *const glow::Context, v: GLenum) -> GLuint {
-    match (*context).create_shader(v) {
+    catch_panic(GLuint::MAX, || match (*context).create_shader(v) {
         Ok(v) => v.0.into(),
         Err(e) => {
-            eprintln!("Failed to create shader: {}", e);
+            log::error!("Failed to create shader: {}", e);
             GLuint::MAX
         }
-    }
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_delete_shader(context: *const glow::Context, shader: GLuint) {
-    (*context).delete_shader(glow::NativeShader(shader.try_into().unwrap()));
+    catch_panic((), || {
+        (*context).delete_shader(glow::NativeShader(shader.try_into().unwrap()));
+    })
 }
 
 #[no_mangle]
@@ -24,40 +26,46 @@ unsafe extern "C" fn guigl_shader_source(
     shader: GLuint,
     s: *const *const GLchar,
 ) {
-    let c_str = std::ffi::CStr::from_ptr(*s);
-    (*context).shader_source(
-        glow::NativeShader(shader.try_into().unwrap()),
-        c_str.to_str().unwrap(),
-    );
+    catch_panic((), || {
+        let c_str = std::ffi::CStr::from_ptr(*s);
+        (*context).shader_source(
+            glow::NativeShader(shader.try_into().unwrap()),
+            c_str.to_str().unwrap(),
+        );
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_compile_shader(context: *const glow::Context, shader: GLuint) {
-    let shader = glow::NativeShader(shader.try_into().unwrap());
-    (*context).compile_shader(shader);
-
-    if !(*context).get_shader_compile_status(shader) {
-        println!(
-            "shader compilation failed: {}",
-            (*context).get_shader_info_log(shader)
-        );
-    }
+    catch_panic((), || {
+        let shader = glow::NativeShader(shader.try_into().unwrap());
+        (*context).compile_shader(shader);
+
+        if !(*context).get_shader_compile_status(shader) {
+            log::error!(
+                "shader compilation failed: {}",
+                (*context).get_shader_info_log(shader)
+            );
+        }
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_create_program(context: *const glow::Context) -> GLuint {
-    match (*context).create_program() {
+    catch_panic(GLuint::MAX, || match (*context).create_program() {
         Ok(v) => v.0.into(),
         Err(e) => {
-            eprintln!("Failed to create program: {}", e);
+            log::error!("Failed to create program: {}", e);
             GLuint::MAX
         }
-    }
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_delete_program(context: *const glow::Context, program: GLuint) {
-    (*context).delete_program(glow::NativeProgram(program.try_into().unwrap()));
+    catch_panic((), || {
+        (*context).delete_program(glow::NativeProgram(program.try_into().unwrap()));
+    })
 }
 
 #[no_mangle]
@@ -66,26 +74,30 @@ unsafe extern "C" fn guigl_attach_shader(
     program: GLuint,
     shader: GLuint,
 ) {
-    (*context).attach_shader(
-        glow::NativeProgram(program.try_into().unwrap()),
-        glow::NativeShader(shader.try_into().unwrap()),
-    );
+    catch_panic((), || {
+        (*context).attach_shader(
+            glow::NativeProgram(program.try_into().unwrap()),
+            glow::NativeShader(shader.try_into().unwrap()),
+        );
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_link_program(context: *const glow::Context, program: GLuint) {
-    (*context).link_program(glow::NativeProgram(program.try_into().unwrap()));
+    catch_panic((), || {
+        (*context).link_program(glow::NativeProgram(program.try_into().unwrap()));
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_gen_texture(context: *const glow::Context) -> GLuint {
-    match (*context).create_texture() {
+    catch_panic(GLuint::MAX, || match (*context).create_texture() {
         Ok(v) => v.0.into(),
         Err(e) => {
-            eprintln!("Failed to create texture: {}", e);
+            log::error!("Failed to create texture: {}", e);
             GLuint::MAX
         }
-    }
+    })
 }
 
 #[no_mangle]
@@ -94,11 +106,13 @@ unsafe extern "C" fn guigl_bind_texture(
     target: GLenum,
     texture: GLuint,
 ) {
-    let texture = match texture {
-        0 => None,
-        v => Some(glow::NativeTexture(v.try_into().unwrap())),
-    };
-    (*context).bind_texture(target, texture);
+    catch_panic((), || {
+        let texture = match texture {
+            0 => None,
+            v => Some(glow::NativeTexture(v.try_into().unwrap())),
+        };
+        (*context).bind_texture(target, texture);
+    })
 }
 
 #[no_mangle]
@@ -108,17 +122,23 @@ unsafe extern "C" fn guigl_tex_parameter_i(
     pname: GLenum,
     param: GLint,
 ) {
-    (*context).tex_parameter_i32(target, pname, param);
+    catch_panic((), || {
+        (*context).tex_parameter_i32(target, pname, param);
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_active_texture(context: *const glow::Context, texture: GLuint) {
-    (*context).active_texture(texture);
+    catch_panic((), || {
+        (*context).active_texture(texture);
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_delete_texture(context: *const glow::Context, texture: GLuint) {
-    (*context).delete_texture(glow::NativeTexture(texture.try_into().unwrap()));
+    catch_panic((), || {
+        (*context).delete_texture(glow::NativeTexture(texture.try_into().unwrap()));
+    })
 }
 
 #[no_mangle]
@@ -128,19 +148,25 @@ unsafe extern "C" fn guigl_draw_arrays(
     first: GLint,
     count: GLsizei,
 ) {
-    (*context).draw_arrays(mode, first, count);
+    catch_panic((), || {
+        (*context).draw_arrays(mode, first, count);
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_uniform_1i(context: *const glow::Context, loc: GLint, val: GLint) {
-    let loc = glow::NativeUniformLocation(loc.try_into().unwrap());
-    (*context).uniform_1_i32(Some(&loc), val);
+    catch_panic((), || {
+        let loc = glow::NativeUniformLocation(loc.try_into().unwrap());
+        (*context).uniform_1_i32(Some(&loc), val);
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_uniform_1f(context: *const glow::Context, loc: GLint, val: GLfloat) {
-    let loc = glow::NativeUniformLocation(loc.try_into().unwrap());
-    (*context).uniform_1_f32(Some(&loc), val);
+    catch_panic((), || {
+        let loc = glow::NativeUniformLocation(loc.try_into().unwrap());
+        (*context).uniform_1_f32(Some(&loc), val);
+    })
 }
 
 #[no_mangle]
@@ -149,18 +175,20 @@ unsafe extern "C" fn guigl_get_uniform_location(
     program: GLuint,
     name: *const GLchar,
 ) -> GLint {
-    let c_name = std::ffi::CStr::from_ptr(name);
-    let ret = (*context).get_uniform_location(
-        glow::NativeProgram(program.try_into().unwrap()),
-        c_name.to_str().unwrap(),
-    );
-    match ret {
-        Some(v) => v.0 as GLint,
-        None => {
-            eprintln!("Failed to get uniform location");
-            -1
+    catch_panic(-1, || {
+        let c_name = std::ffi::CStr::from_ptr(name);
+        let ret = (*context).get_uniform_location(
+            glow::NativeProgram(program.try_into().unwrap()),
+            c_name.to_str().unwrap(),
+        );
+        match ret {
+            Some(v) => v.0 as GLint,
+            None => {
+                log::error!("Failed to get uniform location");
+                -1
+            }
         }
-    }
+    })
 }
 
 #[no_mangle]
@@ -176,34 +204,38 @@ unsafe extern "C" fn guigl_tex_image_2d(
     ty: GLenum,
     pixels: *const c_void,
 ) {
-    let pixels: *const u8 = pixels as *const u8;
-    let pixel_size = match ty {
-        glow::UNSIGNED_BYTE => 1,
-        _ => {
-            unimplemented!();
-        }
-    };
-
-    let pixels = std::slice::from_raw_parts(
-        pixels,
-        width as usize * height as usize * pixel_size as usize,
-    );
-    (*context).tex_image_2d(
-        target,
-        level,
-        internal_format,
-        width,
-        height,
-        border,
-        format,
-        ty,
-        Some(pixels),
-    );
+    catch_panic((), || {
+        let pixels: *const u8 = pixels as *const u8;
+        let pixel_size = match ty {
+            glow::UNSIGNED_BYTE => 1,
+            _ => {
+                unimplemented!();
+            }
+        };
+
+        let pixels = std::slice::from_raw_parts(
+            pixels,
+            width as usize * height as usize * pixel_size as usize,
+        );
+        (*context).tex_image_2d(
+            target,
+            level,
+            internal_format,
+            width,
+            height,
+            border,
+            format,
+            ty,
+            Some(pixels),
+        );
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_use_program(context: *const glow::Context, program: GLuint) {
-    (*context).use_program(Some(glow::NativeProgram(program.try_into().unwrap())));
+    catch_panic((), || {
+        (*context).use_program(Some(glow::NativeProgram(program.try_into().unwrap())));
+    })
 }
 
 #[no_mangle]
@@ -214,27 +246,49 @@ unsafe extern "C" fn guigl_clear_color(
     b: GLfloat,
     a: GLfloat,
 ) {
-    (*context).clear_color(r, g, b, a);
+    catch_panic((), || {
+        (*context).clear_color(r, g, b, a);
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_line_width(context: *const glow::Context, width: GLfloat) {
-    (*context).line_width(width);
+    catch_panic((), || {
+        (*context).line_width(width);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn guigl_enable(context: *const glow::Context, cap: GLenum) {
+    catch_panic((), || {
+        (*context).enable(cap);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn guigl_disable(context: *const glow::Context, cap: GLenum) {
+    catch_panic((), || {
+        (*context).disable(cap);
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_clear(context: *const glow::Context, mask: GLbitfield) {
-    (*context).clear(mask);
+    catch_panic((), || {
+        (*context).clear(mask);
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_create_buffer(context: *const glow::Context) -> GLuint {
-    (*context).create_buffer().unwrap().0.into()
+    catch_panic(GLuint::MAX, || (*context).create_buffer().unwrap().0.into())
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_delete_buffer(context: *const glow::Context, buf_id: GLuint) {
-    (*context).delete_buffer(glow::NativeBuffer(buf_id.try_into().unwrap()));
+    catch_panic((), || {
+        (*context).delete_buffer(glow::NativeBuffer(buf_id.try_into().unwrap()));
+    })
 }
 
 #[no_mangle]
@@ -243,7 +297,9 @@ unsafe extern "C" fn guigl_bind_buffer(
     target: GLenum,
     buf_id: GLuint,
 ) {
-    (*context).bind_buffer(target, Some(glow::NativeBuffer(buf_id.try_into().unwrap())));
+    catch_panic((), || {
+        (*context).bind_buffer(target, Some(glow::NativeBuffer(buf_id.try_into().unwrap())));
+    })
 }
 
 #[no_mangle]
@@ -254,23 +310,31 @@ unsafe extern "C" fn guigl_buffer_data(
     data: *const c_void,
     usage: GLenum,
 ) {
-    let data = std::slice::from_raw_parts(data as *const u8, size as usize);
-    (*context).buffer_data_u8_slice(target, data, usage)
+    catch_panic((), || {
+        let data = std::slice::from_raw_parts(data as *const u8, size as usize);
+        (*context).buffer_data_u8_slice(target, data, usage)
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_create_vertex_array(context: *const glow::Context) -> GLuint {
-    (*context).create_vertex_array().unwrap().0.into()
+    catch_panic(GLuint::MAX, || {
+        (*context).create_vertex_array().unwrap().0.into()
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_delete_vertex_array(context: *const glow::Context, array_id: GLuint) {
-    (*context).delete_vertex_array(glow::NativeVertexArray(array_id.try_into().unwrap()));
+    catch_panic((), || {
+        (*context).delete_vertex_array(glow::NativeVertexArray(array_id.try_into().unwrap()));
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn guigl_bind_vertex_array(context: *const glow::Context, array_id: GLuint) {
-    (*context).bind_vertex_array(Some(glow::NativeVertexArray(array_id.try_into().unwrap())));
+    catch_panic((), || {
+        (*context).bind_vertex_array(Some(glow::NativeVertexArray(array_id.try_into().unwrap())));
+    })
 }
 
 #[no_mangle]
@@ -283,7 +347,9 @@ unsafe extern "C" fn guigl_vertex_attrib_pointer(
     stride: GLsizei,
     p: *const c_void,
 ) {
-    (*context).vertex_attrib_pointer_f32(index, size, typ, norm > 0, stride, p as i32);
+    catch_panic((), || {
+        (*context).vertex_attrib_pointer_f32(index, size, typ, norm > 0, stride, p as i32);
+    })
 }
 
 #[no_mangle]
@@ -291,5 +357,61 @@ unsafe extern "C" fn guigl_enable_vertex_attrib_array(
     context: *const glow::Context,
     index: GLuint,
 ) {
-    (*context).enable_vertex_attrib_array(index);
+    catch_panic((), || {
+        (*context).enable_vertex_attrib_array(index);
+    })
+}
+
+#[no_mangle]
+pub(crate) unsafe extern "C" fn guigl_create_query(context: *const glow::Context) -> GLuint {
+    catch_panic(GLuint::MAX, || match (*context).create_query() {
+        Ok(v) => v.0.into(),
+        Err(e) => {
+            log::error!("Failed to create query: {}", e);
+            GLuint::MAX
+        }
+    })
+}
+
+#[no_mangle]
+pub(crate) unsafe extern "C" fn guigl_delete_query(context: *const glow::Context, query: GLuint) {
+    catch_panic((), || {
+        (*context).delete_query(glow::NativeQuery(query.try_into().unwrap()));
+    })
+}
+
+#[no_mangle]
+pub(crate) unsafe extern "C" fn guigl_begin_query(
+    context: *const glow::Context,
+    target: GLenum,
+    query: GLuint,
+) {
+    catch_panic((), || {
+        (*context).begin_query(target, glow::NativeQuery(query.try_into().unwrap()));
+    })
+}
+
+#[no_mangle]
+pub(crate) unsafe extern "C" fn guigl_end_query(context: *const glow::Context, target: GLenum) {
+    catch_panic((), || {
+        (*context).end_query(target);
+    })
+}
+
+#[no_mangle]
+pub(crate) unsafe extern "C" fn guigl_get_query_result(
+    context: *const glow::Context,
+    query: GLuint,
+    result: *mut GLuint,
+) -> bool {
+    catch_panic(false, || {
+        let query = glow::NativeQuery(query.try_into().unwrap());
+        // Asking for the result before it's ready would stall until the GPU catches up
+        if (*context).get_query_parameter_u32(query, glow::QUERY_RESULT_AVAILABLE) == 0 {
+            return false;
+        }
+
+        *result = (*context).get_query_parameter_u32(query, glow::QUERY_RESULT);
+        true
+    })
 }
@@ -0,0 +1,73 @@
+use eframe::glow;
+
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{c_bindings::GLuint, gl_exports};
+
+/// Upper bound on queries waiting for results. If the GPU falls this far behind we skip timing
+/// frames instead of allocating more queries
+const MAX_PENDING_QUERIES: usize = 4;
+
+/// Measures how long the GPU spends on the draw calls issued in GpuTimer::time. Results only
+/// become available a few frames later, so last_elapsed lags behind a little. Goes through the
+/// same query exports the backend's renderers get, see gl_exports
+#[derive(Default)]
+pub struct GpuTimer {
+    free: Vec<GLuint>,
+    pending: VecDeque<GLuint>,
+    last_elapsed: Option<Duration>,
+}
+
+impl GpuTimer {
+    pub fn time<R>(&mut self, gl: &glow::Context, f: impl FnOnce() -> R) -> R {
+        unsafe {
+            self.collect_results(gl);
+
+            let query = if self.pending.len() < MAX_PENDING_QUERIES {
+                self.free.pop().or_else(|| {
+                    Some(gl_exports::guigl_create_query(gl)).filter(|query| *query != GLuint::MAX)
+                })
+            } else {
+                None
+            };
+
+            if let Some(query) = query {
+                gl_exports::guigl_begin_query(gl, glow::TIME_ELAPSED, query);
+            }
+
+            let ret = f();
+
+            if let Some(query) = query {
+                gl_exports::guigl_end_query(gl, glow::TIME_ELAPSED);
+                self.pending.push_back(query);
+            }
+
+            ret
+        }
+    }
+
+    pub fn last_elapsed(&self) -> Option<Duration> {
+        self.last_elapsed
+    }
+
+    pub fn deinit(&mut self, gl: &glow::Context) {
+        unsafe {
+            for query in self.free.drain(..).chain(self.pending.drain(..)) {
+                gl_exports::guigl_delete_query(gl, query);
+            }
+        }
+    }
+
+    unsafe fn collect_results(&mut self, gl: &glow::Context) {
+        while let Some(&query) = self.pending.front() {
+            let mut elapsed_ns = 0;
+            if !gl_exports::guigl_get_query_result(gl, query, &mut elapsed_ns) {
+                break;
+            }
+
+            self.last_elapsed = Some(Duration::from_nanos(elapsed_ns.into()));
+            self.pending.pop_front();
+            self.free.push(query);
+        }
+    }
+}
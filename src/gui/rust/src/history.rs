@@ -0,0 +1,223 @@
+use eframe::egui;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{actions::Action, c_bindings, ActionRequestor};
+
+/// Every edit since the project was opened. Clicking an entry moves the playhead to it, reverting
+/// puts the project back the way it was right after it
+pub struct HistoryPanel {
+    pub open: bool,
+    /// Newest entry seen, to scroll it into view when another one comes in
+    last_id: u64,
+}
+
+impl HistoryPanel {
+    pub fn new() -> HistoryPanel {
+        HistoryPanel {
+            open: false,
+            last_id: 0,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let entries = entries(state);
+        let newest = entries.last().map_or(0, |entry| entry.id);
+        let scroll_to_newest = newest != self.last_id;
+        self.last_id = newest;
+
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+
+        let mut open = self.open;
+        egui::Window::new("History")
+            .open(&mut open)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (i, entry) in entries.iter().enumerate() {
+                            let is_current = i + 1 == entries.len();
+                            let response = ui
+                                .horizontal(|ui| {
+                                    ui.weak(format_age(now_ms - entry.time_ms));
+
+                                    let text = describe(entry, entries);
+                                    let label = if entry.has_position {
+                                        ui.link(text)
+                                    } else {
+                                        ui.label(text)
+                                    };
+                                    if label.clicked() {
+                                        action_tx.send(Action::Seek(entry.start));
+                                    }
+
+                                    if is_current {
+                                        ui.weak("(current)");
+                                    } else if ui
+                                        .small_button("Revert")
+                                        .on_hover_text("Go back to the project as it was here")
+                                        .clicked()
+                                    {
+                                        action_tx.send(Action::RevertHistory(entry.id));
+                                    }
+                                })
+                                .response;
+
+                            if is_current && scroll_to_newest {
+                                response.scroll_to_me(Some(egui::Align::BOTTOM));
+                            }
+                        }
+                    });
+            });
+        self.open = open;
+    }
+}
+
+pub fn entries(state: &c_bindings::AppStateSnapshot) -> &[c_bindings::GuiHistoryEntry] {
+    if state.num_history == 0 {
+        // history may be null
+        return &[];
+    }
+
+    unsafe { std::slice::from_raw_parts(state.history, state.num_history as usize) }
+}
+
+fn format_range(entry: &c_bindings::GuiHistoryEntry) -> String {
+    if entry.start == entry.end {
+        format!("{:.1}", entry.start)
+    } else {
+        format!("{:.1}–{:.1}", entry.start, entry.end)
+    }
+}
+
+fn describe(
+    entry: &c_bindings::GuiHistoryEntry,
+    entries: &[c_bindings::GuiHistoryEntry],
+) -> String {
+    let what = match entry.kind {
+        c_bindings::GuiHistoryKind_gui_history_opened => return "Opened project".to_string(),
+        c_bindings::GuiHistoryKind_gui_history_clip_add => "Clip added",
+        c_bindings::GuiHistoryKind_gui_history_clip_trim => "Clip trimmed",
+        c_bindings::GuiHistoryKind_gui_history_clip_remove => "Clip removed",
+        c_bindings::GuiHistoryKind_gui_history_clip_move => "Clip moved",
+        c_bindings::GuiHistoryKind_gui_history_clip_speed => "Clip speed changed",
+        c_bindings::GuiHistoryKind_gui_history_freeze_frame => "Freeze frame inserted",
+        c_bindings::GuiHistoryKind_gui_history_mute_add => "Audio muted",
+        c_bindings::GuiHistoryKind_gui_history_mute_trim => "Mute trimmed",
+        c_bindings::GuiHistoryKind_gui_history_mute_remove => "Mute removed",
+        c_bindings::GuiHistoryKind_gui_history_marker_add => "Marker added",
+        c_bindings::GuiHistoryKind_gui_history_marker_remove => "Marker removed",
+        c_bindings::GuiHistoryKind_gui_history_markers_imported => "Markers imported",
+        c_bindings::GuiHistoryKind_gui_history_caption_style => "Caption style changed",
+        c_bindings::GuiHistoryKind_gui_history_revert => {
+            // The target may have fallen off the front of the history
+            let target = entries
+                .iter()
+                .find(|target| target.id == entry.revert_target)
+                .map_or_else(
+                    || "an older edit".to_string(),
+                    |target| describe(target, entries).to_lowercase(),
+                );
+            return format!("Reverted to {target}");
+        }
+        _ => "Unknown edit",
+    };
+
+    if entry.has_position {
+        format!("{what} at {}", format_range(entry))
+    } else {
+        what.to_string()
+    }
+}
+
+fn format_age(age_ms: i64) -> String {
+    let secs = age_ms.max(0) / 1000;
+    if secs < 60 {
+        "just now".to_string()
+    } else if secs < 60 * 60 {
+        format!("{} min ago", secs / 60)
+    } else {
+        format!("{} h ago", secs / (60 * 60))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(
+        id: u64,
+        kind: c_bindings::GuiHistoryKind,
+        position: Option<(f32, f32)>,
+    ) -> c_bindings::GuiHistoryEntry {
+        let (start, end) = position.unwrap_or_default();
+        c_bindings::GuiHistoryEntry {
+            id,
+            kind,
+            time_ms: 0,
+            has_position: position.is_some(),
+            start,
+            end,
+            revert_target: 0,
+        }
+    }
+
+    #[test]
+    fn entries_describe_where_the_edit_happened() {
+        let add = entry(
+            2,
+            c_bindings::GuiHistoryKind_gui_history_clip_add,
+            Some((12.3, 15.7)),
+        );
+        let marker = entry(
+            3,
+            c_bindings::GuiHistoryKind_gui_history_marker_add,
+            Some((4.0, 4.0)),
+        );
+        let style = entry(
+            4,
+            c_bindings::GuiHistoryKind_gui_history_caption_style,
+            None,
+        );
+
+        assert_eq!(describe(&add, &[]), "Clip added at 12.3–15.7");
+        assert_eq!(describe(&marker, &[]), "Marker added at 4.0");
+        assert_eq!(describe(&style, &[]), "Caption style changed");
+    }
+
+    #[test]
+    fn reverts_name_their_target() {
+        let add = entry(
+            2,
+            c_bindings::GuiHistoryKind_gui_history_clip_add,
+            Some((1.0, 2.0)),
+        );
+        let mut revert = entry(
+            5,
+            c_bindings::GuiHistoryKind_gui_history_revert,
+            Some((1.0, 2.0)),
+        );
+        revert.revert_target = 2;
+
+        assert_eq!(
+            describe(&revert, &[add, revert]),
+            "Reverted to clip added at 1.0–2.0"
+        );
+        assert_eq!(describe(&revert, &[revert]), "Reverted to an older edit");
+    }
+
+    #[test]
+    fn ages_are_rounded_down() {
+        assert_eq!(format_age(5_000), "just now");
+        assert_eq!(format_age(125_000), "2 min ago");
+        assert_eq!(format_age(2 * 60 * 60 * 1000 + 5), "2 h ago");
+    }
+}
@@ -0,0 +1,81 @@
+use eframe::egui;
+
+use crate::{actions::Action, c_bindings};
+
+pub const COLOR: egui::Color32 = egui::Color32::LIGHT_GREEN;
+
+/// Marks a range with I and O at the playhead, C turns it into a clip. Quicker than ctrl+dragging
+/// a range out on the timeline when the edges have to be frame accurate
+pub struct InOutPoints {
+    pub in_point: Option<f32>,
+    pub out_point: Option<f32>,
+}
+
+impl InOutPoints {
+    pub fn new() -> InOutPoints {
+        InOutPoints {
+            in_point: None,
+            out_point: None,
+        }
+    }
+
+    /// None if key is not one of ours or there is nothing to send
+    pub fn handle_key(
+        &mut self,
+        key: egui::Key,
+        state: &c_bindings::AppStateSnapshot,
+    ) -> Option<Action> {
+        match key {
+            egui::Key::I => self.in_point = Some(state.current_position),
+            egui::Key::O => self.out_point = Some(state.current_position),
+            egui::Key::C => {
+                let clip = self.clip()?;
+                *self = InOutPoints::new();
+                return Some(Action::ClipAdd(clip));
+            }
+            _ => (),
+        }
+        None
+    }
+
+    /// Points set the wrong way around still make a clip, an empty range does not
+    fn clip(&self) -> Option<c_bindings::Clip> {
+        let (a, b) = (self.in_point?, self.out_point?);
+        if a == b {
+            return None;
+        }
+
+        Some(c_bindings::Clip {
+            id: 0,
+            start: a.min(b),
+            end: a.max(b),
+            speed: 1.0,
+            freeze: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn in_and_out_make_a_clip() {
+        let mut state: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+        let mut points = InOutPoints::new();
+
+        state.current_position = 7.0;
+        assert!(points.handle_key(egui::Key::I, &state).is_none());
+        // Nothing to add with only one point
+        assert!(points.handle_key(egui::Key::C, &state).is_none());
+
+        state.current_position = 3.0;
+        assert!(points.handle_key(egui::Key::O, &state).is_none());
+
+        let Some(Action::ClipAdd(clip)) = points.handle_key(egui::Key::C, &state) else {
+            panic!("expected a clip add");
+        };
+        assert_eq!((clip.start, clip.end), (3.0, 7.0));
+        assert!(points.in_point.is_none() && points.out_point.is_none());
+    }
+}
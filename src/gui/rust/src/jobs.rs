@@ -0,0 +1,181 @@
+use eframe::egui;
+
+use crate::{actions::Action, c_bindings, ActionRequestor};
+
+/// Lists the backend's long running work, e.g. transcription, with a way to cancel it
+pub struct JobsPanel {
+    pub open: bool,
+}
+
+impl JobsPanel {
+    pub fn new() -> JobsPanel {
+        JobsPanel { open: false }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let jobs = jobs(state);
+
+        let mut open = self.open;
+        egui::Window::new("Jobs")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if jobs.is_empty() {
+                    ui.label("No background jobs");
+                    return;
+                }
+
+                egui::Grid::new("jobs")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for job in jobs {
+                            show_job(ui, job, action_tx);
+                            ui.end_row();
+                        }
+                    });
+            });
+        self.open = open;
+    }
+}
+
+fn show_job(ui: &mut egui::Ui, job: &c_bindings::GuiJob, action_tx: &mut ActionRequestor) {
+    ui.label(kind_label(job.kind));
+    ui.label(state_label(job));
+
+    let progress = if job.progress < 0.0 && is_active(job) {
+        egui::ProgressBar::new(0.0).animate(true)
+    } else {
+        egui::ProgressBar::new(job.progress.max(0.0)).show_percentage()
+    };
+    ui.add(progress.desired_width(150.0));
+
+    let can_cancel = job.cancellable && !job.cancel_requested && is_active(job);
+    if ui
+        .add_enabled(can_cancel, egui::Button::new("Cancel"))
+        .clicked()
+    {
+        action_tx.send(Action::CancelJob(job.id));
+    }
+}
+
+/// Jobs that are queued, running or paused, e.g. to show on the button that opens the panel
+pub fn num_active(state: &c_bindings::AppStateSnapshot) -> usize {
+    jobs(state).iter().filter(|job| is_active(job)).count()
+}
+
+/// Jobs of the given kind that have not finished yet, oldest first
+pub fn active_of_kind(
+    state: &c_bindings::AppStateSnapshot,
+    kind: c_bindings::GuiJobKind,
+) -> impl Iterator<Item = &c_bindings::GuiJob> {
+    jobs(state)
+        .iter()
+        .filter(move |job| job.kind == kind && is_active(job))
+}
+
+/// The most recently started job of the given kind that has not finished yet
+pub fn find_active(
+    state: &c_bindings::AppStateSnapshot,
+    kind: c_bindings::GuiJobKind,
+) -> Option<&c_bindings::GuiJob> {
+    jobs(state)
+        .iter()
+        .rev()
+        .find(|job| job.kind == kind && is_active(job))
+}
+
+fn jobs(state: &c_bindings::AppStateSnapshot) -> &[c_bindings::GuiJob] {
+    if state.num_jobs == 0 {
+        // jobs may be null
+        return &[];
+    }
+
+    unsafe { std::slice::from_raw_parts(state.jobs, state.num_jobs as usize) }
+}
+
+fn is_active(job: &c_bindings::GuiJob) -> bool {
+    matches!(
+        job.state,
+        c_bindings::GuiJobState_gui_job_state_queued
+            | c_bindings::GuiJobState_gui_job_state_running
+            | c_bindings::GuiJobState_gui_job_state_paused
+    )
+}
+
+fn kind_label(kind: c_bindings::GuiJobKind) -> &'static str {
+    match kind {
+        c_bindings::GuiJobKind_gui_job_kind_transcription => "transcription",
+        c_bindings::GuiJobKind_gui_job_kind_waveform => "waveform",
+        c_bindings::GuiJobKind_gui_job_kind_thumbnails => "thumbnails",
+        c_bindings::GuiJobKind_gui_job_kind_export => "export",
+        _ => "unknown",
+    }
+}
+
+fn state_label(job: &c_bindings::GuiJob) -> &'static str {
+    if job.cancel_requested && is_active(job) {
+        return "cancelling";
+    }
+
+    match job.state {
+        c_bindings::GuiJobState_gui_job_state_queued => "queued",
+        c_bindings::GuiJobState_gui_job_state_running => "running",
+        c_bindings::GuiJobState_gui_job_state_paused => "paused",
+        c_bindings::GuiJobState_gui_job_state_finished => "finished",
+        c_bindings::GuiJobState_gui_job_state_failed => "failed",
+        c_bindings::GuiJobState_gui_job_state_cancelled => "cancelled",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn job(id: u64, state: c_bindings::GuiJobState) -> c_bindings::GuiJob {
+        c_bindings::GuiJob {
+            id,
+            kind: c_bindings::GuiJobKind_gui_job_kind_transcription,
+            state,
+            progress: 0.5,
+            cancellable: true,
+            cancel_requested: false,
+        }
+    }
+
+    #[test]
+    fn only_unfinished_jobs_are_active() {
+        let jobs = [
+            job(0, c_bindings::GuiJobState_gui_job_state_queued),
+            job(1, c_bindings::GuiJobState_gui_job_state_running),
+            job(2, c_bindings::GuiJobState_gui_job_state_finished),
+            job(3, c_bindings::GuiJobState_gui_job_state_failed),
+            job(4, c_bindings::GuiJobState_gui_job_state_cancelled),
+            job(5, c_bindings::GuiJobState_gui_job_state_paused),
+        ];
+
+        let mut state: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+        assert_eq!(num_active(&state), 0);
+
+        state.jobs = jobs.as_ptr();
+        state.num_jobs = jobs.len() as u64;
+        assert_eq!(num_active(&state), 3);
+    }
+
+    #[test]
+    fn cancel_requests_only_show_while_active() {
+        let mut running = job(0, c_bindings::GuiJobState_gui_job_state_running);
+        running.cancel_requested = true;
+        assert_eq!(state_label(&running), "cancelling");
+
+        let mut cancelled = job(1, c_bindings::GuiJobState_gui_job_state_cancelled);
+        cancelled.cancel_requested = true;
+        assert_eq!(state_label(&cancelled), "cancelled");
+    }
+}
@@ -0,0 +1,243 @@
+use eframe::egui;
+
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::{actions::Action, c_bindings, ActionRequestor};
+
+pub const MAX_SHUTTLE_SPEED: f32 = 8.0;
+/// Fraction of the shuttle around the middle that counts as stopped, so that letting go slightly
+/// off center does not leave the video crawling along
+const SHUTTLE_DEAD_ZONE: f32 = 0.05;
+const SHUTTLE_SIZE: egui::Vec2 = egui::vec2(120.0, 18.0);
+/// Frames stepped per full turn of the jog wheel
+const JOG_FRAMES_PER_TURN: f32 = 24.0;
+const JOG_RADIUS: f32 = 9.0;
+/// Speeds the J and L keys step through, K pauses in between
+const TRANSPORT_SPEEDS: [f32; 8] = [-8.0, -4.0, -2.0, -1.0, 1.0, 2.0, 4.0, 8.0];
+
+/// Jog wheel that steps frames as it is turned, and a spring loaded shuttle that plays at up to 8x
+/// in either direction while held. Both are for hunting down exact cut points
+pub struct JogShuttle {
+    /// Angle of the pointer around the jog wheel last frame, None when not turning
+    jog_angle: Option<f32>,
+    /// Turns that have not added up to a whole frame yet, in frames
+    jog_remainder: f32,
+    /// Where the wheel is drawn as being turned to
+    jog_rotation: f32,
+    /// -1 to 1 across the shuttle, springs back to 0 on release
+    shuttle_pos: f32,
+}
+
+impl JogShuttle {
+    pub fn new() -> JogShuttle {
+        JogShuttle {
+            jog_angle: None,
+            jog_remainder: 0.0,
+            jog_rotation: 0.0,
+            shuttle_pos: 0.0,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        self.show_jog(ui, action_tx);
+        self.show_shuttle(ui, state, action_tx);
+    }
+
+    fn show_jog(&mut self, ui: &mut egui::Ui, action_tx: &mut ActionRequestor) {
+        let size = egui::Vec2::splat(JOG_RADIUS * 2.0);
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::drag());
+        let center = rect.center();
+
+        if response.dragged() {
+            let angle = response
+                .interact_pointer_pos()
+                .map(|pos| (pos - center).angle());
+            if let (Some(prev), Some(angle)) = (self.jog_angle, angle) {
+                let frames = self.jog_frames(angle_delta(prev, angle));
+                if frames != 0 {
+                    action_tx.send(Action::StepFrames(frames));
+                }
+            }
+            self.jog_angle = angle;
+        } else {
+            self.jog_angle = None;
+            self.jog_remainder = 0.0;
+        }
+
+        let visuals = ui.style().interact(&response);
+        let painter = ui.painter();
+        painter.circle(center, JOG_RADIUS - 1.0, visuals.bg_fill, visuals.fg_stroke);
+        let notch = center + egui::Vec2::angled(self.jog_rotation - FRAC_PI_2) * (JOG_RADIUS - 4.0);
+        painter.circle_filled(notch, 2.0, visuals.fg_stroke.color);
+
+        response.on_hover_text("Jog: drag around the wheel to step frames");
+    }
+
+    /// Whole frames for turning the wheel by delta radians, the rest is kept for the next turn
+    fn jog_frames(&mut self, delta: f32) -> i32 {
+        self.jog_rotation = (self.jog_rotation + delta).rem_euclid(TAU);
+        self.jog_remainder += delta / TAU * JOG_FRAMES_PER_TURN;
+        let frames = self.jog_remainder.trunc();
+        self.jog_remainder -= frames;
+        frames as i32
+    }
+
+    fn show_shuttle(
+        &mut self,
+        ui: &mut egui::Ui,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let (rect, response) = ui.allocate_exact_size(SHUTTLE_SIZE, egui::Sense::drag());
+        let half_width = rect.width() / 2.0;
+
+        if response.dragged() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let pos_norm = ((pos.x - rect.center().x) / half_width).clamp(-1.0, 1.0);
+                if pos_norm != self.shuttle_pos {
+                    self.shuttle_pos = pos_norm;
+                    action_tx.send(Action::SetSpeed(shuttle_speed(pos_norm)));
+                }
+            }
+        } else if response.drag_stopped() {
+            self.shuttle_pos = 0.0;
+            action_tx.send(Action::SetSpeed(0.0));
+        }
+
+        let visuals = ui.style().interact(&response);
+        let painter = ui.painter();
+        painter.rect(
+            rect,
+            visuals.rounding,
+            ui.visuals().extreme_bg_color,
+            visuals.bg_stroke,
+        );
+        painter.vline(rect.center().x, rect.y_range(), visuals.bg_stroke);
+
+        let handle_x = rect.center().x + self.shuttle_pos * half_width;
+        let handle = egui::Rect::from_center_size(
+            egui::pos2(handle_x, rect.center().y),
+            egui::vec2(6.0, rect.height()),
+        );
+        painter.rect_filled(handle, visuals.rounding, visuals.fg_stroke.color);
+
+        let speed = if state.paused {
+            0.0
+        } else {
+            state.playback_speed
+        };
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            format!("{speed:+.2}x"),
+            egui::FontId::monospace(11.0),
+            visuals.text_color(),
+        );
+
+        response.on_hover_text("Shuttle: hold and drag to play faster or backwards");
+    }
+}
+
+/// Squared so that most of the shuttle is spent on the slower speeds that are useful for finding a
+/// frame
+pub fn shuttle_speed(pos_norm: f32) -> f32 {
+    if pos_norm.abs() < SHUTTLE_DEAD_ZONE {
+        return 0.0;
+    }
+
+    pos_norm * pos_norm.abs() * MAX_SHUTTLE_SPEED
+}
+
+/// Speed to play at after a press of the J, K or L key, None for any other key. L steps up through
+/// the forward speeds and J down through the reverse ones, so J slows forward playback down
+/// before it turns around
+pub fn transport_speed(key: egui::Key, state: &c_bindings::AppStateSnapshot) -> Option<f32> {
+    let current = if state.paused {
+        0.0
+    } else {
+        state.playback_speed
+    };
+
+    let speed = match key {
+        egui::Key::K => 0.0,
+        egui::Key::L => TRANSPORT_SPEEDS
+            .into_iter()
+            .find(|speed| *speed > current)
+            .unwrap_or(MAX_SHUTTLE_SPEED),
+        egui::Key::J => TRANSPORT_SPEEDS
+            .into_iter()
+            .rev()
+            .find(|speed| *speed < current)
+            .unwrap_or(-MAX_SHUTTLE_SPEED),
+        _ => return None,
+    };
+    Some(speed)
+}
+
+/// Shortest signed rotation from a to b, in radians
+fn angle_delta(a: f32, b: f32) -> f32 {
+    let delta = (b - a).rem_euclid(TAU);
+    if delta > PI {
+        delta - TAU
+    } else {
+        delta
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shuttle_is_stopped_in_the_middle() {
+        assert_eq!(shuttle_speed(0.0), 0.0);
+        assert_eq!(shuttle_speed(SHUTTLE_DEAD_ZONE / 2.0), 0.0);
+        assert_eq!(shuttle_speed(0.5), 2.0);
+        assert_eq!(shuttle_speed(-1.0), -MAX_SHUTTLE_SPEED);
+    }
+
+    #[test]
+    fn transport_keys_step_through_speeds() {
+        let mut state: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+        state.paused = true;
+        state.playback_speed = 1.0;
+        assert_eq!(transport_speed(egui::Key::L, &state), Some(1.0));
+        assert_eq!(transport_speed(egui::Key::J, &state), Some(-1.0));
+        assert_eq!(transport_speed(egui::Key::A, &state), None);
+
+        state.paused = false;
+        state.playback_speed = 2.0;
+        assert_eq!(transport_speed(egui::Key::L, &state), Some(4.0));
+        assert_eq!(transport_speed(egui::Key::J, &state), Some(1.0));
+        assert_eq!(transport_speed(egui::Key::K, &state), Some(0.0));
+
+        state.playback_speed = 1.0;
+        assert_eq!(transport_speed(egui::Key::J, &state), Some(-1.0));
+
+        state.playback_speed = 8.0;
+        assert_eq!(transport_speed(egui::Key::L, &state), Some(8.0));
+        state.playback_speed = -8.0;
+        assert_eq!(transport_speed(egui::Key::J, &state), Some(-8.0));
+    }
+
+    #[test]
+    fn angle_delta_takes_the_short_way_around() {
+        assert!((angle_delta(0.1, -0.1) + 0.2).abs() < 1e-5);
+        assert!((angle_delta(PI - 0.1, -PI + 0.1) - 0.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn jog_keeps_partial_frames() {
+        let mut jog_shuttle = JogShuttle::new();
+        let frame_angle = TAU / JOG_FRAMES_PER_TURN;
+
+        assert_eq!(jog_shuttle.jog_frames(frame_angle * 0.6), 0);
+        assert_eq!(jog_shuttle.jog_frames(frame_angle * 0.6), 1);
+        assert_eq!(jog_shuttle.jog_frames(-frame_angle * 3.5), -3);
+    }
+}
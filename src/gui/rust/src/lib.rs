@@ -1,759 +1,1146 @@
-use eframe::{egui, egui_glow, glow};
+use eframe::{egui, glow};
 
 use std::{
-    ffi::c_void,
+    ffi::{c_char, c_void, CString},
     sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         mpsc::{self, Receiver, Sender},
-        Arc, Condvar, Mutex,
+        Arc, Condvar, Mutex, PoisonError,
     },
+    time::{Duration, Instant},
 };
 
+mod action_dispatcher;
+mod actions;
+mod backend;
 mod c_bindings;
+mod c_renderer;
+mod captions;
+mod clip_properties;
+mod close_prompt;
+mod color;
+mod command_palette;
+mod config;
+mod debug_overlay;
+mod drag_out;
+mod events;
+mod export;
+mod file_watch;
+mod find_cut;
+mod freeze_frame;
+mod gaps;
 mod gl_exports;
-
-#[derive(Clone)]
-struct RendererPtr(*mut c_void);
-unsafe impl Send for RendererPtr {}
-unsafe impl Sync for RendererPtr {}
-
-mod gui_actions {
-    use crate::c_bindings::*;
-
-    fn make_action(tag: GuiActionTag) -> GuiAction {
-        unsafe {
-            let mut ret = std::mem::MaybeUninit::<GuiAction>::zeroed();
-            (*ret.as_mut_ptr()).tag = tag;
-            ret.assume_init()
-        }
-    }
-
-    pub fn none() -> GuiAction {
-        make_action(GuiActionTag_gui_action_none)
-    }
-
-    pub fn toggle_pause() -> GuiAction {
-        make_action(GuiActionTag_gui_action_toggle_pause)
-    }
-
-    pub fn close() -> GuiAction {
-        make_action(GuiActionTag_gui_action_close)
-    }
-
-    pub fn seek(pos: f32) -> GuiAction {
-        let mut ret = make_action(GuiActionTag_gui_action_seek);
-        ret.data.seek_position = pos;
-        ret
-    }
-
-    pub fn clip_add(clip: &Clip) -> GuiAction {
-        let mut ret = make_action(GuiActionTag_gui_action_clip_add);
-        ret.data.clip = *clip;
-        ret
-    }
-
-    pub fn clip_remove(current_pos: f32) -> GuiAction {
-        let mut ret = make_action(GuiActionTag_gui_action_clip_remove);
-        ret.data.seek_position = current_pos;
-        ret
-    }
-
-    pub fn clip_edit(clip: &Clip) -> GuiAction {
-        let mut ret = make_action(GuiActionTag_gui_action_clip_edit);
-        ret.data.clip = *clip;
-        ret
-    }
-
-    pub fn save() -> GuiAction {
-        make_action(GuiActionTag_gui_action_save)
-    }
-}
+mod gpu_timer;
+mod history;
+mod in_out;
+mod jobs;
+mod jog_shuttle;
+mod logging;
+mod markers;
+mod media_keys;
+mod midi;
+mod mute;
+mod osc;
+mod panic_guard;
+mod profiling;
+mod recovery;
+#[cfg(feature = "remote")]
+mod remote;
+mod render;
+mod script_edit;
+mod scripting;
+mod sequence;
+mod snapping;
+mod thumbnails;
+mod timecode;
+mod timeline;
+mod transcribe;
+mod transcript;
+mod trim_preview;
+#[cfg(feature = "wgpu")]
+mod wgpu_renderer;
+
+use action_dispatcher::ActionDispatcher;
+use actions::{Action, PreviewQuality};
+use backend::{Backend, CBackend};
+use gpu_timer::GpuTimer;
+use panic_guard::catch_panic;
+use profiling::profile_scope;
+use render::{CommandRecorder, FrameParams};
+use thumbnails::{ThumbnailCache, ThumbnailKey};
+use timeline::{ProgressBar, SeekState};
+use transcript::TranscriptLayout;
 
 pub struct GuiInner {
     ctx: Option<egui::Context>,
-    action_rx: Receiver<c_bindings::GuiAction>,
-    action_tx: Sender<c_bindings::GuiAction>,
+    /// None once the receiver has been handed off to an ActionDispatcher
+    action_rx: Option<Receiver<Action>>,
+    action_tx: Sender<Action>,
+    /// Bitmask of GuiUpdateKind notified since the last frame
+    updates: c_bindings::GuiUpdateKind,
+    /// Set if gui_run gave up before the context was created, so waiters don't wait forever
+    start_failed: bool,
+    /// Events pushed by the backend since the last frame
+    events: Vec<events::Event>,
 }
 
 pub struct Gui {
     cond: Condvar,
     inner: Mutex<GuiInner>,
     state: *mut c_bindings::AppState,
+    dispatcher: Mutex<Option<ActionDispatcher>>,
+    last_error: Mutex<CString>,
+    panicked: AtomicBool,
+    /// The backend is done with the project, e.g. after an error. Its tab goes away on the next
+    /// frame
+    closed: AtomicBool,
+    /// Number of actions sent but not yet picked up by the backend, for the debug overlay
+    queued_actions: Arc<AtomicUsize>,
+}
+
+impl Gui {
+    fn set_error(&self, status: c_bindings::GuiStatus, msg: &str) -> c_bindings::GuiStatus {
+        log::error!("{msg}");
+        let mut last_error = self
+            .last_error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *last_error = CString::new(msg).unwrap_or_default();
+        status
+    }
+}
+
+/// Whether the backend should consider the GUI gone. A GUI that's panicked while holding the lock
+/// is considered gone as well
+unsafe fn gui_closed(gui: *mut Gui) -> bool {
+    match (*gui).inner.lock() {
+        Ok(inner) => inner.ctx.is_none(),
+        Err(_) => true,
+    }
+}
+
+fn status_from_eframe_error(e: &eframe::Error) -> c_bindings::GuiStatus {
+    match e {
+        eframe::Error::WinitEventLoop(_) | eframe::Error::Winit(_) => {
+            c_bindings::GuiStatus_gui_status_no_display
+        }
+        eframe::Error::Glutin(_)
+        | eframe::Error::NoGlutinConfigs(..)
+        | eframe::Error::OpenGL(_) => c_bindings::GuiStatus_gui_status_gl_init_failed,
+        _ => c_bindings::GuiStatus_gui_status_error,
+    }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn gui_init(state: *mut c_bindings::AppState) -> *mut Gui {
-    let (action_tx, action_rx) = mpsc::channel();
-
-    let inner = GuiInner {
-        ctx: None,
-        action_tx,
-        action_rx,
-    };
+    catch_panic(std::ptr::null_mut(), || {
+        logging::init();
+        panic_guard::install_hook();
+
+        let (action_tx, action_rx) = mpsc::channel();
+
+        let inner = GuiInner {
+            ctx: None,
+            action_tx,
+            action_rx: Some(action_rx),
+            updates: 0,
+            start_failed: false,
+            events: Vec::new(),
+        };
 
-    let gui = Gui {
-        cond: Condvar::new(),
-        inner: Mutex::new(inner),
-        state,
-    };
+        let gui = Gui {
+            cond: Condvar::new(),
+            inner: Mutex::new(inner),
+            state,
+            dispatcher: Mutex::new(None),
+            last_error: Mutex::new(CString::default()),
+            panicked: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            queued_actions: Arc::new(AtomicUsize::new(0)),
+        };
 
-    Box::leak(Box::new(gui))
+        Box::leak(Box::new(gui))
+    })
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn gui_free(gui: *mut Gui) {
-    drop(Box::from_raw(gui));
+    catch_panic((), || {
+        drop(Box::from_raw(gui));
+    })
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn gui_run(
-    gui: *mut Gui,
-    frame_renderer: *mut c_bindings::FrameRenderer,
-    audio_renderer: *mut c_bindings::AudioRenderer,
-    wtm: *mut c_bindings::WordTimestampMap,
-) {
+    projects: *const c_bindings::GuiProject,
+    num_projects: u64,
+) -> c_bindings::GuiStatus {
+    if num_projects == 0 {
+        return c_bindings::GuiStatus_gui_status_invalid_argument;
+    }
+
+    let projects = std::slice::from_raw_parts(projects, num_projects as usize);
+    let guis: Vec<*mut Gui> = projects
+        .iter()
+        .map(|project| project.gui as *mut Gui)
+        .collect();
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([800.0, 600.0]),
-        multisampling: 4,
+        multisampling: MULTISAMPLING,
+        #[cfg(not(feature = "wgpu"))]
         renderer: eframe::Renderer::Glow,
+        #[cfg(feature = "wgpu")]
+        renderer: eframe::Renderer::Wgpu,
         ..Default::default()
     };
 
-    let frame_renderer = RendererPtr(frame_renderer);
-    let audio_renderer = RendererPtr(audio_renderer);
-    let wtm = RendererPtr(wtm);
-
-    eframe::run_native(
-        "video editor",
-        options,
-        Box::new(move |cc| {
-            let mut inner = (*gui).inner.lock().unwrap();
-            inner.ctx = Some(cc.egui_ctx.clone());
-            (*gui).cond.notify_all();
-            let action_tx = inner.action_tx.clone();
-            Box::new(EframeImpl::new(
-                cc,
-                frame_renderer,
-                audio_renderer,
-                wtm,
-                gui,
-                action_tx,
-            ))
-        }),
-    )
-    .unwrap();
-}
+    let backends: Vec<(*mut Gui, CBackend, String)> = projects
+        .iter()
+        .zip(&guis)
+        .map(|(project, gui)| {
+            let backend = CBackend::new(
+                (**gui).state,
+                project.frame_renderer,
+                project.audio_renderer,
+                project.wtm,
+            );
+            (*gui, backend, tab_name(project.save_path))
+        })
+        .collect();
+
+    let res = catch_panic(None, || {
+        Some(eframe::run_native(
+            "video editor",
+            options,
+            Box::new(move |cc| {
+                let views = backends
+                    .into_iter()
+                    .map(|(gui, backend, name)| {
+                        let mut inner = (*gui).inner.lock().unwrap();
+                        inner.ctx = Some(cc.egui_ctx.clone());
+                        (*gui).cond.notify_all();
+                        let action_tx = inner.action_tx.clone();
+                        ProjectView::new(cc, backend, gui, action_tx, name)
+                    })
+                    .collect();
+                Box::new(EframeImpl::new(views))
+            }),
+        ))
+    });
+
+    let panicked = guis
+        .iter()
+        .any(|gui| (**gui).panicked.load(Ordering::Relaxed));
+    let status = match res {
+        Some(Ok(())) if !panicked => {
+            return c_bindings::GuiStatus_gui_status_ok;
+        }
+        Some(Ok(())) => set_errors(
+            &guis,
+            c_bindings::GuiStatus_gui_status_error,
+            "Gui shut down after a panic",
+        ),
+        Some(Err(e)) => set_errors(
+            &guis,
+            status_from_eframe_error(&e),
+            &format!("Failed to run gui: {e}"),
+        ),
+        None => set_errors(
+            &guis,
+            c_bindings::GuiStatus_gui_status_error,
+            "Gui panicked",
+        ),
+    };
 
-#[no_mangle]
-pub unsafe extern "C" fn gui_next_action(gui: *mut Gui) -> c_bindings::GuiAction {
-    let inner = (*gui).inner.lock().unwrap();
-    if let Ok(v) = inner.action_rx.try_recv() {
-        return v;
+    for gui in &guis {
+        let mut inner = (**gui).inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.start_failed = inner.ctx.is_none();
+        inner.ctx = None;
+        (**gui).cond.notify_all();
     }
+    status
+}
 
-    if inner.ctx.is_some() {
-        gui_actions::none()
-    } else {
-        gui_actions::close()
+/// Every project's backend asks its own Gui what went wrong
+unsafe fn set_errors(
+    guis: &[*mut Gui],
+    status: c_bindings::GuiStatus,
+    msg: &str,
+) -> c_bindings::GuiStatus {
+    log::error!("{msg}");
+    let msg = CString::new(msg).unwrap_or_default();
+    for gui in guis {
+        let mut last_error = (**gui)
+            .last_error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *last_error = msg.clone();
     }
+    status
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn gui_wait_start(gui: *mut Gui) {
-    let mut inner = (*gui).inner.lock().unwrap();
-    while inner.ctx.is_none() {
-        inner = (*gui).cond.wait(inner).unwrap();
+/// File name of the save, which is what tells projects apart
+unsafe fn tab_name(save_path: *const c_char) -> String {
+    if save_path.is_null() {
+        return "Project".to_string();
     }
+
+    let save_path = std::ffi::CStr::from_ptr(save_path).to_string_lossy();
+    let path = std::path::Path::new(save_path.as_ref());
+    path.file_name().map_or_else(
+        || save_path.to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn gui_notify_update(gui: *mut Gui) {
-    let gui = (*gui).inner.lock().unwrap();
-    if let Some(ctx) = &gui.ctx {
-        ctx.request_repaint();
-    }
+pub unsafe extern "C" fn gui_next_action(gui: *mut Gui) -> c_bindings::GuiAction {
+    catch_panic(Action::Close.into(), || {
+        let inner = (*gui).inner.lock().unwrap();
+        if let Some(Ok(v)) = inner.action_rx.as_ref().map(|rx| rx.try_recv()) {
+            (*gui).queued_actions.fetch_sub(1, Ordering::Relaxed);
+            return v.into();
+        }
+
+        if inner.ctx.is_some() {
+            Action::None.into()
+        } else {
+            Action::Close.into()
+        }
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn gui_close(gui: *mut Gui) {
-    let gui = (*gui).inner.lock().unwrap();
-    if let Some(ctx) = &gui.ctx {
-        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+pub unsafe extern "C" fn gui_next_actions(
+    gui: *mut Gui,
+    out: *mut c_bindings::GuiAction,
+    capacity: u64,
+) -> u64 {
+    if capacity == 0 {
+        return 0;
+    }
+
+    let out = std::slice::from_raw_parts_mut(out, capacity as usize);
+
+    let num_actions = catch_panic(None, || {
+        let inner = (*gui).inner.lock().unwrap();
+        let mut num_actions = 0;
+        if let Some(action_rx) = &inner.action_rx {
+            for slot in out.iter_mut() {
+                match action_rx.try_recv() {
+                    Ok(v) => *slot = v.into(),
+                    Err(_) => break,
+                }
+                num_actions += 1;
+            }
+        }
+        (*gui)
+            .queued_actions
+            .fetch_sub(num_actions as usize, Ordering::Relaxed);
+        Some(num_actions)
+    });
+
+    match num_actions {
+        Some(0) | None if gui_closed(gui) => {
+            out[0] = Action::Close.into();
+            1
+        }
+        Some(n) => n,
+        None => 0,
     }
 }
 
-struct SeekState {
-    paused_on_click: bool,
+#[no_mangle]
+pub unsafe extern "C" fn gui_set_action_callback(
+    gui: *mut Gui,
+    callback: c_bindings::GuiActionCallback,
+    userdata: *mut c_void,
+) -> c_bindings::GuiStatus {
+    catch_panic(c_bindings::GuiStatus_gui_status_error, || {
+        let Some(callback) = callback else {
+            return (*gui).set_error(
+                c_bindings::GuiStatus_gui_status_invalid_argument,
+                "Action callback cannot be null",
+            );
+        };
+
+        let Some(action_rx) = (*gui).inner.lock().unwrap().action_rx.take() else {
+            return (*gui).set_error(
+                c_bindings::GuiStatus_gui_status_invalid_argument,
+                "Action callback already registered",
+            );
+        };
+
+        let queued_actions = Arc::clone(&(*gui).queued_actions);
+        let dispatcher = ActionDispatcher::new(action_rx, queued_actions, callback, userdata);
+        *(*gui).dispatcher.lock().unwrap() = Some(dispatcher);
+        c_bindings::GuiStatus_gui_status_ok
+    })
 }
 
-impl SeekState {
-    fn should_toggle_pause(
-        &mut self,
-        response: &egui::Response,
-        state: &c_bindings::AppStateSnapshot,
-    ) -> bool {
-        if response.drag_started_by(egui::PointerButton::Primary) {
-            self.paused_on_click = state.paused;
-            if !state.paused {
-                return true;
-            }
+#[no_mangle]
+pub unsafe extern "C" fn gui_wait_start(gui: *mut Gui) -> c_bindings::GuiStatus {
+    catch_panic(c_bindings::GuiStatus_gui_status_error, || {
+        let mut inner = (*gui).inner.lock().unwrap();
+        while inner.ctx.is_none() && !inner.start_failed {
+            inner = (*gui).cond.wait(inner).unwrap();
         }
 
-        if response.drag_stopped_by(egui::PointerButton::Primary)
-            // You may think we should check the current state here, but that is untrue. When we
-            // execute a seek, we may not finish the seek before the next render frame in the UI.
-            // Because of this we may not see the applied pause yet. If we manage to check this
-            // condition before the pause is applied, we will not correctly unpause orselves.
-            //
-            // The failure condition here is if we somehow change the pause state while we are
-            // seeking (i.e. pressing spacebar), in this case we may end up with an extra toggle
-            // that we didn't want, but that's a fine tradeoff here
-            && !self.paused_on_click
-        {
-            return true;
+        if inner.start_failed {
+            c_bindings::GuiStatus_gui_status_error
+        } else {
+            c_bindings::GuiStatus_gui_status_ok
         }
+    })
+}
 
-        false
-    }
+#[no_mangle]
+pub unsafe extern "C" fn gui_cancel_start(gui: *mut Gui) {
+    catch_panic((), || {
+        let mut inner = (*gui).inner.lock().unwrap();
+        inner.start_failed = inner.ctx.is_none();
+        (*gui).cond.notify_all();
+    })
 }
 
-struct ClipTimelineRenderer<'a> {
-    converter: &'a ProgressPosConverter,
-    ui: &'a mut egui::Ui,
-    progress_bar: &'a mut ProgressBar,
-    state: &'a c_bindings::AppStateSnapshot,
-    action_tx: &'a mut ActionRequestor,
+#[no_mangle]
+pub unsafe extern "C" fn gui_last_error_message(
+    gui: *mut Gui,
+    buf: *mut c_char,
+    buf_len: u64,
+) -> u64 {
+    catch_panic(0, || {
+        // Copied out under the lock, a pointer into the message would dangle as soon as the next
+        // error replaces it
+        let last_error = (*gui)
+            .last_error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let msg = last_error.as_bytes();
+        if buf_len > 0 {
+            let len = msg.len().min(buf_len as usize - 1);
+            std::ptr::copy_nonoverlapping(msg.as_ptr() as *const c_char, buf, len);
+            *buf.add(len) = 0;
+        }
+        msg.len() as u64
+    })
 }
 
-impl ClipTimelineRenderer<'_> {
-    fn render_clip(&mut self, clip: &c_bindings::Clip, seek_state: &mut SeekState) {
-        let mut edited_clip = *clip;
+#[no_mangle]
+pub unsafe extern "C" fn gui_notify_update(gui: *mut Gui) {
+    gui_notify_update_kind(gui, c_bindings::GuiUpdateKind_gui_update_all);
+}
 
-        let mut changed = false;
+#[no_mangle]
+pub unsafe extern "C" fn gui_notify_update_kind(gui: *mut Gui, kind: c_bindings::GuiUpdateKind) {
+    catch_panic((), || {
+        let mut gui = (*gui).inner.lock().unwrap();
+        gui.updates |= kind;
+        if let Some(ctx) = &gui.ctx {
+            ctx.request_repaint();
+        }
+    })
+}
 
-        let sense = egui::Sense {
-            click: false,
-            drag: true,
-            focusable: false,
-        };
+#[no_mangle]
+pub unsafe extern "C" fn gui_close(gui: *mut Gui) {
+    catch_panic((), || {
+        // Only this project's tab goes, the others may still have work in them
+        (*gui).closed.store(true, Ordering::Relaxed);
+        let gui = (*gui).inner.lock().unwrap();
+        if let Some(ctx) = &gui.ctx {
+            ctx.request_repaint();
+        }
+    })
+}
 
-        let start_rect = self.converter.duration_to_full_rect(clip.start, 2.0);
-        let start_response = self.ui.allocate_rect(start_rect, sense);
-        if let Some(pos) = self.progress_bar.handle_seek(
-            self.converter,
-            &start_response,
-            self.state,
-            self.action_tx,
-            seek_state,
-        ) {
-            changed = true;
-            edited_clip.start = pos;
-        }
-
-        let end_rect = self.converter.duration_to_full_rect(clip.end, 2.0);
-        let end_response = self.ui.allocate_rect(end_rect, sense);
-        if let Some(pos) = self.progress_bar.handle_seek(
-            self.converter,
-            &end_response,
-            self.state,
-            self.action_tx,
-            seek_state,
-        ) {
-            changed = true;
-            println!("end pos: {pos}");
-            edited_clip.end = pos;
-        }
-
-        let mut clip_rect = self.converter.rect;
-        clip_rect.set_left(self.converter.duration_to_rect_pos(clip.start));
-        clip_rect.set_right(self.converter.duration_to_rect_pos(clip.end));
-
-        let stroke = egui::Stroke {
-            width: 2.0,
-            color: egui::Color32::RED,
+#[no_mangle]
+pub unsafe extern "C" fn gui_push_event(gui: *mut Gui, event: *const c_bindings::GuiEvent) {
+    catch_panic((), || {
+        // Copy out of the backend's buffers before returning, they are only valid for this call
+        let event = match events::Event::from_c(&*event) {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("Dropping event from backend: {e:?}");
+                return;
+            }
         };
-        self.ui.painter().rect_stroke(clip_rect, 0.0, stroke);
-        let red = egui::Color32::RED;
-        let red_feint = egui::Color32::from_rgba_unmultiplied(red.r(), red.g(), red.b(), 20);
-        self.ui.painter().rect_filled(clip_rect, 0.0, red_feint);
 
-        if changed {
-            self.action_tx
-                .send(gui_actions::clip_edit(&edited_clip));
+        let mut gui = (*gui).inner.lock().unwrap();
+        gui.events.push(event);
+        if let Some(ctx) = &gui.ctx {
+            ctx.request_repaint();
         }
-    }
+    })
 }
 
-/// Conversions between "rect" space, which is the position in the window in pixels, and "audio"
-/// space, which is the normalized position in the un-zoomed audio widget.
-struct ProgressPosConverter {
-    zoom: f32,
-    widget_center_norm: f32,
-    rect: egui::Rect,
-    total_runtime: f32,
+/// Collects the actions requested during a frame and hands them to the backend in one go. Drags
+/// generate a seek/clip_edit every frame, and only the latest of those is interesting, so
+/// replaceable actions overwrite the pending one instead of queueing up behind it
+struct ActionRequestor {
+    action_tx: Sender<Action>,
+    queued_actions: Arc<AtomicUsize>,
+    scroll_to_pts: Option<f32>,
+    pending: Vec<Action>,
 }
 
-impl ProgressPosConverter {
-    fn duration_to_rect_pos(&self, duration_pos: f32) -> f32 {
-        let duration_pos_norm = duration_pos / self.total_runtime;
-        let duration_norm_adjusted =
-            (duration_pos_norm - self.widget_center_norm) * self.zoom + 0.5;
-        duration_norm_adjusted * self.rect.width() + self.rect.left()
-    }
-
-    fn duration_to_full_rect(&self, duration_pos: f32, width: f32) -> egui::Rect {
-        let progress_rect_cx = self.duration_to_rect_pos(duration_pos);
-        let mut progress_rect = self.rect;
-        progress_rect.set_width(width);
-        progress_rect.set_center(egui::pos2(progress_rect_cx, progress_rect.center().y));
-
-        progress_rect
+impl ActionRequestor {
+    fn new(action_tx: Sender<Action>, queued_actions: Arc<AtomicUsize>) -> ActionRequestor {
+        ActionRequestor {
+            action_tx,
+            queued_actions,
+            scroll_to_pts: None,
+            pending: Vec::new(),
+        }
     }
 
-    fn rect_to_duration_norm(&self, x_pos_rect: f32) -> f32 {
-        let rect_pos_norm = (x_pos_rect - self.rect.left()) / self.rect.width();
-        (rect_pos_norm - 0.5) / self.zoom + self.widget_center_norm
+    fn reset_state(&mut self) {
+        self.scroll_to_pts = None;
     }
 
-    fn rect_to_duration(&self, x_pos_rect: f32) -> f32 {
-        self.rect_to_duration_norm(x_pos_rect) * self.total_runtime
+    /// Actions that only matter for their latest value, a newer one of the same kind can take
+    /// their place
+    fn replaceable(action: &Action) -> bool {
+        matches!(
+            action,
+            Action::Seek(_) | Action::ClipEdit(_) | Action::SetSpeed(_)
+        )
     }
-}
-
-struct ProgressBar {
-    zoom: f32,
-    widget_center_norm: f32,
-    pending_clip: Option<c_bindings::Clip>,
-}
 
-impl ProgressBar {
-    fn handle_clip_creation(
-        &mut self,
-        converter: &ProgressPosConverter,
-        ui: &egui::Ui,
-        response: &egui::Response,
-        action_tx: &mut ActionRequestor,
-    ) {
-        let primary_down = response.dragged_by(egui::PointerButton::Primary);
-        let ctrl_down = ui.input(|i| i.modifiers.ctrl);
-
-        if let Some(pending_clip) = &mut self.pending_clip {
-            if response.drag_stopped_by(egui::PointerButton::Primary) {
-                action_tx.send(gui_actions::clip_add(pending_clip));
-                self.pending_clip = None;
-            } else {
-                let pos = response
-                    .interact_pointer_pos()
-                    .expect("Pointer should interact if dragging");
-                let duration_pos = converter.rect_to_duration(pos.x);
-                pending_clip.end = duration_pos;
+    fn replaces(action: &Action, pending: &Action) -> bool {
+        match (action, pending) {
+            (Action::ClipEdit(clip), Action::ClipEdit(pending_clip)) => pending_clip.id == clip.id,
+            _ => {
+                Self::replaceable(action)
+                    && std::mem::discriminant(action) == std::mem::discriminant(pending)
             }
-        } else if primary_down && ctrl_down {
-            let pos = response
-                .interact_pointer_pos()
-                .expect("Pointer should interact if dragging");
-            let duration_pos = converter.rect_to_duration(pos.x);
-            self.pending_clip = Some(c_bindings::Clip {
-                id: 0,
-                start: duration_pos,
-                end: duration_pos,
-            });
         }
     }
 
-    fn handle_seek(
-        &mut self,
-        converter: &ProgressPosConverter,
-        response: &egui::Response,
-        state: &c_bindings::AppStateSnapshot,
-        action_tx: &mut ActionRequestor,
-        seek_state: &mut SeekState,
-    ) -> Option<f32> {
-        let mut ret = None;
-
-        if response.dragged_by(egui::PointerButton::Primary) {
-            let pos = response
-                .interact_pointer_pos()
-                .expect("Pointer should interact if dragging");
-            let duration_pos = converter.rect_to_duration(pos.x.clamp(converter.rect.left(), converter.rect.right()));
-            println!("duration pos {duration_pos}");
-            action_tx.send(gui_actions::seek(duration_pos));
-            ret = Some(duration_pos);
+    fn send(&mut self, action: Action) {
+        if let Action::Seek(pos) = action {
+            self.scroll_to_pts = Some(pos);
         }
 
-        if seek_state.should_toggle_pause(response, state) {
-            action_tx.send(gui_actions::toggle_pause());
+        // Only the last one, replacing one further back would move the new action ahead of the
+        // ones sent after it
+        if let Some(pending) = self
+            .pending
+            .last_mut()
+            .filter(|pending| Self::replaces(&action, pending))
+        {
+            *pending = action;
+            return;
         }
 
-        ret
-    }
-
-    fn handle_pan(&mut self, ui: &egui::Ui, response: &egui::Response) {
-        if response.dragged_by(egui::PointerButton::Secondary) {
-            let x_delta = ui.input(|i| i.pointer.delta().x);
-            self.widget_center_norm -= x_delta / response.rect.width() / self.zoom;
-            self.widget_center_norm = self.widget_center_norm.clamp(0.0, 1.0);
-        }
+        self.pending.push(action);
     }
 
-    fn handle_zoom(
-        &mut self,
-        converter: &ProgressPosConverter,
-        ui: &egui::Ui,
-        response: &egui::Response,
-    ) {
-        if response.contains_pointer() {
-            // If for whatever reason we cannot find the pointer pos, just use the middle of the
-            // widget
-            let mut pointer_pos_audio = 0.5;
-            if let Some(pointer_pos) = ui.input(|i| i.pointer.latest_pos()) {
-                // NOTE: We want to zoom so that the mouse stays in the same spot. This means that the
-                // distance from the center to the pointer needs to stay the same
-                pointer_pos_audio = converter.rect_to_duration_norm(pointer_pos.x);
-            }
-
-            let old_zoom = self.zoom;
-            let scroll_delta = ui.input(|i| i.raw_scroll_delta.y);
-
-            // lol I don't know, it feels good to me
-            const SCROLL_FACTOR: f32 = 3.0;
-            self.zoom *= 1.001_f32.powf(scroll_delta * SCROLL_FACTOR);
-            self.zoom = self.zoom.max(1.0);
+    /// While the backend has not caught up with what was sent before, a trailing replaceable
+    /// action is held back so that the next one can still take its place. It goes out with a
+    /// later flush, once the backend is done
+    fn flush(&mut self) {
+        let backend_behind = self.queued_actions.load(Ordering::Relaxed) > 0;
+        let held = match self.pending.last() {
+            Some(last) if backend_behind && Self::replaceable(last) => self.pending.pop(),
+            _ => None,
+        };
 
-            // In order to zoom "at the mouse", we have to ensure that mouse position does not
-            // change in either audio space OR rect space.
-            // We can calculate how far the point moved from the center in audio space, and then
-            // just adjust to keep that at the same point in rect space
-            let dist_from_center = pointer_pos_audio - self.widget_center_norm;
-            let new_dist_from_center = old_zoom / self.zoom * dist_from_center;
-            self.widget_center_norm += dist_from_center - new_dist_from_center;
+        for action in self.pending.drain(..) {
+            // Count before sending so the backend can never see the action before we do
+            self.queued_actions.fetch_add(1, Ordering::Relaxed);
+            self.action_tx.send(action).unwrap();
         }
-    }
 
-    fn clamp_widget_center(&mut self) {
-        let min = 0.5 / self.zoom;
-        let max = 1.0 - min;
-        self.widget_center_norm = self.widget_center_norm.clamp(min, max);
+        self.pending.extend(held);
     }
 
-    fn handle_response(
-        &mut self,
-        converter: &ProgressPosConverter,
-        ui: &egui::Ui,
-        response: &egui::Response,
-        state: &c_bindings::AppStateSnapshot,
-        action_tx: &mut ActionRequestor,
-        seek_state: &mut SeekState,
-    ) {
-        self.handle_clip_creation(converter, ui, response, action_tx);
-        self.handle_seek(converter, response, state, action_tx, seek_state);
-        self.handle_pan(ui, response);
-        self.handle_zoom(converter, ui, response);
-        self.clamp_widget_center();
+    /// Whether the last flush held an action back, see flush
+    fn holding_back(&self) -> bool {
+        !self.pending.is_empty()
     }
+}
 
-    fn show(
-        &mut self,
-        ui: &mut egui::Ui,
-        state: &SnapshotHolder,
-        action_tx: &mut ActionRequestor,
-        audio_renderer: RendererPtr,
-        seek_state: &mut SeekState,
-        scroll_to_pos: Option<f32>,
-    ) {
-        ui.with_layout(egui::Layout::right_to_left(Default::default()), |ui| {
-            let response = ui.allocate_response(
-                egui::vec2(ui.available_width(), 60.0),
-                egui::Sense {
-                    click: false,
-                    drag: true,
-                    focusable: false,
-                },
-            );
-
-            let converter = ProgressPosConverter {
-                zoom: self.zoom,
-                widget_center_norm: self.widget_center_norm,
-                rect: response.rect,
-                total_runtime: state.total_runtime,
-            };
-
-            let rect = response.rect;
-            let zoom = self.zoom;
-            let center_norm = self.widget_center_norm;
-            let callback = egui::PaintCallback {
-                rect,
-                callback: std::sync::Arc::new(egui_glow::CallbackFn::new(move |_info, painter| {
-                    let audio_renderer = &audio_renderer;
-                    unsafe {
-                        let userdata: *const glow::Context = &**painter.gl();
-                        c_bindings::audiorenderer_render(
-                            audio_renderer.0,
-                            userdata as *mut c_void,
-                            zoom,
-                            center_norm,
-                        );
-                    }
-                })),
-            };
-            ui.painter().add(callback);
-
-            let pending_clip = self.pending_clip;
-            let mut clip_renderer = ClipTimelineRenderer {
-                converter: &converter,
-                ui,
-                progress_bar: self,
-                state,
-                action_tx,
-            };
-
-            for i in 0..state.num_clips {
-                let clip = unsafe { *state.clips.add(i as usize) };
-                clip_renderer.render_clip(&clip, seek_state);
-            }
+const MULTISAMPLING: u16 = 4;
+/// Box the timeline hover preview is fit into
+const TIMELINE_THUMBNAIL_SIZE: [u32; 2] = [160, 90];
 
-            if let Some(pending_clip) = pending_clip {
-                clip_renderer.render_clip(&pending_clip, seek_state)
-            }
+#[cfg(not(feature = "wgpu"))]
+fn init_renderers<B: Backend>(cc: &eframe::CreationContext<'_>, backend: &B) {
+    let gl = cc
+        .gl
+        .as_ref()
+        .expect("You need to run eframe with the glow backend");
 
-            let progress_rect = converter.duration_to_full_rect(state.current_position, 3.0);
-            ui.painter()
-                .rect_filled(progress_rect, 0.0, egui::Color32::YELLOW);
+    let mut recorder = CommandRecorder::Glow(gl);
+    backend.frame_renderer().init(&mut recorder);
+    backend.waveform_renderer().init(&mut recorder);
+}
 
-            self.handle_response(&converter, ui, &response, state, action_tx, seek_state);
+#[cfg(feature = "wgpu")]
+fn init_renderers<B: Backend>(cc: &eframe::CreationContext<'_>, backend: &B) {
+    use render::wgpu_callback::{Recorder, Target};
 
-            if let Some(scroll_to_pos) = scroll_to_pos {
-                let half_visible = 0.5 / self.zoom;
-                let min_visible = self.widget_center_norm - half_visible;
-                let max_visible = self.widget_center_norm + half_visible;
+    let render_state = cc
+        .wgpu_render_state
+        .as_ref()
+        .expect("You need to run eframe with the wgpu backend");
 
-                let scroll_pos_norm = scroll_to_pos / state.total_runtime;
-                if scroll_pos_norm < min_visible || scroll_pos_norm > max_visible {
-                    self.widget_center_norm = scroll_pos_norm;
-                }
-            }
-        });
-    }
+    let target = Target {
+        format: render_state.target_format,
+        sample_count: MULTISAMPLING.into(),
+    };
+    render_state
+        .renderer
+        .write()
+        .callback_resources
+        .insert(target);
+
+    let mut recorder = CommandRecorder::Wgpu(Recorder {
+        device: &render_state.device,
+        queue: &render_state.queue,
+        target,
+        bundles: &mut Vec::new(),
+    });
+    backend.frame_renderer().init(&mut recorder);
+    backend.waveform_renderer().init(&mut recorder);
 }
 
-struct SnapshotHolder {
-    app_state: *mut c_bindings::AppState,
-    snapshot: c_bindings::AppStateSnapshot,
+/// Panels that are about the window rather than a project, shown for whichever project is active
+struct WindowPanels {
+    color_menu: color::ColorMenu,
+    command_palette: command_palette::CommandPalette,
+    log_panel: logging::LogPanel,
+    midi_panel: midi::MidiPanel,
+    osc_panel: osc::OscPanel,
+    media_keys: media_keys::MediaKeys,
+    profiler: profiling::Profiler,
+    #[cfg(feature = "remote")]
+    remote_panel: remote::RemotePanel,
+    script_console: scripting::ScriptConsole,
 }
 
-impl SnapshotHolder {
-    fn new(app_state: *mut c_bindings::AppState) -> SnapshotHolder {
-        let snapshot = unsafe { c_bindings::appstate_snapshot(app_state) };
-        SnapshotHolder {
-            app_state,
-            snapshot,
+impl WindowPanels {
+    fn new() -> WindowPanels {
+        WindowPanels {
+            color_menu: color::ColorMenu::new(),
+            command_palette: command_palette::CommandPalette::new(),
+            log_panel: logging::LogPanel::new(),
+            midi_panel: midi::MidiPanel::new(),
+            osc_panel: osc::OscPanel::new(),
+            media_keys: media_keys::MediaKeys::new(),
+            profiler: profiling::Profiler::new(),
+            #[cfg(feature = "remote")]
+            remote_panel: remote::RemotePanel::new(),
+            script_console: scripting::ScriptConsole::new(),
         }
     }
 }
 
-impl std::ops::Deref for SnapshotHolder {
-    type Target = c_bindings::AppStateSnapshot;
-    fn deref(&self) -> &Self::Target {
-        &self.snapshot
-    }
-}
-
-impl Drop for SnapshotHolder {
-    fn drop(&mut self) {
-        unsafe { c_bindings::appstate_deinit(self.app_state, &self.snapshot) }
-    }
-}
-
-
-struct ActionRequestor {
-    action_tx: Sender<c_bindings::GuiAction>,
-    scroll_to_pts: Option<f32>,
-}
+/// Time between two frames of playback at its current speed
+fn playback_frame_interval(state: &c_bindings::AppStateSnapshot) -> Duration {
+    // Anything the video does not tell us is shown at 60 fps
+    const FALLBACK: f32 = 1.0 / 60.0;
 
-impl ActionRequestor {
-    fn reset_state(&mut self) {
-        self.scroll_to_pts = None;
-    }
-
-    fn send(&mut self, action: c_bindings::GuiAction) {
-        match action.tag {
-            c_bindings::GuiActionTag_gui_action_seek => unsafe {
-                self.scroll_to_pts = Some(action.data.seek_position);
-            }
-            _ => (),
-        }
-        self.action_tx.send(action).unwrap();
-    }
+    let speed = state.playback_speed.abs();
+    let interval = if state.frame_duration > 0.0 && speed > 0.0 {
+        state.frame_duration / speed
+    } else {
+        FALLBACK
+    };
+    Duration::from_secs_f32(interval)
 }
 
-struct EframeImpl {
-    frame_renderer: RendererPtr,
-    audio_renderer: RendererPtr,
-    wtm: RendererPtr,
+/// Everything shown for one project, i.e. one tab
+struct ProjectView<B: Backend> {
+    name: String,
+    /// As of the last frame the project was shown, to pause it when switching away
+    paused: bool,
+    /// Snapshot generation as of the last frame, work that only depends on the snapshot is
+    /// skipped while it stays the same
+    generation: Option<u64>,
+    backend: B,
     action_tx: ActionRequestor,
     gui: *mut Gui,
     progress_bar: ProgressBar,
     seek_state: SeekState,
+    jog_shuttle: jog_shuttle::JogShuttle,
+    transcript_layout: Option<TranscriptLayout>,
+    script_selection: script_edit::ScriptSelection,
+    jobs_panel: jobs::JobsPanel,
+    transcribe_dialog: transcribe::TranscribeDialog,
+    find_cut_dialog: find_cut::FindCutDialog,
+    caption_preview: captions::CaptionPreview,
+    caption_style_editor: captions::CaptionStyleEditor,
+    markers_dialog: markers::MarkersDialog,
+    history_panel: history::HistoryPanel,
+    sequence_panel: sequence::SequencePanel,
+    clip_properties: clip_properties::ClipPropertiesPanel,
+    export_dialog: export::ExportDialog,
+    go_to_timecode: timecode::GoToTimecode,
+    freeze_frame: freeze_frame::FreezeFrame,
+    trim_preview: trim_preview::TrimPreview,
+    file_watcher: file_watch::FileWatcher,
+    recovery_prompt: recovery::RecoveryPrompt,
+    debug_overlay: debug_overlay::DebugOverlay,
+    frame_stats: debug_overlay::FrameStats,
+    frame_gpu_timer: Arc<Mutex<GpuTimer>>,
+    thumbnails: ThumbnailCache,
 }
 
-impl EframeImpl {
+impl<B: Backend> ProjectView<B> {
     fn new(
         cc: &eframe::CreationContext<'_>,
-        frame_renderer: RendererPtr,
-        audio_renderer: RendererPtr,
-        wtm: RendererPtr,
+        backend: B,
         gui: *mut Gui,
-        action_tx: Sender<c_bindings::GuiAction>,
+        action_tx: Sender<Action>,
+        name: String,
     ) -> Self {
-        let gl = cc
-            .gl
-            .as_ref()
-            .expect("You need to run eframe with the glow backend");
+        init_renderers(cc, &backend);
 
-        unsafe {
-            let userdata: *const glow::Context = &**gl;
-            c_bindings::framerenderer_init_gl(frame_renderer.0, userdata as *mut c_void);
-            c_bindings::audiorenderer_init_gl(audio_renderer.0, userdata as *mut c_void);
-        }
         Self {
-            frame_renderer,
-            audio_renderer,
-            wtm,
-            action_tx: ActionRequestor {
-                action_tx,
-                scroll_to_pts: None,
-            },
+            name,
+            paused: true,
+            generation: None,
+            backend,
+            action_tx: ActionRequestor::new(action_tx, unsafe {
+                Arc::clone(&(*gui).queued_actions)
+            }),
             gui,
-            progress_bar: ProgressBar {
-                zoom: 1.0,
-                widget_center_norm: 0.5,
-                pending_clip: None,
-            },
-            seek_state: SeekState {
-                paused_on_click: false,
-            },
+            progress_bar: ProgressBar::new(),
+            seek_state: SeekState::new(),
+            jog_shuttle: jog_shuttle::JogShuttle::new(),
+            transcript_layout: None,
+            script_selection: script_edit::ScriptSelection::new(),
+            jobs_panel: jobs::JobsPanel::new(),
+            transcribe_dialog: transcribe::TranscribeDialog::new(),
+            find_cut_dialog: find_cut::FindCutDialog::new(),
+            caption_preview: captions::CaptionPreview::new(),
+            caption_style_editor: captions::CaptionStyleEditor::new(),
+            markers_dialog: markers::MarkersDialog::new(),
+            history_panel: history::HistoryPanel::new(),
+            sequence_panel: sequence::SequencePanel::new(),
+            clip_properties: clip_properties::ClipPropertiesPanel::new(),
+            export_dialog: export::ExportDialog::new(),
+            go_to_timecode: timecode::GoToTimecode::new(),
+            freeze_frame: freeze_frame::FreezeFrame::new(),
+            trim_preview: trim_preview::TrimPreview::new(),
+            file_watcher: file_watch::FileWatcher::new(),
+            recovery_prompt: recovery::RecoveryPrompt::new(),
+            debug_overlay: debug_overlay::DebugOverlay::new(),
+            frame_stats: Default::default(),
+            frame_gpu_timer: Default::default(),
+            thumbnails: ThumbnailCache::new(),
         }
     }
+
+    fn paint_frame(
+        &self,
+        ui: &egui::Ui,
+        rect: egui::Rect,
+        view: c_bindings::GuiFrameView,
+        color: c_bindings::GuiColorManagement,
+    ) {
+        let params = FrameParams {
+            width: rect.width(),
+            height: rect.height(),
+            view,
+            color,
+        };
+        let callback = render::paint_callback(
+            rect,
+            self.backend.frame_renderer(),
+            params,
+            Arc::clone(&self.frame_gpu_timer),
+        );
+        ui.painter().add(callback);
+    }
 }
 
-impl eframe::App for EframeImpl {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+impl<B: Backend> ProjectView<B> {
+    fn update_ui(&mut self, ctx: &egui::Context, window: &mut WindowPanels) {
+        profile_scope!("ProjectView::update");
+
+        let update_start = Instant::now();
         let scroll_to_pts = self.action_tx.scroll_to_pts;
         self.action_tx.reset_state();
 
         let mut frame = egui::Frame::central_panel(&ctx.style());
         frame.inner_margin = egui::Margin::same(0.0);
 
-        let state = unsafe { SnapshotHolder::new((*self.gui).state) };
+        let (updates, events) = unsafe {
+            let mut inner = (*self.gui).inner.lock().unwrap();
+            (
+                std::mem::take(&mut inner.updates),
+                std::mem::take(&mut inner.events),
+            )
+        };
+        for event in events {
+            match event {
+                events::Event::TranscriptionFinished | events::Event::TranscriptionFailed => {
+                    self.transcribe_dialog.handle_event(&event)
+                }
+                events::Event::ExportFinished { .. } | events::Event::ExportFailed { .. } => {
+                    self.progress_bar.drag_out.handle_event(&event);
+                    self.export_dialog.handle_event(&event)
+                }
+                _ => self.thumbnails.handle_event(ctx, event),
+            }
+        }
+        for text in self.progress_bar.drag_out.hand_off(ctx) {
+            self.export_dialog.notify(text);
+        }
+        let transcript_updated = updates & c_bindings::GuiUpdateKind_gui_update_transcript != 0;
+
+        let snapshot_start = Instant::now();
+        let state = self.backend.snapshot();
+        self.paused = state.paused;
+        let state_changed = self.generation != Some(state.generation);
+        self.generation = Some(state.generation);
+        self.frame_stats.snapshot_time = snapshot_start.elapsed();
+        self.frame_stats.decode_fps = state.decode_fps;
+        self.frame_stats.frame_cache_bytes = state.frame_cache_bytes;
+        self.frame_stats.audio_cache_bytes = state.audio_cache_bytes;
+        self.frame_stats.queued_actions =
+            unsafe { (*self.gui).queued_actions.load(Ordering::Relaxed) };
+        self.frame_stats.frame_gpu_time = self.frame_gpu_timer.lock().unwrap().last_elapsed();
+        self.frame_stats.audio_gpu_time =
+            self.progress_bar.gpu_timer.lock().unwrap().last_elapsed();
+
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                // Reflects what the backend is decoding at, not what was last requested
+                let preview_quality = PreviewQuality::from_c(state.preview_quality);
+                ui.label("preview:");
+                for quality in PreviewQuality::ALL {
+                    let selected = quality == preview_quality;
+                    if ui.selectable_label(selected, quality.label()).clicked() && !selected {
+                        self.action_tx.send(Action::SetPreviewQuality(quality));
+                    }
+                }
+
+                ui.separator();
+                let mut dual_preview = state.dual_preview;
+                if ui
+                    .checkbox(&mut dual_preview, "source/program")
+                    .on_hover_text("Show the raw source next to the edited program")
+                    .changed()
+                {
+                    self.action_tx.send(Action::SetDualPreview(dual_preview));
+                }
+
+                // The wgpu renderer does not manage color
+                if cfg!(not(feature = "wgpu")) {
+                    ui.separator();
+                    window.color_menu.show(ui);
+                }
+
+                self.export_dialog
+                    .show_progress(ui, &state, &mut self.action_tx);
+            });
+        });
 
         egui::TopBottomPanel::bottom("controls").show(ctx, |ui| {
             let button_text = if state.paused { "play" } else { "pause" };
 
             ui.horizontal(|ui| {
                 if ui.button(button_text).clicked() {
-                    self.action_tx
-                        .send(gui_actions::toggle_pause());
+                    self.action_tx.send(Action::TogglePause);
                 };
 
-                ui.label(format!(
-                    "{:.02}/{:.02}",
-                    state.current_position, state.total_runtime
-                ));
+                ui.add(
+                    egui::Label::new(format!(
+                        "{:.02}/{:.02}",
+                        state.current_position, state.total_runtime
+                    ))
+                    .sense(egui::Sense::click()),
+                )
+                .on_hover_text("Right click to copy or enter a timecode")
+                .context_menu(|ui| {
+                    if ui.button("Copy current timecode").clicked() {
+                        ui.output_mut(|o| o.copied_text = timecode::format(state.current_position));
+                        ui.close_menu();
+                    }
+                    if ui.button("Go to timecode…").clicked() {
+                        self.go_to_timecode.open = true;
+                        ui.close_menu();
+                    }
+                });
+
+                self.jog_shuttle.show(ui, &state, &mut self.action_tx);
+                self.progress_bar.snapping.show(ui);
+                self.progress_bar.show_zoom_presets(ui, &state);
 
                 ui.spacing_mut().slider_width = ui.available_width();
 
                 if ui.button("Delete clip").clicked() {
                     self.action_tx
-                        .send(gui_actions::clip_remove(state.current_position));
+                        .send(Action::ClipRemove(state.current_position));
+                }
+
+                if ui
+                    .button("Close gap")
+                    .on_hover_text(
+                        "Extend the clip before the gap nearest the playhead up to the next clip",
+                    )
+                    .clicked()
+                {
+                    let clips = transcript::clips(&state);
+                    if let Some(action) = gaps::close_gap_action(clips, state.current_position) {
+                        self.action_tx.send(action);
+                    }
+                }
+
+                self.freeze_frame.show(ui, &state, &mut self.action_tx);
+
+                let mute_region =
+                    mute::region_at(mute::mute_regions(&state), state.current_position);
+                if ui
+                    .add_enabled(mute_region.is_some(), egui::Button::new("Unmute"))
+                    .on_hover_text("Remove the mute region under the playhead")
+                    .on_disabled_hover_text("Alt+drag on the timeline to mute a section")
+                    .clicked()
+                {
+                    if let Some(region) = mute_region {
+                        self.action_tx.send(Action::MuteRegionRemove(region.id));
+                    }
+                }
+
+                if ui.button("Logs").clicked() {
+                    window.log_panel.open = !window.log_panel.open;
+                }
+
+                let jobs_label = match jobs::num_active(&state) {
+                    0 => "Jobs".to_string(),
+                    n => format!("Jobs ({n})"),
+                };
+                if ui.button(jobs_label).clicked() {
+                    self.jobs_panel.open = !self.jobs_panel.open;
+                }
+
+                if ui.button("Transcribe…").clicked() {
+                    self.transcribe_dialog.open = !self.transcribe_dialog.open;
+                }
+
+                if ui.button("Find & cut…").clicked() {
+                    self.find_cut_dialog.open = !self.find_cut_dialog.open;
+                }
+
+                ui.checkbox(&mut self.caption_preview.enabled, "Captions");
+                if ui.button("Caption style…").clicked() {
+                    self.caption_style_editor.open = !self.caption_style_editor.open;
+                }
+
+                if ui.button("Markers…").clicked() {
+                    self.markers_dialog.open = !self.markers_dialog.open;
+                }
+
+                if ui.button("Sequence").clicked() {
+                    self.sequence_panel.open = !self.sequence_panel.open;
+                }
+
+                if ui.button("Clip…").clicked() {
+                    self.clip_properties.open = !self.clip_properties.open;
+                }
+
+                if ui.button("Export…").clicked() {
+                    self.export_dialog.open = !self.export_dialog.open;
+                }
+
+                if ui.button("History").clicked() {
+                    self.history_panel.open = !self.history_panel.open;
+                }
+
+                if cfg!(feature = "midi") && ui.button("MIDI…").clicked() {
+                    window.midi_panel.open = !window.midi_panel.open;
+                }
+
+                if ui.button("OSC…").clicked() {
+                    window.osc_panel.open = !window.osc_panel.open;
+                }
+
+                #[cfg(feature = "remote")]
+                if ui.button("Remote…").clicked() {
+                    window.remote_panel.open = !window.remote_panel.open;
+                }
+
+                if cfg!(feature = "scripting") && ui.button("Scripts…").clicked() {
+                    window.script_console.open = !window.script_console.open;
+                }
+
+                if cfg!(feature = "profiling") && ui.button("Profiler").clicked() {
+                    window.profiler.open = !window.profiler.open;
                 }
             });
 
-            self.progress_bar.show(
+            let words = self
+                .transcript_layout
+                .as_ref()
+                .map_or(&[][..], |layout| &layout.words);
+            let hovered_time = self.progress_bar.show(
                 ui,
                 &state,
+                words,
                 &mut self.action_tx,
-                self.audio_renderer.clone(),
+                &self.backend,
                 &mut self.seek_state,
                 scroll_to_pts,
             );
+
+            self.trim_preview.show(
+                ctx,
+                self.progress_bar.trim_edge,
+                state.frame_duration,
+                &mut self.thumbnails,
+                &mut self.action_tx,
+            );
+
+            if let Some(hovered_time) = hovered_time {
+                // Snapped so that sweeping over the timeline does not request a thumbnail for
+                // every pixel
+                let key = ThumbnailKey::new(hovered_time.round(), TIMELINE_THUMBNAIL_SIZE);
+                if let Some(texture) = self.thumbnails.get(&mut self.action_tx, key) {
+                    egui::show_tooltip_at_pointer(ctx, egui::Id::new("timeline_thumbnail"), |ui| {
+                        ui.image(egui::load::SizedTexture::from_handle(&texture));
+                    });
+                }
+            }
         });
 
         egui::SidePanel::right("script").show(ctx, |ui| unsafe {
-            let s = std::slice::from_raw_parts(state.text as *const u8, state.text_len as usize);
-            let s = std::str::from_utf8_unchecked(s);
-
-            let mut font_id = ui.style().text_styles[&egui::TextStyle::Body].clone();
-            font_id.size = 20.0;
             let wrap_width = ui.available_width();
+            let needs_layout = match &self.transcript_layout {
+                Some(layout) => {
+                    transcript_updated
+                        || layout.wrap_width != wrap_width
+                        || (state_changed && layout.clips_changed(&state))
+                }
+                None => true,
+            };
 
-            let mut galleys = Vec::new();
-            // [ 5, 10, 15]
-            let mut last_idx = 0;
-            for i in 0..state.text_split_indices_len {
-                let i: usize = i.try_into().unwrap();
-                let text_idx: usize = (*state.text_split_indices.add(i)).try_into().unwrap();
-
-                let end_idx = text_idx.min(s.len());
-                let layout = egui::text::LayoutJob::simple(
-                    s[last_idx..end_idx].to_string(),
-                    font_id.clone(),
-                    ui.visuals().text_color(),
-                    wrap_width,
-                );
-
-                galleys.push((ui.painter().layout_job(layout), last_idx, end_idx));
-                last_idx = end_idx;
+            if transcript_updated {
+                // Text positions in the selection may not mean the same thing anymore
+                self.script_selection.clear();
+                self.find_cut_dialog.transcript_changed();
+                self.caption_preview.transcript_changed();
             }
 
-            let layout = egui::text::LayoutJob::simple(
-                s[last_idx.min(s.len())..s.len()].to_string(),
-                font_id.clone(),
-                ui.visuals().text_color(),
-                wrap_width,
-            );
+            if needs_layout {
+                let words = self.backend.wtm_get_words();
+                self.transcript_layout = Some(TranscriptLayout::new(ui, &state, words, wrap_width));
+            }
 
-            galleys.push((ui.painter().layout_job(layout), last_idx, s.len()));
+            let transcript_layout = self.transcript_layout.as_ref().unwrap();
+            let blocks = &transcript_layout.blocks;
+            let words = &transcript_layout.words;
+            let (shift_down, pointer_pos, primary_down) = ui.input(|i| {
+                (
+                    i.modifiers.shift,
+                    i.pointer.interact_pos(),
+                    i.pointer.primary_down(),
+                )
+            });
+            if !primary_down {
+                self.script_selection.finish();
+            }
 
             egui::ScrollArea::vertical()
                 .drag_to_scroll(false)
                 .show(ui, |ui| {
+                    let current_char_pos: Option<usize> = self
+                        .backend
+                        .wtm_get_char_pos(state.current_position)
+                        .map(|pos| pos.try_into().unwrap());
+
+                    let scroll_char_pos: Option<usize> = scroll_to_pts
+                        .and_then(|pts| self.backend.wtm_get_char_pos(pts))
+                        .map(|pos| pos.try_into().unwrap());
+
+                    for block in blocks {
+                        let galley = Arc::clone(&block.galley);
+                        let start_idx = block.start_idx;
+                        let end_idx = block.end_idx;
+
+                        if block.paragraph_start {
+                            ui.add_space(10.0);
+                        }
 
-                    let current_char_pos: Option<usize> = if self.wtm.0.is_null() {
-                        None
-                    } else {
-                        Some(c_bindings::wtm_get_char_pos(self.wtm.0, state.current_position).try_into().unwrap())
-                    };
-
-                    let scroll_char_pos: Option<usize> = if self.wtm.0.is_null() {
-                        None
-                    } else {
-                        scroll_to_pts.as_ref().map(|pts| {
-                            c_bindings::wtm_get_char_pos(self.wtm.0, *pts).try_into().unwrap()
-                        })
-                    };
-
-
-                    for (galley, start_idx, end_idx) in galleys {
-                        let response = ui.allocate_response(
-                            galley.rect.size(),
-                            egui::Sense {
-                                click: false,
-                                drag: true,
-                                focusable: false,
-                            },
-                        );
+                        let (gutter, response) = ui
+                            .horizontal_top(|ui| {
+                                ui.spacing_mut().item_spacing.x = 0.0;
+                                let gutter = ui.allocate_response(
+                                    egui::vec2(transcript::GUTTER_WIDTH, galley.rect.height()),
+                                    egui::Sense::click(),
+                                );
+                                let response = ui.allocate_response(
+                                    galley.rect.size(),
+                                    egui::Sense {
+                                        click: false,
+                                        drag: true,
+                                        focusable: false,
+                                    },
+                                );
+                                (gutter, response)
+                            })
+                            .inner;
+
+                        if let Some(start_time) = block.start_time {
+                            let color = if gutter.hovered() {
+                                ui.visuals().strong_text_color()
+                            } else {
+                                ui.visuals().weak_text_color()
+                            };
+                            ui.painter().text(
+                                gutter.rect.left_top() + egui::vec2(0.0, 4.0),
+                                egui::Align2::LEFT_TOP,
+                                transcript::format_timecode(start_time),
+                                egui::FontId::monospace(12.0),
+                                color,
+                            );
+
+                            if gutter.clicked() {
+                                self.action_tx.send(Action::Seek(start_time));
+                            }
+                            gutter.on_hover_cursor(egui::CursorIcon::PointingHand);
+                        }
 
                         if let Some(scroll_char_pos) = scroll_char_pos.as_ref() {
-                            if let Some(rect) = char_pos_to_text_pos(*scroll_char_pos, start_idx, end_idx, &galley, response.rect.left_top()) {
+                            if let Some(rect) = transcript::char_pos_to_text_pos(
+                                *scroll_char_pos,
+                                start_idx,
+                                end_idx,
+                                &galley,
+                                response.rect.left_top(),
+                            ) {
                                 ui.scroll_to_rect(rect, None);
                             }
                         }
 
                         if let Some(current_char_pos) = current_char_pos {
-                            if let Some(rect) = char_pos_to_text_pos(current_char_pos, start_idx, end_idx, &galley, response.rect.left_top()) {
+                            if let Some(rect) = transcript::char_pos_to_text_pos(
+                                current_char_pos,
+                                start_idx,
+                                end_idx,
+                                &galley,
+                                response.rect.left_top(),
+                            ) {
                                 ui.painter().rect_filled(rect, 0.0, egui::Color32::YELLOW);
                             }
                         }
 
+                        if let Some(selection) = self.script_selection.range.clone() {
+                            for rect in transcript::char_range_rects(
+                                selection,
+                                start_idx,
+                                &galley,
+                                response.rect.left_top(),
+                            ) {
+                                ui.painter()
+                                    .rect_filled(rect, 0.0, ui.visuals().selection.bg_fill);
+                            }
+                        }
+
                         ui.painter().galley(
                             egui::pos2(response.rect.left(), response.rect.top()),
                             Arc::clone(&galley),
@@ -761,57 +1148,97 @@ impl eframe::App for EframeImpl {
                         );
 
                         if self.seek_state.should_toggle_pause(&response, &state) {
-                            self.action_tx.send(gui_actions::toggle_pause());
+                            self.action_tx.send(Action::TogglePause);
                         }
 
-                        if response.dragged_by(egui::PointerButton::Primary) {
-                            let mut pixel_pos = response.interact_pointer_pos().unwrap();
-                            pixel_pos.y -= response.rect.top();
-                            pixel_pos.x -= response.rect.left();
-                            let mut row = 0;
-                            let mut col = 0;
-                            let mut char_pos = 0;
+                        let pointer_char_pos = pointer_pos.map(|pos| {
+                            transcript::pointer_to_char_pos(&galley, pos - response.rect.left_top())
+                                + start_idx
+                        });
 
-                            while row < galley.rows.len()
-                                && galley.rows[row].rect.bottom() < pixel_pos.y
-                            {
-                                char_pos += galley.rows[row].glyphs.len();
-                                row += 1;
+                        // Shift + drag selects words, possibly across blocks, a plain drag seeks
+                        if response.drag_started_by(egui::PointerButton::Primary) && shift_down {
+                            if let Some(char_pos) = pointer_char_pos {
+                                self.script_selection.start(char_pos, words);
                             }
-                            // I want B to be no larger then A
-                            // The maximum value of B is A
-                            // max(a, b)
-                            row = row.min(galley.rows.len() - 1);
-
-                            let glyphs = &galley.rows[row].glyphs;
-                            while col < glyphs.len()
-                                && glyphs[col].pos.x + glyphs[col].size.x < pixel_pos.x
-                            {
-                                char_pos += 1;
-                                col += 1;
+                        } else if self.script_selection.is_dragging() {
+                            let over_block = pointer_pos
+                                .is_some_and(|pos| response.rect.y_range().contains(pos.y));
+                            if let (true, Some(char_pos)) = (over_block, pointer_char_pos) {
+                                self.script_selection.update(char_pos, words);
+                            }
+                        } else if response.dragged_by(egui::PointerButton::Primary) {
+                            let pixel_pos =
+                                response.interact_pointer_pos().unwrap() - response.rect.left_top();
+                            let char_pos =
+                                transcript::pointer_to_char_pos(&galley, pixel_pos) + start_idx;
+
+                            if let Some(pts) = self.backend.wtm_get_time(char_pos as u64) {
+                                self.action_tx.send(Action::Seek(pts));
                             }
-
-                            char_pos += start_idx;
-
-                            let pts = c_bindings::wtm_get_time(self.wtm.0, char_pos as u64);
-                            self.action_tx.send(gui_actions::seek(pts));
                         }
-                        ui.allocate_space(egui::vec2(0.0, 10.0));
+                        ui.add_space(4.0);
                     }
                 });
+
+            if ctx.wants_keyboard_input() {
+                return;
+            }
+
+            let (delete_pressed, escape_pressed) = ui.input(|i| {
+                (
+                    i.key_pressed(egui::Key::Delete) || i.key_pressed(egui::Key::Backspace),
+                    i.key_pressed(egui::Key::Escape),
+                )
+            });
+
+            if escape_pressed {
+                self.script_selection.clear();
+            }
+
+            if delete_pressed {
+                let cut = self
+                    .script_selection
+                    .range
+                    .take()
+                    .and_then(|range| script_edit::selected_time_range(words, range));
+                if let Some(cut) = cut {
+                    let clips = transcript::clips(&state);
+                    for action in script_edit::cut_actions(clips, state.total_runtime, &[cut]) {
+                        self.action_tx.send(action);
+                    }
+                }
+            }
         });
 
         egui::CentralPanel::default().frame(frame).show(ctx, |ui| {
+            let typing = ctx.wants_keyboard_input();
+            let mut copy_timecode = false;
             ui.input(|input| {
                 for event in &input.events {
                     match event {
+                        // ctrl+c and ctrl+v when no text field has focus
+                        egui::Event::Copy if !typing => copy_timecode = true,
+                        egui::Event::Paste(text) if !typing => {
+                            if let Some(pts) = timecode::parse(text) {
+                                self.action_tx
+                                    .send(Action::Seek(pts.min(state.total_runtime)));
+                            }
+                        }
+                        egui::Event::Key {
+                            key: egui::Key::G,
+                            pressed: true,
+                            modifiers: egui::Modifiers { ctrl: true, .. },
+                            ..
+                        } => {
+                            self.go_to_timecode.open = true;
+                        }
                         egui::Event::Key {
                             key: egui::Key::Space,
                             pressed: true,
                             ..
                         } => {
-                            self.action_tx
-                                .send(gui_actions::toggle_pause());
+                            self.action_tx.send(Action::TogglePause);
                         }
                         egui::Event::Key {
                             key: egui::Key::S,
@@ -819,80 +1246,366 @@ impl eframe::App for EframeImpl {
                             modifiers: egui::Modifiers { ctrl: true, .. },
                             ..
                         } => {
-                            self.action_tx
-                                .send(gui_actions::save());
+                            self.file_watcher.saving();
+                            self.action_tx.send(Action::Save);
+                        }
+                        egui::Event::Key {
+                            key: egui::Key::ArrowRight,
+                            pressed: true,
+                            modifiers: egui::Modifiers { ctrl: true, .. },
+                            ..
+                        } if !typing => {
+                            let words = self.backend.wtm_get_words();
+                            if let Some(pts) =
+                                transcript::next_word_start(&words, state.current_position)
+                            {
+                                self.action_tx.send(Action::Seek(pts));
+                            }
+                        }
+                        egui::Event::Key {
+                            key: egui::Key::ArrowLeft,
+                            pressed: true,
+                            modifiers: egui::Modifiers { ctrl: true, .. },
+                            ..
+                        } if !typing => {
+                            let words = self.backend.wtm_get_words();
+                            if let Some(pts) =
+                                transcript::prev_word_start(&words, state.current_position)
+                            {
+                                self.action_tx.send(Action::Seek(pts));
+                            }
+                        }
+                        egui::Event::Key {
+                            key,
+                            pressed: true,
+                            repeat: false,
+                            modifiers,
+                            ..
+                        } if modifiers.is_none() && !typing => {
+                            if let Some(speed) = jog_shuttle::transport_speed(*key, &state) {
+                                self.action_tx.send(Action::SetSpeed(speed));
+                            } else if let Some(action) =
+                                self.progress_bar.in_out.handle_key(*key, &state)
+                            {
+                                self.action_tx.send(action);
+                            }
                         }
                         _ => (),
                     }
                 }
             });
 
-            let frame_renderer = self.frame_renderer.clone();
+            if copy_timecode {
+                ctx.output_mut(|o| o.copied_text = timecode::format(state.current_position));
+            }
 
             let rect = ui.max_rect();
-            let callback = egui::PaintCallback {
-                rect,
-                callback: std::sync::Arc::new(egui_glow::CallbackFn::new(move |_info, painter| {
-                    let frame_renderer = &frame_renderer;
-                    unsafe {
-                        let userdata: *const glow::Context = &**painter.gl();
-                        c_bindings::framerenderer_render(
-                            frame_renderer.0,
-                            rect.width(),
-                            rect.height(),
-                            userdata as *mut c_void,
-                        );
-                    }
-                })),
-            };
-            ui.painter().add(callback);
+            let color = window.color_menu.preferences.to_c();
+            if state.dual_preview {
+                let source_rect = rect.with_max_x(rect.center().x);
+                let program_rect = rect.with_min_x(rect.center().x);
+                self.paint_frame(
+                    ui,
+                    source_rect,
+                    c_bindings::GuiFrameView_gui_frame_view_source,
+                    color,
+                );
+                self.paint_frame(
+                    ui,
+                    program_rect,
+                    c_bindings::GuiFrameView_gui_frame_view_program,
+                    color,
+                );
+
+                for (rect, label) in [(source_rect, "Source"), (program_rect, "Program")] {
+                    ui.painter().text(
+                        rect.left_top() + egui::vec2(8.0, 8.0),
+                        egui::Align2::LEFT_TOP,
+                        label,
+                        egui::FontId::proportional(14.0),
+                        ui.visuals().strong_text_color(),
+                    );
+                }
+
+                // Captions are part of the edit
+                self.caption_preview
+                    .show(ui, program_rect, &state, &self.backend);
+            } else {
+                self.paint_frame(
+                    ui,
+                    rect,
+                    c_bindings::GuiFrameView_gui_frame_view_source,
+                    color,
+                );
+                self.caption_preview.show(ui, rect, &state, &self.backend);
+            }
         });
-    }
 
-    fn on_exit(&mut self, gl: Option<&glow::Context>) {
-        unsafe {
-            let gl = gl.unwrap();
-            let userdata: *const glow::Context = gl;
-            c_bindings::framerenderer_deinit_gl(self.frame_renderer.0, userdata as *mut c_void);
-            c_bindings::audiorenderer_deinit_gl(self.audio_renderer.0, userdata as *mut c_void);
-            (*self.gui).inner.lock().unwrap().ctx = None;
+        window.log_panel.show(ctx);
+        self.jobs_panel.show(ctx, &state, &mut self.action_tx);
+        self.transcribe_dialog
+            .show(ctx, &state, &mut self.action_tx);
+        let words = self
+            .transcript_layout
+            .as_ref()
+            .map_or(&[][..], |layout| &layout.words);
+        self.find_cut_dialog
+            .show(ctx, &state, words, &mut self.action_tx);
+        self.caption_style_editor
+            .show(ctx, &state, &mut self.action_tx);
+        self.markers_dialog.show(ctx, &state, &mut self.action_tx);
+        self.history_panel.show(ctx, &state, &mut self.action_tx);
+        self.sequence_panel.show(ctx, &state, &mut self.action_tx);
+        self.clip_properties.show(ctx, &state, &mut self.action_tx);
+        self.export_dialog.show(ctx, &state, &mut self.action_tx);
+        self.go_to_timecode.show(ctx, &state, &mut self.action_tx);
+        window.midi_panel.show(ctx, &state, &mut self.action_tx);
+        window.osc_panel.show(ctx, &state, &mut self.action_tx);
+        window.script_console.show(ctx, &state, &mut self.action_tx);
+        if let Some(command_palette::Command::RunScript(path)) = window.command_palette.show(ctx) {
+            window
+                .script_console
+                .run_saved(&path, &state, &mut self.action_tx);
         }
+        #[cfg(feature = "remote")]
+        window.remote_panel.show(ctx, &state, &mut self.action_tx);
+        window.media_keys.update(ctx, &state, &mut self.action_tx);
+        self.file_watcher
+            .show(ctx, &state, &mut self.action_tx, &mut self.thumbnails);
+        self.recovery_prompt.show(ctx, &state, &mut self.action_tx);
+
+        self.debug_overlay.handle_input(ctx);
+        self.debug_overlay
+            .show(ctx, &self.frame_stats, &mut self.action_tx);
+        window.profiler.show(ctx);
+
+        // A new generation is a new frame to show, which the backend wakes us up for. While
+        // playing the next one is due a frame from now either way, so that is when we look again
+        // rather than whenever the next input or notification comes in
+        if !state.paused {
+            ctx.request_repaint_after(playback_frame_interval(&state));
+        }
+
+        self.thumbnails.end_frame();
+        self.action_tx.flush();
+        if self.action_tx.holding_back() {
+            ctx.request_repaint();
+        }
+        self.frame_stats.update_time = update_start.elapsed();
     }
 }
 
+/// The window, with a tab per project. Only the active project is drawn, the others keep running
+/// in the background
+struct EframeImpl<B: Backend> {
+    projects: Vec<ProjectView<B>>,
+    active: usize,
+    window: WindowPanels,
+    close_prompt: close_prompt::ClosePrompt,
+}
 
-fn char_pos_to_text_pos(pos: usize, galley_start_char: usize, galley_end_char: usize, galley: &egui::Galley, galley_tl: egui::Pos2) -> Option<egui::Rect> {
-    if pos >= galley_start_char && pos < galley_end_char {
-        let galley_char_pos = pos - galley_start_char;
+impl<B: Backend> EframeImpl<B> {
+    fn new(projects: Vec<ProjectView<B>>) -> Self {
+        Self {
+            projects,
+            active: 0,
+            window: WindowPanels::new(),
+            close_prompt: close_prompt::ClosePrompt::new(),
+        }
+    }
 
-        let mut acc: usize = 0;
-        let mut row = 0;
+    fn prompt_before_close(&mut self, ctx: &egui::Context) {
+        let forced = self
+            .projects
+            .iter()
+            .any(|project| unsafe { (*project.gui).panicked.load(Ordering::Relaxed) });
+        let projects = &self.projects;
+        let answer = self.close_prompt.show(ctx, forced, || {
+            projects
+                .iter()
+                .filter(|project| project.backend.snapshot().unsaved_changes)
+                .map(|project| project.name.as_str())
+                .collect()
+        });
+        if let Some(close_prompt::Answer::Save) = answer {
+            for project in &mut self.projects {
+                if project.backend.snapshot().unsaved_changes {
+                    project.file_watcher.saving();
+                    project.action_tx.send(Action::Save);
+                    project.action_tx.flush();
+                }
+            }
+        }
+    }
 
-        loop {
-            if row >= galley.rows.len() {
-                panic!("Row index out of bounds, this should never happen");
+    /// Drops the tabs of projects whose backend exited. The window only goes with the last one
+    fn drop_closed_projects(&mut self, ctx: &egui::Context, gl: Option<&glow::Context>) {
+        let mut i = 0;
+        while i < self.projects.len() {
+            if !unsafe { (*self.projects[i].gui).closed.load(Ordering::Relaxed) } {
+                i += 1;
+                continue;
             }
-            let row_len = galley.rows[row].glyphs.len();
 
-            if acc + row_len >= galley_char_pos {
-                break;
+            let mut project = self.projects.remove(i);
+            log::info!("Closing {}, its backend exited", project.name);
+            project.on_exit(gl);
+            if i < self.active || self.active >= self.projects.len() {
+                self.active = self.active.saturating_sub(1);
             }
+        }
+
+        if self.projects.is_empty() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+
+    fn show_tabs(&mut self, ctx: &egui::Context) {
+        if self.projects.len() < 2 {
+            return;
+        }
+
+        let mut selected = self.active;
+        egui::TopBottomPanel::top("project_tabs").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for (i, project) in self.projects.iter().enumerate() {
+                    if ui.selectable_label(i == selected, &project.name).clicked() {
+                        selected = i;
+                    }
+                }
+            });
+        });
 
-            acc += row_len;
-            row += 1;
+        if selected != self.active {
+            // Two projects playing over each other is never what anyone wants
+            let previous = &mut self.projects[self.active];
+            if !previous.paused {
+                previous.action_tx.send(Action::TogglePause);
+                previous.action_tx.flush();
+            }
+            self.active = selected;
         }
+    }
+}
+
+impl<B: Backend> eframe::App for EframeImpl<B> {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let cpu_usage = frame.info().cpu_usage;
+        let gl = frame.gl().cloned();
+
+        if catch_panic(false, || {
+            self.drop_closed_projects(ctx, gl.as_deref());
+            if self.projects.is_empty() {
+                return true;
+            }
 
-        let col = (galley_char_pos - acc).min(galley.rows[row].glyphs.len() - 1);
-        let glyph = galley.rows[row].glyphs[col];
+            self.window.profiler.new_frame();
+            self.show_tabs(ctx);
+            self.prompt_before_close(ctx);
 
-        let left = glyph.pos.x + galley_tl.x;
-        let bottom = glyph.pos.y + galley_tl.y + 5.0;
-        let top = bottom - 20.0;
-        let right = left + 3.0;
+            let project = &mut self.projects[self.active];
+            project.frame_stats.cpu_usage = cpu_usage;
+            project.update_ui(ctx, &mut self.window);
+            true
+        }) {
+            return;
+        }
 
-        let cursor_rect = egui::Rect::from_min_max(egui::pos2(left, top), egui::pos2(right, bottom));
-        return Some(cursor_rect);
+        // Whatever state we were in is no longer trustworthy, get out and let the backends know
+        // why
+        for project in &mut self.projects {
+            unsafe {
+                (*project.gui).panicked.store(true, Ordering::Relaxed);
+            }
+            project.action_tx.pending.clear();
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
     }
 
-    None
+    fn on_exit(&mut self, gl: Option<&glow::Context>) {
+        for project in &mut self.projects {
+            project.on_exit(gl);
+        }
+    }
+}
+
+impl<B: Backend> ProjectView<B> {
+    fn on_exit(&mut self, gl: Option<&glow::Context>) {
+        // Pollers notice that the context is gone, but callback users need to be told explicitly
+        self.action_tx.send(Action::Close);
+        self.action_tx.flush();
+
+        // eframe does not hand out the wgpu device here, wgpu resources just get dropped with the
+        // renderers
+        if let Some(gl) = gl {
+            self.frame_gpu_timer.lock().unwrap().deinit(gl);
+            self.progress_bar.gpu_timer.lock().unwrap().deinit(gl);
+            let mut recorder = CommandRecorder::Glow(gl);
+            self.backend.frame_renderer().deinit(&mut recorder);
+            self.backend.waveform_renderer().deinit(&mut recorder);
+        }
+
+        unsafe {
+            (*self.gui).inner.lock().unwrap().ctx = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn requestor() -> (ActionRequestor, Receiver<Action>, Arc<AtomicUsize>) {
+        let (action_tx, action_rx) = mpsc::channel();
+        let queued_actions = Arc::new(AtomicUsize::new(0));
+        let requestor = ActionRequestor::new(action_tx, Arc::clone(&queued_actions));
+        (requestor, action_rx, queued_actions)
+    }
+
+    #[test]
+    fn only_the_last_pending_action_is_replaced() {
+        let (mut action_tx, _action_rx, _) = requestor();
+        action_tx.send(Action::Seek(1.0));
+        action_tx.send(Action::TogglePause);
+        action_tx.send(Action::Seek(2.0));
+        action_tx.send(Action::Seek(3.0));
+
+        match action_tx.pending.as_slice() {
+            [Action::Seek(a), Action::TogglePause, Action::Seek(b)] => {
+                assert_eq!(*a, 1.0);
+                assert_eq!(*b, 3.0);
+            }
+            other => panic!("unexpected actions: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn seeks_wait_for_the_backend_to_catch_up() {
+        let (mut action_tx, action_rx, queued_actions) = requestor();
+        action_tx.send(Action::Seek(1.0));
+        action_tx.flush();
+        assert!(!action_tx.holding_back());
+
+        // The first seek has not been picked up yet
+        action_tx.send(Action::TogglePause);
+        action_tx.send(Action::Seek(2.0));
+        action_tx.flush();
+        assert!(action_tx.holding_back());
+        action_tx.send(Action::Seek(3.0));
+        action_tx.flush();
+        assert!(action_tx.holding_back());
+
+        queued_actions.store(0, Ordering::Relaxed);
+        action_tx.flush();
+        assert!(!action_tx.holding_back());
+
+        let sent: Vec<_> = action_rx.try_iter().collect();
+        match sent.as_slice() {
+            [Action::Seek(a), Action::TogglePause, Action::Seek(b)] => {
+                assert_eq!(*a, 1.0);
+                assert_eq!(*b, 3.0);
+            }
+            other => panic!("unexpected actions: {other:?}"),
+        }
+    }
 }
@@ -0,0 +1,156 @@
+use eframe::egui;
+
+use std::{
+    collections::VecDeque,
+    ffi::{c_char, CStr},
+    sync::{Mutex, Once},
+};
+
+use crate::c_bindings;
+
+const MAX_ENTRIES: usize = 2000;
+
+struct LogEntry {
+    level: log::Level,
+    target: String,
+    msg: String,
+}
+
+/// Prints to stderr like we used to, but also keeps the most recent messages around so that they
+/// can be shown in the log panel
+struct Logger {
+    entries: Mutex<VecDeque<LogEntry>>,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        // egui and friends are very chatty at debug levels, only keep what's interesting to us
+        let is_ours = record.target() == BACKEND_TARGET || record.target().starts_with("gui");
+        if !is_ours && record.level() > log::Level::Warn {
+            return;
+        }
+
+        eprintln!("[{} {}] {}", record.level(), record.target(), record.args());
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(LogEntry {
+            level: record.level(),
+            target: record.target().to_string(),
+            msg: record.args().to_string(),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Logger = Logger {
+    entries: Mutex::new(VecDeque::new()),
+};
+
+const BACKEND_TARGET: &str = "backend";
+
+pub fn init() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(log::LevelFilter::Debug);
+        }
+    });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gui_log(level: c_bindings::GuiLogLevel, msg: *const c_char) {
+    crate::panic_guard::catch_panic((), || {
+        // The backend may log before the GUI exists
+        init();
+
+        let level = match level {
+            c_bindings::GuiLogLevel_gui_log_level_error => log::Level::Error,
+            c_bindings::GuiLogLevel_gui_log_level_warn => log::Level::Warn,
+            c_bindings::GuiLogLevel_gui_log_level_info => log::Level::Info,
+            _ => log::Level::Debug,
+        };
+
+        let msg = CStr::from_ptr(msg).to_string_lossy();
+        log::log!(target: BACKEND_TARGET, level, "{msg}");
+    })
+}
+
+pub struct LogPanel {
+    pub open: bool,
+    max_level: log::Level,
+}
+
+impl LogPanel {
+    pub fn new() -> LogPanel {
+        LogPanel {
+            open: false,
+            max_level: log::Level::Info,
+        }
+    }
+
+    /// Copies out the entries we want to show. Anything drawn while holding the lock that happens
+    /// to log would deadlock
+    fn visible_lines(&self) -> Vec<(log::Level, String)> {
+        LOGGER
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.level <= self.max_level)
+            .map(|e| (e.level, format!("[{} {}] {}", e.level, e.target, e.msg)))
+            .collect()
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Logs")
+            .open(&mut open)
+            .default_size([600.0, 300.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Level")
+                        .selected_text(self.max_level.as_str())
+                        .show_ui(ui, |ui| {
+                            for level in log::Level::iter() {
+                                ui.selectable_value(&mut self.max_level, level, level.as_str());
+                            }
+                        });
+
+                    if ui.button("Copy").clicked() {
+                        let text = self
+                            .visible_lines()
+                            .into_iter()
+                            .map(|(_, line)| line)
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        ui.output_mut(|o| o.copied_text = text);
+                    }
+                });
+
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for (level, line) in self.visible_lines() {
+                            let color = match level {
+                                log::Level::Error => egui::Color32::RED,
+                                log::Level::Warn => egui::Color32::YELLOW,
+                                _ => ui.visuals().text_color(),
+                            };
+                            ui.colored_label(color, line);
+                        }
+                    });
+            });
+        self.open = open;
+    }
+}
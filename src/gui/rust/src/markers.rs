@@ -0,0 +1,177 @@
+use eframe::egui;
+
+use std::ffi::c_char;
+
+use crate::{
+    actions::{self, Action},
+    c_bindings, captions, transcript, ActionRequestor,
+};
+
+/// Same as the backend uses for imported markers without a color
+pub const DEFAULT_COLOR: u32 = 0xffd700ff;
+
+/// Lists the markers, adds new ones at the playhead and moves them in and out of CSV files, e.g.
+/// hotkey logs from recording or chapter lists for other tools
+pub struct MarkersDialog {
+    pub open: bool,
+    name: String,
+    color: u32,
+    import_path: String,
+}
+
+impl MarkersDialog {
+    pub fn new() -> MarkersDialog {
+        MarkersDialog {
+            open: false,
+            name: String::new(),
+            color: DEFAULT_COLOR,
+            import_path: String::new(),
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let markers = markers(state);
+
+        let mut open = self.open;
+        egui::Window::new("Markers")
+            .open(&mut open)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let default_name = format!("Marker {}", markers.len() + 1);
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.name)
+                            .hint_text(&default_name)
+                            .desired_width(150.0),
+                    );
+                    captions::color_edit(ui, &mut self.color);
+
+                    if ui.button("Add at playhead").clicked() {
+                        let name = match self.name.trim() {
+                            "" => default_name.as_str(),
+                            name => name,
+                        };
+                        action_tx.send(Action::MarkerAdd(c_bindings::GuiMarker {
+                            id: 0,
+                            time: state.current_position,
+                            color: self.color,
+                            name: name_to_c(name),
+                        }));
+                        self.name.clear();
+                    }
+                });
+
+                egui::ScrollArea::vertical()
+                    .max_height(250.0)
+                    .show(ui, |ui| {
+                        for marker in markers {
+                            ui.horizontal(|ui| {
+                                let (swatch, _) = ui.allocate_exact_size(
+                                    egui::vec2(10.0, 10.0),
+                                    egui::Sense::hover(),
+                                );
+                                let color = captions::color_from_c(marker.color);
+                                ui.painter().rect_filled(swatch, 2.0, color);
+
+                                if ui.link(transcript::format_timecode(marker.time)).clicked() {
+                                    action_tx.send(Action::Seek(marker.time));
+                                }
+                                ui.label(marker_name(marker));
+
+                                if ui.button("Remove").clicked() {
+                                    action_tx.send(Action::MarkerRemove(marker.id));
+                                }
+                            });
+                        }
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.import_path).hint_text("path to .csv"),
+                    );
+
+                    let request = actions::import_request(self.import_path.trim());
+                    let import = ui.add_enabled(request.is_some(), egui::Button::new("Import"));
+                    if import.clicked() {
+                        if let Some(request) = request {
+                            action_tx.send(Action::ImportMarkers(request));
+                        }
+                    }
+                });
+                ui.label("Columns are time, name and color, e.g. 1:23.5,Intro,#ff0000");
+
+                if ui.button("Export CSV").clicked() {
+                    action_tx.send(Action::ExportMarkers);
+                }
+            });
+        self.open = open;
+    }
+}
+
+pub fn markers(state: &c_bindings::AppStateSnapshot) -> &[c_bindings::GuiMarker] {
+    if state.num_markers == 0 {
+        // markers may be null
+        return &[];
+    }
+
+    unsafe { std::slice::from_raw_parts(state.markers, state.num_markers as usize) }
+}
+
+pub fn marker_name(marker: &c_bindings::GuiMarker) -> String {
+    let bytes: Vec<u8> = marker
+        .name
+        .iter()
+        .take_while(|c| **c != 0)
+        .map(|c| *c as u8)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Truncates on a char boundary so that the terminator always fits
+pub fn name_to_c(name: &str) -> [c_char; 64] {
+    let mut ret = [0; 64];
+
+    let mut len = name.len().min(ret.len() - 1);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+
+    for (dst, src) in ret.iter_mut().zip(name[..len].bytes()) {
+        *dst = src as c_char;
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn marker(name: &str) -> c_bindings::GuiMarker {
+        c_bindings::GuiMarker {
+            id: 1,
+            time: 0.0,
+            color: DEFAULT_COLOR,
+            name: name_to_c(name),
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        assert_eq!(marker_name(&marker("Intro")), "Intro");
+        assert_eq!(marker_name(&marker("")), "");
+    }
+
+    #[test]
+    fn long_names_are_truncated_on_char_boundaries() {
+        let name = "é".repeat(40);
+        let c_name = name_to_c(&name);
+        assert_eq!(c_name[63], 0);
+        assert_eq!(marker_name(&marker(&name)), "é".repeat(31));
+    }
+}
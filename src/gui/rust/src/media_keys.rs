@@ -0,0 +1,372 @@
+use eframe::egui;
+
+use std::sync::mpsc::{self, Receiver};
+
+use crate::{actions::Action, c_bindings, transcript};
+
+/// Seek step for media keys that do not say how far to go
+const SEEK_STEP_S: f32 = 5.0;
+/// Previous goes to the start of the current clip unless we are this close to it already, like
+/// the previous track button of a music player
+const RESTART_THRESHOLD_S: f32 = 1.0;
+/// Position drift from what the OS would have extrapolated before it is told again
+const POSITION_SYNC_THRESHOLD_S: f32 = 1.0;
+
+/// OS agnostic media key presses
+#[cfg_attr(not(feature = "media_keys"), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MediaKey {
+    Play,
+    Pause,
+    Toggle,
+    Stop,
+    Next,
+    Previous,
+    /// Relative seek in seconds, None when the OS leaves the distance up to us
+    SeekBy(Option<f32>),
+    SetPosition(f32),
+}
+
+/// Playback as it was last reported to the OS
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ReportedPlayback {
+    paused: bool,
+    position: f32,
+    speed: f32,
+    /// egui time of the report
+    time: f64,
+}
+
+/// Takes the system Play/Pause/Next/Previous keys (MPRIS on Linux, the media transport controls on
+/// Windows and the now playing center on macOS), so that playback can be controlled while another
+/// window has focus
+pub struct MediaKeys {
+    keys: Receiver<MediaKey>,
+    reported: Option<ReportedPlayback>,
+    #[cfg(feature = "media_keys")]
+    controls: Option<os::OsControls>,
+}
+
+impl MediaKeys {
+    pub fn new() -> MediaKeys {
+        let (tx, keys) = mpsc::channel();
+        #[cfg(not(feature = "media_keys"))]
+        drop(tx);
+
+        MediaKeys {
+            keys,
+            reported: None,
+            #[cfg(feature = "media_keys")]
+            controls: os::OsControls::new(tx)
+                .map_err(|e| log::warn!("Media keys are unavailable: {e}"))
+                .ok(),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut crate::ActionRequestor,
+    ) {
+        while let Ok(key) = self.keys.try_recv() {
+            if let Some(action) = key_action(key, state) {
+                action_tx.send(action);
+            }
+        }
+
+        let playback = ReportedPlayback {
+            paused: state.paused,
+            position: state.current_position,
+            speed: state.playback_speed,
+            time: ctx.input(|i| i.time),
+        };
+        if !needs_report(self.reported.as_ref(), &playback) {
+            return;
+        }
+        self.reported = Some(playback);
+
+        #[cfg(feature = "media_keys")]
+        if let Some(controls) = &mut self.controls {
+            controls.set_repaint_ctx(ctx);
+            controls.report(&playback);
+        }
+    }
+}
+
+/// The OS extrapolates the position while playing, so it only needs to hear about pauses, speed
+/// changes and seeks
+fn needs_report(reported: Option<&ReportedPlayback>, playback: &ReportedPlayback) -> bool {
+    let Some(reported) = reported else {
+        return true;
+    };
+
+    if reported.paused != playback.paused || reported.speed != playback.speed {
+        return true;
+    }
+
+    let mut expected = reported.position;
+    if !reported.paused {
+        expected += (playback.time - reported.time) as f32 * reported.speed;
+    }
+    (playback.position - expected).abs() > POSITION_SYNC_THRESHOLD_S
+}
+
+fn key_action(key: MediaKey, state: &c_bindings::AppStateSnapshot) -> Option<Action> {
+    let position = state.current_position;
+    match key {
+        MediaKey::Toggle => Some(Action::TogglePause),
+        MediaKey::Play if state.paused => Some(Action::TogglePause),
+        MediaKey::Pause | MediaKey::Stop if !state.paused => Some(Action::TogglePause),
+        MediaKey::Play | MediaKey::Pause | MediaKey::Stop => None,
+        MediaKey::Next => {
+            let next = transcript::clips(state)
+                .iter()
+                .map(|clip| clip.start)
+                .filter(|start| *start > position)
+                .fold(None, |acc: Option<f32>, start| {
+                    Some(acc.map_or(start, |acc| acc.min(start)))
+                })?;
+            Some(Action::Seek(next))
+        }
+        MediaKey::Previous => {
+            let previous = transcript::clips(state)
+                .iter()
+                .map(|clip| clip.start)
+                .filter(|start| *start < position - RESTART_THRESHOLD_S)
+                .fold(0.0, f32::max);
+            Some(Action::Seek(previous))
+        }
+        MediaKey::SeekBy(offset) => {
+            let target = position + offset.unwrap_or(SEEK_STEP_S);
+            Some(Action::Seek(target.clamp(0.0, state.total_runtime)))
+        }
+        MediaKey::SetPosition(pos) => Some(Action::Seek(pos.clamp(0.0, state.total_runtime))),
+    }
+}
+
+#[cfg(feature = "media_keys")]
+mod os {
+    use eframe::egui;
+    use souvlaki::{
+        MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition,
+        PlatformConfig, SeekDirection,
+    };
+
+    use std::{
+        sync::{mpsc::Sender, Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::{MediaKey, ReportedPlayback};
+
+    pub struct OsControls {
+        controls: MediaControls,
+        repaint_ctx: Arc<Mutex<Option<egui::Context>>>,
+    }
+
+    impl OsControls {
+        pub fn new(tx: Sender<MediaKey>) -> Result<OsControls, String> {
+            let config = PlatformConfig {
+                dbus_name: "video_editor",
+                display_name: "Video editor",
+                // Windows wants the window handle, which eframe does not give out. Windows users
+                // get the keys while focused at best
+                hwnd: None,
+            };
+
+            let mut controls = MediaControls::new(config).map_err(|e| format!("{e:?}"))?;
+            let repaint_ctx: Arc<Mutex<Option<egui::Context>>> = Default::default();
+
+            let callback_ctx = Arc::clone(&repaint_ctx);
+            controls
+                .attach(move |event| {
+                    let Some(key) = media_key(event) else {
+                        return;
+                    };
+
+                    // Key presses have to be handled even when the window is not focused, which
+                    // is when nothing else would wake up the GUI
+                    if tx.send(key).is_ok() {
+                        if let Some(ctx) = &*callback_ctx.lock().unwrap() {
+                            ctx.request_repaint();
+                        }
+                    }
+                })
+                .map_err(|e| format!("{e:?}"))?;
+
+            let metadata = MediaMetadata {
+                title: Some("Video editor"),
+                ..Default::default()
+            };
+            if let Err(e) = controls.set_metadata(metadata) {
+                log::warn!("Failed to set media metadata: {e:?}");
+            }
+
+            Ok(OsControls {
+                controls,
+                repaint_ctx,
+            })
+        }
+
+        pub fn set_repaint_ctx(&mut self, ctx: &egui::Context) {
+            let mut repaint_ctx = self.repaint_ctx.lock().unwrap();
+            if repaint_ctx.is_none() {
+                *repaint_ctx = Some(ctx.clone());
+            }
+        }
+
+        pub fn report(&mut self, playback: &ReportedPlayback) {
+            let progress = Some(MediaPosition(Duration::from_secs_f32(
+                playback.position.max(0.0),
+            )));
+            let media_playback = if playback.paused {
+                MediaPlayback::Paused { progress }
+            } else {
+                MediaPlayback::Playing { progress }
+            };
+
+            if let Err(e) = self.controls.set_playback(media_playback) {
+                log::debug!("Failed to report playback state: {e:?}");
+            }
+        }
+    }
+
+    fn media_key(event: MediaControlEvent) -> Option<MediaKey> {
+        let ret = match event {
+            MediaControlEvent::Play => MediaKey::Play,
+            MediaControlEvent::Pause => MediaKey::Pause,
+            MediaControlEvent::Toggle => MediaKey::Toggle,
+            MediaControlEvent::Stop => MediaKey::Stop,
+            MediaControlEvent::Next => MediaKey::Next,
+            MediaControlEvent::Previous => MediaKey::Previous,
+            MediaControlEvent::Seek(direction) => MediaKey::SeekBy(match direction {
+                SeekDirection::Forward => None,
+                SeekDirection::Backward => Some(-super::SEEK_STEP_S),
+            }),
+            MediaControlEvent::SeekBy(direction, amount) => {
+                let amount = amount.as_secs_f32();
+                MediaKey::SeekBy(Some(match direction {
+                    SeekDirection::Forward => amount,
+                    SeekDirection::Backward => -amount,
+                }))
+            }
+            MediaControlEvent::SetPosition(MediaPosition(pos)) => {
+                MediaKey::SetPosition(pos.as_secs_f32())
+            }
+            _ => return None,
+        };
+        Some(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn state(paused: bool, clips: &[c_bindings::Clip]) -> c_bindings::AppStateSnapshot {
+        let mut state: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+        state.paused = paused;
+        state.clips = clips.as_ptr();
+        state.num_clips = clips.len() as u64;
+        state.current_position = 10.0;
+        state.total_runtime = 100.0;
+        state.playback_speed = 1.0;
+        state
+    }
+
+    const CLIPS: [c_bindings::Clip; 3] = [
+        c_bindings::Clip {
+            id: 1,
+            start: 2.0,
+            end: 5.0,
+            speed: 1.0,
+            freeze: false,
+        },
+        c_bindings::Clip {
+            id: 2,
+            start: 9.5,
+            end: 20.0,
+            speed: 1.0,
+            freeze: false,
+        },
+        c_bindings::Clip {
+            id: 3,
+            start: 30.0,
+            end: 40.0,
+            speed: 1.0,
+            freeze: false,
+        },
+    ];
+
+    #[test]
+    fn play_and_pause_only_toggle_when_needed() {
+        let paused = state(true, &[]);
+        let playing = state(false, &[]);
+
+        assert!(matches!(
+            key_action(MediaKey::Play, &paused),
+            Some(Action::TogglePause)
+        ));
+        assert!(key_action(MediaKey::Play, &playing).is_none());
+        assert!(matches!(
+            key_action(MediaKey::Pause, &playing),
+            Some(Action::TogglePause)
+        ));
+        assert!(key_action(MediaKey::Stop, &paused).is_none());
+        assert!(matches!(
+            key_action(MediaKey::Toggle, &paused),
+            Some(Action::TogglePause)
+        ));
+    }
+
+    #[test]
+    fn next_and_previous_move_between_clips() {
+        let state = state(false, &CLIPS);
+
+        assert!(
+            matches!(key_action(MediaKey::Next, &state), Some(Action::Seek(pos)) if pos == 30.0)
+        );
+        // Only half a second into the second clip, so previous goes to the first
+        assert!(
+            matches!(key_action(MediaKey::Previous, &state), Some(Action::Seek(pos)) if pos == 2.0)
+        );
+    }
+
+    #[test]
+    fn seeks_stay_in_the_video() {
+        let state = state(false, &[]);
+
+        assert!(
+            matches!(key_action(MediaKey::SeekBy(None), &state), Some(Action::Seek(pos)) if pos == 15.0)
+        );
+        assert!(
+            matches!(key_action(MediaKey::SeekBy(Some(-20.0)), &state), Some(Action::Seek(pos)) if pos == 0.0)
+        );
+        assert!(
+            matches!(key_action(MediaKey::SetPosition(500.0), &state), Some(Action::Seek(pos)) if pos == 100.0)
+        );
+    }
+
+    #[test]
+    fn only_pauses_and_seeks_are_reported() {
+        let reported = ReportedPlayback {
+            paused: false,
+            position: 10.0,
+            speed: 1.0,
+            freeze: false,
+            time: 0.0,
+        };
+        let mut playback = reported;
+
+        playback.time = 2.0;
+        playback.position = 12.1;
+        assert!(!needs_report(Some(&reported), &playback));
+        playback.position = 20.0;
+        assert!(needs_report(Some(&reported), &playback));
+        playback.position = 12.0;
+        playback.paused = true;
+        assert!(needs_report(Some(&reported), &playback));
+        assert!(needs_report(None, &playback));
+    }
+}
@@ -0,0 +1,730 @@
+use eframe::egui;
+
+use std::{
+    fmt,
+    sync::mpsc::{self, Receiver},
+};
+
+use crate::{
+    actions::Action, c_bindings, config, jog_shuttle, markers, transcript, ActionRequestor,
+};
+
+/// How far one tick of a relative knob moves a clip edge, roughly a frame
+const NUDGE_S: f32 = 0.04;
+
+/// A knob, fader or button on a controller. Channels are 1 based as printed on hardware
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    Note { channel: u8, note: u8 },
+    Cc { channel: u8, number: u8 },
+}
+
+impl fmt::Display for Control {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Control::Note { channel, note } => write!(f, "Note {note} (ch {channel})"),
+            Control::Cc { channel, number } => write!(f, "CC {number} (ch {channel})"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Message {
+    pub control: Control,
+    pub value: u8,
+}
+
+/// What a control does once bound. Buttons fire on press, knobs either jump to an absolute
+/// position or, for endless encoders, move relative to where things are
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    TogglePause,
+    StepForward,
+    StepBackward,
+    /// Endless encoder or jog wheel, one frame per tick
+    Jog,
+    /// Fader or knob across the whole video
+    Seek,
+    /// Spring loaded ring, centered is stopped
+    Shuttle,
+    NudgeClipStart,
+    NudgeClipEnd,
+    DropMarker,
+}
+
+impl Command {
+    const ALL: [Command; 9] = [
+        Command::TogglePause,
+        Command::StepForward,
+        Command::StepBackward,
+        Command::Jog,
+        Command::Seek,
+        Command::Shuttle,
+        Command::NudgeClipStart,
+        Command::NudgeClipEnd,
+        Command::DropMarker,
+    ];
+
+    /// Name in the preferences file
+    fn name(&self) -> &'static str {
+        match self {
+            Command::TogglePause => "toggle_pause",
+            Command::StepForward => "step_forward",
+            Command::StepBackward => "step_backward",
+            Command::Jog => "jog",
+            Command::Seek => "seek",
+            Command::Shuttle => "shuttle",
+            Command::NudgeClipStart => "nudge_clip_start",
+            Command::NudgeClipEnd => "nudge_clip_end",
+            Command::DropMarker => "drop_marker",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Command::TogglePause => "Play/pause",
+            Command::StepForward => "Next frame",
+            Command::StepBackward => "Previous frame",
+            Command::Jog => "Jog (relative)",
+            Command::Seek => "Seek (fader)",
+            Command::Shuttle => "Shuttle",
+            Command::NudgeClipStart => "Nudge clip start (relative)",
+            Command::NudgeClipEnd => "Nudge clip end (relative)",
+            Command::DropMarker => "Drop marker",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Command> {
+        Command::ALL
+            .into_iter()
+            .find(|command| command.name() == name)
+    }
+}
+
+/// Bindings and the port to listen on, kept in a preferences file so that a controller only has to
+/// be set up once
+#[derive(Debug, Default, PartialEq)]
+pub struct MidiPreferences {
+    pub port: Option<String>,
+    pub bindings: Vec<(Control, Command)>,
+}
+
+impl MidiPreferences {
+    /// One entry per line, e.g. "cc 1 7 seek" or "port nanoKONTROL2"
+    pub fn parse(data: &str) -> MidiPreferences {
+        let mut ret = MidiPreferences::default();
+
+        for (line_num, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(port) = line.strip_prefix("port ") {
+                ret.port = Some(port.trim().to_string());
+                continue;
+            }
+
+            match parse_binding(line) {
+                Some(binding) => ret.bindings.push(binding),
+                None => log::warn!("Ignoring MIDI binding on line {}: {line}", line_num + 1),
+            }
+        }
+
+        ret
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut ret = String::new();
+        if let Some(port) = &self.port {
+            ret.push_str(&format!("port {port}\n"));
+        }
+
+        for (control, command) in &self.bindings {
+            let (kind, channel, number) = match control {
+                Control::Note { channel, note } => ("note", channel, note),
+                Control::Cc { channel, number } => ("cc", channel, number),
+            };
+            ret.push_str(&format!("{kind} {channel} {number} {}\n", command.name()));
+        }
+        ret
+    }
+
+    fn command(&self, control: Control) -> Option<Command> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == control)
+            .map(|(_, command)| *command)
+    }
+
+    /// A control only does one thing, so binding it takes it away from whatever it did before
+    fn bind(&mut self, control: Control, command: Command) {
+        self.bindings.retain(|(bound, _)| *bound != control);
+        self.bindings.push((control, command));
+    }
+
+    fn unbind(&mut self, command: Command) {
+        self.bindings.retain(|(_, bound)| *bound != command);
+    }
+}
+
+fn parse_binding(line: &str) -> Option<(Control, Command)> {
+    let mut it = line.split_whitespace();
+    let kind = it.next()?;
+    let channel = it
+        .next()?
+        .parse::<u8>()
+        .ok()
+        .filter(|c| (1..=16).contains(c))?;
+    let number = it.next()?.parse::<u8>().ok().filter(|n| *n < 128)?;
+    let command = Command::from_name(it.next()?)?;
+    if it.next().is_some() {
+        return None;
+    }
+
+    let control = match kind {
+        "note" => Control::Note {
+            channel,
+            note: number,
+        },
+        "cc" => Control::Cc { channel, number },
+        _ => return None,
+    };
+    Some((control, command))
+}
+
+/// Note ons and control changes, everything else (note offs, clock, sysex...) is of no use to us
+#[cfg_attr(not(feature = "midi"), allow(dead_code))]
+pub fn parse_message(bytes: &[u8]) -> Option<Message> {
+    let [status, number, value] = *bytes else {
+        return None;
+    };
+    let channel = (status & 0x0f) + 1;
+
+    match status & 0xf0 {
+        // Note on with a velocity of 0 is a note off
+        0x90 if value > 0 => Some(Message {
+            control: Control::Note {
+                channel,
+                note: number,
+            },
+            value,
+        }),
+        0xb0 => Some(Message {
+            control: Control::Cc { channel, number },
+            value,
+        }),
+        _ => None,
+    }
+}
+
+/// Ticks for an endless encoder in the common two's complement style, 1 is one step clockwise and
+/// 127 one step counter clockwise
+fn relative_delta(value: u8) -> i32 {
+    match value {
+        1..=63 => value as i32,
+        65..=127 => value as i32 - 128,
+        _ => 0,
+    }
+}
+
+/// The actions a control sends, the same ones the keyboard and mouse would
+fn command_action(
+    command: Command,
+    value: u8,
+    state: &c_bindings::AppStateSnapshot,
+) -> Option<Action> {
+    let pressed = value > 0;
+    match command {
+        Command::TogglePause if pressed => Some(Action::TogglePause),
+        Command::StepForward if pressed => Some(Action::StepFrames(1)),
+        Command::StepBackward if pressed => Some(Action::StepFrames(-1)),
+        Command::Jog => match relative_delta(value) {
+            0 => None,
+            frames => Some(Action::StepFrames(frames)),
+        },
+        Command::Seek => Some(Action::Seek(value as f32 / 127.0 * state.total_runtime)),
+        Command::Shuttle => {
+            let pos_norm = ((value as f32 - 64.0) / 63.0).clamp(-1.0, 1.0);
+            Some(Action::SetSpeed(jog_shuttle::shuttle_speed(pos_norm)))
+        }
+        Command::NudgeClipStart | Command::NudgeClipEnd => {
+            let delta = relative_delta(value) as f32 * NUDGE_S;
+            let position = state.current_position;
+            let mut clip = *transcript::clips(state)
+                .iter()
+                .find(|clip| clip.start <= position && position < clip.end)?;
+
+            if command == Command::NudgeClipStart {
+                clip.start = (clip.start + delta).clamp(0.0, clip.end);
+            } else {
+                clip.end = (clip.end + delta).clamp(clip.start, state.total_runtime);
+            }
+            Some(Action::ClipEdit(clip))
+        }
+        Command::DropMarker if pressed => {
+            let name = format!("Marker {}", markers::markers(state).len() + 1);
+            Some(Action::MarkerAdd(c_bindings::GuiMarker {
+                id: 0,
+                time: state.current_position,
+                color: markers::DEFAULT_COLOR,
+                name: markers::name_to_c(&name),
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Maps controller knobs and buttons to editor commands. Bindings are made by picking a command
+/// and then moving the control that should do it
+pub struct MidiPanel {
+    pub open: bool,
+    preferences: MidiPreferences,
+    /// Command waiting for the next control to be moved
+    learning: Option<Command>,
+    messages: Receiver<Message>,
+    #[cfg(feature = "midi")]
+    input: input::MidiInput,
+}
+
+impl MidiPanel {
+    pub fn new() -> MidiPanel {
+        let preferences = config::load_preferences("midi.conf")
+            .map(|data| MidiPreferences::parse(&data))
+            .unwrap_or_default();
+
+        let (tx, messages) = mpsc::channel();
+        #[cfg(not(feature = "midi"))]
+        drop(tx);
+
+        MidiPanel {
+            open: false,
+            #[cfg(feature = "midi")]
+            input: input::MidiInput::new(tx, preferences.port.as_deref()),
+            preferences,
+            learning: None,
+            messages,
+        }
+    }
+
+    /// Handles incoming messages every frame, whether the window is open or not
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        #[cfg(feature = "midi")]
+        self.input.set_repaint_ctx(ctx);
+
+        while let Ok(message) = self.messages.try_recv() {
+            self.handle_message(message, state, action_tx);
+        }
+
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("MIDI")
+            .open(&mut open)
+            .default_width(350.0)
+            .show(ctx, |ui| {
+                #[cfg(feature = "midi")]
+                if let Some(port) = self.input.show(ui) {
+                    self.preferences.port = Some(port);
+                    self.save();
+                }
+
+                ui.separator();
+                if let Some(command) = self.learning {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Move a control for \"{}\"", command.label()));
+                        if ui.button("Cancel").clicked() {
+                            self.learning = None;
+                        }
+                    });
+                }
+
+                let mut unbind = None;
+                egui::Grid::new("midi_bindings")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for command in Command::ALL {
+                            ui.label(command.label());
+
+                            let bound: Vec<String> = self
+                                .preferences
+                                .bindings
+                                .iter()
+                                .filter(|(_, bound)| *bound == command)
+                                .map(|(control, _)| control.to_string())
+                                .collect();
+                            if bound.is_empty() {
+                                ui.weak("Unbound");
+                            } else {
+                                ui.label(bound.join(", "));
+                            }
+
+                            let learning = self.learning == Some(command);
+                            if ui.selectable_label(learning, "Learn").clicked() {
+                                self.learning = if learning { None } else { Some(command) };
+                            }
+                            if ui.button("Clear").clicked() {
+                                unbind = Some(command);
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                if let Some(command) = unbind {
+                    self.preferences.unbind(command);
+                    self.save();
+                }
+            });
+        self.open = open;
+    }
+
+    fn handle_message(
+        &mut self,
+        message: Message,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        if let Some(command) = self.learning {
+            // Note ons always have a velocity, but a released CC button sends 0 and should not
+            // be what gets learned from a knob that was turned all the way down
+            if message.value == 0 {
+                return;
+            }
+
+            self.preferences.bind(message.control, command);
+            self.learning = None;
+            self.save();
+            return;
+        }
+
+        let Some(command) = self.preferences.command(message.control) else {
+            return;
+        };
+
+        if let Some(action) = command_action(command, message.value, state) {
+            action_tx.send(action);
+        }
+    }
+
+    fn save(&self) {
+        config::save_preferences("midi.conf", &self.preferences.serialize());
+    }
+}
+
+#[cfg(feature = "midi")]
+mod input {
+    use eframe::egui;
+
+    use std::sync::{mpsc::Sender, Arc, Mutex};
+
+    use super::{parse_message, Message};
+
+    const CLIENT_NAME: &str = "video-editor";
+
+    /// midir connection to one input port. Messages arrive on midir's thread and are passed on to
+    /// the GUI thread, which is woken up to handle them
+    pub struct MidiInput {
+        tx: Sender<Message>,
+        ports: Vec<String>,
+        selected: Option<String>,
+        connection: Option<midir::MidiInputConnection<()>>,
+        error: Option<String>,
+        repaint_ctx: Arc<Mutex<Option<egui::Context>>>,
+    }
+
+    impl MidiInput {
+        pub fn new(tx: Sender<Message>, port: Option<&str>) -> MidiInput {
+            let mut ret = MidiInput {
+                tx,
+                ports: Vec::new(),
+                selected: None,
+                connection: None,
+                error: None,
+                repaint_ctx: Default::default(),
+            };
+
+            ret.refresh_ports();
+            if let Some(port) = port {
+                ret.connect(port);
+            }
+            ret
+        }
+
+        pub fn set_repaint_ctx(&mut self, ctx: &egui::Context) {
+            let mut repaint_ctx = self.repaint_ctx.lock().unwrap();
+            if repaint_ctx.is_none() {
+                *repaint_ctx = Some(ctx.clone());
+            }
+        }
+
+        /// Returns the port name when a new port was connected to
+        pub fn show(&mut self, ui: &mut egui::Ui) -> Option<String> {
+            let mut ret = None;
+
+            ui.horizontal(|ui| {
+                let selected_text = self.selected.as_deref().unwrap_or("None");
+                let mut picked = None;
+                egui::ComboBox::from_label("Port")
+                    .selected_text(selected_text)
+                    .show_ui(ui, |ui| {
+                        for port in &self.ports {
+                            let selected = self.selected.as_ref() == Some(port);
+                            if ui.selectable_label(selected, port).clicked() {
+                                picked = Some(port.clone());
+                            }
+                        }
+                    });
+
+                if ui.button("Refresh").clicked() {
+                    self.refresh_ports();
+                }
+
+                if let Some(port) = picked {
+                    if self.connect(&port) {
+                        ret = Some(port);
+                    }
+                }
+            });
+
+            if let Some(error) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            } else if self.connection.is_none() {
+                ui.weak("Not connected");
+            }
+
+            ret
+        }
+
+        fn refresh_ports(&mut self) {
+            match midir::MidiInput::new(CLIENT_NAME) {
+                Ok(midi_in) => {
+                    self.ports = midi_in
+                        .ports()
+                        .iter()
+                        .filter_map(|port| midi_in.port_name(port).ok())
+                        .collect();
+                }
+                Err(e) => self.error = Some(format!("Failed to open MIDI input: {e}")),
+            }
+        }
+
+        fn connect(&mut self, name: &str) -> bool {
+            // Only one port at a time, the old connection has to go before a new client can be
+            // made
+            if let Some(connection) = self.connection.take() {
+                connection.close();
+            }
+            self.selected = Some(name.to_string());
+
+            match self.try_connect(name) {
+                Ok(connection) => {
+                    self.connection = Some(connection);
+                    self.error = None;
+                    true
+                }
+                Err(e) => {
+                    log::warn!("Failed to connect to MIDI port {name}: {e}");
+                    self.error = Some(e);
+                    false
+                }
+            }
+        }
+
+        fn try_connect(&self, name: &str) -> Result<midir::MidiInputConnection<()>, String> {
+            let midi_in = midir::MidiInput::new(CLIENT_NAME).map_err(|e| e.to_string())?;
+            let port = midi_in
+                .ports()
+                .into_iter()
+                .find(|port| midi_in.port_name(port).ok().as_deref() == Some(name))
+                .ok_or_else(|| format!("{name} is not plugged in"))?;
+
+            let tx = self.tx.clone();
+            let repaint_ctx = Arc::clone(&self.repaint_ctx);
+            midi_in
+                .connect(
+                    &port,
+                    "video-editor-input",
+                    move |_, bytes, _| {
+                        let Some(message) = parse_message(bytes) else {
+                            return;
+                        };
+
+                        if tx.send(message).is_ok() {
+                            if let Some(ctx) = &*repaint_ctx.lock().unwrap() {
+                                ctx.request_repaint();
+                            }
+                        }
+                    },
+                    (),
+                )
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn state(clips: &[c_bindings::Clip]) -> c_bindings::AppStateSnapshot {
+        let mut state: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+        state.clips = clips.as_ptr();
+        state.num_clips = clips.len() as u64;
+        state.current_position = 5.0;
+        state.total_runtime = 100.0;
+        state
+    }
+
+    #[test]
+    fn parses_note_ons_and_control_changes() {
+        assert_eq!(
+            parse_message(&[0x91, 60, 100]),
+            Some(Message {
+                control: Control::Note {
+                    channel: 2,
+                    note: 60
+                },
+                value: 100
+            })
+        );
+        assert_eq!(
+            parse_message(&[0xb0, 7, 0]),
+            Some(Message {
+                control: Control::Cc {
+                    channel: 1,
+                    number: 7
+                },
+                value: 0
+            })
+        );
+        assert_eq!(parse_message(&[0x90, 60, 0]), None);
+        assert_eq!(parse_message(&[0x80, 60, 64]), None);
+        assert_eq!(parse_message(&[0xf8]), None);
+    }
+
+    #[test]
+    fn relative_values_are_twos_complement() {
+        assert_eq!(relative_delta(1), 1);
+        assert_eq!(relative_delta(3), 3);
+        assert_eq!(relative_delta(127), -1);
+        assert_eq!(relative_delta(125), -3);
+        assert_eq!(relative_delta(64), 0);
+    }
+
+    #[test]
+    fn preferences_round_trip() {
+        let mut preferences = MidiPreferences {
+            port: Some("nanoKONTROL2 MIDI 1".to_string()),
+            bindings: vec![],
+        };
+        preferences.bind(
+            Control::Cc {
+                channel: 1,
+                number: 16,
+            },
+            Command::Jog,
+        );
+        preferences.bind(
+            Control::Note {
+                channel: 10,
+                note: 41,
+            },
+            Command::TogglePause,
+        );
+
+        let serialized = preferences.serialize();
+        assert_eq!(
+            serialized,
+            "port nanoKONTROL2 MIDI 1\ncc 1 16 jog\nnote 10 41 toggle_pause\n"
+        );
+        assert_eq!(MidiPreferences::parse(&serialized), preferences);
+    }
+
+    #[test]
+    fn bad_preference_lines_are_skipped() {
+        let preferences = MidiPreferences::parse(
+            "cc 17 1 jog\nnote 1 200 seek\ncc 1 1 fly\n# cc 1 2 jog\ncc 1 3 seek\n",
+        );
+        assert_eq!(
+            preferences.bindings,
+            vec![(
+                Control::Cc {
+                    channel: 1,
+                    number: 3
+                },
+                Command::Seek
+            )]
+        );
+    }
+
+    #[test]
+    fn rebinding_a_control_replaces_its_command() {
+        let control = Control::Cc {
+            channel: 1,
+            number: 1,
+        };
+        let mut preferences = MidiPreferences::default();
+        preferences.bind(control, Command::Jog);
+        preferences.bind(control, Command::Seek);
+        assert_eq!(preferences.command(control), Some(Command::Seek));
+        assert_eq!(preferences.bindings.len(), 1);
+    }
+
+    #[test]
+    fn buttons_fire_on_press() {
+        let state = state(&[]);
+        assert!(matches!(
+            command_action(Command::TogglePause, 127, &state),
+            Some(Action::TogglePause)
+        ));
+        assert!(command_action(Command::TogglePause, 0, &state).is_none());
+        assert!(matches!(
+            command_action(Command::Jog, 126, &state),
+            Some(Action::StepFrames(-2))
+        ));
+        assert!(matches!(
+            command_action(Command::Seek, 127, &state),
+            Some(Action::Seek(pos)) if pos == 100.0
+        ));
+    }
+
+    #[test]
+    fn nudges_the_clip_under_the_playhead() {
+        let clips = [
+            c_bindings::Clip {
+                id: 1,
+                start: 0.0,
+                end: 2.0,
+                speed: 1.0,
+                freeze: false,
+            },
+            c_bindings::Clip {
+                id: 2,
+                start: 4.0,
+                end: 8.0,
+                speed: 1.0,
+                freeze: false,
+            },
+        ];
+        let state = state(&clips);
+
+        let Some(Action::ClipEdit(clip)) = command_action(Command::NudgeClipEnd, 2, &state) else {
+            panic!("expected a clip edit");
+        };
+        assert_eq!(clip.id, 2);
+        assert!((clip.end - (8.0 + 2.0 * NUDGE_S)).abs() < 1e-5);
+
+        let Some(Action::ClipEdit(clip)) = command_action(Command::NudgeClipStart, 127, &state)
+        else {
+            panic!("expected a clip edit");
+        };
+        assert!((clip.start - (4.0 - NUDGE_S)).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,47 @@
+use eframe::egui;
+
+use crate::c_bindings;
+
+/// Mute regions on the timeline, apart from the red of clips
+pub const COLOR: egui::Color32 = egui::Color32::from_rgb(0x40, 0x90, 0xff);
+
+/// Sorted by start
+pub fn mute_regions(state: &c_bindings::AppStateSnapshot) -> &[c_bindings::GuiMuteRegion] {
+    if state.num_mute_regions == 0 {
+        // mute_regions may be null
+        return &[];
+    }
+
+    unsafe { std::slice::from_raw_parts(state.mute_regions, state.num_mute_regions as usize) }
+}
+
+/// The region under position, if any
+pub fn region_at(
+    regions: &[c_bindings::GuiMuteRegion],
+    position: f32,
+) -> Option<&c_bindings::GuiMuteRegion> {
+    regions
+        .iter()
+        .find(|region| (region.start..=region.end).contains(&position))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn region_under_position_is_found() {
+        let region = |id, start, end| c_bindings::GuiMuteRegion { id, start, end };
+        let regions = [region(1, 1.0, 2.0), region(2, 5.0, 6.5)];
+
+        assert_eq!(region_at(&regions, 6.0).map(|region| region.id), Some(2));
+        assert_eq!(region_at(&regions, 1.0).map(|region| region.id), Some(1));
+        assert!(region_at(&regions, 3.0).is_none());
+
+        let mut state: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+        assert!(mute_regions(&state).is_empty());
+        state.mute_regions = regions.as_ptr();
+        state.num_mute_regions = regions.len() as u64;
+        assert_eq!(mute_regions(&state).len(), 2);
+    }
+}
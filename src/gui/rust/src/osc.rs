@@ -0,0 +1,630 @@
+use eframe::egui;
+
+use std::{
+    io,
+    net::UdpSocket,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::{actions::Action, c_bindings, config, jog_shuttle, markers, ActionRequestor};
+
+const DEFAULT_PORT: u16 = 9000;
+
+/// How long the listener blocks before checking whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Arg {
+    Int(i32),
+    Float(f32),
+    Str(String),
+}
+
+impl Arg {
+    fn number(&self) -> Option<f32> {
+        match self {
+            Arg::Int(i) => Some(*i as f32),
+            Arg::Float(f) => Some(*f),
+            Arg::Str(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    pub address: String,
+    pub args: Vec<Arg>,
+}
+
+/// What an address does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    TogglePause,
+    /// Seconds into the video
+    Seek,
+    /// 0 to 1 across the whole video, for faders
+    SeekFraction,
+    /// Multiple of normal speed, negative is backwards and 0 pauses
+    Speed,
+    /// Frames, negative is backwards
+    Step,
+    /// Named after the string argument if there is one
+    DropMarker,
+    Save,
+}
+
+impl Command {
+    const ALL: [Command; 7] = [
+        Command::TogglePause,
+        Command::Seek,
+        Command::SeekFraction,
+        Command::Speed,
+        Command::Step,
+        Command::DropMarker,
+        Command::Save,
+    ];
+
+    /// Name in the preferences file
+    fn name(&self) -> &'static str {
+        match self {
+            Command::TogglePause => "toggle_pause",
+            Command::Seek => "seek",
+            Command::SeekFraction => "seek_fraction",
+            Command::Speed => "speed",
+            Command::Step => "step",
+            Command::DropMarker => "drop_marker",
+            Command::Save => "save",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Command::TogglePause => "Play/pause",
+            Command::Seek => "Seek (seconds)",
+            Command::SeekFraction => "Seek (0 to 1)",
+            Command::Speed => "Speed",
+            Command::Step => "Step frames",
+            Command::DropMarker => "Drop marker",
+            Command::Save => "Save",
+        }
+    }
+
+    fn default_address(&self) -> &'static str {
+        match self {
+            Command::TogglePause => "/editor/play",
+            Command::Seek => "/editor/seek",
+            Command::SeekFraction => "/editor/seek_fraction",
+            Command::Speed => "/editor/speed",
+            Command::Step => "/editor/step",
+            Command::DropMarker => "/editor/marker",
+            Command::Save => "/editor/save",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Command> {
+        Command::ALL
+            .into_iter()
+            .find(|command| command.name() == name)
+    }
+}
+
+/// Port and address map, kept in a preferences file next to the MIDI one
+#[derive(Debug, PartialEq)]
+pub struct OscPreferences {
+    pub port: u16,
+    /// Whether to start listening with the editor
+    pub listen: bool,
+    /// Every command in Command::ALL order, an empty address leaves it unmapped
+    pub addresses: Vec<(Command, String)>,
+}
+
+impl Default for OscPreferences {
+    fn default() -> OscPreferences {
+        OscPreferences {
+            port: DEFAULT_PORT,
+            listen: false,
+            addresses: Command::ALL
+                .into_iter()
+                .map(|command| (command, command.default_address().to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl OscPreferences {
+    /// One entry per line, e.g. "port 9000", "listen" or "seek /deck/seek". Commands that are not
+    /// mentioned keep their default address
+    pub fn parse(data: &str) -> OscPreferences {
+        let mut ret = OscPreferences::default();
+
+        for (line_num, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line == "listen" {
+                ret.listen = true;
+                continue;
+            }
+
+            if let Some(port) = line.strip_prefix("port ") {
+                match port.trim().parse() {
+                    Ok(port) => ret.port = port,
+                    Err(_) => log::warn!("Ignoring OSC port on line {}: {line}", line_num + 1),
+                }
+                continue;
+            }
+
+            let (name, address) = line.split_once(' ').unwrap_or((line, ""));
+            let address = address.trim();
+            let command = Command::from_name(name);
+            match command {
+                Some(command) if address.is_empty() || address.starts_with('/') => {
+                    ret.set_address(command, address);
+                }
+                _ => log::warn!("Ignoring OSC address on line {}: {line}", line_num + 1),
+            }
+        }
+
+        ret
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut ret = format!("port {}\n", self.port);
+        if self.listen {
+            ret.push_str("listen\n");
+        }
+
+        for (command, address) in &self.addresses {
+            ret.push_str(command.name());
+            if !address.is_empty() {
+                ret.push(' ');
+                ret.push_str(address);
+            }
+            ret.push('\n');
+        }
+        ret
+    }
+
+    fn command(&self, address: &str) -> Option<Command> {
+        self.addresses
+            .iter()
+            .find(|(_, mapped)| !mapped.is_empty() && mapped == address)
+            .map(|(command, _)| *command)
+    }
+
+    fn set_address(&mut self, command: Command, address: &str) {
+        if let Some((_, mapped)) = self.addresses.iter_mut().find(|(c, _)| *c == command) {
+            *mapped = address.to_string();
+        }
+    }
+}
+
+/// Maps OSC addresses from control surfaces and companion apps (TouchOSC, Bitfocus Companion...)
+/// to editor commands
+pub struct OscPanel {
+    pub open: bool,
+    preferences: OscPreferences,
+    listener: Option<Listener>,
+    error: Option<String>,
+    tx: Sender<Message>,
+    messages: Receiver<Message>,
+    /// Shown to make it easier to find out what a surface sends
+    last_address: Option<String>,
+}
+
+impl OscPanel {
+    pub fn new() -> OscPanel {
+        let preferences = config::load_preferences("osc.conf")
+            .map(|data| OscPreferences::parse(&data))
+            .unwrap_or_default();
+
+        let (tx, messages) = mpsc::channel();
+        OscPanel {
+            open: false,
+            preferences,
+            listener: None,
+            error: None,
+            tx,
+            messages,
+            last_address: None,
+        }
+    }
+
+    /// Handles incoming messages every frame, whether the window is open or not
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        // Started from here rather than new() since waking the GUI up needs the context. A failed
+        // start is not retried until the user asks for it
+        if self.preferences.listen && self.listener.is_none() && self.error.is_none() {
+            self.start(ctx);
+        }
+
+        if self
+            .listener
+            .as_ref()
+            .and_then(|listener| listener.thread.as_ref())
+            .is_some_and(JoinHandle::is_finished)
+        {
+            self.error = Some("Stopped listening, see the logs".to_string());
+            self.listener = None;
+        }
+
+        while let Ok(message) = self.messages.try_recv() {
+            if let Some(action) = self
+                .preferences
+                .command(&message.address)
+                .and_then(|command| command_action(command, &message.args, state))
+            {
+                action_tx.send(action);
+            }
+            self.last_address = Some(message.address);
+        }
+
+        let mut open = self.open;
+        egui::Window::new("OSC")
+            .open(&mut open)
+            .default_width(350.0)
+            .show(ctx, |ui| {
+                let mut changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("UDP port");
+                    let port = ui.add(
+                        egui::DragValue::new(&mut self.preferences.port)
+                            .clamp_range(1024..=u16::MAX),
+                    );
+                    let listen = ui.checkbox(&mut self.preferences.listen, "Listen");
+
+                    if port.changed() || listen.changed() {
+                        self.listener = None;
+                        self.error = None;
+                        changed = true;
+                    }
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                } else if self.listener.is_some() {
+                    ui.weak("Listening on every network interface");
+                }
+
+                match &self.last_address {
+                    Some(address) => ui.label(format!("Last received {address}")),
+                    None => ui.weak("Nothing received yet"),
+                };
+
+                ui.separator();
+                egui::Grid::new("osc_addresses")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (command, address) in &mut self.preferences.addresses {
+                            ui.label(command.label());
+                            let response = ui.add(
+                                egui::TextEdit::singleline(address)
+                                    .hint_text("Unmapped")
+                                    .desired_width(180.0),
+                            );
+                            changed |= response.lost_focus();
+                            ui.end_row();
+                        }
+                    });
+
+                if ui.button("Reset to defaults").clicked() {
+                    self.preferences.addresses = OscPreferences::default().addresses;
+                    changed = true;
+                }
+
+                if changed {
+                    self.save();
+                }
+            });
+        self.open = open;
+    }
+
+    fn start(&mut self, ctx: &egui::Context) {
+        let port = self.preferences.port;
+        match Listener::start(port, self.tx.clone(), ctx.clone()) {
+            Ok(listener) => self.listener = Some(listener),
+            Err(e) => {
+                log::warn!("Failed to listen for OSC on port {port}: {e}");
+                self.error = Some(format!("Failed to listen on port {port}: {e}"));
+            }
+        }
+    }
+
+    fn save(&self) {
+        config::save_preferences("osc.conf", &self.preferences.serialize());
+    }
+}
+
+/// Receives packets on a thread of its own and passes the messages on to the GUI thread. Stops
+/// when dropped
+struct Listener {
+    shutdown: Arc<AtomicBool>,
+    /// Only None while dropping
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Listener {
+    fn start(port: u16, tx: Sender<Message>, ctx: egui::Context) -> io::Result<Listener> {
+        // Surfaces are usually tablets or other machines, so not just localhost
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+
+        let shutdown: Arc<AtomicBool> = Default::default();
+        let thread_shutdown = Arc::clone(&shutdown);
+        let thread = std::thread::spawn(move || {
+            let mut buf = [0; 4096];
+            while !thread_shutdown.load(Ordering::Relaxed) {
+                let len = match socket.recv(&mut buf) {
+                    Ok(len) => len,
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) =>
+                    {
+                        continue;
+                    }
+                    Err(e) => {
+                        log::error!("Failed to receive OSC: {e}");
+                        return;
+                    }
+                };
+
+                for message in parse_packet(&buf[..len]) {
+                    if tx.send(message).is_err() {
+                        return;
+                    }
+                }
+                ctx.request_repaint();
+            }
+        });
+
+        Ok(Listener {
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        // Waits for the socket to be closed, so that listening again on the same port works
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Messages of a packet, with bundles flattened. Malformed messages are dropped, as are messages
+/// with argument types we have no use for
+pub fn parse_packet(bytes: &[u8]) -> Vec<Message> {
+    let mut ret = Vec::new();
+    parse_packet_into(bytes, &mut ret);
+    ret
+}
+
+fn parse_packet_into(bytes: &[u8], out: &mut Vec<Message>) {
+    let Some(elements) = bytes.strip_prefix(b"#bundle\0") else {
+        out.extend(parse_message(bytes));
+        return;
+    };
+
+    // Time tag, everything is handled as soon as it arrives
+    let mut pos = 8;
+    while let Some(size) = read_bytes::<4>(elements, &mut pos) {
+        let size = u32::from_be_bytes(size) as usize;
+        let Some(element) = elements.get(pos..).and_then(|rest| rest.get(..size)) else {
+            return;
+        };
+        parse_packet_into(element, out);
+        pos += size;
+    }
+}
+
+fn parse_message(bytes: &[u8]) -> Option<Message> {
+    let mut pos = 0;
+    let address = read_string(bytes, &mut pos)?;
+    if !address.starts_with('/') {
+        return None;
+    }
+
+    // Old senders may leave out the type tags, which is fine for messages without arguments
+    let tags = match read_string(bytes, &mut pos) {
+        Some(tags) => tags,
+        None if pos >= bytes.len() => String::new(),
+        None => return None,
+    };
+
+    let mut args = Vec::new();
+    for tag in tags.strip_prefix(',').unwrap_or("").chars() {
+        let arg = match tag {
+            'i' => Arg::Int(i32::from_be_bytes(read_bytes(bytes, &mut pos)?)),
+            'f' => Arg::Float(f32::from_be_bytes(read_bytes(bytes, &mut pos)?)),
+            'h' => Arg::Int(i64::from_be_bytes(read_bytes(bytes, &mut pos)?) as i32),
+            'd' => Arg::Float(f64::from_be_bytes(read_bytes(bytes, &mut pos)?) as f32),
+            's' => Arg::Str(read_string(bytes, &mut pos)?),
+            'T' => Arg::Int(1),
+            'F' => Arg::Int(0),
+            _ => return None,
+        };
+        args.push(arg);
+    }
+
+    Some(Message { address, args })
+}
+
+/// Null terminated and padded to a multiple of 4 bytes
+fn read_string(bytes: &[u8], pos: &mut usize) -> Option<String> {
+    let rest = bytes.get(*pos..)?;
+    let len = rest.iter().position(|b| *b == 0)?;
+    let s = std::str::from_utf8(&rest[..len]).ok()?.to_string();
+    *pos += (len + 4) & !3;
+    Some(s)
+}
+
+fn read_bytes<const N: usize>(bytes: &[u8], pos: &mut usize) -> Option<[u8; N]> {
+    let ret = bytes.get(*pos..*pos + N)?.try_into().ok()?;
+    *pos += N;
+    Some(ret)
+}
+
+/// The actions a message sends. Buttons on most surfaces send 1 when pressed and 0 when released,
+/// a message without arguments counts as a press
+fn command_action(
+    command: Command,
+    args: &[Arg],
+    state: &c_bindings::AppStateSnapshot,
+) -> Option<Action> {
+    let value = args.first().and_then(Arg::number);
+    let pressed = value.map_or(true, |value| value != 0.0);
+
+    match command {
+        Command::TogglePause if pressed => Some(Action::TogglePause),
+        Command::Seek => Some(Action::Seek(value?.clamp(0.0, state.total_runtime))),
+        Command::SeekFraction => Some(Action::Seek(value?.clamp(0.0, 1.0) * state.total_runtime)),
+        Command::Speed => Some(Action::SetSpeed(value?.clamp(
+            -jog_shuttle::MAX_SHUTTLE_SPEED,
+            jog_shuttle::MAX_SHUTTLE_SPEED,
+        ))),
+        Command::Step => match value? as i32 {
+            0 => None,
+            frames => Some(Action::StepFrames(frames)),
+        },
+        Command::DropMarker if pressed => {
+            let name = args
+                .iter()
+                .find_map(|arg| match arg {
+                    Arg::Str(name) => Some(name.clone()),
+                    _ => None,
+                })
+                .unwrap_or_else(|| format!("Marker {}", markers::markers(state).len() + 1));
+            Some(Action::MarkerAdd(c_bindings::GuiMarker {
+                id: 0,
+                time: state.current_position,
+                color: markers::DEFAULT_COLOR,
+                name: markers::name_to_c(&name),
+            }))
+        }
+        Command::Save if pressed => Some(Action::Save),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn padded(s: &str) -> Vec<u8> {
+        let mut ret = s.as_bytes().to_vec();
+        ret.push(0);
+        while ret.len() % 4 != 0 {
+            ret.push(0);
+        }
+        ret
+    }
+
+    fn message(address: &str, tags: &str, args: &[u8]) -> Vec<u8> {
+        let mut ret = padded(address);
+        ret.extend(padded(tags));
+        ret.extend(args);
+        ret
+    }
+
+    #[test]
+    fn parses_messages_and_bundles() {
+        let seek = message("/editor/seek", ",f", &12.5f32.to_be_bytes());
+        assert_eq!(
+            parse_packet(&seek),
+            vec![Message {
+                address: "/editor/seek".to_string(),
+                args: vec![Arg::Float(12.5)],
+            }]
+        );
+
+        let marker = message("/editor/marker", ",s", &padded("intro"));
+        let mut bundle = padded("#bundle");
+        bundle.extend([0; 8]);
+        for element in [&seek, &marker] {
+            bundle.extend((element.len() as i32).to_be_bytes());
+            bundle.extend(element);
+        }
+        let messages = parse_packet(&bundle);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].args, vec![Arg::Str("intro".to_string())]);
+
+        // Truncated argument
+        assert!(parse_packet(&seek[..seek.len() - 1]).is_empty());
+        assert!(parse_packet(b"not osc\0").is_empty());
+    }
+
+    #[test]
+    fn preferences_round_trip() {
+        let mut preferences = OscPreferences {
+            port: 8000,
+            listen: true,
+            ..Default::default()
+        };
+        preferences.set_address(Command::Seek, "/deck/seek");
+        preferences.set_address(Command::Save, "");
+
+        let serialized = preferences.serialize();
+        assert!(serialized.starts_with("port 8000\nlisten\ntoggle_pause /editor/play\n"));
+        assert_eq!(OscPreferences::parse(&serialized), preferences);
+        assert_eq!(preferences.command("/deck/seek"), Some(Command::Seek));
+        assert_eq!(preferences.command("/editor/save"), None);
+        assert_eq!(preferences.command(""), None);
+    }
+
+    #[test]
+    fn bad_preference_lines_are_skipped() {
+        let preferences = OscPreferences::parse("port 99999\nfly /fly\nseek deck\nstep /s\n");
+        let defaults = OscPreferences::default();
+        assert_eq!(preferences.port, defaults.port);
+        assert_eq!(preferences.command("/editor/seek"), Some(Command::Seek));
+        assert_eq!(preferences.command("/s"), Some(Command::Step));
+    }
+
+    #[test]
+    fn buttons_fire_on_press() {
+        let mut state: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+        state.total_runtime = 100.0;
+
+        assert!(matches!(
+            command_action(Command::TogglePause, &[], &state),
+            Some(Action::TogglePause)
+        ));
+        assert!(command_action(Command::TogglePause, &[Arg::Float(0.0)], &state).is_none());
+        assert!(matches!(
+            command_action(Command::SeekFraction, &[Arg::Float(0.25)], &state),
+            Some(Action::Seek(pos)) if pos == 25.0
+        ));
+        assert!(matches!(
+            command_action(Command::Speed, &[Arg::Int(20)], &state),
+            Some(Action::SetSpeed(speed)) if speed == jog_shuttle::MAX_SHUTTLE_SPEED
+        ));
+        assert!(command_action(Command::Seek, &[], &state).is_none());
+
+        let Some(Action::MarkerAdd(marker)) = command_action(
+            Command::DropMarker,
+            &[Arg::Str("intro".to_string())],
+            &state,
+        ) else {
+            panic!("expected a marker add");
+        };
+        assert_eq!(markers::marker_name(&marker), "intro");
+    }
+}
@@ -0,0 +1,22 @@
+use std::{
+    backtrace::Backtrace,
+    panic::{self, AssertUnwindSafe},
+    sync::Once,
+};
+
+/// Logs panics with a backtrace from where they happened. By the time catch_panic sees the panic
+/// the interesting part of the stack is already gone
+pub fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        panic::set_hook(Box::new(|info| {
+            log::error!("{info}\n{}", Backtrace::force_capture());
+        }));
+    });
+}
+
+/// Unwinding across the C boundary is UB, so every exported function runs its body through here
+/// and hands on_panic back to the caller instead
+pub fn catch_panic<R>(on_panic: R, f: impl FnOnce() -> R) -> R {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
@@ -0,0 +1,41 @@
+use eframe::egui;
+
+/// puffin::profile_scope!, but compiles away when the profiling feature is disabled
+macro_rules! profile_scope {
+    ($name:expr) => {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!($name);
+    };
+}
+
+pub(crate) use profile_scope;
+
+pub struct Profiler {
+    pub open: bool,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler { open: false }
+    }
+
+    pub fn new_frame(&self) {
+        #[cfg(feature = "profiling")]
+        puffin::GlobalProfiler::lock().new_frame();
+    }
+
+    /// Scopes are only recorded while the window is open, so the instrumentation is close to free
+    /// otherwise
+    pub fn show(&mut self, ctx: &egui::Context) {
+        #[cfg(feature = "profiling")]
+        {
+            puffin::set_scopes_on(self.open);
+            if self.open {
+                self.open = puffin_egui::profiler_window(ctx);
+            }
+        }
+
+        #[cfg(not(feature = "profiling"))]
+        let _ = ctx;
+    }
+}
@@ -0,0 +1,70 @@
+use eframe::egui;
+
+use crate::{actions::Action, c_bindings, ActionRequestor};
+
+/// Offers to bring back the edits of a run that did not exit cleanly
+pub struct RecoveryPrompt {
+    /// Edit count the user already answered for. The backend takes a frame or two to catch up, the
+    /// prompt should not flash back up in the meantime
+    answered: Option<u64>,
+}
+
+impl RecoveryPrompt {
+    pub fn new() -> RecoveryPrompt {
+        RecoveryPrompt { answered: None }
+    }
+
+    fn visible(&self, num_edits: u64) -> bool {
+        num_edits > 0 && self.answered != Some(num_edits)
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let num_edits = state.num_recoverable_edits;
+        if !self.visible(num_edits) {
+            return;
+        }
+
+        egui::Window::new("Recover edits")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The editor did not shut down cleanly. {num_edits} edits made since the last \
+                     save can be replayed on top of it."
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Replay").clicked() {
+                        action_tx.send(Action::ReplayJournal);
+                        self.answered = Some(num_edits);
+                    }
+                    if ui.button("Discard").clicked() {
+                        action_tx.send(Action::DiscardJournal);
+                        self.answered = Some(num_edits);
+                    }
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prompt_stays_hidden_once_answered() {
+        let mut prompt = RecoveryPrompt::new();
+        assert!(!prompt.visible(0));
+        assert!(prompt.visible(3));
+
+        prompt.answered = Some(3);
+        assert!(!prompt.visible(3));
+        // Another crash before the answer was acted on
+        assert!(prompt.visible(5));
+    }
+}
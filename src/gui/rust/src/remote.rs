@@ -0,0 +1,442 @@
+use eframe::egui;
+use serde_json::{json, Value};
+use tungstenite::Message;
+
+use std::{
+    io,
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::{actions::Action, c_bindings, markers, transcript, ActionRequestor};
+
+const DEFAULT_PORT: u16 = 9455;
+
+/// How long the server threads block before checking for shutdown and outgoing messages
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Lets external tools, e.g. Stream Deck plugins or a browser remote, drive the editor through a
+/// WebSocket on localhost. Commands are JSON objects like {"action": "seek", "time": 12.5}, and
+/// every client is sent the state whenever it changes
+pub struct RemotePanel {
+    pub open: bool,
+    port: u16,
+    server: Option<Server>,
+    error: Option<String>,
+    /// Generation of the last state sent out
+    sent_generation: Option<u64>,
+}
+
+impl RemotePanel {
+    pub fn new() -> RemotePanel {
+        RemotePanel {
+            open: false,
+            port: DEFAULT_PORT,
+            server: None,
+            error: None,
+            sent_generation: None,
+        }
+    }
+
+    /// Handles commands and sends out the state every frame, whether the window is open or not
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        self.update_server(state, action_tx);
+        if self
+            .server
+            .as_ref()
+            .is_some_and(|server| !server.is_running())
+        {
+            self.error = Some("Server stopped, see the logs".to_string());
+            self.server = None;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Remote control")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Port");
+                    ui.add_enabled(
+                        self.server.is_none(),
+                        egui::DragValue::new(&mut self.port).clamp_range(1024..=u16::MAX),
+                    );
+
+                    if let Some(num_clients) = self.server.as_ref().map(Server::num_clients) {
+                        if ui.button("Stop").clicked() {
+                            self.server = None;
+                        }
+                        ui.label(format!("{num_clients} clients"));
+                    } else if ui.button("Start").clicked() {
+                        self.start(ctx);
+                    }
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                } else if self.server.is_some() {
+                    ui.weak(format!("Listening on ws://127.0.0.1:{}", self.port));
+                }
+            });
+        self.open = open;
+    }
+
+    fn start(&mut self, ctx: &egui::Context) {
+        match Server::start(self.port, ctx.clone()) {
+            Ok(server) => {
+                self.server = Some(server);
+                self.error = None;
+                self.sent_generation = None;
+            }
+            Err(e) => {
+                log::warn!("Failed to listen for remotes on port {}: {e}", self.port);
+                self.error = Some(format!("Failed to listen on port {}: {e}", self.port));
+            }
+        }
+    }
+
+    fn update_server(
+        &mut self,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let Some(server) = &self.server else {
+            return;
+        };
+
+        let mut resend = false;
+        while let Ok(incoming) = server.incoming.try_recv() {
+            match incoming {
+                Incoming::Connected => resend = true,
+                Incoming::Action(action) => action_tx.send(action),
+            }
+        }
+
+        if server.num_clients() == 0 {
+            return;
+        }
+
+        if resend || self.sent_generation != Some(state.generation) {
+            server.broadcast(&state_message(state).to_string());
+            self.sent_generation = Some(state.generation);
+        }
+    }
+}
+
+enum Incoming {
+    /// New clients need the state even if it has not changed
+    Connected,
+    Action(Action),
+}
+
+/// Accepts connections on one thread and serves each client on a thread of its own. Stops when
+/// dropped
+struct Server {
+    incoming: Receiver<Incoming>,
+    /// One sender per client, the ones whose client went away are dropped on the next broadcast
+    clients: Arc<Mutex<Vec<Sender<String>>>>,
+    num_clients: Arc<AtomicUsize>,
+    shutdown: Arc<AtomicBool>,
+    listener_thread: std::thread::JoinHandle<()>,
+}
+
+impl Server {
+    fn start(port: u16, ctx: egui::Context) -> io::Result<Server> {
+        // Localhost only, anything that can connect can edit the project
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+
+        let (incoming_tx, incoming) = mpsc::channel();
+        let clients: Arc<Mutex<Vec<Sender<String>>>> = Default::default();
+        let num_clients: Arc<AtomicUsize> = Default::default();
+        let shutdown: Arc<AtomicBool> = Default::default();
+
+        let client = Client {
+            incoming_tx,
+            clients: Arc::clone(&clients),
+            num_clients: Arc::clone(&num_clients),
+            shutdown: Arc::clone(&shutdown),
+            ctx,
+        };
+        let listener_thread = std::thread::spawn(move || client.accept_loop(listener));
+
+        Ok(Server {
+            incoming,
+            clients,
+            num_clients,
+            shutdown,
+            listener_thread,
+        })
+    }
+
+    fn is_running(&self) -> bool {
+        !self.listener_thread.is_finished()
+    }
+
+    fn num_clients(&self) -> usize {
+        self.num_clients.load(Ordering::Relaxed)
+    }
+
+    fn broadcast(&self, message: &str) {
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(message.to_string()).is_ok());
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Everything a connection thread needs, cloned for every client
+#[derive(Clone)]
+struct Client {
+    incoming_tx: Sender<Incoming>,
+    clients: Arc<Mutex<Vec<Sender<String>>>>,
+    num_clients: Arc<AtomicUsize>,
+    shutdown: Arc<AtomicBool>,
+    ctx: egui::Context,
+}
+
+impl Client {
+    fn accept_loop(self, listener: TcpListener) {
+        while !self.shutdown.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, addr)) => {
+                    let client = self.clone();
+                    std::thread::spawn(move || {
+                        client.num_clients.fetch_add(1, Ordering::Relaxed);
+                        if let Err(e) = client.serve(stream) {
+                            log::warn!("Remote {addr} disconnected: {e}");
+                        }
+                        client.num_clients.fetch_sub(1, Ordering::Relaxed);
+                        client.ctx.request_repaint();
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(POLL_INTERVAL)
+                }
+                Err(e) => {
+                    log::error!("Failed to accept remote connection: {e}");
+                    return;
+                }
+            }
+        }
+    }
+
+    fn serve(&self, stream: TcpStream) -> Result<(), String> {
+        // Accepted sockets do not reliably inherit the listener's mode
+        stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+        stream
+            .set_read_timeout(Some(POLL_INTERVAL))
+            .map_err(|e| e.to_string())?;
+        let mut socket = tungstenite::accept(stream).map_err(|e| e.to_string())?;
+
+        let (tx, outgoing) = mpsc::channel();
+        self.clients.lock().unwrap().push(tx);
+        self.send(Incoming::Connected);
+
+        while !self.shutdown.load(Ordering::Relaxed) {
+            for message in outgoing.try_iter() {
+                socket
+                    .send(Message::Text(message))
+                    .map_err(|e| e.to_string())?;
+            }
+
+            match socket.read() {
+                Ok(Message::Text(text)) => match parse_command(&text) {
+                    Ok(action) => self.send(Incoming::Action(action)),
+                    Err(e) => socket
+                        .send(Message::Text(error_message(&e).to_string()))
+                        .map_err(|e| e.to_string())?,
+                },
+                Ok(_) => (),
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+
+        // Best effort, the editor is going away either way
+        let _ = socket.close(None);
+        Ok(())
+    }
+
+    fn send(&self, incoming: Incoming) {
+        if self.incoming_tx.send(incoming).is_ok() {
+            self.ctx.request_repaint();
+        }
+    }
+}
+
+/// e.g. {"action": "clip_add", "start": 10, "end": 12.5}
+fn parse_command(text: &str) -> Result<Action, String> {
+    let command: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let name = command["action"]
+        .as_str()
+        .ok_or("expected an object with an \"action\"")?;
+    let number = |key: &str| {
+        command[key]
+            .as_f64()
+            .map(|v| v as f32)
+            .ok_or_else(|| format!("{name} needs a number \"{key}\""))
+    };
+
+    let action = match name {
+        "toggle_pause" => Action::TogglePause,
+        "seek" => Action::Seek(number("time")?),
+        "set_speed" => Action::SetSpeed(number("speed")?),
+        "step_frames" => Action::StepFrames(number("frames")? as i32),
+        "clip_add" => {
+            let (start, end) = (number("start")?, number("end")?);
+            if end <= start {
+                return Err(format!("clip ends at {end} before it starts at {start}"));
+            }
+            Action::ClipAdd(c_bindings::Clip {
+                id: 0,
+                start,
+                end,
+                speed: 1.0,
+                freeze: false,
+            })
+        }
+        "clip_remove" => Action::ClipRemove(number("time")?),
+        "marker_add" => Action::MarkerAdd(c_bindings::GuiMarker {
+            id: 0,
+            time: number("time")?,
+            color: markers::DEFAULT_COLOR,
+            name: markers::name_to_c(command["name"].as_str().unwrap_or("Marker")),
+        }),
+        "marker_remove" => Action::MarkerRemove(
+            command["id"]
+                .as_u64()
+                .ok_or("marker_remove needs an \"id\"")?,
+        ),
+        "save" => Action::Save,
+        _ => return Err(format!("unknown action \"{name}\"")),
+    };
+    Ok(action)
+}
+
+fn error_message(error: &str) -> Value {
+    json!({ "type": "error", "message": error })
+}
+
+fn state_message(state: &c_bindings::AppStateSnapshot) -> Value {
+    let clips: Vec<Value> = transcript::clips(state)
+        .iter()
+        .map(|clip| {
+            json!({
+                "id": clip.id,
+                "start": clip.start,
+                "end": clip.end,
+                "speed": clip.speed,
+                "freeze": clip.freeze,
+            })
+        })
+        .collect();
+    let markers: Vec<Value> = markers::markers(state)
+        .iter()
+        .map(|marker| {
+            json!({
+                "id": marker.id,
+                "time": marker.time,
+                "name": markers::marker_name(marker),
+            })
+        })
+        .collect();
+
+    json!({
+        "type": "state",
+        "generation": state.generation,
+        "paused": state.paused,
+        "speed": state.playback_speed,
+        "position": state.current_position,
+        "duration": state.total_runtime,
+        "clips": clips,
+        "markers": markers,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn commands_map_to_actions() {
+        assert!(matches!(
+            parse_command(r#"{"action": "toggle_pause"}"#),
+            Ok(Action::TogglePause)
+        ));
+        assert!(matches!(
+            parse_command(r#"{"action": "seek", "time": 12}"#),
+            Ok(Action::Seek(t)) if t == 12.0
+        ));
+
+        let Ok(Action::ClipAdd(clip)) =
+            parse_command(r#"{"action": "clip_add", "start": 1.5, "end": 3}"#)
+        else {
+            panic!("expected a clip add");
+        };
+        assert_eq!((clip.start, clip.end), (1.5, 3.0));
+
+        let Ok(Action::MarkerAdd(marker)) =
+            parse_command(r#"{"action": "marker_add", "time": 4, "name": "intro"}"#)
+        else {
+            panic!("expected a marker add");
+        };
+        assert_eq!(markers::marker_name(&marker), "intro");
+    }
+
+    #[test]
+    fn bad_commands_are_explained() {
+        assert!(parse_command("seek").is_err());
+        assert_eq!(
+            parse_command(r#"{"action": "seek"}"#).unwrap_err(),
+            "seek needs a number \"time\""
+        );
+        assert_eq!(
+            parse_command(r#"{"action": "explode"}"#).unwrap_err(),
+            "unknown action \"explode\""
+        );
+        assert!(parse_command(r#"{"action": "clip_add", "start": 3, "end": 1}"#).is_err());
+    }
+
+    #[test]
+    fn state_lists_clips() {
+        let clips = [c_bindings::Clip {
+            id: 7,
+            start: 1.0,
+            end: 2.0,
+            speed: 1.0,
+            freeze: false,
+        }];
+        let mut state: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+        state.clips = clips.as_ptr();
+        state.num_clips = clips.len() as u64;
+        state.current_position = 1.5;
+
+        let message = state_message(&state);
+        assert_eq!(message["type"], "state");
+        assert_eq!(message["position"], 1.5);
+        assert_eq!(message["clips"][0]["id"], 7);
+        assert_eq!(message["markers"], json!([]));
+    }
+}
@@ -0,0 +1,167 @@
+use eframe::{egui, glow};
+
+use std::sync::{Arc, Mutex};
+
+use crate::{c_bindings, gpu_timer::GpuTimer};
+
+/// Whatever the active eframe renderer records draw commands with. Renderers match on this and
+/// draw with the APIs they support
+pub enum CommandRecorder<'a> {
+    Glow(&'a glow::Context),
+    #[cfg(feature = "wgpu")]
+    Wgpu(wgpu_callback::Recorder<'a>),
+}
+
+/// Something that draws into a region of the window, e.g. the video frame or the waveform
+pub trait Renderer: Send + Sync + 'static {
+    type Params: Copy + Send + Sync + 'static;
+
+    fn init(&self, recorder: &mut CommandRecorder);
+    fn render(&self, recorder: &mut CommandRecorder, params: Self::Params);
+    fn deinit(&self, recorder: &mut CommandRecorder);
+}
+
+#[derive(Clone, Copy)]
+pub struct FrameParams {
+    pub width: f32,
+    pub height: f32,
+    /// Source or program monitor, see GuiFrameView
+    pub view: c_bindings::GuiFrameView,
+    /// Only the GL renderer manages color, wgpu shows what it always did
+    pub color: c_bindings::GuiColorManagement,
+}
+
+#[derive(Clone, Copy)]
+pub struct WaveformParams {
+    pub zoom: f32,
+    pub center_norm: f32,
+}
+
+pub type FrameRenderer = dyn Renderer<Params = FrameParams>;
+pub type WaveformRenderer = dyn Renderer<Params = WaveformParams>;
+
+/// Schedules renderer to draw into rect when egui paints, timing the GPU work with gpu_timer
+#[cfg(not(feature = "wgpu"))]
+pub fn paint_callback<P: Copy + Send + Sync + 'static>(
+    rect: egui::Rect,
+    renderer: Arc<dyn Renderer<Params = P>>,
+    params: P,
+    gpu_timer: Arc<Mutex<GpuTimer>>,
+) -> egui::PaintCallback {
+    use eframe::egui_glow;
+
+    egui::PaintCallback {
+        rect,
+        callback: Arc::new(egui_glow::CallbackFn::new(move |_info, painter| {
+            let gl = &**painter.gl();
+            gpu_timer.lock().unwrap().time(gl, || {
+                renderer.render(&mut CommandRecorder::Glow(gl), params);
+            });
+        })),
+    }
+}
+
+/// Schedules renderer to draw into rect when egui paints. GPU timing is GL only for now, so
+/// gpu_timer never reports anything
+#[cfg(feature = "wgpu")]
+pub fn paint_callback<P: Copy + Send + Sync + 'static>(
+    rect: egui::Rect,
+    renderer: Arc<dyn Renderer<Params = P>>,
+    params: P,
+    _gpu_timer: Arc<Mutex<GpuTimer>>,
+) -> egui::PaintCallback {
+    eframe::egui_wgpu::Callback::new_paint_callback(
+        rect,
+        wgpu_callback::Callback {
+            renderer,
+            params,
+            bundles: Default::default(),
+        },
+    )
+}
+
+#[cfg(feature = "wgpu")]
+pub mod wgpu_callback {
+    use eframe::{egui_wgpu, wgpu};
+
+    use std::sync::{Arc, OnceLock};
+
+    use super::{CommandRecorder, Renderer};
+
+    /// Render target description, stored in egui_wgpu's callback resources at startup so that
+    /// renderers can build pipelines that are compatible with egui's render pass
+    #[derive(Clone, Copy)]
+    pub struct Target {
+        pub format: wgpu::TextureFormat,
+        pub sample_count: u32,
+    }
+
+    /// egui only hands out its render pass for the duration of the paint call, with lifetimes that
+    /// renderers cannot hold on to. Renderers record into render bundles instead, which get
+    /// executed in egui's pass afterwards
+    pub struct Recorder<'a> {
+        pub device: &'a wgpu::Device,
+        pub queue: &'a wgpu::Queue,
+        pub target: Target,
+        pub bundles: &'a mut Vec<wgpu::RenderBundle>,
+    }
+
+    impl Recorder<'_> {
+        pub fn bundle_encoder(&self) -> wgpu::RenderBundleEncoder {
+            self.device
+                .create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                    label: None,
+                    color_formats: &[Some(self.target.format)],
+                    depth_stencil: None,
+                    sample_count: self.target.sample_count,
+                    multiview: None,
+                })
+        }
+    }
+
+    pub(super) struct Callback<P> {
+        pub renderer: Arc<dyn Renderer<Params = P>>,
+        pub params: P,
+        pub bundles: OnceLock<Vec<wgpu::RenderBundle>>,
+    }
+
+    impl<P: Copy + Send + Sync + 'static> egui_wgpu::CallbackTrait for Callback<P> {
+        fn prepare(
+            &self,
+            device: &wgpu::Device,
+            queue: &wgpu::Queue,
+            _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+            _egui_encoder: &mut wgpu::CommandEncoder,
+            callback_resources: &mut egui_wgpu::CallbackResources,
+        ) -> Vec<wgpu::CommandBuffer> {
+            let target = *callback_resources
+                .get::<Target>()
+                .expect("wgpu target has to be registered before painting");
+
+            let mut bundles = Vec::new();
+            self.renderer.render(
+                &mut CommandRecorder::Wgpu(Recorder {
+                    device,
+                    queue,
+                    target,
+                    bundles: &mut bundles,
+                }),
+                self.params,
+            );
+            let _ = self.bundles.set(bundles);
+
+            Vec::new()
+        }
+
+        fn paint<'a>(
+            &'a self,
+            _info: eframe::egui::PaintCallbackInfo,
+            render_pass: &mut wgpu::RenderPass<'a>,
+            _callback_resources: &'a egui_wgpu::CallbackResources,
+        ) {
+            if let Some(bundles) = self.bundles.get() {
+                render_pass.execute_bundles(bundles.iter());
+            }
+        }
+    }
+}
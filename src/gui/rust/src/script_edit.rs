@@ -0,0 +1,307 @@
+use std::ops::Range;
+
+use crate::{actions::Action, c_bindings};
+
+/// Words selected in the script panel with shift + drag. The selection is kept in text positions
+/// and always covers whole words
+pub struct ScriptSelection {
+    /// Where the drag started, set while the drag is going
+    anchor: Option<usize>,
+    pub range: Option<Range<usize>>,
+}
+
+impl ScriptSelection {
+    pub fn new() -> ScriptSelection {
+        ScriptSelection {
+            anchor: None,
+            range: None,
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    pub fn start(&mut self, char_pos: usize, words: &[c_bindings::GuiWord]) {
+        self.anchor = Some(char_pos);
+        self.update(char_pos, words);
+    }
+
+    pub fn update(&mut self, char_pos: usize, words: &[c_bindings::GuiWord]) {
+        let Some(anchor) = self.anchor else {
+            return;
+        };
+
+        let range = anchor.min(char_pos)..anchor.max(char_pos);
+        self.range = snap_to_words(words, range);
+    }
+
+    pub fn finish(&mut self) {
+        self.anchor = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.anchor = None;
+        self.range = None;
+    }
+}
+
+/// Indices of the words that overlap range. A range that is empty still picks the word it is in
+fn selected_words(words: &[c_bindings::GuiWord], range: Range<usize>) -> Range<usize> {
+    let first = words.partition_point(|word| word.char_end as usize <= range.start);
+    let last =
+        words.partition_point(|word| (word.char_start as usize) < range.end.max(range.start + 1));
+    first..last.max(first)
+}
+
+/// Grows range to the bounds of the words it touches, None if it does not touch any
+fn snap_to_words(words: &[c_bindings::GuiWord], range: Range<usize>) -> Option<Range<usize>> {
+    let selected = &words[selected_words(words, range)];
+    let first = selected.first()?;
+    let last = selected.last()?;
+    Some(first.char_start as usize..last.char_end as usize)
+}
+
+/// Time span covered by the words in range
+pub fn selected_time_range(
+    words: &[c_bindings::GuiWord],
+    range: Range<usize>,
+) -> Option<Range<f32>> {
+    let selected = &words[selected_words(words, range)];
+    let start = selected.iter().map(|word| word.start).reduce(f32::min)?;
+    let end = selected.iter().map(|word| word.end).reduce(f32::max)?;
+    Some(start..end)
+}
+
+/// A word is cut if it is not part of any clip. Without clips everything plays, so nothing is cut
+pub fn is_cut(word: &c_bindings::GuiWord, clips: &[c_bindings::Clip]) -> bool {
+    let mid = (word.start + word.end) / 2.0;
+    !clips.is_empty()
+        && !clips
+            .iter()
+            .any(|clip| mid >= clip.start && mid <= clip.end)
+}
+
+#[derive(Clone, Copy)]
+struct KeptSpan {
+    /// None for spans that do not have a clip yet
+    id: Option<u64>,
+    start: f32,
+    end: f32,
+}
+
+/// Clip actions that take every range in cuts out of the edit. Clips are what is kept, so this
+/// trims the clips that overlap a cut, and splits the ones that a cut lands in the middle of
+pub fn cut_actions(
+    clips: &[c_bindings::Clip],
+    total_runtime: f32,
+    cuts: &[Range<f32>],
+) -> Vec<Action> {
+    let mut cuts = cuts.iter().filter(|cut| !cut.is_empty()).peekable();
+    if cuts.peek().is_none() {
+        return Vec::new();
+    }
+
+    let mut spans: Vec<KeptSpan> = if clips.is_empty() {
+        // Everything is kept, so keep everything around the cuts
+        vec![KeptSpan {
+            id: None,
+            start: 0.0,
+            end: total_runtime,
+        }]
+    } else {
+        clips
+            .iter()
+            .map(|clip| KeptSpan {
+                id: Some(clip.id),
+                start: clip.start,
+                end: clip.end,
+            })
+            .collect()
+    };
+
+    for cut in cuts {
+        let mut next = Vec::with_capacity(spans.len());
+        for span in spans {
+            if span.end <= cut.start || span.start >= cut.end {
+                next.push(span);
+                continue;
+            }
+
+            let keeps_start = span.start < cut.start;
+            if keeps_start {
+                next.push(KeptSpan {
+                    end: cut.start,
+                    ..span
+                });
+            }
+            if span.end > cut.end {
+                // The clip stays with the part before the cut if there is one
+                next.push(KeptSpan {
+                    id: if keeps_start { None } else { span.id },
+                    start: cut.end,
+                    end: span.end,
+                });
+            }
+        }
+        spans = next;
+    }
+
+    // The backend finds clips to remove by position, so removals go first while the clips are
+    // still where we think they are
+    let mut ret: Vec<Action> = clips
+        .iter()
+        .filter(|clip| !spans.iter().any(|span| span.id == Some(clip.id)))
+        .map(|clip| Action::ClipRemove((clip.start + clip.end) / 2.0))
+        .collect();
+
+    for clip in clips {
+        let Some(span) = spans.iter().find(|span| span.id == Some(clip.id)) else {
+            continue;
+        };
+
+        if span.start != clip.start || span.end != clip.end {
+            ret.push(Action::ClipEdit(c_bindings::Clip {
+                start: span.start,
+                end: span.end,
+                ..*clip
+            }));
+        }
+    }
+
+    ret.extend(spans.iter().filter(|span| span.id.is_none()).map(|span| {
+        Action::ClipAdd(c_bindings::Clip {
+            id: 0,
+            start: span.start,
+            end: span.end,
+            speed: 1.0,
+            freeze: false,
+        })
+    }));
+
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::mock::clip;
+
+    fn word(char_start: u64, char_end: u64, start: f32, end: f32) -> c_bindings::GuiWord {
+        c_bindings::GuiWord {
+            char_start,
+            char_end,
+            start,
+            end,
+            confidence: 1.0,
+        }
+    }
+
+    fn spans(actions: &[Action]) -> Vec<(&'static str, f32, f32)> {
+        actions
+            .iter()
+            .map(|action| match action {
+                Action::ClipAdd(clip) => ("add", clip.start, clip.end),
+                Action::ClipEdit(clip) => ("edit", clip.start, clip.end),
+                Action::ClipRemove(pos) => ("remove", *pos, *pos),
+                action => panic!("unexpected action {action:?}"),
+            })
+            .collect()
+    }
+
+    // "one two three"
+    const WORDS: [c_bindings::GuiWord; 3] = [
+        c_bindings::GuiWord {
+            char_start: 0,
+            char_end: 3,
+            start: 0.0,
+            end: 1.0,
+            confidence: 1.0,
+        },
+        c_bindings::GuiWord {
+            char_start: 3,
+            char_end: 7,
+            start: 1.0,
+            end: 2.0,
+            confidence: 1.0,
+        },
+        c_bindings::GuiWord {
+            char_start: 7,
+            char_end: 13,
+            start: 2.5,
+            end: 3.0,
+            confidence: 1.0,
+        },
+    ];
+
+    #[test]
+    fn selection_snaps_to_whole_words() {
+        let mut selection = ScriptSelection::new();
+        selection.start(5, &WORDS);
+        assert_eq!(selection.range, Some(3..7));
+
+        selection.update(1, &WORDS);
+        assert_eq!(selection.range, Some(0..7));
+
+        selection.update(9, &WORDS);
+        selection.finish();
+        assert!(!selection.is_dragging());
+        assert_eq!(selection.range, Some(3..13));
+    }
+
+    #[test]
+    fn selection_covers_the_words_time() {
+        assert_eq!(selected_time_range(&WORDS, 3..13), Some(1.0..3.0));
+        assert_eq!(selected_time_range(&WORDS, 20..25), None);
+    }
+
+    #[test]
+    fn cutting_without_clips_keeps_both_sides() {
+        let actions = cut_actions(&[], 10.0, &[2.0..3.0]);
+        assert_eq!(spans(&actions), [("add", 0.0, 2.0), ("add", 3.0, 10.0)]);
+
+        let actions = cut_actions(&[], 10.0, &[0.0..3.0]);
+        assert_eq!(spans(&actions), [("add", 3.0, 10.0)]);
+    }
+
+    #[test]
+    fn cutting_trims_splits_and_removes_clips() {
+        let clips = [
+            clip(1, 0.0, 2.5),
+            clip(2, 3.0, 4.0),
+            clip(3, 4.5, 6.0),
+            clip(4, 7.0, 9.0),
+        ];
+        let actions = cut_actions(&clips, 10.0, &[2.0..5.0]);
+        assert_eq!(
+            spans(&actions),
+            [("remove", 3.5, 3.5), ("edit", 0.0, 2.0), ("edit", 5.0, 6.0),]
+        );
+
+        let actions = cut_actions(&clips, 10.0, &[7.5..8.0]);
+        assert_eq!(spans(&actions), [("edit", 7.0, 7.5), ("add", 8.0, 9.0)]);
+    }
+
+    #[test]
+    fn cuts_apply_on_top_of_each_other() {
+        let actions = cut_actions(&[], 10.0, &[2.0..3.0, 5.0..6.0, 9.0..10.0]);
+        assert_eq!(
+            spans(&actions),
+            [("add", 0.0, 2.0), ("add", 3.0, 5.0), ("add", 6.0, 9.0)]
+        );
+
+        let clips = [clip(1, 0.0, 4.0)];
+        let actions = cut_actions(&clips, 10.0, &[1.0..2.0, 0.0..0.5]);
+        assert_eq!(spans(&actions), [("edit", 0.5, 1.0), ("add", 2.0, 4.0)]);
+
+        assert!(cut_actions(&clips, 10.0, &[]).is_empty());
+    }
+
+    #[test]
+    fn words_outside_clips_are_cut() {
+        let clips = [clip(1, 0.0, 1.2)];
+        assert!(!is_cut(&word(0, 3, 0.0, 1.0), &clips));
+        assert!(is_cut(&word(3, 7, 1.0, 2.0), &clips));
+        assert!(!is_cut(&word(3, 7, 1.0, 2.0), &[]));
+    }
+}
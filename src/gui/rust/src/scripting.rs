@@ -0,0 +1,432 @@
+use eframe::egui;
+
+use std::path::{Path, PathBuf};
+
+use crate::{actions::Action, c_bindings, config, ActionRequestor};
+
+const EXAMPLE: &str = "\
+// A marker every 10 minutes
+for t in range(600, duration().to_int(), 600) {
+    add_marker(t, `${t / 60} min`);
+}
+
+// Drop clips shorter than half a second
+for clip in clips() {
+    if clip.end - clip.start < 0.5 {
+        remove_clip_at((clip.start + clip.end) / 2.0);
+    }
+}
+";
+
+const FUNCTIONS: &str = "\
+position(), duration(), frame_duration(), paused()
+clips()    [#{id, start, end, speed, freeze}] in playback order
+markers()  [#{id, time, name}] sorted by time
+seek(t), set_speed(x), save()
+add_clip(start, end), remove_clip_at(t)
+add_marker(t, name), remove_marker(id)";
+
+/// What a script produced. Actions are only sent once the whole script ran, so that one failing
+/// half way through leaves the edit alone
+#[derive(Debug, Default)]
+#[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+pub struct ScriptRun {
+    pub actions: Vec<Action>,
+    pub output: Vec<String>,
+}
+
+/// Runs rhai scripts against the edit, with a read only view of the state and functions that
+/// queue up actions. Scripts saved in ~/.config/video-editor/scripts can be run from the list
+/// without opening them first
+pub struct ScriptConsole {
+    pub open: bool,
+    source: String,
+    /// print() lines of the last run followed by its error, if any
+    output: Vec<String>,
+    /// Rescanned whenever the console is opened
+    saved: Vec<PathBuf>,
+    was_open: bool,
+}
+
+impl ScriptConsole {
+    pub fn new() -> ScriptConsole {
+        ScriptConsole {
+            open: false,
+            source: EXAMPLE.to_string(),
+            output: Vec::new(),
+            saved: Vec::new(),
+            was_open: false,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        if self.open && !self.was_open {
+            self.saved = saved_script_paths();
+        }
+        self.was_open = self.open;
+
+        let mut open = self.open;
+        egui::Window::new("Script console")
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                let mut to_run = None;
+                ui.horizontal(|ui| {
+                    // Consumed before the editor would turn it into a newline
+                    let run_shortcut =
+                        ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter));
+                    if ui.button("Run").on_hover_text("ctrl+enter").clicked() || run_shortcut {
+                        to_run = Some(self.source.clone());
+                    }
+
+                    ui.menu_button("Saved scripts", |ui| {
+                        if self.saved.is_empty() {
+                            ui.weak("No .rhai files in ~/.config/video-editor/scripts");
+                        }
+                        for path in &self.saved {
+                            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                            ui.horizontal(|ui| {
+                                ui.label(name);
+                                if ui.small_button("Run").clicked() {
+                                    to_run = read_script(path, &mut self.output);
+                                    ui.close_menu();
+                                }
+                                if ui.small_button("Open").clicked() {
+                                    if let Some(source) = read_script(path, &mut self.output) {
+                                        self.source = source;
+                                    }
+                                    ui.close_menu();
+                                }
+                            });
+                        }
+                    });
+                });
+
+                ui.collapsing("Functions", |ui| ui.monospace(FUNCTIONS));
+
+                egui::ScrollArea::vertical()
+                    .id_source("script_source")
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut self.source)
+                                .code_editor()
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .id_source("script_output")
+                    .max_height(150.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in &self.output {
+                            ui.monospace(line);
+                        }
+                    });
+
+                if let Some(source) = to_run {
+                    self.output = run_and_send(&source, state, action_tx);
+                }
+            });
+        self.open = open;
+    }
+
+    /// Runs a saved script without going through the editor, opening the console to show how it
+    /// went
+    pub fn run_saved(
+        &mut self,
+        path: &Path,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        if let Some(source) = read_script(path, &mut self.output) {
+            self.output = run_and_send(&source, state, action_tx);
+        }
+        self.open = true;
+    }
+}
+
+/// Output of the run, with the number of queued actions or the error at the end
+fn run_and_send(
+    source: &str,
+    state: &c_bindings::AppStateSnapshot,
+    action_tx: &mut ActionRequestor,
+) -> Vec<String> {
+    match run(source, state) {
+        Ok(run) => {
+            let mut output = run.output;
+            output.push(format!("Done, {} actions sent", run.actions.len()));
+            for action in run.actions {
+                action_tx.send(action);
+            }
+            output
+        }
+        Err(e) => vec![e],
+    }
+}
+
+fn read_script(path: &Path, output: &mut Vec<String>) -> Option<String> {
+    match std::fs::read_to_string(path) {
+        Ok(source) => Some(source),
+        Err(e) => {
+            *output = vec![format!("Failed to read {}: {e}", path.display())];
+            None
+        }
+    }
+}
+
+/// Scripts in ~/.config/video-editor/scripts, sorted by name
+pub fn saved_script_paths() -> Vec<PathBuf> {
+    config::config_path("scripts")
+        .map(|dir| saved_scripts(&dir))
+        .unwrap_or_default()
+}
+
+/// Sorted by name, a missing directory just means there are none yet
+fn saved_scripts(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut ret: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+        .collect();
+    ret.sort();
+    ret
+}
+
+#[cfg(not(feature = "scripting"))]
+pub fn run(_source: &str, _state: &c_bindings::AppStateSnapshot) -> Result<ScriptRun, String> {
+    Err("Built without scripting, rebuild with -Dscripting=true".to_string())
+}
+
+#[cfg(feature = "scripting")]
+pub use engine::run;
+
+#[cfg(feature = "scripting")]
+mod engine {
+    use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, INT};
+
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::ScriptRun;
+    use crate::{actions::Action, c_bindings, markers, transcript};
+
+    /// Keeps an endless loop from hanging the GUI, generous enough for walking every word of a
+    /// long stream
+    const MAX_OPERATIONS: u64 = 10_000_000;
+
+    /// Copy of the parts of the snapshot scripts can see, the snapshot itself does not outlive
+    /// the frame
+    struct StateView {
+        position: f32,
+        duration: f32,
+        frame_duration: f32,
+        paused: bool,
+        clips: Vec<c_bindings::Clip>,
+        markers: Vec<c_bindings::GuiMarker>,
+    }
+
+    impl StateView {
+        fn new(state: &c_bindings::AppStateSnapshot) -> StateView {
+            StateView {
+                position: state.current_position,
+                duration: state.total_runtime,
+                frame_duration: state.frame_duration,
+                paused: state.paused,
+                clips: transcript::clips(state).to_vec(),
+                markers: markers::markers(state).to_vec(),
+            }
+        }
+
+        fn clips(&self) -> Array {
+            self.clips
+                .iter()
+                .map(|clip| {
+                    let mut map = Map::new();
+                    map.insert("id".into(), (clip.id as INT).into());
+                    map.insert("start".into(), Dynamic::from_float(clip.start.into()));
+                    map.insert("end".into(), Dynamic::from_float(clip.end.into()));
+                    map.insert("speed".into(), Dynamic::from_float(clip.speed.into()));
+                    map.insert("freeze".into(), clip.freeze.into());
+                    Dynamic::from_map(map)
+                })
+                .collect()
+        }
+
+        fn markers(&self) -> Array {
+            self.markers
+                .iter()
+                .map(|marker| {
+                    let mut map = Map::new();
+                    map.insert("id".into(), (marker.id as INT).into());
+                    map.insert("time".into(), Dynamic::from_float(marker.time.into()));
+                    map.insert("name".into(), markers::marker_name(marker).into());
+                    Dynamic::from_map(map)
+                })
+                .collect()
+        }
+    }
+
+    /// Times can be written as ints or floats, 600 is as good as 600.0
+    fn seconds(value: Dynamic) -> Result<f32, Box<EvalAltResult>> {
+        if let Ok(f) = value.as_float() {
+            return Ok(f as f32);
+        }
+        if let Ok(i) = value.as_int() {
+            return Ok(i as f32);
+        }
+        Err(format!("expected a time in seconds, got {}", value.type_name()).into())
+    }
+
+    pub fn run(source: &str, state: &c_bindings::AppStateSnapshot) -> Result<ScriptRun, String> {
+        let view = Rc::new(StateView::new(state));
+        let run = Rc::new(RefCell::new(ScriptRun::default()));
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let output = Rc::clone(&run);
+        engine.on_print(move |s| output.borrow_mut().output.push(s.to_string()));
+        let output = Rc::clone(&run);
+        engine.on_debug(move |s, _, pos| {
+            output.borrow_mut().output.push(format!("{pos:?}: {s}"));
+        });
+
+        let v = Rc::clone(&view);
+        engine.register_fn("position", move || v.position as f64);
+        let v = Rc::clone(&view);
+        engine.register_fn("duration", move || v.duration as f64);
+        let v = Rc::clone(&view);
+        engine.register_fn("frame_duration", move || v.frame_duration as f64);
+        let v = Rc::clone(&view);
+        engine.register_fn("paused", move || v.paused);
+        let v = Rc::clone(&view);
+        engine.register_fn("clips", move || v.clips());
+        let v = Rc::clone(&view);
+        engine.register_fn("markers", move || v.markers());
+
+        let send = {
+            let run = Rc::clone(&run);
+            move |action: Action| run.borrow_mut().actions.push(action)
+        };
+
+        let s = send.clone();
+        engine.register_fn("seek", move |t: Dynamic| {
+            s(Action::Seek(seconds(t)?));
+            Ok::<_, Box<EvalAltResult>>(())
+        });
+        let s = send.clone();
+        engine.register_fn("set_speed", move |speed: Dynamic| {
+            s(Action::SetSpeed(seconds(speed)?));
+            Ok::<_, Box<EvalAltResult>>(())
+        });
+        let s = send.clone();
+        engine.register_fn("add_clip", move |start: Dynamic, end: Dynamic| {
+            let (start, end) = (seconds(start)?, seconds(end)?);
+            if end <= start {
+                return Err(format!("clip ends at {end} before it starts at {start}").into());
+            }
+            s(Action::ClipAdd(c_bindings::Clip {
+                id: 0,
+                start,
+                end,
+                speed: 1.0,
+                freeze: false,
+            }));
+            Ok::<_, Box<EvalAltResult>>(())
+        });
+        let s = send.clone();
+        engine.register_fn("remove_clip_at", move |t: Dynamic| {
+            s(Action::ClipRemove(seconds(t)?));
+            Ok::<_, Box<EvalAltResult>>(())
+        });
+        let s = send.clone();
+        engine.register_fn("add_marker", move |t: Dynamic, name: &str| {
+            s(Action::MarkerAdd(c_bindings::GuiMarker {
+                id: 0,
+                time: seconds(t)?,
+                color: markers::DEFAULT_COLOR,
+                name: markers::name_to_c(name),
+            }));
+            Ok::<_, Box<EvalAltResult>>(())
+        });
+        let s = send.clone();
+        engine.register_fn("remove_marker", move |id: INT| {
+            s(Action::MarkerRemove(id as u64));
+        });
+        let s = send;
+        engine.register_fn("save", move || s(Action::Save));
+
+        engine.run(source).map_err(|e| e.to_string())?;
+        drop(engine);
+
+        Rc::try_unwrap(run)
+            .map(RefCell::into_inner)
+            .map_err(|_| "script state still in use".to_string())
+    }
+}
+
+#[cfg(all(test, feature = "scripting"))]
+mod test {
+    use super::*;
+    use crate::backend::mock::clip;
+
+    fn state(clips: &[c_bindings::Clip]) -> c_bindings::AppStateSnapshot {
+        let mut state: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+        state.clips = clips.as_ptr();
+        state.num_clips = clips.len() as u64;
+        state.total_runtime = 1900.0;
+        state
+    }
+
+    #[test]
+    fn example_script_queues_actions() {
+        let clips = [clip(1, 0.0, 10.0), clip(2, 20.0, 20.25)];
+        let run = run(EXAMPLE, &state(&clips)).unwrap();
+
+        let marker_times: Vec<f32> = run
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::MarkerAdd(marker) => Some(marker.time),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(marker_times, [600.0, 1200.0, 1800.0]);
+
+        let removed: Vec<f32> = run
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::ClipRemove(t) => Some(*t),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(removed, [20.125]);
+    }
+
+    #[test]
+    fn failing_scripts_send_nothing() {
+        let err = run("seek(1); add_clip(5, 2);", &state(&[])).unwrap_err();
+        assert!(err.contains("before it starts"), "{err}");
+
+        assert!(run("loop {}", &state(&[])).is_err());
+    }
+
+    #[test]
+    fn print_goes_to_the_output() {
+        let run = run("print(clips().len())", &state(&[clip(1, 0.0, 1.0)])).unwrap();
+        assert_eq!(run.output, ["1"]);
+        assert!(run.actions.is_empty());
+    }
+}
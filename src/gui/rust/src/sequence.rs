@@ -0,0 +1,129 @@
+use eframe::egui;
+
+use crate::{actions::Action, c_bindings, clip_properties, transcript, ActionRequestor};
+
+/// The clips in the order the edited video plays them. Rows can be dragged around, so the edit
+/// does not have to follow the source
+pub struct SequencePanel {
+    pub open: bool,
+}
+
+/// Drag payload, position in the sequence the drag started at
+struct DraggedClip(usize);
+
+impl SequencePanel {
+    pub fn new() -> SequencePanel {
+        SequencePanel { open: false }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let clips = transcript::clips(state);
+
+        let mut open = self.open;
+        egui::Window::new("Sequence")
+            .open(&mut open)
+            .default_width(260.0)
+            .show(ctx, |ui| {
+                if clips.is_empty() {
+                    ui.weak("No clips yet");
+                    return;
+                }
+
+                let total: f32 = clips
+                    .iter()
+                    .map(|clip| (clip.end - clip.start) / clip.speed)
+                    .sum();
+                ui.label(format!("{} clips, {total:.1}s", clips.len()));
+                ui.weak("Drag clips to change the order they play in");
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (i, clip) in clips.iter().enumerate() {
+                            let id = egui::Id::new(("sequence clip", clip.id));
+                            let response = ui
+                                .dnd_drag_source(id, DraggedClip(i), |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label(format!("{}.", i + 1));
+                                        ui.label(format!("{:.1}–{:.1}", clip.start, clip.end));
+                                        ui.weak(format!(
+                                            "{:.1}s",
+                                            (clip.end - clip.start) / clip.speed
+                                        ));
+                                        if clip.speed != 1.0 {
+                                            ui.weak(clip_properties::speed_label(clip.speed));
+                                        }
+                                        if clip.freeze {
+                                            ui.weak("freeze");
+                                        }
+                                    });
+                                })
+                                .response;
+
+                            let below = ui
+                                .input(|i| i.pointer.interact_pos())
+                                .is_some_and(|pos| pos.y > response.rect.center().y);
+
+                            if response.dnd_hover_payload::<DraggedClip>().is_some() {
+                                let y = if below {
+                                    response.rect.bottom()
+                                } else {
+                                    response.rect.top()
+                                };
+                                ui.painter().hline(
+                                    response.rect.x_range(),
+                                    y,
+                                    ui.visuals().selection.stroke,
+                                );
+                            }
+
+                            if let Some(dragged) = response.dnd_release_payload::<DraggedClip>() {
+                                let index = drop_index(dragged.0, i, below);
+                                if index != dragged.0 {
+                                    action_tx.send(Action::ClipMove(c_bindings::GuiClipMove {
+                                        id: clips[dragged.0].id,
+                                        index: index as u64,
+                                    }));
+                                }
+                            }
+                        }
+                    });
+            });
+        self.open = open;
+    }
+}
+
+/// Where the clip dragged from `from` ends up when dropped above or below the row at `target`.
+/// Counted after it was taken out, like GuiClipMove
+fn drop_index(from: usize, target: usize, below: bool) -> usize {
+    let before = if below { target + 1 } else { target };
+    if from < before {
+        before - 1
+    } else {
+        before
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn drops_account_for_the_dragged_clip() {
+        // Down past the third clip
+        assert_eq!(drop_index(0, 2, true), 2);
+        assert_eq!(drop_index(0, 2, false), 1);
+        // Up to the top
+        assert_eq!(drop_index(3, 0, false), 0);
+        assert_eq!(drop_index(3, 0, true), 1);
+        // Onto itself
+        assert_eq!(drop_index(1, 1, false), 1);
+        assert_eq!(drop_index(1, 1, true), 1);
+    }
+}
@@ -0,0 +1,187 @@
+use eframe::egui;
+
+use crate::{c_bindings, markers, mute, transcript};
+
+/// Pulls timeline drags onto nearby points of interest. Every drag on the timeline goes through
+/// here, so the toggle and the distance apply to all of them
+pub struct Snapping {
+    pub enabled: bool,
+    /// How close a target has to be on screen to pull
+    pub distance_px: f32,
+    /// Most drags move the playhead along with them, so it only counts as a target where it was
+    /// before the drag started
+    playhead: f32,
+}
+
+/// What a drag can snap to besides the frame grid
+pub struct SnapTargets<'a> {
+    pub state: &'a c_bindings::AppStateSnapshot,
+    /// In text order, see TranscriptLayout::words
+    pub words: &'a [c_bindings::GuiWord],
+    /// The clip being dragged, its own edges would hold it in place
+    pub exclude_clip: Option<u64>,
+    /// Same as exclude_clip for mute regions, which have ids of their own
+    pub exclude_mute_region: Option<u64>,
+}
+
+impl Snapping {
+    pub fn new() -> Snapping {
+        Snapping {
+            enabled: true,
+            distance_px: 8.0,
+            playhead: 0.0,
+        }
+    }
+
+    /// Has to run every frame before anything is snapped
+    pub fn update(&mut self, ui: &egui::Ui, state: &c_bindings::AppStateSnapshot) {
+        if !ui.input(|i| i.pointer.any_down()) {
+            self.playhead = state.current_position;
+        }
+    }
+
+    /// Magnet toggle for the controls row, the distance is in its context menu
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.toggle_value(&mut self.enabled, "Snap")
+            .on_hover_text(
+                "Snap timeline drags to the playhead, clip and mute edges, markers, words and \
+                 frames. Right click to change the distance",
+            )
+            .context_menu(|ui| {
+                ui.add(
+                    egui::Slider::new(&mut self.distance_px, 1.0..=32.0)
+                        .text("Snap distance (px)")
+                        .integer(),
+                );
+            });
+    }
+
+    /// time moved onto the closest target within reach, or onto the closest frame if there is
+    /// none
+    pub fn snap(&self, time: f32, seconds_per_px: f32, targets: &SnapTargets) -> f32 {
+        if !self.enabled {
+            return time;
+        }
+
+        let max_distance = self.distance_px * seconds_per_px;
+        let nearest = targets
+            .times(self.playhead)
+            .min_by(|a, b| (a - time).abs().total_cmp(&(b - time).abs()));
+
+        match nearest {
+            Some(target) if (target - time).abs() <= max_distance => target,
+            _ => snap_to_grid(time, targets.state.frame_duration, max_distance),
+        }
+    }
+}
+
+impl SnapTargets<'_> {
+    fn times(&self, playhead: f32) -> impl Iterator<Item = f32> + '_ {
+        let clips = transcript::clips(self.state)
+            .iter()
+            .filter(|clip| Some(clip.id) != self.exclude_clip)
+            .flat_map(|clip| [clip.start, clip.end]);
+        let mute_regions = mute::mute_regions(self.state)
+            .iter()
+            .filter(|region| Some(region.id) != self.exclude_mute_region)
+            .flat_map(|region| [region.start, region.end]);
+        let markers = markers::markers(self.state)
+            .iter()
+            .map(|marker| marker.time);
+        let words = self.words.iter().flat_map(|word| [word.start, word.end]);
+
+        std::iter::once(playhead)
+            .chain(clips)
+            .chain(mute_regions)
+            .chain(markers)
+            .chain(words)
+    }
+}
+
+/// frame_duration is 0 while the backend does not know it
+fn snap_to_grid(time: f32, frame_duration: f32, max_distance: f32) -> f32 {
+    if frame_duration <= 0.0 {
+        return time;
+    }
+
+    let snapped = (time / frame_duration).round() * frame_duration;
+    if (snapped - time).abs() <= max_distance {
+        snapped
+    } else {
+        time
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CLIPS: [c_bindings::Clip; 2] = [
+        c_bindings::Clip {
+            id: 1,
+            start: 2.0,
+            end: 4.0,
+            speed: 1.0,
+            freeze: false,
+        },
+        c_bindings::Clip {
+            id: 2,
+            start: 6.0,
+            end: 8.0,
+            speed: 1.0,
+            freeze: false,
+        },
+    ];
+
+    fn state(frame_duration: f32) -> c_bindings::AppStateSnapshot {
+        let mut state: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+        state.clips = CLIPS.as_ptr();
+        state.num_clips = CLIPS.len() as u64;
+        state.frame_duration = frame_duration;
+        state
+    }
+
+    #[test]
+    fn snaps_to_targets_within_reach() {
+        let state = state(0.0);
+        let targets = SnapTargets {
+            state: &state,
+            words: &[],
+            exclude_clip: None,
+            exclude_mute_region: None,
+        };
+        let snapping = Snapping::new();
+
+        // 8px at 0.01s per px
+        assert_eq!(snapping.snap(5.95, 0.01, &targets), 6.0);
+        assert_eq!(snapping.snap(5.5, 0.01, &targets), 5.5);
+    }
+
+    #[test]
+    fn dragged_clip_does_not_snap_to_itself() {
+        let state = state(0.0);
+        let targets = SnapTargets {
+            state: &state,
+            words: &[],
+            exclude_clip: Some(2),
+            exclude_mute_region: None,
+        };
+        assert_eq!(Snapping::new().snap(5.95, 0.01, &targets), 5.95);
+    }
+
+    #[test]
+    fn falls_back_to_the_frame_grid() {
+        let state = state(0.5);
+        let targets = SnapTargets {
+            state: &state,
+            words: &[],
+            exclude_clip: None,
+            exclude_mute_region: None,
+        };
+        let mut snapping = Snapping::new();
+        assert_eq!(snapping.snap(5.3, 0.05, &targets), 5.5);
+
+        snapping.enabled = false;
+        assert_eq!(snapping.snap(5.3, 0.05, &targets), 5.3);
+    }
+}
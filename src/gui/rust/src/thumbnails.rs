@@ -0,0 +1,266 @@
+use eframe::egui;
+
+use std::collections::HashMap;
+
+use crate::{actions::Action, c_bindings, events::Event, ActionRequestor};
+
+/// How many thumbnails stay in memory before the least recently used ones get dropped
+const DEFAULT_CAPACITY: usize = 256;
+
+/// Identifies a thumbnail. Times are rounded to the millisecond so that callers asking for the
+/// "same" time from different floating point math still hit the cache
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ThumbnailKey {
+    time_ms: u64,
+    width: u32,
+    height: u32,
+}
+
+impl ThumbnailKey {
+    pub fn new(time: f32, size: [u32; 2]) -> ThumbnailKey {
+        ThumbnailKey {
+            time_ms: (time.max(0.0) * 1000.0).round() as u64,
+            width: size[0],
+            height: size[1],
+        }
+    }
+
+    fn time(&self) -> f32 {
+        self.time_ms as f32 / 1000.0
+    }
+}
+
+enum EntryState {
+    /// Requested, waiting on the backend
+    Pending,
+    Ready(egui::TextureHandle),
+    /// Kept around so that we do not keep asking for a thumbnail the backend cannot make
+    Failed,
+}
+
+struct Entry {
+    state: EntryState,
+    /// ThumbnailCache::frame of the last lookup
+    last_used: u64,
+}
+
+/// Thumbnails come from the backend asynchronously. get() requests whatever is missing and hands
+/// out what has arrived, making sure that every thumbnail is only in flight once
+pub struct ThumbnailCache {
+    entries: HashMap<ThumbnailKey, Entry>,
+    /// Requests the backend has not answered yet
+    in_flight: HashMap<u64, ThumbnailKey>,
+    next_request_id: u64,
+    /// Incremented on every end_frame, used to find the least recently used entries
+    frame: u64,
+    capacity: usize,
+}
+
+impl ThumbnailCache {
+    pub fn new() -> ThumbnailCache {
+        ThumbnailCache::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> ThumbnailCache {
+        ThumbnailCache {
+            entries: HashMap::new(),
+            in_flight: HashMap::new(),
+            next_request_id: 0,
+            frame: 0,
+            capacity,
+        }
+    }
+
+    /// Returns the thumbnail if it is available, otherwise requests it from the backend and
+    /// returns None until it shows up
+    pub fn get(
+        &mut self,
+        action_tx: &mut ActionRequestor,
+        key: ThumbnailKey,
+    ) -> Option<egui::TextureHandle> {
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.last_used = self.frame;
+            return match &entry.state {
+                EntryState::Ready(texture) => Some(texture.clone()),
+                EntryState::Pending | EntryState::Failed => None,
+            };
+        }
+
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+
+        action_tx.send(Action::RequestThumbnail(c_bindings::GuiThumbnailRequest {
+            request_id,
+            time: key.time(),
+            width: key.width,
+            height: key.height,
+        }));
+
+        self.in_flight.insert(request_id, key);
+        self.entries.insert(
+            key,
+            Entry {
+                state: EntryState::Pending,
+                last_used: self.frame,
+            },
+        );
+
+        None
+    }
+
+    pub fn handle_event(&mut self, ctx: &egui::Context, event: Event) {
+        let (request_id, state) = match event {
+            Event::ThumbnailReady { request_id, image } => {
+                let name = format!("thumbnail_{request_id}");
+                let texture = ctx.load_texture(name, image, egui::TextureOptions::LINEAR);
+                (request_id, EntryState::Ready(texture))
+            }
+            Event::ThumbnailFailed { request_id } => (request_id, EntryState::Failed),
+            // Not a thumbnail event
+            _ => return,
+        };
+
+        // Unknown ids are answers to requests we never made, nothing to do with those
+        let Some(key) = self.in_flight.remove(&request_id) else {
+            return;
+        };
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.state = state;
+        }
+    }
+
+    /// Forgets every thumbnail, e.g. after the source changed. Answers to requests that were
+    /// in flight are ignored when they arrive
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.in_flight.clear();
+    }
+
+    /// Evicts the least recently used thumbnails that exceed the capacity. Pending entries are
+    /// kept so that they are not requested a second time
+    pub fn end_frame(&mut self) {
+        self.frame += 1;
+
+        let num_to_evict = self.entries.len().saturating_sub(self.capacity);
+        if num_to_evict == 0 {
+            return;
+        }
+
+        let mut candidates: Vec<(u64, ThumbnailKey)> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| !matches!(entry.state, EntryState::Pending))
+            .map(|(key, entry)| (entry.last_used, *key))
+            .collect();
+        candidates.sort_unstable_by_key(|(last_used, _)| *last_used);
+
+        for (_, key) in candidates.into_iter().take(num_to_evict) {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::{atomic::AtomicUsize, mpsc, Arc};
+
+    fn requestor() -> (ActionRequestor, mpsc::Receiver<Action>) {
+        let (action_tx, action_rx) = mpsc::channel();
+        let requestor = ActionRequestor::new(action_tx, Arc::new(AtomicUsize::new(0)));
+        (requestor, action_rx)
+    }
+
+    fn sent_requests(
+        action_tx: &mut ActionRequestor,
+        action_rx: &mpsc::Receiver<Action>,
+    ) -> Vec<u64> {
+        action_tx.flush();
+        action_rx
+            .try_iter()
+            .filter_map(|action| match action {
+                Action::RequestThumbnail(request) => Some(request.request_id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn ready(request_id: u64) -> Event {
+        Event::ThumbnailReady {
+            request_id,
+            image: egui::ColorImage::new([1, 1], egui::Color32::BLACK),
+        }
+    }
+
+    #[test]
+    fn in_flight_requests_are_deduplicated() {
+        let (mut action_tx, action_rx) = requestor();
+        let mut cache = ThumbnailCache::new();
+        let key = ThumbnailKey::new(1.0, [160, 90]);
+
+        assert!(cache.get(&mut action_tx, key).is_none());
+        cache.end_frame();
+        assert!(cache.get(&mut action_tx, key).is_none());
+        // Rounds to the same millisecond
+        assert!(cache
+            .get(&mut action_tx, ThumbnailKey::new(1.0001, [160, 90]))
+            .is_none());
+
+        assert_eq!(sent_requests(&mut action_tx, &action_rx), [0]);
+    }
+
+    #[test]
+    fn ready_thumbnails_are_served_from_cache() {
+        let ctx = egui::Context::default();
+        let (mut action_tx, action_rx) = requestor();
+        let mut cache = ThumbnailCache::new();
+        let key = ThumbnailKey::new(1.0, [160, 90]);
+
+        cache.get(&mut action_tx, key);
+        cache.handle_event(&ctx, ready(0));
+
+        assert!(cache.get(&mut action_tx, key).is_some());
+        assert_eq!(sent_requests(&mut action_tx, &action_rx), [0]);
+    }
+
+    #[test]
+    fn failed_thumbnails_are_not_rerequested() {
+        let ctx = egui::Context::default();
+        let (mut action_tx, action_rx) = requestor();
+        let mut cache = ThumbnailCache::new();
+        let key = ThumbnailKey::new(1.0, [160, 90]);
+
+        cache.get(&mut action_tx, key);
+        cache.handle_event(&ctx, Event::ThumbnailFailed { request_id: 0 });
+
+        assert!(cache.get(&mut action_tx, key).is_none());
+        assert_eq!(sent_requests(&mut action_tx, &action_rx), [0]);
+    }
+
+    #[test]
+    fn least_recently_used_thumbnails_are_evicted() {
+        let ctx = egui::Context::default();
+        let (mut action_tx, action_rx) = requestor();
+        let mut cache = ThumbnailCache::with_capacity(2);
+        let keys = [0.0, 1.0, 2.0].map(|time| ThumbnailKey::new(time, [160, 90]));
+
+        for (request_id, key) in keys.iter().enumerate() {
+            // Touch the oldest before the last insert so that the second one is the least
+            // recently used
+            if request_id == 2 {
+                assert!(cache.get(&mut action_tx, keys[0]).is_some());
+            }
+
+            cache.get(&mut action_tx, *key);
+            cache.handle_event(&ctx, ready(request_id as u64));
+            cache.end_frame();
+        }
+
+        assert!(cache.get(&mut action_tx, keys[0]).is_some());
+        assert!(cache.get(&mut action_tx, keys[2]).is_some());
+        assert!(cache.get(&mut action_tx, keys[1]).is_none());
+        assert_eq!(sent_requests(&mut action_tx, &action_rx), [0, 1, 2, 3]);
+    }
+}
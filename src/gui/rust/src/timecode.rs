@@ -0,0 +1,154 @@
+use eframe::egui;
+
+use crate::{actions::Action, c_bindings, ActionRequestor};
+
+/// Asks for a timecode, typed or pasted from review notes, and seeks to it
+pub struct GoToTimecode {
+    pub open: bool,
+    text: String,
+    error: Option<String>,
+    /// Focus goes to the text field the first frame after opening
+    was_open: bool,
+}
+
+impl GoToTimecode {
+    pub fn new() -> GoToTimecode {
+        GoToTimecode {
+            open: false,
+            text: String::new(),
+            error: None,
+            was_open: false,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let just_opened = self.open && !self.was_open;
+        self.was_open = self.open;
+
+        let mut open = self.open;
+        let mut go = false;
+        egui::Window::new("Go to timecode")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.text)
+                            .hint_text("HH:MM:SS.mmm")
+                            .desired_width(120.0),
+                    );
+                    if just_opened {
+                        response.request_focus();
+                    }
+                    if response.changed() {
+                        self.error = None;
+                    }
+
+                    let enter =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    go = ui.button("Go").clicked() || enter;
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+
+        if go {
+            match parse(&self.text) {
+                Some(pts) => {
+                    action_tx.send(Action::Seek(pts.min(state.total_runtime)));
+                    open = false;
+                }
+                None => self.error = Some(format!("\"{}\" is not a timecode", self.text.trim())),
+            }
+        }
+        self.open = open;
+    }
+}
+
+/// HH:MM:SS.mmm, e.g. for review notes
+pub fn format(pts: f32) -> String {
+    let millis = (pts.max(0.0) as f64 * 1000.0).round() as u64;
+    let secs = millis / 1000;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        millis % 1000
+    )
+}
+
+/// Accepts what format() writes, shorter forms like 1:05 or 90.5 and SRT style commas. Minutes
+/// and seconds have to stay below 60 when a bigger unit is given, which catches most typos
+pub fn parse(s: &str) -> Option<f32> {
+    let s = s.trim().replace(',', ".");
+    let parts: Vec<&str> = s.split(':').collect();
+    let (seconds, bigger) = parts.split_last()?;
+    if bigger.len() > 2 {
+        return None;
+    }
+
+    let seconds: f64 = seconds
+        .parse()
+        .ok()
+        .filter(|v: &f64| v.is_finite() && *v >= 0.0)?;
+    if !bigger.is_empty() && seconds >= 60.0 {
+        return None;
+    }
+
+    let mut ret = seconds;
+    for (i, part) in bigger.iter().rev().enumerate() {
+        if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let value: u32 = part.parse().ok()?;
+        // Minutes, unless they are the biggest unit given
+        if i == 0 && bigger.len() == 2 && value >= 60 {
+            return None;
+        }
+        ret += value as f64 * 60f64.powi(i as i32 + 1);
+    }
+
+    Some(ret as f32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn timecodes_round_trip() {
+        assert_eq!(format(0.0), "00:00:00.000");
+        assert_eq!(format(3723.5), "01:02:03.500");
+        assert_eq!(format(-1.0), "00:00:00.000");
+
+        for pts in [0.0, 59.999, 3723.5, 36000.25] {
+            assert!((parse(&format(pts)).unwrap() - pts).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn short_forms_parse() {
+        assert_eq!(parse("1:05"), Some(65.0));
+        assert_eq!(parse(" 90.5 "), Some(90.5));
+        assert_eq!(parse("00:01:02,500"), Some(62.5));
+        assert_eq!(parse("90:00"), Some(5400.0));
+    }
+
+    #[test]
+    fn nonsense_is_rejected() {
+        for s in [
+            "", "abc", "1:2:3:4", "1:60", "1:60:00", "-5", "1::5", "1:-5", "inf",
+        ] {
+            assert_eq!(parse(s), None, "{s}");
+        }
+    }
+}
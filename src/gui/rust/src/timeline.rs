@@ -0,0 +1,1263 @@
+use eframe::egui;
+
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    actions::Action,
+    backend::Backend,
+    c_bindings, captions, clip_properties,
+    drag_out::DragOut,
+    gpu_timer::GpuTimer,
+    in_out::{self, InOutPoints},
+    markers, mute,
+    profiling::profile_scope,
+    render::{self, WaveformParams},
+    snapping::{SnapTargets, Snapping},
+    transcript,
+    trim_preview::TrimEdge,
+    ActionRequestor,
+};
+
+/// Clips that overlap another one or end before they start, the backend plays them but the
+/// result is rarely what was meant
+const INVALID_CLIP_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 140, 0);
+
+pub struct SeekState {
+    paused_on_click: bool,
+}
+
+impl SeekState {
+    pub fn new() -> SeekState {
+        SeekState {
+            paused_on_click: false,
+        }
+    }
+
+    pub fn should_toggle_pause(
+        &mut self,
+        response: &egui::Response,
+        state: &c_bindings::AppStateSnapshot,
+    ) -> bool {
+        if response.drag_started_by(egui::PointerButton::Primary) {
+            self.paused_on_click = state.paused;
+            if !state.paused {
+                return true;
+            }
+        }
+
+        if response.drag_stopped_by(egui::PointerButton::Primary)
+            // You may think we should check the current state here, but that is untrue. When we
+            // execute a seek, we may not finish the seek before the next render frame in the UI.
+            // Because of this we may not see the applied pause yet. If we manage to check this
+            // condition before the pause is applied, we will not correctly unpause orselves.
+            //
+            // The failure condition here is if we somehow change the pause state while we are
+            // seeking (i.e. pressing spacebar), in this case we may end up with an extra toggle
+            // that we didn't want, but that's a fine tradeoff here
+            && !self.paused_on_click
+        {
+            return true;
+        }
+
+        false
+    }
+}
+
+struct ClipTimelineRenderer<'a> {
+    converter: &'a ProgressPosConverter,
+    ui: &'a mut egui::Ui,
+    progress_bar: &'a mut ProgressBar,
+    state: &'a c_bindings::AppStateSnapshot,
+    words: &'a [c_bindings::GuiWord],
+    action_tx: &'a mut ActionRequestor,
+}
+
+impl ClipTimelineRenderer<'_> {
+    /// Drag handles on both edges of a clip or mute region. Returns the new edges if either of
+    /// them was dragged
+    fn drag_edges(
+        &mut self,
+        start: f32,
+        end: f32,
+        targets: &SnapTargets,
+        seek_state: &mut SeekState,
+    ) -> Option<(f32, f32)> {
+        let mut ret = None;
+
+        let sense = egui::Sense {
+            click: false,
+            drag: true,
+            focusable: false,
+        };
+
+        let start_rect = self.converter.duration_to_full_rect(start, 2.0);
+        let start_response = self.ui.allocate_rect(start_rect, sense);
+        if let Some(pos) = self.progress_bar.handle_seek(
+            self.converter,
+            &start_response,
+            targets,
+            self.action_tx,
+            seek_state,
+        ) {
+            ret = Some((pos, end));
+            self.progress_bar.trim_edge = Some(TrimEdge {
+                time: pos,
+                is_start: true,
+                pos: start_rect.center_top(),
+            });
+        }
+
+        let end_rect = self.converter.duration_to_full_rect(end, 2.0);
+        let end_response = self.ui.allocate_rect(end_rect, sense);
+        if let Some(pos) = self.progress_bar.handle_seek(
+            self.converter,
+            &end_response,
+            targets,
+            self.action_tx,
+            seek_state,
+        ) {
+            log::debug!("end pos: {pos}");
+            ret = Some((ret.map_or(start, |(start, _)| start), pos));
+            self.progress_bar.trim_edge = Some(TrimEdge {
+                time: pos,
+                is_start: false,
+                pos: end_rect.center_top(),
+            });
+        }
+
+        ret
+    }
+
+    /// Outlined and lightly filled span between start and end, returns its rect
+    fn paint_region(&self, start: f32, end: f32, color: egui::Color32) -> egui::Rect {
+        let mut rect = self.converter.rect;
+        rect.set_left(self.converter.duration_to_rect_pos(start));
+        rect.set_right(self.converter.duration_to_rect_pos(end));
+
+        let stroke = egui::Stroke { width: 2.0, color };
+        self.ui.painter().rect_stroke(rect, 0.0, stroke);
+        let feint = egui::Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b(), 20);
+        self.ui.painter().rect_filled(rect, 0.0, feint);
+
+        rect
+    }
+
+    fn render_clip(&mut self, clip: &c_bindings::Clip, invalid: bool, seek_state: &mut SeekState) {
+        let targets = SnapTargets {
+            state: self.state,
+            words: self.words,
+            exclude_clip: Some(clip.id),
+            exclude_mute_region: None,
+        };
+        let edited = self.drag_edges(clip.start, clip.end, &targets, seek_state);
+
+        let red = if invalid {
+            INVALID_CLIP_COLOR
+        } else {
+            egui::Color32::RED
+        };
+        let clip_rect = self.paint_region(clip.start, clip.end, red);
+        if clip.freeze {
+            paint_hatching(&self.ui.painter_at(clip_rect), clip_rect, red);
+        }
+
+        if invalid {
+            self.ui.painter_at(clip_rect).text(
+                clip_rect.left_bottom() + egui::vec2(4.0, -2.0),
+                egui::Align2::LEFT_BOTTOM,
+                "overlap",
+                egui::FontId::proportional(11.0),
+                red,
+            );
+        }
+
+        if clip.speed != 1.0 {
+            self.ui.painter_at(clip_rect).text(
+                clip_rect.left_top() + egui::vec2(4.0, 2.0),
+                egui::Align2::LEFT_TOP,
+                clip_properties::speed_label(clip.speed),
+                egui::FontId::proportional(11.0),
+                red,
+            );
+        }
+
+        paint_centered_label(
+            self.ui,
+            clip_rect,
+            &duration_label((clip.end - clip.start) / clip.speed),
+            red,
+        );
+
+        if let Some((start, end)) = edited {
+            let (start, end) = constrain_clip_edit(transcript::clips(self.state), clip, start, end);
+            self.action_tx.send(Action::ClipEdit(c_bindings::Clip {
+                start,
+                end,
+                ..*clip
+            }));
+        }
+    }
+
+    fn render_mute_region(
+        &mut self,
+        region: &c_bindings::GuiMuteRegion,
+        seek_state: &mut SeekState,
+    ) {
+        let targets = SnapTargets {
+            state: self.state,
+            words: self.words,
+            exclude_clip: None,
+            exclude_mute_region: Some(region.id),
+        };
+        let edited = self.drag_edges(region.start, region.end, &targets, seek_state);
+
+        let color = mute::COLOR;
+        let rect = self.paint_region(region.start, region.end, color);
+        self.ui.painter_at(rect).text(
+            rect.left_bottom() + egui::vec2(4.0, -2.0),
+            egui::Align2::LEFT_BOTTOM,
+            "muted",
+            egui::FontId::proportional(11.0),
+            color,
+        );
+
+        if let Some((start, end)) = edited {
+            self.action_tx
+                .send(Action::MuteRegionEdit(c_bindings::GuiMuteRegion {
+                    start,
+                    end,
+                    ..*region
+                }));
+        }
+    }
+}
+
+/// Keeps an edge drag from crossing into the clips on either side and flips a clip that was
+/// dragged inside out. Clips that already overlap clip are left to the warning, the drag can only
+/// make the overlap smaller
+fn constrain_clip_edit(
+    clips: &[c_bindings::Clip],
+    clip: &c_bindings::Clip,
+    start: f32,
+    end: f32,
+) -> (f32, f32) {
+    let (start, end) = (start.min(end), start.max(end));
+
+    let others = clips.iter().filter(|other| other.id != clip.id);
+    let prev_end = others
+        .clone()
+        .filter(|other| other.end <= clip.start)
+        .map(|other| other.end)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let next_start = others
+        .filter(|other| other.start >= clip.end)
+        .map(|other| other.start)
+        .fold(f32::INFINITY, f32::min);
+
+    (start.max(prev_end), end.min(next_start))
+}
+
+/// Ids of clips that overlap another clip or are empty. Touching clips, e.g. around a freeze
+/// frame, are fine
+fn invalid_clips(clips: &[c_bindings::Clip]) -> Vec<u64> {
+    clips
+        .iter()
+        .filter(|clip| {
+            let empty = clip.end <= clip.start;
+            let overlaps = clips.iter().any(|other| {
+                other.id != clip.id && other.start < clip.end && clip.start < other.end
+            });
+            empty || overlaps
+        })
+        .map(|clip| clip.id)
+        .collect()
+}
+
+/// Left out if it does not fit, e.g. when zoomed out far enough that the clip is a sliver
+fn paint_centered_label(ui: &egui::Ui, rect: egui::Rect, text: &str, color: egui::Color32) {
+    const PADDING: f32 = 4.0;
+
+    let galley =
+        ui.painter()
+            .layout_no_wrap(text.to_string(), egui::FontId::proportional(11.0), color);
+    if galley.size().x + 2.0 * PADDING > rect.width() {
+        return;
+    }
+
+    let pos = rect.center() - galley.size() / 2.0;
+    ui.painter().galley(pos, galley, color);
+}
+
+/// How long a clip plays for, e.g. "4.2s" or "1:05.3"
+fn duration_label(seconds: f32) -> String {
+    let seconds = seconds.max(0.0);
+    if seconds < 60.0 {
+        format!("{seconds:.1}s")
+    } else {
+        let minutes = (seconds / 60.0).floor();
+        format!("{minutes:.0}:{:04.1}", seconds - minutes * 60.0)
+    }
+}
+
+/// Diagonal lines across rect, marking freeze frames
+fn paint_hatching(painter: &egui::Painter, rect: egui::Rect, color: egui::Color32) {
+    const SPACING: f32 = 8.0;
+
+    let stroke = egui::Stroke::new(1.0, color.gamma_multiply(0.5));
+    let mut x = rect.left() - rect.height();
+    while x < rect.right() {
+        painter.line_segment(
+            [
+                egui::pos2(x, rect.bottom()),
+                egui::pos2(x + rect.height(), rect.top()),
+            ],
+            stroke,
+        );
+        x += SPACING;
+    }
+}
+
+/// Markers only show up on the timeline, they are edited in the markers dialog
+fn paint_marker(ui: &egui::Ui, converter: &ProgressPosConverter, marker: &c_bindings::GuiMarker) {
+    let x = converter.duration_to_rect_pos(marker.time);
+    if x < converter.rect.left() || x > converter.rect.right() {
+        return;
+    }
+
+    let color = captions::color_from_c(marker.color);
+    let painter = ui.painter_at(converter.rect);
+    painter.vline(x, converter.rect.y_range(), egui::Stroke::new(1.0, color));
+    painter.text(
+        egui::pos2(x + 2.0, converter.rect.top()),
+        egui::Align2::LEFT_TOP,
+        markers::marker_name(marker),
+        egui::FontId::proportional(11.0),
+        color,
+    );
+}
+
+/// "[" at an in point, "]" at an out point, spanning the height of the timeline
+fn paint_bracket(ui: &egui::Ui, converter: &ProgressPosConverter, time: f32, is_in: bool) {
+    const ARM: f32 = 6.0;
+
+    let x = converter.duration_to_rect_pos(time);
+    let arm_x = if is_in { x + ARM } else { x - ARM };
+    let rect = converter.rect;
+    let stroke = egui::Stroke::new(2.0, in_out::COLOR);
+
+    ui.painter_at(rect).add(egui::Shape::line(
+        vec![
+            egui::pos2(arm_x, rect.top()),
+            egui::pos2(x, rect.top()),
+            egui::pos2(x, rect.bottom()),
+            egui::pos2(arm_x, rect.bottom()),
+        ],
+        stroke,
+    ));
+}
+
+/// Conversions between "rect" space, which is the position in the window in pixels, and "audio"
+/// space, which is the normalized position in the un-zoomed audio widget.
+struct ProgressPosConverter {
+    zoom: f32,
+    widget_center_norm: f32,
+    rect: egui::Rect,
+    total_runtime: f32,
+}
+
+impl ProgressPosConverter {
+    fn duration_to_rect_pos(&self, duration_pos: f32) -> f32 {
+        let duration_pos_norm = duration_pos / self.total_runtime;
+        let duration_norm_adjusted =
+            (duration_pos_norm - self.widget_center_norm) * self.zoom + 0.5;
+        duration_norm_adjusted * self.rect.width() + self.rect.left()
+    }
+
+    fn duration_to_full_rect(&self, duration_pos: f32, width: f32) -> egui::Rect {
+        let progress_rect_cx = self.duration_to_rect_pos(duration_pos);
+        let mut progress_rect = self.rect;
+        progress_rect.set_width(width);
+        progress_rect.set_center(egui::pos2(progress_rect_cx, progress_rect.center().y));
+
+        progress_rect
+    }
+
+    fn rect_to_duration_norm(&self, x_pos_rect: f32) -> f32 {
+        let rect_pos_norm = (x_pos_rect - self.rect.left()) / self.rect.width();
+        (rect_pos_norm - 0.5) / self.zoom + self.widget_center_norm
+    }
+
+    fn rect_to_duration(&self, x_pos_rect: f32) -> f32 {
+        self.rect_to_duration_norm(x_pos_rect) * self.total_runtime
+    }
+
+    fn seconds_per_px(&self) -> f32 {
+        self.total_runtime / self.rect.width() / self.zoom
+    }
+}
+
+/// Views the timeline can jump to in one click
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ZoomPreset {
+    FitAll,
+    /// Seconds visible around the playhead
+    Window(f32),
+}
+
+impl ZoomPreset {
+    pub const ALL: [ZoomPreset; 3] = [
+        ZoomPreset::FitAll,
+        ZoomPreset::Window(10.0),
+        ZoomPreset::Window(60.0),
+    ];
+
+    fn label(self) -> String {
+        match self {
+            ZoomPreset::FitAll => "Fit".to_string(),
+            ZoomPreset::Window(seconds) if seconds >= 60.0 => {
+                format!("{:.0} min", seconds / 60.0)
+            }
+            ZoomPreset::Window(seconds) => format!("{seconds:.0} s"),
+        }
+    }
+
+    /// Shortcuts are the presets' positions in ALL, counting from 1
+    fn key(self) -> Option<egui::Key> {
+        let keys = [egui::Key::Num1, egui::Key::Num2, egui::Key::Num3];
+        let idx = ZoomPreset::ALL.iter().position(|preset| *preset == self)?;
+        keys.get(idx).copied()
+    }
+}
+
+pub struct ProgressBar {
+    zoom: f32,
+    widget_center_norm: f32,
+    /// Normalized units per second, used to keep the timeline coasting after a pan
+    pan_velocity: f32,
+    pending_clip: Option<c_bindings::Clip>,
+    pending_mute_region: Option<c_bindings::GuiMuteRegion>,
+    pub snapping: Snapping,
+    pub in_out: InOutPoints,
+    pub drag_out: DragOut,
+    /// Clip edge dragged this frame
+    pub trim_edge: Option<TrimEdge>,
+    pub gpu_timer: Arc<Mutex<GpuTimer>>,
+}
+
+impl ProgressBar {
+    pub fn new() -> ProgressBar {
+        ProgressBar {
+            zoom: 1.0,
+            widget_center_norm: 0.5,
+            pan_velocity: 0.0,
+            pending_clip: None,
+            pending_mute_region: None,
+            snapping: Snapping::new(),
+            in_out: InOutPoints::new(),
+            drag_out: DragOut::new(),
+            trim_edge: None,
+            gpu_timer: Default::default(),
+        }
+    }
+
+    /// Buttons for the controls row, the number keys pick the same presets
+    pub fn show_zoom_presets(&mut self, ui: &mut egui::Ui, state: &c_bindings::AppStateSnapshot) {
+        for preset in ZoomPreset::ALL {
+            let key = preset.key();
+            let mut response = ui.button(preset.label());
+            if let Some(key) = key {
+                response = response.on_hover_text(format!("Shortcut: {}", key.name()));
+            }
+
+            // Only the bare number keys, shift+1 and friends are someone else's shortcuts.
+            // consume_key on its own lets extra shift and alt through
+            let key_pressed = key.is_some_and(|key| {
+                !ui.ctx().wants_keyboard_input()
+                    && ui.input_mut(|i| {
+                        i.modifiers.is_none() && i.consume_key(egui::Modifiers::NONE, key)
+                    })
+            });
+            if response.clicked() || key_pressed {
+                self.apply_zoom_preset(preset, state);
+            }
+        }
+    }
+
+    /// Windows are centered on the playhead as far as the ends of the video allow
+    fn apply_zoom_preset(&mut self, preset: ZoomPreset, state: &c_bindings::AppStateSnapshot) {
+        self.pan_velocity = 0.0;
+
+        match preset {
+            ZoomPreset::FitAll => {
+                self.zoom = 1.0;
+                self.widget_center_norm = 0.5;
+            }
+            ZoomPreset::Window(seconds) => {
+                if state.total_runtime <= 0.0 {
+                    return;
+                }
+                self.zoom = (state.total_runtime / seconds).max(1.0);
+                self.widget_center_norm = state.current_position / state.total_runtime;
+                self.clamp_widget_center();
+            }
+        }
+    }
+
+    fn paint_waveform<B: Backend>(&self, ui: &egui::Ui, rect: egui::Rect, backend: &B) {
+        let params = WaveformParams {
+            zoom: self.zoom,
+            center_norm: self.widget_center_norm,
+        };
+        let callback = render::paint_callback(
+            rect,
+            backend.waveform_renderer(),
+            params,
+            Arc::clone(&self.gpu_timer),
+        );
+        ui.painter().add(callback);
+    }
+
+    fn handle_clip_creation(
+        &mut self,
+        converter: &ProgressPosConverter,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        targets: &SnapTargets,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let primary_down = response.dragged_by(egui::PointerButton::Primary);
+        let ctrl_down = ui.input(|i| i.modifiers.ctrl);
+
+        if let Some(pending_clip) = &mut self.pending_clip {
+            if response.drag_stopped_by(egui::PointerButton::Primary) {
+                action_tx.send(Action::ClipAdd(*pending_clip));
+                self.pending_clip = None;
+            } else {
+                let pos = response
+                    .interact_pointer_pos()
+                    .expect("Pointer should interact if dragging");
+                let duration_pos = converter.rect_to_duration(pos.x);
+                pending_clip.end =
+                    self.snapping
+                        .snap(duration_pos, converter.seconds_per_px(), targets);
+            }
+        } else if primary_down && ctrl_down {
+            let pos = response
+                .interact_pointer_pos()
+                .expect("Pointer should interact if dragging");
+            let duration_pos = converter.rect_to_duration(pos.x);
+            let duration_pos =
+                self.snapping
+                    .snap(duration_pos, converter.seconds_per_px(), targets);
+            self.pending_clip = Some(c_bindings::Clip {
+                id: 0,
+                start: duration_pos,
+                end: duration_pos,
+                speed: 1.0,
+                freeze: false,
+            });
+        }
+    }
+
+    /// Same as clip creation, with alt instead of ctrl
+    fn handle_mute_region_creation(
+        &mut self,
+        converter: &ProgressPosConverter,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        targets: &SnapTargets,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let primary_down = response.dragged_by(egui::PointerButton::Primary);
+        let alt_down = ui.input(|i| i.modifiers.alt);
+
+        if let Some(pending) = &mut self.pending_mute_region {
+            if response.drag_stopped_by(egui::PointerButton::Primary) {
+                action_tx.send(Action::MuteRegionAdd(*pending));
+                self.pending_mute_region = None;
+            } else {
+                let pos = response
+                    .interact_pointer_pos()
+                    .expect("Pointer should interact if dragging");
+                let duration_pos = converter.rect_to_duration(pos.x);
+                pending.end = self
+                    .snapping
+                    .snap(duration_pos, converter.seconds_per_px(), targets);
+            }
+        } else if primary_down && alt_down && self.pending_clip.is_none() {
+            let pos = response
+                .interact_pointer_pos()
+                .expect("Pointer should interact if dragging");
+            let duration_pos = converter.rect_to_duration(pos.x);
+            let duration_pos =
+                self.snapping
+                    .snap(duration_pos, converter.seconds_per_px(), targets);
+            self.pending_mute_region = Some(c_bindings::GuiMuteRegion {
+                id: 0,
+                start: duration_pos,
+                end: duration_pos,
+            });
+        }
+    }
+
+    fn handle_seek(
+        &mut self,
+        converter: &ProgressPosConverter,
+        response: &egui::Response,
+        targets: &SnapTargets,
+        action_tx: &mut ActionRequestor,
+        seek_state: &mut SeekState,
+    ) -> Option<f32> {
+        let mut ret = None;
+
+        if self.drag_out.active() {
+            return ret;
+        }
+
+        if response.dragged_by(egui::PointerButton::Primary) {
+            let pos = response
+                .interact_pointer_pos()
+                .expect("Pointer should interact if dragging");
+            let duration_pos = converter
+                .rect_to_duration(pos.x.clamp(converter.rect.left(), converter.rect.right()));
+            let duration_pos = self
+                .snapping
+                .snap(duration_pos, converter.seconds_per_px(), targets)
+                .clamp(0.0, converter.total_runtime);
+            log::debug!("duration pos {duration_pos}");
+            action_tx.send(Action::Seek(duration_pos));
+            ret = Some(duration_pos);
+        }
+
+        if seek_state.should_toggle_pause(response, targets.state) {
+            action_tx.send(Action::TogglePause);
+        }
+
+        ret
+    }
+
+    /// Shift drags that start on a clip take it out of the window instead of seeking, see DragOut
+    fn handle_drag_out(
+        &mut self,
+        converter: &ProgressPosConverter,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        targets: &SnapTargets,
+        action_tx: &mut ActionRequestor,
+    ) {
+        if response.drag_started_by(egui::PointerButton::Primary) {
+            let (origin, modifiers) = ui.input(|i| (i.pointer.press_origin(), i.modifiers));
+            let clip_id = origin
+                .filter(|_| modifiers.shift_only())
+                .map(|pos| converter.rect_to_duration(pos.x))
+                .and_then(|time| {
+                    transcript::clips(targets.state)
+                        .iter()
+                        .find(|clip| clip.start <= time && time < clip.end)
+                })
+                .map(|clip| clip.id);
+            self.drag_out.start(clip_id);
+        }
+
+        if response.dragged_by(egui::PointerButton::Primary) {
+            let Some(pos) = ui.input(|i| i.pointer.latest_pos()) else {
+                return;
+            };
+            if let Some(action) = self.drag_out.drag_to(pos, ui.ctx().screen_rect()) {
+                action_tx.send(action);
+            }
+        }
+    }
+
+    fn handle_pan(&mut self, ui: &egui::Ui, response: &egui::Response) {
+        let dt = ui.input(|i| i.stable_dt);
+
+        if response.dragged_by(egui::PointerButton::Secondary)
+            || response.dragged_by(egui::PointerButton::Middle)
+        {
+            let x_delta = ui.input(|i| i.pointer.delta().x);
+            let center_delta = -x_delta / response.rect.width() / self.zoom;
+            self.widget_center_norm += center_delta;
+            self.clamp_widget_center();
+            if dt > 0.0 {
+                self.pan_velocity = center_delta / dt;
+            }
+            return;
+        }
+
+        // Any click on the timeline should stop us from sliding around under the user
+        if response.is_pointer_button_down_on() {
+            self.pan_velocity = 0.0;
+            return;
+        }
+
+        // Velocity is in normalized units, scale by zoom so that we stop at the same visual speed
+        // regardless of how far in we are
+        const MIN_VISIBLE_WIDTHS_PER_S: f32 = 0.02;
+        if self.pan_velocity.abs() * self.zoom < MIN_VISIBLE_WIDTHS_PER_S {
+            self.pan_velocity = 0.0;
+            return;
+        }
+
+        // Fraction of velocity remaining after one second of coasting
+        const PAN_DECAY_PER_S: f32 = 0.01;
+        let unclamped = self.widget_center_norm + self.pan_velocity * dt;
+        self.pan_velocity *= PAN_DECAY_PER_S.powf(dt);
+
+        // Same bounds as dragging, stop dead once we run into either end
+        self.widget_center_norm = unclamped;
+        self.clamp_widget_center();
+        if self.widget_center_norm != unclamped {
+            self.pan_velocity = 0.0;
+        }
+
+        ui.ctx().request_repaint();
+    }
+
+    fn handle_zoom(
+        &mut self,
+        converter: &ProgressPosConverter,
+        ui: &egui::Ui,
+        response: &egui::Response,
+    ) {
+        if response.contains_pointer() {
+            // If for whatever reason we cannot find the pointer pos, just use the middle of the
+            // widget
+            let mut pointer_pos_audio = 0.5;
+            if let Some(pointer_pos) = ui.input(|i| i.pointer.latest_pos()) {
+                // NOTE: We want to zoom so that the mouse stays in the same spot. This means that the
+                // distance from the center to the pointer needs to stay the same
+                pointer_pos_audio = converter.rect_to_duration_norm(pointer_pos.x);
+            }
+
+            let old_zoom = self.zoom;
+            let scroll_delta = ui.input(|i| i.raw_scroll_delta.y);
+
+            // lol I don't know, it feels good to me
+            const SCROLL_FACTOR: f32 = 3.0;
+            self.zoom *= 1.001_f32.powf(scroll_delta * SCROLL_FACTOR);
+            self.zoom = self.zoom.max(1.0);
+
+            // In order to zoom "at the mouse", we have to ensure that mouse position does not
+            // change in either audio space OR rect space.
+            // We can calculate how far the point moved from the center in audio space, and then
+            // just adjust to keep that at the same point in rect space
+            let dist_from_center = pointer_pos_audio - self.widget_center_norm;
+            let new_dist_from_center = old_zoom / self.zoom * dist_from_center;
+            self.widget_center_norm += dist_from_center - new_dist_from_center;
+        }
+    }
+
+    fn clamp_widget_center(&mut self) {
+        let min = 0.5 / self.zoom;
+        let max = 1.0 - min;
+        self.widget_center_norm = self.widget_center_norm.clamp(min, max);
+    }
+
+    fn handle_response(
+        &mut self,
+        converter: &ProgressPosConverter,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        targets: &SnapTargets,
+        action_tx: &mut ActionRequestor,
+        seek_state: &mut SeekState,
+    ) {
+        self.handle_clip_creation(converter, ui, response, targets, action_tx);
+        self.handle_mute_region_creation(converter, ui, response, targets, action_tx);
+        // Before seeking, which leaves drags the drag out took alone
+        self.handle_drag_out(converter, ui, response, targets, action_tx);
+        self.handle_seek(converter, response, targets, action_tx, seek_state);
+        self.handle_pan(ui, response);
+        self.handle_zoom(converter, ui, response);
+        self.clamp_widget_center();
+    }
+
+    /// Returns the time under the pointer if the timeline is hovered. words are snap targets, see
+    /// SnapTargets
+    #[allow(clippy::too_many_arguments)]
+    pub fn show<B: Backend>(
+        &mut self,
+        ui: &mut egui::Ui,
+        state: &c_bindings::AppStateSnapshot,
+        words: &[c_bindings::GuiWord],
+        action_tx: &mut ActionRequestor,
+        backend: &B,
+        seek_state: &mut SeekState,
+        scroll_to_pos: Option<f32>,
+    ) -> Option<f32> {
+        profile_scope!("ProgressBar::show");
+
+        self.snapping.update(ui, state);
+        self.trim_edge = None;
+
+        ui.with_layout(egui::Layout::right_to_left(Default::default()), |ui| {
+            let response = ui.allocate_response(
+                egui::vec2(ui.available_width(), 60.0),
+                egui::Sense {
+                    click: false,
+                    drag: true,
+                    focusable: false,
+                },
+            );
+
+            let converter = ProgressPosConverter {
+                zoom: self.zoom,
+                widget_center_norm: self.widget_center_norm,
+                rect: response.rect,
+                total_runtime: state.total_runtime,
+            };
+
+            self.paint_waveform(ui, response.rect, backend);
+
+            let pending_clip = self.pending_clip;
+            let pending_mute_region = self.pending_mute_region;
+            let mut clip_renderer = ClipTimelineRenderer {
+                converter: &converter,
+                ui,
+                progress_bar: self,
+                state,
+                words,
+                action_tx,
+            };
+
+            let invalid = invalid_clips(transcript::clips(state));
+            for i in 0..state.num_clips {
+                let clip = unsafe { *state.clips.add(i as usize) };
+                clip_renderer.render_clip(&clip, invalid.contains(&clip.id), seek_state);
+            }
+
+            if let Some(pending_clip) = pending_clip {
+                clip_renderer.render_clip(&pending_clip, false, seek_state)
+            }
+
+            for region in mute::mute_regions(state) {
+                clip_renderer.render_mute_region(region, seek_state);
+            }
+
+            if let Some(pending_mute_region) = pending_mute_region {
+                clip_renderer.render_mute_region(&pending_mute_region, seek_state);
+            }
+
+            for marker in markers::markers(state) {
+                paint_marker(ui, &converter, marker);
+            }
+
+            if let Some(in_point) = self.in_out.in_point {
+                paint_bracket(ui, &converter, in_point, true);
+            }
+            if let Some(out_point) = self.in_out.out_point {
+                paint_bracket(ui, &converter, out_point, false);
+            }
+
+            let progress_rect = converter.duration_to_full_rect(state.current_position, 3.0);
+            ui.painter()
+                .rect_filled(progress_rect, 0.0, egui::Color32::YELLOW);
+
+            let targets = SnapTargets {
+                state,
+                words,
+                exclude_clip: None,
+                exclude_mute_region: None,
+            };
+            self.handle_response(&converter, ui, &response, &targets, action_tx, seek_state);
+
+            if let Some(scroll_to_pos) = scroll_to_pos {
+                let half_visible = 0.5 / self.zoom;
+                let min_visible = self.widget_center_norm - half_visible;
+                let max_visible = self.widget_center_norm + half_visible;
+
+                let scroll_pos_norm = scroll_to_pos / state.total_runtime;
+                if scroll_pos_norm < min_visible || scroll_pos_norm > max_visible {
+                    self.widget_center_norm = scroll_pos_norm;
+                }
+            }
+
+            response
+                .hover_pos()
+                .map(|pos| converter.rect_to_duration(pos.x))
+                .filter(|time| (0.0..=state.total_runtime).contains(time))
+        })
+        .inner
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::backend::mock::{clip, MockBackend};
+
+    use std::sync::{atomic::AtomicUsize, mpsc};
+
+    const SCREEN_WIDTH: f32 = 800.0;
+    const RUNTIME: f32 = 10.0;
+
+    /// Drives a ProgressBar through egui frames without a window or a backend
+    struct Harness {
+        ctx: egui::Context,
+        progress_bar: ProgressBar,
+        seek_state: SeekState,
+        action_tx: ActionRequestor,
+        _action_rx: mpsc::Receiver<Action>,
+        backend: MockBackend,
+        modifiers: egui::Modifiers,
+    }
+
+    impl Harness {
+        fn new(paused: bool, clips: Vec<c_bindings::Clip>) -> Harness {
+            let (action_tx, action_rx) = mpsc::channel();
+
+            let backend = MockBackend::default();
+            {
+                let mut state = backend.state();
+                state.paused = paused;
+                state.total_runtime = RUNTIME;
+                state.clips = clips;
+            }
+
+            let mut ret = Harness {
+                ctx: egui::Context::default(),
+                progress_bar: ProgressBar::new(),
+                seek_state: SeekState::new(),
+                action_tx: ActionRequestor::new(action_tx, Arc::new(AtomicUsize::new(0))),
+                _action_rx: action_rx,
+                backend,
+                modifiers: Default::default(),
+            };
+
+            // egui hit tests against the widgets of the previous frame, so nothing can be
+            // interacted with until we've drawn once
+            ret.frame(Vec::new());
+            ret
+        }
+
+        /// Runs a single frame with the given input, returning the actions the timeline sent
+        fn frame(&mut self, events: Vec<egui::Event>) -> Vec<Action> {
+            let state = self.backend.snapshot();
+            let input = egui::RawInput {
+                screen_rect: Some(egui::Rect::from_min_size(
+                    egui::Pos2::ZERO,
+                    egui::vec2(SCREEN_WIDTH, 100.0),
+                )),
+                modifiers: self.modifiers,
+                events,
+                ..Default::default()
+            };
+
+            let _ = self.ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::none())
+                    .show(ctx, |ui| {
+                        self.progress_bar.show(
+                            ui,
+                            &state,
+                            &[],
+                            &mut self.action_tx,
+                            &self.backend,
+                            &mut self.seek_state,
+                            None,
+                        );
+                    });
+            });
+
+            std::mem::take(&mut self.action_tx.pending)
+        }
+
+        fn button_event(&self, pos: egui::Pos2, pressed: bool) -> egui::Event {
+            egui::Event::PointerButton {
+                pos,
+                button: egui::PointerButton::Primary,
+                pressed,
+                modifiers: self.modifiers,
+            }
+        }
+
+        fn press(&mut self, time: f32) -> Vec<Action> {
+            let pos = pos_for(time);
+            let events = vec![egui::Event::PointerMoved(pos), self.button_event(pos, true)];
+            self.frame(events)
+        }
+
+        fn move_to(&mut self, time: f32) -> Vec<Action> {
+            self.frame(vec![egui::Event::PointerMoved(pos_for(time))])
+        }
+
+        fn release(&mut self, time: f32) -> Vec<Action> {
+            let pos = pos_for(time);
+            let events = vec![
+                egui::Event::PointerMoved(pos),
+                self.button_event(pos, false),
+            ];
+            self.frame(events)
+        }
+    }
+
+    /// The timeline spans the whole screen when un-zoomed
+    fn pos_for(time: f32) -> egui::Pos2 {
+        egui::pos2(time / RUNTIME * SCREEN_WIDTH, 30.0)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn drag_seeks_and_pauses_while_dragging() {
+        let mut harness = Harness::new(false, Vec::new());
+
+        assert!(harness.press(2.0).is_empty());
+
+        match harness.move_to(5.0).as_slice() {
+            [Action::Seek(pos), Action::TogglePause] => assert_close(*pos, 5.0),
+            other => panic!("unexpected actions: {other:?}"),
+        }
+
+        match harness.move_to(6.0).as_slice() {
+            [Action::Seek(pos)] => assert_close(*pos, 6.0),
+            other => panic!("unexpected actions: {other:?}"),
+        }
+
+        match harness.release(6.0).as_slice() {
+            [Action::TogglePause] => (),
+            other => panic!("unexpected actions: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn drag_while_paused_stays_paused() {
+        let mut harness = Harness::new(true, Vec::new());
+
+        harness.press(2.0);
+        match harness.move_to(5.0).as_slice() {
+            [Action::Seek(pos)] => assert_close(*pos, 5.0),
+            other => panic!("unexpected actions: {other:?}"),
+        }
+
+        assert!(harness.release(5.0).is_empty());
+    }
+
+    #[test]
+    fn ctrl_drag_creates_clip() {
+        let mut harness = Harness::new(true, Vec::new());
+        harness.modifiers.ctrl = true;
+
+        harness.press(2.0);
+        harness.move_to(3.0);
+        harness.move_to(7.0);
+
+        match harness.release(7.0).as_slice() {
+            [Action::ClipAdd(clip)] => {
+                assert_close(clip.start, 3.0);
+                assert_close(clip.end, 7.0);
+            }
+            other => panic!("unexpected actions: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn alt_drag_creates_mute_region() {
+        let mut harness = Harness::new(true, Vec::new());
+        harness.modifiers.alt = true;
+
+        harness.press(2.0);
+        harness.move_to(3.0);
+        harness.move_to(4.5);
+
+        match harness.release(4.5).as_slice() {
+            [Action::MuteRegionAdd(region)] => {
+                assert_close(region.start, 3.0);
+                assert_close(region.end, 4.5);
+            }
+            other => panic!("unexpected actions: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn clip_edges_stop_at_neighbours() {
+        let clips = [clip(1, 0.0, 2.0), clip(2, 4.0, 6.0)];
+        let mut harness = Harness::new(true, clips.to_vec());
+
+        harness.press(2.0);
+        harness.move_to(3.0);
+        let edit = harness
+            .move_to(5.0)
+            .into_iter()
+            .find_map(|action| match action {
+                Action::ClipEdit(clip) => Some(clip),
+                _ => None,
+            })
+            .expect("expected a clip edit");
+        assert_eq!(edit.id, 1);
+        assert_close(edit.end, 4.0);
+
+        assert_eq!(constrain_clip_edit(&clips, &clips[1], 5.0, 3.0), (3.0, 5.0));
+        assert_eq!(constrain_clip_edit(&clips, &clips[1], 1.0, 6.0), (2.0, 6.0));
+    }
+
+    #[test]
+    fn overlapping_and_empty_clips_are_invalid() {
+        let clips = [
+            clip(1, 0.0, 2.0),
+            clip(2, 2.0, 4.0),
+            clip(3, 3.0, 5.0),
+            clip(4, 7.0, 7.0),
+        ];
+        assert_eq!(invalid_clips(&clips), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn zoom_presets_center_on_the_playhead() {
+        let mut progress_bar = ProgressBar::new();
+        let mut state: c_bindings::AppStateSnapshot = unsafe { std::mem::zeroed() };
+        state.total_runtime = 100.0;
+        state.current_position = 50.0;
+
+        progress_bar.apply_zoom_preset(ZoomPreset::Window(10.0), &state);
+        assert_close(progress_bar.zoom, 10.0);
+        assert_close(progress_bar.widget_center_norm, 0.5);
+
+        // Kept from scrolling past the end
+        state.current_position = 99.0;
+        progress_bar.apply_zoom_preset(ZoomPreset::Window(10.0), &state);
+        assert_close(progress_bar.widget_center_norm, 0.95);
+
+        // Longer than the video
+        progress_bar.apply_zoom_preset(ZoomPreset::Window(600.0), &state);
+        assert_close(progress_bar.zoom, 1.0);
+
+        progress_bar.apply_zoom_preset(ZoomPreset::FitAll, &state);
+        assert_close(progress_bar.zoom, 1.0);
+        assert_close(progress_bar.widget_center_norm, 0.5);
+    }
+
+    #[test]
+    fn durations_switch_to_minutes() {
+        assert_eq!(duration_label(4.24), "4.2s");
+        assert_eq!(duration_label(65.3), "1:05.3");
+        assert_eq!(duration_label(600.0), "10:00.0");
+    }
+
+    #[test]
+    fn zoom_presets_have_labels_and_keys() {
+        let labels: Vec<_> = ZoomPreset::ALL
+            .iter()
+            .map(|preset| preset.label())
+            .collect();
+        assert_eq!(labels, ["Fit", "10 s", "1 min"]);
+        assert_eq!(ZoomPreset::Window(60.0).key(), Some(egui::Key::Num3));
+        assert_eq!(ZoomPreset::Window(5.0).key(), None);
+    }
+
+    #[test]
+    fn panning_stops_at_the_zoomed_in_edge() {
+        let mut harness = Harness::new(true, Vec::new());
+        harness.progress_bar.zoom = 4.0;
+        let max_center = 1.0 - 0.5 / 4.0;
+
+        let middle = |pos, pressed| egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Middle,
+            pressed,
+            modifiers: Default::default(),
+        };
+        let start = pos_for(9.0);
+        harness.frame(vec![egui::Event::PointerMoved(start), middle(start, true)]);
+        // Far enough left to push the center well past the end of the video
+        let end = pos_for(-40.0);
+        harness.frame(vec![egui::Event::PointerMoved(end)]);
+        assert_close(harness.progress_bar.widget_center_norm, max_center);
+
+        // Coasting after the release has nowhere further to go either
+        harness.frame(vec![middle(end, false)]);
+        harness.frame(Vec::new());
+        assert_close(harness.progress_bar.widget_center_norm, max_center);
+        assert_eq!(harness.progress_bar.pan_velocity, 0.0);
+    }
+
+    #[test]
+    fn zoom_preset_keys_ignore_modified_presses() {
+        let mut harness = Harness::new(true, Vec::new());
+        // Long enough for the 10 s preset to zoom in
+        harness.backend.state().total_runtime = 100.0;
+        let state = harness.backend.snapshot();
+        let mut press = |modifiers: egui::Modifiers| {
+            let input = egui::RawInput {
+                modifiers,
+                events: vec![egui::Event::Key {
+                    key: egui::Key::Num2,
+                    physical_key: None,
+                    pressed: true,
+                    repeat: false,
+                    modifiers,
+                }],
+                ..Default::default()
+            };
+            let _ = harness.ctx.run(input, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    harness.progress_bar.show_zoom_presets(ui, &state);
+                });
+            });
+            harness.progress_bar.zoom
+        };
+
+        assert_eq!(press(egui::Modifiers::CTRL), 1.0);
+        assert_eq!(press(egui::Modifiers::SHIFT), 1.0);
+        assert!(press(egui::Modifiers::NONE) > 1.0);
+    }
+
+    #[test]
+    fn shift_dragging_a_clip_off_the_bottom_exports_it() {
+        let mut harness = Harness::new(true, vec![clip(4, 2.0, 8.0)]);
+        let below_window = egui::pos2(pos_for(5.5).x, 150.0);
+
+        harness.modifiers.shift = true;
+        harness.press(5.0);
+        harness.move_to(5.5);
+        let actions = harness.frame(vec![egui::Event::PointerMoved(below_window)]);
+        match actions.as_slice() {
+            [Action::QuickExport(4)] => (),
+            other => panic!("unexpected actions: {other:?}"),
+        }
+
+        // Off the end of the timeline is left alone
+        harness.release(5.5);
+        harness.press(3.0);
+        harness.move_to(3.5);
+        let past_start = egui::pos2(-50.0, 150.0);
+        assert!(harness
+            .frame(vec![egui::Event::PointerMoved(past_start)])
+            .is_empty());
+        harness.release(3.5);
+
+        // Without shift leaving the window is only a seek
+        harness.modifiers.shift = false;
+        harness.press(5.0);
+        harness.move_to(5.5);
+        let actions = harness.frame(vec![egui::Event::PointerMoved(below_window)]);
+        match actions.as_slice() {
+            [Action::Seek(pos)] => assert_close(*pos, 5.5),
+            other => panic!("unexpected actions: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn dragging_clip_end_edits_clip() {
+        let mut harness = Harness::new(true, vec![clip(1, 2.0, 8.0)]);
+
+        harness.press(8.0);
+
+        match harness.move_to(9.0).as_slice() {
+            [Action::Seek(pos), Action::ClipEdit(edited)] => {
+                assert_close(*pos, 9.0);
+                assert_eq!(edited.id, 1);
+                assert_close(edited.start, 2.0);
+                assert_close(edited.end, 9.0);
+            }
+            other => panic!("unexpected actions: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use eframe::egui;
+
+use std::ffi::c_char;
+
+use crate::{
+    actions::{self, Action},
+    c_bindings,
+    events::Event,
+    jobs, ActionRequestor,
+};
+
+/// Whisper language codes offered in the picker, "auto" lets whisper guess
+const LANGUAGES: [&str; 11] = [
+    "auto", "en", "de", "es", "fr", "it", "ja", "nl", "pt", "ru", "zh",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TranscriptionModel {
+    Builtin,
+    Tiny,
+    Base,
+    Small,
+    Medium,
+    Large,
+}
+
+impl TranscriptionModel {
+    pub const ALL: [TranscriptionModel; 6] = [
+        TranscriptionModel::Builtin,
+        TranscriptionModel::Tiny,
+        TranscriptionModel::Base,
+        TranscriptionModel::Small,
+        TranscriptionModel::Medium,
+        TranscriptionModel::Large,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TranscriptionModel::Builtin => "built-in (tiny, English)",
+            TranscriptionModel::Tiny => "tiny",
+            TranscriptionModel::Base => "base",
+            TranscriptionModel::Small => "small",
+            TranscriptionModel::Medium => "medium",
+            TranscriptionModel::Large => "large",
+        }
+    }
+}
+
+impl From<TranscriptionModel> for c_bindings::GuiTranscriptionModel {
+    fn from(model: TranscriptionModel) -> c_bindings::GuiTranscriptionModel {
+        match model {
+            TranscriptionModel::Builtin => {
+                c_bindings::GuiTranscriptionModel_gui_transcription_model_builtin
+            }
+            TranscriptionModel::Tiny => {
+                c_bindings::GuiTranscriptionModel_gui_transcription_model_tiny
+            }
+            TranscriptionModel::Base => {
+                c_bindings::GuiTranscriptionModel_gui_transcription_model_base
+            }
+            TranscriptionModel::Small => {
+                c_bindings::GuiTranscriptionModel_gui_transcription_model_small
+            }
+            TranscriptionModel::Medium => {
+                c_bindings::GuiTranscriptionModel_gui_transcription_model_medium
+            }
+            TranscriptionModel::Large => {
+                c_bindings::GuiTranscriptionModel_gui_transcription_model_large
+            }
+        }
+    }
+}
+
+/// Picks a model and language and asks the backend to transcribe again. The backend keeps
+/// serving the old transcript until the new one is done, so there is nothing to wait on here.
+/// Subtitles that already exist can be imported instead
+pub struct TranscribeDialog {
+    pub open: bool,
+    model: TranscriptionModel,
+    language: &'static str,
+    import_path: String,
+    /// Outcome of the last run that finished while the GUI was up
+    last_result: Option<&'static str>,
+}
+
+impl TranscribeDialog {
+    pub fn new() -> TranscribeDialog {
+        TranscribeDialog {
+            open: false,
+            model: TranscriptionModel::Builtin,
+            language: "en",
+            import_path: String::new(),
+            last_result: None,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::TranscriptionFinished => {
+                log::info!("Transcript replaced");
+                self.last_result = Some("finished");
+            }
+            // The backend logs why
+            Event::TranscriptionFailed => self.last_result = Some("failed"),
+            _ => (),
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &c_bindings::AppStateSnapshot,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let mut open = self.open;
+        egui::Window::new("Transcribe")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::ComboBox::from_label("model")
+                    .selected_text(self.model.label())
+                    .show_ui(ui, |ui| {
+                        for model in TranscriptionModel::ALL {
+                            ui.selectable_value(&mut self.model, model, model.label());
+                        }
+                    });
+
+                egui::ComboBox::from_label("language")
+                    .selected_text(self.language)
+                    .show_ui(ui, |ui| {
+                        for language in LANGUAGES {
+                            ui.selectable_value(&mut self.language, language, language);
+                        }
+                    });
+
+                if self.model == TranscriptionModel::Builtin && self.language != "en" {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        "The built-in model only understands English",
+                    );
+                }
+
+                ui.label("Clips are kept, the transcript is replaced once the new one is done");
+
+                let kind = c_bindings::GuiJobKind_gui_job_kind_transcription;
+                match jobs::find_active(state, kind) {
+                    Some(job) => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(format!("transcribing, {:.0}%", job.progress * 100.0));
+                        });
+                    }
+                    None => {
+                        if let Some(last_result) = self.last_result {
+                            ui.label(format!("last run {last_result}"));
+                        }
+                    }
+                }
+
+                // Starting over while a run is going is fine, the backend stops the old one
+                if ui.button("Transcribe").clicked() {
+                    self.last_result = None;
+                    action_tx.send(Action::Transcribe(c_bindings::GuiTranscribeRequest {
+                        model: self.model.into(),
+                        language: language_to_c(self.language),
+                    }));
+                }
+
+                ui.separator();
+                ui.label("Or use existing subtitles as the transcript");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.import_path)
+                            .hint_text("path to .srt or .vtt"),
+                    );
+
+                    let request = actions::import_request(self.import_path.trim());
+                    let import = ui.add_enabled(request.is_some(), egui::Button::new("Import"));
+                    if import.clicked() {
+                        if let Some(request) = request {
+                            action_tx.send(Action::ImportCaptions(request));
+                        }
+                    }
+                });
+            });
+        self.open = open;
+    }
+}
+
+/// Truncates to the 8 bytes the request has room for, the backend does not need the terminator
+fn language_to_c(language: &str) -> [c_char; 8] {
+    let mut ret = [0; 8];
+    for (dst, src) in ret.iter_mut().zip(language.bytes()) {
+        *dst = src as c_char;
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn language_is_zero_padded() {
+        let c_language = language_to_c("en");
+        assert_eq!(
+            c_language,
+            [b'e' as c_char, b'n' as c_char, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn long_languages_are_truncated() {
+        let c_language = language_to_c("abcdefghij");
+        assert_eq!(c_language.map(|c| c as u8), *b"abcdefgh");
+    }
+}
@@ -0,0 +1,598 @@
+use eframe::egui;
+
+use std::{ops::Range, sync::Arc};
+
+use crate::{c_bindings, script_edit};
+
+/// Words the transcription is less sure about than this get tinted
+const UNSURE_CONFIDENCE: f32 = 0.8;
+/// Words below this are likely wrong
+const DOUBTFUL_CONFIDENCE: f32 = 0.5;
+/// How close to a word start counts as being on it. Seeks do not land exactly where they were
+/// asked to, without this stepping back would keep finding the word we are already on
+const WORD_START_TOLERANCE: f32 = 0.05;
+
+/// A silence at least this long between two words starts a new paragraph, even in the middle of a
+/// sentence
+const PARAGRAPH_PAUSE_S: f32 = 1.5;
+/// Room left of each block for its start time
+pub const GUTTER_WIDTH: f32 = 50.0;
+
+/// One sentence of the transcript, or the part of one between two long pauses
+pub struct TranscriptBlock {
+    pub galley: Arc<egui::Galley>,
+    pub start_idx: usize,
+    pub end_idx: usize,
+    /// Start of the first word in the block, None if no word lines up with it
+    pub start_time: Option<f32>,
+    /// Comes after a long pause, so it gets extra space above it
+    pub paragraph_start: bool,
+}
+
+/// Laid out transcript text, split into blocks. Laying out the whole transcript is expensive, so
+/// we only redo it when the backend tells us the transcript changed, or when the clips that decide
+/// what is struck through change
+pub struct TranscriptLayout {
+    /// Width of the panel the layout was made for, the text itself wraps short of the gutter
+    pub wrap_width: f32,
+    pub blocks: Vec<TranscriptBlock>,
+    /// The words the layout was made from, in text order
+    pub words: Vec<c_bindings::GuiWord>,
+    clip_spans: Vec<(f32, f32)>,
+}
+
+impl TranscriptLayout {
+    /// words are used to find pauses, to color the text by confidence and to strike through what
+    /// is not in any clip. They have to be in text order
+    pub unsafe fn new(
+        ui: &egui::Ui,
+        state: &c_bindings::AppStateSnapshot,
+        words: Vec<c_bindings::GuiWord>,
+        wrap_width: f32,
+    ) -> Self {
+        let s = std::slice::from_raw_parts(state.text as *const u8, state.text_len as usize);
+        let s = std::str::from_utf8_unchecked(s);
+        let clips = clips(state);
+
+        let sentence_ends: &[u64] = if state.text_split_indices_len == 0 {
+            // text_split_indices may be null
+            &[]
+        } else {
+            std::slice::from_raw_parts(
+                state.text_split_indices,
+                state.text_split_indices_len as usize,
+            )
+        };
+
+        let mut font_id = ui.style().text_styles[&egui::TextStyle::Body].clone();
+        font_id.size = 20.0;
+
+        let blocks = split_blocks(s, sentence_ends, &words)
+            .into_iter()
+            .map(|bounds| {
+                let layout = words_layout_job(
+                    s,
+                    bounds.range.clone(),
+                    &words,
+                    clips,
+                    font_id.clone(),
+                    ui.visuals().text_color(),
+                    (wrap_width - GUTTER_WIDTH).max(0.0),
+                );
+
+                TranscriptBlock {
+                    galley: ui.painter().layout_job(layout),
+                    start_idx: bounds.range.start,
+                    end_idx: bounds.range.end,
+                    start_time: bounds.start_time,
+                    paragraph_start: bounds.paragraph_start,
+                }
+            })
+            .collect();
+
+        TranscriptLayout {
+            wrap_width,
+            blocks,
+            words,
+            clip_spans: clip_spans(clips),
+        }
+    }
+
+    pub fn clips_changed(&self, state: &c_bindings::AppStateSnapshot) -> bool {
+        self.clip_spans != clip_spans(clips(state))
+    }
+}
+
+pub fn clips(state: &c_bindings::AppStateSnapshot) -> &[c_bindings::Clip] {
+    if state.num_clips == 0 {
+        // clips may be null
+        return &[];
+    }
+
+    unsafe { std::slice::from_raw_parts(state.clips, state.num_clips as usize) }
+}
+
+fn clip_spans(clips: &[c_bindings::Clip]) -> Vec<(f32, f32)> {
+    clips.iter().map(|clip| (clip.start, clip.end)).collect()
+}
+
+#[derive(Debug, PartialEq)]
+struct BlockBounds {
+    range: Range<usize>,
+    start_time: Option<f32>,
+    paragraph_start: bool,
+}
+
+/// Splits s at the ends of sentences the backend found, and in front of words that follow a long
+/// pause. Empty blocks are dropped
+fn split_blocks(s: &str, sentence_ends: &[u64], words: &[c_bindings::GuiWord]) -> Vec<BlockBounds> {
+    let follows_pause =
+        |idx: usize| idx > 0 && words[idx].start - words[idx - 1].end >= PARAGRAPH_PAUSE_S;
+
+    let mut splits: Vec<usize> = sentence_ends.iter().map(|idx| *idx as usize).collect();
+    splits.extend(
+        (0..words.len())
+            .filter(|idx| follows_pause(*idx))
+            .map(|idx| words[idx].char_start as usize),
+    );
+    // Words may be newer than s
+    splits.retain(|idx| *idx > 0 && *idx < s.len() && s.is_char_boundary(*idx));
+    splits.sort_unstable();
+    splits.dedup();
+    splits.push(s.len());
+
+    let mut ret = Vec::new();
+    let mut block_start = 0;
+    for block_end in splits {
+        let range = block_start..block_end;
+        block_start = block_end;
+        if range.is_empty() {
+            continue;
+        }
+
+        let first_word = words.partition_point(|word| word.char_end as usize <= range.start);
+        let first_word = Some(first_word)
+            .filter(|idx| *idx < words.len() && (words[*idx].char_start as usize) < range.end);
+
+        ret.push(BlockBounds {
+            start_time: first_word.map(|idx| words[idx].start),
+            paragraph_start: ret.is_empty() || first_word.is_some_and(follows_pause),
+            range,
+        });
+    }
+
+    ret
+}
+
+/// Formats pts as m:ss for the gutter
+pub fn format_timecode(pts: f32) -> String {
+    let secs = pts.max(0.0) as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn confidence_color(confidence: f32) -> Option<egui::Color32> {
+    if confidence < DOUBTFUL_CONFIDENCE {
+        Some(egui::Color32::LIGHT_RED)
+    } else if confidence < UNSURE_CONFIDENCE {
+        Some(egui::Color32::GOLD)
+    } else {
+        None
+    }
+}
+
+/// Lays out s[range], tinting the words the transcription is unsure about and striking through the
+/// ones that were cut. Words may be newer than s, anything that does not line up with it is left
+/// alone
+fn words_layout_job(
+    s: &str,
+    range: Range<usize>,
+    words: &[c_bindings::GuiWord],
+    clips: &[c_bindings::Clip],
+    font_id: egui::FontId,
+    color: egui::Color32,
+    wrap_width: f32,
+) -> egui::text::LayoutJob {
+    let mut job = egui::text::LayoutJob::default();
+    job.wrap.max_width = wrap_width;
+
+    let format = |color| egui::TextFormat::simple(font_id.clone(), color);
+
+    let mut pos = range.start;
+    let first_word = words.partition_point(|word| word.char_end as usize <= range.start);
+    for word in &words[first_word..] {
+        let start = (word.char_start as usize).max(pos);
+        if start >= range.end {
+            break;
+        }
+        let end = (word.char_end as usize).min(range.end);
+
+        let word_color = confidence_color(word.confidence);
+        let cut = script_edit::is_cut(word, clips);
+        if word_color.is_none() && !cut {
+            continue;
+        }
+
+        if start >= end || !s.is_char_boundary(start) || !s.is_char_boundary(end) {
+            continue;
+        }
+
+        if pos < start {
+            job.append(&s[pos..start], 0.0, format(color));
+        }
+        let mut word_format = format(word_color.unwrap_or(color));
+        if cut {
+            word_format.strikethrough = egui::Stroke::new(1.5, word_format.color);
+        }
+        job.append(&s[start..end], 0.0, word_format);
+        pos = end;
+    }
+
+    // Always at least one section, so that the galley has a row to hit test against
+    if pos < range.end || job.sections.is_empty() {
+        job.append(&s[pos..range.end], 0.0, format(color));
+    }
+
+    job
+}
+
+/// Finds the character under pixel_pos, which is relative to the top left of the galley. Positions
+/// past the end of a row resolve to the end of that row
+pub fn pointer_to_char_pos(galley: &egui::Galley, pixel_pos: egui::Vec2) -> usize {
+    let mut row = 0;
+    let mut col = 0;
+    let mut char_pos = 0;
+
+    while row < galley.rows.len() && galley.rows[row].rect.bottom() < pixel_pos.y {
+        char_pos += galley.rows[row].glyphs.len();
+        row += 1;
+    }
+    // I want B to be no larger then A
+    // The maximum value of B is A
+    // max(a, b)
+    row = row.min(galley.rows.len() - 1);
+
+    let glyphs = &galley.rows[row].glyphs;
+    while col < glyphs.len() && glyphs[col].pos.x + glyphs[col].size.x < pixel_pos.x {
+        char_pos += 1;
+        col += 1;
+    }
+
+    char_pos
+}
+
+pub fn char_pos_to_text_pos(
+    pos: usize,
+    galley_start_char: usize,
+    galley_end_char: usize,
+    galley: &egui::Galley,
+    galley_tl: egui::Pos2,
+) -> Option<egui::Rect> {
+    if pos >= galley_start_char && pos < galley_end_char {
+        let galley_char_pos = pos - galley_start_char;
+
+        let mut acc: usize = 0;
+        let mut row = 0;
+
+        loop {
+            if row >= galley.rows.len() {
+                panic!("Row index out of bounds, this should never happen");
+            }
+            let row_len = galley.rows[row].glyphs.len();
+
+            if acc + row_len >= galley_char_pos {
+                break;
+            }
+
+            acc += row_len;
+            row += 1;
+        }
+
+        let col = (galley_char_pos - acc).min(galley.rows[row].glyphs.len() - 1);
+        let glyph = galley.rows[row].glyphs[col];
+
+        let left = glyph.pos.x + galley_tl.x;
+        let bottom = glyph.pos.y + galley_tl.y + 5.0;
+        let top = bottom - 20.0;
+        let right = left + 3.0;
+
+        let cursor_rect =
+            egui::Rect::from_min_max(egui::pos2(left, top), egui::pos2(right, bottom));
+        return Some(cursor_rect);
+    }
+
+    None
+}
+
+/// Rects covering the characters of range that are in the galley, one per row. Like the other
+/// helpers here, this counts a glyph as one character
+pub fn char_range_rects(
+    range: Range<usize>,
+    galley_start_char: usize,
+    galley: &egui::Galley,
+    galley_tl: egui::Pos2,
+) -> Vec<egui::Rect> {
+    let mut ret = Vec::new();
+    let mut char_pos = galley_start_char;
+    for row in &galley.rows {
+        let mut row_rect = egui::Rect::NOTHING;
+        for glyph in &row.glyphs {
+            if range.contains(&char_pos) {
+                let glyph_rect = egui::Rect::from_min_size(
+                    egui::pos2(glyph.pos.x, row.rect.top()),
+                    egui::vec2(glyph.size.x, row.rect.height()),
+                );
+                row_rect = row_rect.union(glyph_rect);
+            }
+            char_pos += 1;
+        }
+
+        if row_rect.is_positive() {
+            ret.push(row_rect.translate(galley_tl.to_vec2()));
+        }
+    }
+    ret
+}
+
+/// Start of the first word that starts after pts, None past the last word
+pub fn next_word_start(words: &[c_bindings::GuiWord], pts: f32) -> Option<f32> {
+    words
+        .iter()
+        .map(|word| word.start)
+        .find(|start| *start > pts + WORD_START_TOLERANCE)
+}
+
+/// Start of the last word that starts before pts, None before the first word
+pub fn prev_word_start(words: &[c_bindings::GuiWord], pts: f32) -> Option<f32> {
+    words
+        .iter()
+        .rev()
+        .map(|word| word.start)
+        .find(|start| *start < pts - WORD_START_TOLERANCE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEXT: &str = "hello world";
+
+    fn layout() -> Arc<egui::Galley> {
+        let ctx = egui::Context::default();
+        let mut galley = None;
+        // Fonts are only available once a frame has started
+        let _ = ctx.run(Default::default(), |ctx| {
+            galley = Some(ctx.fonts(|f| {
+                f.layout_no_wrap(
+                    TEXT.to_string(),
+                    egui::FontId::monospace(10.0),
+                    egui::Color32::WHITE,
+                )
+            }));
+        });
+        galley.unwrap()
+    }
+
+    #[test]
+    fn pointer_resolves_to_glyph_under_it() {
+        let galley = layout();
+        let glyph = galley.rows[0].glyphs[4];
+        let pos = egui::vec2(glyph.pos.x + 1.0, 1.0);
+        assert_eq!(pointer_to_char_pos(&galley, pos), 4);
+    }
+
+    #[test]
+    fn pointer_past_end_of_row_resolves_to_row_end() {
+        let galley = layout();
+        let pos = egui::vec2(10000.0, 1.0);
+        assert_eq!(pointer_to_char_pos(&galley, pos), TEXT.len());
+    }
+
+    #[test]
+    fn cursor_rect_follows_galley_position() {
+        let galley = layout();
+        let galley_tl = egui::pos2(10.0, 20.0);
+        let rect = char_pos_to_text_pos(4, 0, TEXT.len(), &galley, galley_tl).unwrap();
+        assert_eq!(rect.left(), galley.rows[0].glyphs[4].pos.x + galley_tl.x);
+    }
+
+    fn word(char_start: u64, char_end: u64, confidence: f32) -> c_bindings::GuiWord {
+        c_bindings::GuiWord {
+            char_start,
+            char_end,
+            start: 0.0,
+            end: 0.0,
+            confidence,
+        }
+    }
+
+    fn section_colors(job: &egui::text::LayoutJob) -> Vec<(Range<usize>, egui::Color32)> {
+        job.sections
+            .iter()
+            .map(|section| (section.byte_range.clone(), section.format.color))
+            .collect()
+    }
+
+    #[test]
+    fn unsure_words_are_tinted() {
+        let words = [word(0, 5, 0.9), word(5, 11, 0.3)];
+        let job = words_layout_job(
+            TEXT,
+            0..TEXT.len(),
+            &words,
+            &[],
+            egui::FontId::monospace(10.0),
+            egui::Color32::WHITE,
+            100.0,
+        );
+
+        assert_eq!(job.text, TEXT);
+        assert_eq!(
+            section_colors(&job),
+            [
+                (0..5, egui::Color32::WHITE),
+                (5..11, egui::Color32::LIGHT_RED)
+            ]
+        );
+    }
+
+    #[test]
+    fn words_are_clipped_to_the_laid_out_range() {
+        let words = [word(0, 5, 0.6), word(5, 11, 0.9)];
+        let job = words_layout_job(
+            TEXT,
+            3..8,
+            &words,
+            &[],
+            egui::FontId::monospace(10.0),
+            egui::Color32::WHITE,
+            100.0,
+        );
+
+        assert_eq!(job.text, "lo wo");
+        assert_eq!(
+            section_colors(&job),
+            [(0..2, egui::Color32::GOLD), (2..5, egui::Color32::WHITE)]
+        );
+    }
+
+    fn timed_word(start: f32, end: f32) -> c_bindings::GuiWord {
+        c_bindings::GuiWord {
+            char_start: 0,
+            char_end: 0,
+            start,
+            end,
+            confidence: 1.0,
+        }
+    }
+
+    #[test]
+    fn word_navigation_steps_over_word_starts() {
+        let words = [
+            timed_word(1.0, 1.5),
+            timed_word(1.5, 2.0),
+            timed_word(3.0, 3.2),
+        ];
+
+        assert_eq!(next_word_start(&words, 0.0), Some(1.0));
+        assert_eq!(next_word_start(&words, 1.2), Some(1.5));
+        assert_eq!(next_word_start(&words, 3.0), None);
+
+        assert_eq!(prev_word_start(&words, 2.5), Some(1.5));
+        assert_eq!(prev_word_start(&words, 1.2), Some(1.0));
+        assert_eq!(prev_word_start(&words, 1.0), None);
+    }
+
+    #[test]
+    fn word_navigation_does_not_get_stuck_near_a_word_start() {
+        let words = [timed_word(1.0, 1.5), timed_word(1.5, 2.0)];
+
+        // Where a previous seek to 1.5 may have ended up
+        assert_eq!(prev_word_start(&words, 1.52), Some(1.0));
+        assert_eq!(next_word_start(&words, 1.48), None);
+    }
+
+    fn sentence_word(char_start: u64, char_end: u64, start: f32, end: f32) -> c_bindings::GuiWord {
+        c_bindings::GuiWord {
+            char_start,
+            char_end,
+            start,
+            end,
+            confidence: 1.0,
+        }
+    }
+
+    #[test]
+    fn blocks_split_at_sentence_ends() {
+        let s = "Hi there. Bye.";
+        let words = [
+            sentence_word(0, 2, 0.0, 0.5),
+            sentence_word(2, 8, 0.5, 1.0),
+            sentence_word(8, 9, 1.0, 1.0),
+            sentence_word(9, 13, 1.2, 1.5),
+            sentence_word(13, 14, 1.5, 1.5),
+        ];
+
+        let blocks = split_blocks(s, &[10], &words);
+        assert_eq!(
+            blocks,
+            [
+                BlockBounds {
+                    range: 0..10,
+                    start_time: Some(0.0),
+                    paragraph_start: true,
+                },
+                BlockBounds {
+                    range: 10..14,
+                    start_time: Some(1.2),
+                    paragraph_start: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn long_pauses_start_paragraphs() {
+        let s = "one two three";
+        let words = [
+            sentence_word(0, 3, 0.0, 0.5),
+            sentence_word(3, 7, 0.5, 1.0),
+            sentence_word(7, 13, 5.0, 5.5),
+        ];
+
+        let blocks = split_blocks(s, &[], &words);
+        assert_eq!(
+            blocks,
+            [
+                BlockBounds {
+                    range: 0..7,
+                    start_time: Some(0.0),
+                    paragraph_start: true,
+                },
+                BlockBounds {
+                    range: 7..13,
+                    start_time: Some(5.0),
+                    paragraph_start: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_past_the_text_are_ignored() {
+        let blocks = split_blocks("abc", &[2, 10], &[]);
+        let ranges: Vec<_> = blocks.iter().map(|block| block.range.clone()).collect();
+        assert_eq!(ranges, [0..2, 2..3]);
+        assert!(blocks.iter().all(|block| block.start_time.is_none()));
+    }
+
+    #[test]
+    fn timecodes_are_minutes_and_seconds() {
+        assert_eq!(format_timecode(0.0), "0:00");
+        assert_eq!(format_timecode(65.7), "1:05");
+        assert_eq!(format_timecode(3600.0), "60:00");
+    }
+
+    #[test]
+    fn range_rects_cover_selected_glyphs() {
+        let galley = layout();
+        let galley_tl = egui::pos2(10.0, 20.0);
+        let glyphs = &galley.rows[0].glyphs;
+
+        let rects = char_range_rects(6..11, 0, &galley, galley_tl);
+        assert_eq!(rects.len(), 1);
+        assert_eq!(rects[0].left(), glyphs[6].pos.x + galley_tl.x);
+        assert_eq!(
+            rects[0].right(),
+            glyphs[10].pos.x + glyphs[10].size.x + galley_tl.x
+        );
+
+        assert!(char_range_rects(0..5, 20, &galley, galley_tl).is_empty());
+    }
+
+    #[test]
+    fn cursor_outside_galley_has_no_rect() {
+        let galley = layout();
+        let rect = char_pos_to_text_pos(TEXT.len(), 0, TEXT.len(), &galley, egui::Pos2::ZERO);
+        assert!(rect.is_none());
+    }
+}
@@ -0,0 +1,122 @@
+use eframe::egui;
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    thumbnails::{ThumbnailCache, ThumbnailKey},
+    ActionRequestor,
+};
+
+const THUMBNAIL_SIZE: [u32; 2] = [120, 68];
+
+/// How long the comparison stays up after the edge is let go
+const LINGER: Duration = Duration::from_secs(1);
+
+/// A clip edge being dragged on the timeline
+#[derive(Clone, Copy)]
+pub struct TrimEdge {
+    pub time: f32,
+    pub is_start: bool,
+    /// Top of the handle in window coordinates
+    pub pos: egui::Pos2,
+}
+
+/// Shows the last frame kept next to the first frame cut while a clip edge is dragged, so that a
+/// trim can be checked for cut off words or blinks without playing it back
+pub struct TrimPreview {
+    last: Option<(TrimEdge, Instant)>,
+}
+
+impl TrimPreview {
+    pub fn new() -> TrimPreview {
+        TrimPreview { last: None }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        edge: Option<TrimEdge>,
+        frame_duration: f32,
+        thumbnails: &mut ThumbnailCache,
+        action_tx: &mut ActionRequestor,
+    ) {
+        let now = Instant::now();
+        if let Some(edge) = edge {
+            self.last = Some((edge, now));
+        }
+
+        let Some((edge, last_dragged)) = self.last else {
+            return;
+        };
+        let since_drag = now - last_dragged;
+        if since_drag > LINGER {
+            self.last = None;
+            return;
+        }
+        ctx.request_repaint_after(LINGER - since_drag);
+
+        let (before, after) = frames_around(edge, frame_duration);
+        let (before_label, after_label) = if edge.is_start {
+            ("cut", "kept")
+        } else {
+            ("kept", "cut")
+        };
+
+        egui::Area::new(egui::Id::new("trim_preview"))
+            .order(egui::Order::Tooltip)
+            .fixed_pos(edge.pos)
+            .pivot(egui::Align2::CENTER_BOTTOM)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        for (time, label) in [(before, before_label), (after, after_label)] {
+                            ui.vertical(|ui| {
+                                let key = ThumbnailKey::new(time, THUMBNAIL_SIZE);
+                                let size =
+                                    egui::vec2(THUMBNAIL_SIZE[0] as f32, THUMBNAIL_SIZE[1] as f32);
+                                match thumbnails.get(action_tx, key) {
+                                    Some(texture) => {
+                                        ui.image(egui::load::SizedTexture::new(texture.id(), size));
+                                    }
+                                    None => {
+                                        ui.allocate_ui(size, |ui| ui.spinner());
+                                    }
+                                }
+                                ui.weak(format!("{label} {time:.2}"));
+                            });
+                        }
+                    });
+                });
+            });
+    }
+}
+
+/// Times of the frames on either side of the edge, in timeline order. Thumbnails show the first
+/// frame at or after the requested time
+fn frames_around(edge: TrimEdge, frame_duration: f32) -> (f32, f32) {
+    let frame_duration = if frame_duration > 0.0 {
+        frame_duration
+    } else {
+        1.0 / 30.0
+    };
+    ((edge.time - frame_duration).max(0.0), edge.time)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frames_sit_on_either_side_of_the_edge() {
+        let edge = TrimEdge {
+            time: 2.0,
+            is_start: false,
+            pos: egui::Pos2::ZERO,
+        };
+        assert_eq!(frames_around(edge, 0.5), (1.5, 2.0));
+
+        let at_zero = TrimEdge { time: 0.0, ..edge };
+        assert_eq!(frames_around(at_zero, 0.5), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,526 @@
+use eframe::wgpu::{self, util::DeviceExt};
+
+use std::sync::Mutex;
+
+use crate::{
+    c_bindings,
+    c_renderer::{CFrameRenderer, CWaveformRenderer, VideoFrame},
+    profiling::profile_scope,
+    render::{wgpu_callback::Recorder, CommandRecorder, FrameParams, Renderer, WaveformParams},
+};
+
+fn f32_bytes(vals: &[f32]) -> Vec<u8> {
+    vals.iter().flat_map(|v| v.to_ne_bytes()).collect()
+}
+
+fn uniform_buffer(device: &wgpu::Device, size: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn uniform_layout_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn create_pipeline(
+    recorder: &Recorder,
+    label: &str,
+    shader_source: &str,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    vertex_buffers: &[wgpu::VertexBufferLayout],
+    topology: wgpu::PrimitiveTopology,
+) -> wgpu::RenderPipeline {
+    let device = recorder.device;
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+    });
+
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: vertex_buffers,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(recorder.target.format.into())],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: recorder.target.sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
+
+struct FrameTextures {
+    width: u32,
+    height: u32,
+    stride: u32,
+    y: wgpu::Texture,
+    u: wgpu::Texture,
+    v: wgpu::Texture,
+    views: [wgpu::TextureView; 3],
+}
+
+/// One per GuiFrameView
+struct ViewState {
+    /// Separate per view since both views are drawn in the same pass with their own sizes
+    uniforms: wgpu::Buffer,
+    /// None until the first frame arrives
+    textures: Option<FrameTextures>,
+}
+
+struct FrameState {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Indexed by GuiFrameView
+    views: [ViewState; 2],
+}
+
+/// wgpu version of FrameRenderer.zig. Decoding still happens on the backend, we only pull the YUV
+/// planes out of it and convert them to RGB in a shader
+pub struct WgpuFrameRenderer {
+    source: CFrameRenderer,
+    state: Mutex<Option<FrameState>>,
+}
+
+impl WgpuFrameRenderer {
+    pub fn new(source: CFrameRenderer) -> WgpuFrameRenderer {
+        WgpuFrameRenderer {
+            source,
+            state: Mutex::new(None),
+        }
+    }
+}
+
+impl FrameState {
+    fn new(recorder: &Recorder) -> FrameState {
+        let device = recorder.device;
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("frame"),
+            entries: &[
+                uniform_layout_entry(0, wgpu::ShaderStages::FRAGMENT),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture_entry(2),
+                texture_entry(3),
+                texture_entry(4),
+            ],
+        });
+
+        let pipeline = create_pipeline(
+            recorder,
+            "frame",
+            include_str!("wgpu_renderer/frame.wgsl"),
+            &bind_group_layout,
+            &[],
+            wgpu::PrimitiveTopology::TriangleStrip,
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let view_state = || ViewState {
+            uniforms: uniform_buffer(device, 8),
+            textures: None,
+        };
+
+        FrameState {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            views: [view_state(), view_state()],
+        }
+    }
+}
+
+impl ViewState {
+    fn upload_frame(&mut self, recorder: &Recorder, frame: VideoFrame) {
+        let size_matches = self.textures.as_ref().is_some_and(|textures| {
+            textures.width == frame.width
+                && textures.height == frame.height
+                && textures.stride == frame.stride
+        });
+
+        if !size_matches {
+            self.textures = Some(create_textures(recorder.device, &frame));
+        }
+
+        let textures = self.textures.as_ref().unwrap();
+        let planes = [
+            (&textures.y, frame.y, frame.stride, frame.height),
+            (&textures.u, frame.u, frame.stride / 2, frame.height / 2),
+            (&textures.v, frame.v, frame.stride / 2, frame.height / 2),
+        ];
+
+        for (texture, data, width, height) in planes {
+            recorder.queue.write_texture(
+                texture.as_image_copy(),
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(width),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+}
+
+fn create_textures(device: &wgpu::Device, frame: &VideoFrame) -> FrameTextures {
+    let create_texture = |width, height| {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("frame plane"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    };
+
+    let y = create_texture(frame.stride, frame.height);
+    let u = create_texture(frame.stride / 2, frame.height / 2);
+    let v = create_texture(frame.stride / 2, frame.height / 2);
+
+    let views = [&y, &u, &v].map(|texture| texture.create_view(&Default::default()));
+
+    FrameTextures {
+        width: frame.width,
+        height: frame.height,
+        stride: frame.stride,
+        y,
+        u,
+        v,
+        views,
+    }
+}
+
+impl FrameState {
+    /// Made per draw since a view may be drawing the textures of the other one
+    fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        uniforms: &wgpu::Buffer,
+        textures: &FrameTextures,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("frame"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniforms.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&textures.views[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&textures.views[1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&textures.views[2]),
+                },
+            ],
+        })
+    }
+}
+
+impl Renderer for WgpuFrameRenderer {
+    type Params = FrameParams;
+
+    fn init(&self, recorder: &mut CommandRecorder) {
+        if let CommandRecorder::Wgpu(recorder) = recorder {
+            *self.state.lock().unwrap() = Some(FrameState::new(recorder));
+        }
+    }
+
+    fn render(&self, recorder: &mut CommandRecorder, params: FrameParams) {
+        profile_scope!("WgpuFrameRenderer::render");
+        let CommandRecorder::Wgpu(recorder) = recorder else {
+            return;
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let Some(state) = state.as_mut() else {
+            return;
+        };
+
+        let view = params.view as usize;
+        if view >= state.views.len() {
+            return;
+        }
+
+        // Views showing the source leave their own textures alone, the source view is what
+        // consumes those frames
+        let textures_view = match self.source.content(params.view) {
+            c_bindings::GuiFrameContent_gui_frame_content_blank => return,
+            c_bindings::GuiFrameContent_gui_frame_content_source => {
+                c_bindings::GuiFrameView_gui_frame_view_source as usize
+            }
+            _ => {
+                let view_state = &mut state.views[view];
+                self.source.consume_frame(params.view, |frame| {
+                    view_state.upload_frame(recorder, frame)
+                });
+                view
+            }
+        };
+
+        let Some(textures) = &state.views[textures_view].textures else {
+            return;
+        };
+
+        let uniforms = &state.views[view].uniforms;
+        let width_ratio = textures.width as f32 / textures.stride as f32;
+        let image_aspect_ratio = textures.width as f32 / textures.height as f32;
+        let aspect_ratio_ratio = params.width / params.height / image_aspect_ratio;
+        recorder
+            .queue
+            .write_buffer(uniforms, 0, &f32_bytes(&[width_ratio, aspect_ratio_ratio]));
+        let bind_group = state.bind_group(recorder.device, uniforms, textures);
+
+        let mut encoder = recorder.bundle_encoder();
+        encoder.set_pipeline(&state.pipeline);
+        encoder.set_bind_group(0, &bind_group, &[]);
+        encoder.draw(0..4, 0..1);
+        recorder
+            .bundles
+            .push(encoder.finish(&wgpu::RenderBundleDescriptor { label: None }));
+    }
+
+    fn deinit(&self, recorder: &mut CommandRecorder) {
+        if let CommandRecorder::Wgpu(_) = recorder {
+            *self.state.lock().unwrap() = None;
+        }
+    }
+}
+
+struct WaveformState {
+    pipeline: wgpu::RenderPipeline,
+    uniforms: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    samples: Vec<c_bindings::GuiWaveformSample>,
+    /// Backend generation that samples were read from
+    generation: u64,
+    /// None while there are no samples to draw
+    vertices: Option<wgpu::Buffer>,
+}
+
+/// wgpu version of AudioRenderer.zig. The backend still computes the min/max envelope, we only turn
+/// it into vertical lines
+pub struct WgpuWaveformRenderer {
+    source: CWaveformRenderer,
+    state: Mutex<Option<WaveformState>>,
+}
+
+impl WgpuWaveformRenderer {
+    pub fn new(source: CWaveformRenderer) -> WgpuWaveformRenderer {
+        WgpuWaveformRenderer {
+            source,
+            state: Mutex::new(None),
+        }
+    }
+}
+
+impl WaveformState {
+    fn new(recorder: &Recorder) -> WaveformState {
+        let device = recorder.device;
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("waveform"),
+            entries: &[uniform_layout_entry(0, wgpu::ShaderStages::VERTEX)],
+        });
+
+        const ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![0 => Float32x2];
+        // Line width is not a thing in wgpu, so unlike the GL version the lines are a pixel wide
+        let pipeline = create_pipeline(
+            recorder,
+            "waveform",
+            include_str!("wgpu_renderer/waveform.wgsl"),
+            &bind_group_layout,
+            &[wgpu::VertexBufferLayout {
+                array_stride: 2 * 4,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &ATTRIBUTES,
+            }],
+            wgpu::PrimitiveTopology::LineList,
+        );
+
+        let uniforms = uniform_buffer(device, 8);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("waveform"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniforms.as_entire_binding(),
+            }],
+        });
+
+        WaveformState {
+            pipeline,
+            uniforms,
+            bind_group,
+            samples: Vec::new(),
+            generation: 0,
+            vertices: None,
+        }
+    }
+
+    fn update_vertices(&mut self, device: &wgpu::Device, source: &CWaveformRenderer) {
+        let generation = source.generation();
+        if generation != self.generation {
+            self.generation = generation;
+            self.samples.clear();
+            self.vertices = None;
+        }
+
+        let num_loaded = self.samples.len();
+        let total = source.read_samples(&mut self.samples);
+        if num_loaded == self.samples.len() && self.vertices.is_some() {
+            return;
+        }
+
+        if self.samples.is_empty() {
+            return;
+        }
+
+        // Same layout as AudioRenderer.updateVertexBuffer, one vertical line per sample spread
+        // over the full expected length so that the waveform lines up with the timeline while
+        // it is still loading
+        let total = total.max(1) as f32;
+        let mut vertices = Vec::with_capacity(self.samples.len() * 4);
+        for (i, sample) in self.samples.iter().enumerate() {
+            let x_norm = (2.0 * i as f32 - total) / total;
+            vertices.extend_from_slice(&[x_norm, sample.min, x_norm, sample.max]);
+        }
+
+        self.vertices = Some(
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("waveform"),
+                contents: &f32_bytes(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+        );
+    }
+}
+
+impl Renderer for WgpuWaveformRenderer {
+    type Params = WaveformParams;
+
+    fn init(&self, recorder: &mut CommandRecorder) {
+        if let CommandRecorder::Wgpu(recorder) = recorder {
+            *self.state.lock().unwrap() = Some(WaveformState::new(recorder));
+        }
+    }
+
+    fn render(&self, recorder: &mut CommandRecorder, params: WaveformParams) {
+        profile_scope!("WgpuWaveformRenderer::render");
+        let CommandRecorder::Wgpu(recorder) = recorder else {
+            return;
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let Some(state) = state.as_mut() else {
+            return;
+        };
+
+        state.update_vertices(recorder.device, &self.source);
+        let Some(vertices) = &state.vertices else {
+            return;
+        };
+
+        recorder.queue.write_buffer(
+            &state.uniforms,
+            0,
+            &f32_bytes(&[params.zoom, params.center_norm]),
+        );
+
+        let mut encoder = recorder.bundle_encoder();
+        encoder.set_pipeline(&state.pipeline);
+        encoder.set_bind_group(0, &state.bind_group, &[]);
+        encoder.set_vertex_buffer(0, vertices.slice(..));
+        encoder.draw(0..state.samples.len() as u32 * 2, 0..1);
+        recorder
+            .bundles
+            .push(encoder.finish(&wgpu::RenderBundleDescriptor { label: None }));
+    }
+
+    fn deinit(&self, recorder: &mut CommandRecorder) {
+        if let CommandRecorder::Wgpu(_) = recorder {
+            *self.state.lock().unwrap() = None;
+        }
+    }
+}
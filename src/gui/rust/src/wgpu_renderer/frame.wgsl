@@ -0,0 +1,75 @@
+// Port of FrameRenderer/fragment.glsl, see there for how the coordinate
+// remapping works
+
+struct Uniforms {
+    width_ratio: f32,
+    aspect_ratio_ratio: f32,
+};
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var tex_sampler: sampler;
+@group(0) @binding(2) var y_tex: texture_2d<f32>;
+@group(0) @binding(3) var u_tex: texture_2d<f32>;
+@group(0) @binding(4) var v_tex: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) vert_coord_2d: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var vertices = array<vec2<f32>, 4>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+    );
+
+    var out: VertexOutput;
+    out.vert_coord_2d = vertices[vertex_index];
+    out.position = vec4<f32>(vertices[vertex_index], 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let width_ratio = uniforms.width_ratio;
+    let aspect_ratio_ratio = uniforms.aspect_ratio_ratio;
+
+    var frag_coord = (in.vert_coord_2d + 1.0) / 2.0;
+    frag_coord.x *= width_ratio;
+    frag_coord.y = 1.0 - frag_coord.y;
+
+    if (aspect_ratio_ratio < 1.0) {
+        let y_offs = (aspect_ratio_ratio - 1.0) / 2.0;
+        frag_coord.y += y_offs;
+        frag_coord.y /= aspect_ratio_ratio;
+    } else {
+        let x_offs = ((width_ratio / aspect_ratio_ratio) - width_ratio) / 2.0;
+        frag_coord.x += x_offs;
+        frag_coord.x *= aspect_ratio_ratio;
+    }
+
+    // Sampling has to happen in uniform control flow, so sample before
+    // discarding
+    var y = textureSample(y_tex, tex_sampler, frag_coord).r;
+    var u = textureSample(u_tex, tex_sampler, frag_coord).r;
+    var v = textureSample(v_tex, tex_sampler, frag_coord).r;
+
+    if (frag_coord.x < 0.0 || frag_coord.x >= width_ratio || frag_coord.y < 0.0 || frag_coord.y >= 1.0) {
+        discard;
+    }
+
+    // https://en.wikipedia.org/wiki/YCbCr#ITU-R_BT.601_conversion
+    y -= 16.0 / 255.0;
+    v -= 0.5;
+    u -= 0.5;
+    y *= 255.0 / 219.0;
+    u *= 255.0 / 224.0 * 1.772;
+    v *= 255.0 / 224.0 * 1.402;
+    let r = y + v;
+    let g = y - u * 0.114 / 0.587 - v * 0.299 / 0.587;
+    let b = y + u;
+    return vec4<f32>(r, g, b, 1.0);
+}
@@ -0,0 +1,21 @@
+// Port of AudioRenderer/vertex.glsl and fragment.glsl
+
+struct Uniforms {
+    zoom: f32,
+    center: f32,
+};
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+@vertex
+fn vs_main(@location(0) in_coord: vec2<f32>) -> @builtin(position) vec4<f32> {
+    // center adjustment from normalized to ogl space
+    var x_coord = in_coord.x - (uniforms.center - 0.5) * 2.0;
+    x_coord *= uniforms.zoom;
+    return vec4<f32>(x_coord, in_coord.y, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0, 0.0, 1.0, 1.0);
+}
@@ -3,22 +3,48 @@ const builtin = @import("builtin");
 const Allocator = std.mem.Allocator;
 
 const c = @import("c.zig");
-const WordTimestampGenerator = @import("WordTimestampGenerator.zig");
-const FrameRenderer = @import("FrameRenderer.zig");
-const decoder = @import("decoder.zig");
-const audio = @import("audio.zig");
-const save = @import("save.zig");
-const App = @import("App.zig");
-const AudioRenderer = @import("AudioRenderer.zig");
-const ClipManager = @import("ClipManager.zig");
+const Project = @import("Project.zig");
 
-const ArgParseError = std.process.ArgIterator.InitError;
+const ArgParseError = std.process.ArgIterator.InitError || Allocator.Error;
+
+pub const std_options: std.Options = .{
+    .logFn = guiLogFn,
+};
+
+// Forward std.log to the GUI so that backend and GUI logs end up in the same
+// place
+fn guiLogFn(
+    comptime level: std.log.Level,
+    comptime scope: @TypeOf(.EnumLiteral),
+    comptime format: []const u8,
+    args: anytype,
+) void {
+    const gui_level = switch (level) {
+        .err => c.gui_log_level_error,
+        .warn => c.gui_log_level_warn,
+        .info => c.gui_log_level_info,
+        .debug => c.gui_log_level_debug,
+    };
+
+    const prefix = if (scope == .default) "" else "(" ++ @tagName(scope) ++ ") ";
+
+    // Long messages get truncated, logging should never fail
+    var buf: [1024]u8 = undefined;
+    const msg = std.fmt.bufPrintZ(&buf, prefix ++ format, args) catch blk: {
+        buf[buf.len - 1] = 0;
+        break :blk buf[0 .. buf.len - 1 :0];
+    };
+
+    c.gui_log(gui_level, msg.ptr);
+}
 
 const Args = struct {
     it: std.process.ArgIterator,
-    input: [:0]const u8,
-    output: [:0]const u8,
+    // The nth input is saved to the nth output, each pair opens as a project
+    inputs: std.ArrayList([:0]const u8),
+    outputs: std.ArrayList([:0]const u8),
     debug_output: ?[:0]const u8,
+    model_dir: [:0]const u8,
     generate_subtitles: bool,
 
     const Switch = enum {
@@ -26,6 +52,7 @@ const Args = struct {
         @"--output",
         @"--skip-subtitles",
         @"--debug-output",
+        @"--model-dir",
         @"--help",
 
         fn parse(s: []const u8) ?Switch {
@@ -45,11 +72,17 @@ const Args = struct {
 
     pub fn init(alloc: std.mem.Allocator) ArgParseError!Args {
         var args = try std.process.argsWithAllocator(alloc);
+        errdefer args.deinit();
+
+        var inputs = std.ArrayList([:0]const u8).init(alloc);
+        errdefer inputs.deinit();
+
+        var outputs = std.ArrayList([:0]const u8).init(alloc);
+        errdefer outputs.deinit();
 
-        var input: ?[:0]const u8 = null;
-        var output: ?[:0]const u8 = null;
         var generate_subtitles: bool = true;
         var debug_output: ?[:0]const u8 = null;
+        var model_dir: [:0]const u8 = "models";
         const process_name = args.next() orelse "video-editor";
         while (args.next()) |arg| {
             const s = Switch.parse(arg) orelse {
@@ -59,16 +92,16 @@ const Args = struct {
 
             switch (s) {
                 .@"--input" => {
-                    input = args.next() orelse {
+                    try inputs.append(args.next() orelse {
                         print("--input provided with no file\n", .{});
                         help(process_name);
-                    };
+                    });
                 },
                 .@"--output" => {
-                    output = args.next() orelse {
+                    try outputs.append(args.next() orelse {
                         print("--output provided with no file\n", .{});
                         help(process_name);
-                    };
+                    });
                 },
                 .@"--skip-subtitles" => {
                     generate_subtitles = false;
@@ -76,23 +109,34 @@ const Args = struct {
                 .@"--debug-output" => {
                     debug_output = args.next();
                 },
+                .@"--model-dir" => {
+                    model_dir = args.next() orelse {
+                        print("--model-dir provided with no directory\n", .{});
+                        help(process_name);
+                    };
+                },
                 .@"--help" => {
                     help(process_name);
                 },
             }
         }
 
+        if (inputs.items.len == 0) {
+            print("input not provided\n", .{});
+            help(process_name);
+        }
+
+        if (outputs.items.len != inputs.items.len) {
+            print("every --input needs an --output\n", .{});
+            help(process_name);
+        }
+
         return .{
             .it = args,
-            .input = input orelse {
-                print("input not provided\n", .{});
-                help(process_name);
-            },
-            .output = output orelse {
-                print("output not provided\n", .{});
-                help(process_name);
-            },
+            .inputs = inputs,
+            .outputs = outputs,
             .debug_output = debug_output,
+            .model_dir = model_dir,
             .generate_subtitles = generate_subtitles,
         };
     }
@@ -106,10 +150,10 @@ const Args = struct {
 
             switch (value) {
                 .@"--input" => {
-                    print("File to work with", .{});
+                    print("File to work with, repeat to open more than one project", .{});
                 },
                 .@"--output" => {
-                    print("Save file", .{});
+                    print("Save file, one per --input", .{});
                 },
                 .@"--skip-subtitles" => {
                     print("Skip subtitle generation", .{});
@@ -117,6 +161,9 @@ const Args = struct {
                 .@"--debug-output" => {
                     print("Optional location to put extra debugging files", .{});
                 },
+                .@"--model-dir" => {
+                    print("Directory with ggml-<model>.bin whisper models to pick from when re-transcribing (default: models)", .{});
+                },
                 .@"--help" => {
                     print("Show this help", .{});
                 },
@@ -128,43 +175,12 @@ const Args = struct {
     }
 
     pub fn deinit(self: *Args) void {
+        self.inputs.deinit();
+        self.outputs.deinit();
         self.it.deinit();
     }
 };
 
-fn makeAudioPlayer(alloc: Allocator, dec: *decoder.VideoDecoder) !?*audio.Player {
-    var audio_player: ?*audio.Player = null;
-
-    var streams = dec.streams();
-    while (try streams.next()) |stream| {
-        switch (stream) {
-            .audio => |params| {
-                audio_player = try audio.Player.init(alloc, .{
-                    .channels = params.num_channels,
-                    .format = params.format,
-                    .sample_rate = params.sample_rate,
-                });
-                // No UI for dealing with multiple streams, video ignored
-                break;
-            },
-            else => {},
-        }
-    }
-
-    return audio_player;
-}
-
-fn main_loop(refs: App.AppRefs) !void {
-    // If main thread init fails, we need to close the GUI, but if the GUI
-    // hadn't launched yet it will miss the shutdown notification and stay
-    // open forever
-    c.gui_wait_start(refs.gui);
-    defer c.gui_close(refs.gui);
-
-    var app = try App.init(refs);
-    try app.run();
-}
-
 pub fn main() !void {
     var gpa = std.heap.GeneralPurposeAllocator(.{}){};
     defer _ = gpa.deinit();
@@ -177,54 +193,49 @@ pub fn main() !void {
     var args = try Args.init(alloc);
     defer args.deinit();
 
-    var dec = try decoder.VideoDecoder.init(alloc, args.input);
-    defer dec.deinit();
-
-    var audio_renderer = try AudioRenderer.init(alloc, args.input);
-    defer audio_renderer.deinit();
-
-    var frame_renderer_shared = FrameRenderer.SharedData{};
-    defer frame_renderer_shared.deinit();
-
-    var frame_renderer = FrameRenderer.init(&frame_renderer_shared);
-
-    var app_state = App.AppState.init(alloc);
-    defer app_state.deinit();
-
-    var save_data = App.Save.load(alloc, args.output);
-    defer save_data.deinit();
+    var projects = std.ArrayList(*Project).init(alloc);
+    defer {
+        for (projects.items) |project| project.destroy();
+        projects.deinit();
+    }
 
-    var clip_manager = try ClipManager.init(alloc, save_data.clips());
-    defer clip_manager.deinit();
+    for (args.inputs.items, args.outputs.items, 0..) |input, output, i| {
+        const project = try Project.create(alloc, input, output, .{
+            // Debug files have fixed names, more than one project would
+            // write over each other
+            .debug_output = if (i == 0) args.debug_output else null,
+            .model_dir = args.model_dir,
+            .generate_subtitles = args.generate_subtitles,
+        });
+        errdefer project.destroy();
+        try projects.append(project);
+    }
 
-    const audio_player = try makeAudioPlayer(alloc, &dec);
-    defer if (audio_player) |p| p.deinit();
+    const gui_projects = try alloc.alloc(c.GuiProject, projects.items.len);
+    defer alloc.free(gui_projects);
+
+    for (projects.items, gui_projects, 0..) |project, *gui_project, i| {
+        gui_project.* = project.guiProject();
+        project.start() catch |e| {
+            // The ones already started are waiting for a GUI that is never
+            // going to show up, and have to be gone before they are destroyed
+            for (projects.items[0..i]) |started| {
+                c.gui_cancel_start(started.gui);
+                started.join(c.gui_status_error);
+            }
+            return e;
+        };
+    }
 
-    var wtm: ?WordTimestampGenerator = null;
-    if (args.generate_subtitles) {
-        wtm = try WordTimestampGenerator.init(alloc, args.input, save_data.wordTimestampMap(), args.debug_output);
+    const gui_status = c.gui_run(gui_projects.ptr, gui_projects.len);
+    for (projects.items) |project| {
+        project.join(gui_status);
     }
-    defer if (wtm) |*w| w.deinit();
-
-    var wtm_ptr: ?*WordTimestampGenerator = null;
-    if (wtm) |*w| wtm_ptr = w;
-
-    const gui = c.gui_init(&app_state);
-    defer c.gui_free(gui);
-
-    const app_refs: App.AppRefs = .{
-        .alloc = alloc,
-        .frame_renderer = &frame_renderer_shared,
-        .gui = gui,
-        .app_state = &app_state,
-        .dec = &dec,
-        .audio_player = audio_player,
-        .clip_manager = &clip_manager,
-        .wtm = wtm_ptr,
-        .save_path = args.output,
-    };
 
-    const main_loop_thread = try std.Thread.spawn(.{}, main_loop, .{app_refs});
-    c.gui_run(gui, &frame_renderer, &audio_renderer, wtm_ptr);
-    main_loop_thread.join();
+    if (gui_status != c.gui_status_ok) {
+        var msg_buf: [1024]u8 = undefined;
+        _ = c.gui_last_error_message(projects.items[0].gui, &msg_buf, msg_buf.len);
+        std.log.err("GUI exited with error: {s}", .{std.mem.sliceTo(&msg_buf, 0)});
+        return error.Gui;
+    }
 }
@@ -14,6 +14,12 @@ fn fromJsonLeaky(comptime T: type, alloc: Allocator, json: std.json.Value) !T {
             var ret: T = undefined;
             inline for (s.fields) |field| {
                 const json_field = json.object.get(field.name) orelse {
+                    // Fields with defaults can be added without breaking
+                    // older saves
+                    if (field.default_value) |default| {
+                        @field(ret, field.name) = @as(*const field.type, @ptrCast(@alignCast(default))).*;
+                        continue;
+                    }
                     std.log.err("Field {s} is not present in JSON data for {any}", .{ field.name, T });
                     return error.MissingField;
                 };
@@ -72,6 +78,13 @@ fn fromJsonLeaky(comptime T: type, alloc: Allocator, json: std.json.Value) !T {
 
             return ret.items;
         },
+        .Bool => {
+            if (json != .bool) {
+                std.log.err("Expected bool, got {any}", .{json});
+                return error.InvalidData;
+            }
+            return json.bool;
+        },
         .Int => {
             switch (json) {
                 .integer => |i| {